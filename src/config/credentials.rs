@@ -0,0 +1,85 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+/// Where the Claude CLI keeps its OAuth login, next to `settings.json`
+pub const CREDENTIALS_FILE_NAME: &str = ".credentials.json";
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CredentialsFile {
+    claude_ai_oauth: Option<OAuthCredentials>,
+}
+
+/// The `claudeAiOauth` entry of `.credentials.json`; other fields are ignored
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OAuthCredentials {
+    pub access_token: String,
+    /// Milliseconds since the Unix epoch
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+}
+
+impl OAuthCredentials {
+    pub fn expires_at(&self) -> Option<DateTime<Utc>> {
+        self.expires_at.and_then(DateTime::from_timestamp_millis)
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at()
+            .is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// The OAuth login in `path`, or `None` when the file is missing, unreadable,
+/// unparseable or holds no access token. Never fails: callers fall back to
+/// asking the user to log in again.
+pub fn load(path: &Path) -> Option<OAuthCredentials> {
+    let content = fs::read_to_string(path).ok()?;
+    let file: CredentialsFile = serde_json::from_str(&content).ok()?;
+    file.claude_ai_oauth
+        .filter(|oauth| !oauth.access_token.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_reads_access_token_and_expiry() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CREDENTIALS_FILE_NAME);
+        fs::write(
+            &path,
+            r#"{"claudeAiOauth": {"accessToken": "sk-ant-oat01-abc", "refreshToken": "sk-ant-ort01-def",
+                "expiresAt": 1767225600000, "scopes": ["user:inference"], "subscriptionType": "max"}}"#,
+        )
+        .unwrap();
+
+        let oauth = load(&path).unwrap();
+        assert_eq!(oauth.access_token, "sk-ant-oat01-abc");
+        let expires_at = oauth.expires_at().unwrap();
+        assert_eq!(expires_at.to_rfc3339(), "2026-01-01T00:00:00+00:00");
+        assert!(oauth.is_expired(expires_at));
+        assert!(!oauth.is_expired(expires_at - chrono::Duration::seconds(1)));
+    }
+
+    #[test]
+    fn test_load_returns_none_for_unusable_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CREDENTIALS_FILE_NAME);
+        assert!(load(&path).is_none());
+
+        for content in [
+            "not json",
+            "{}",
+            r#"{"claudeAiOauth": {"refreshToken": "x"}}"#,
+            r#"{"claudeAiOauth": {"accessToken": "  "}}"#,
+        ] {
+            fs::write(&path, content).unwrap();
+            assert!(load(&path).is_none(), "{}", content);
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use crate::provider::detector::ProviderDetector;
+use std::collections::{BTreeSet, HashMap};
+
+/// One differing `env` key between two configs
+#[derive(Debug, Clone, PartialEq)]
+pub enum EnvChange {
+    /// Only present in the newer config
+    Added { key: String, value: String },
+    /// Only present in the older config
+    Removed { key: String, value: String },
+    Changed {
+        key: String,
+        old: String,
+        new: String,
+    },
+}
+
+impl EnvChange {
+    pub fn key(&self) -> &str {
+        match self {
+            EnvChange::Added { key, .. }
+            | EnvChange::Removed { key, .. }
+            | EnvChange::Changed { key, .. } => key,
+        }
+    }
+
+    /// `+ KEY=value`, `- KEY=value` or `~ KEY: old → new`, secrets masked
+    pub fn describe(&self) -> String {
+        let show = ProviderDetector::display_value;
+        match self {
+            EnvChange::Added { key, value } => format!("+ {}={}", key, show(key, value)),
+            EnvChange::Removed { key, value } => format!("- {}={}", key, show(key, value)),
+            EnvChange::Changed { key, old, new } => {
+                format!("~ {}: {} → {}", key, show(key, old), show(key, new))
+            }
+        }
+    }
+}
+
+/// Differences going from `old` to `new`, sorted by key
+pub fn diff_env(old: &HashMap<String, String>, new: &HashMap<String, String>) -> Vec<EnvChange> {
+    let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    keys.into_iter()
+        .filter_map(|key| match (old.get(key), new.get(key)) {
+            (Some(old), Some(new)) if old != new => Some(EnvChange::Changed {
+                key: key.clone(),
+                old: old.clone(),
+                new: new.clone(),
+            }),
+            (None, Some(value)) => Some(EnvChange::Added {
+                key: key.clone(),
+                value: value.clone(),
+            }),
+            (Some(value), None) => Some(EnvChange::Removed {
+                key: key.clone(),
+                value: value.clone(),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_identical_env_has_no_changes() {
+        let config = env(&[("ANTHROPIC_AUTH_TOKEN", "token-1234567890")]);
+        assert!(diff_env(&config, &config.clone()).is_empty());
+    }
+
+    #[test]
+    fn test_diverged_env_is_reported_masked() {
+        let backup = env(&[
+            ("ANTHROPIC_AUTH_TOKEN", "old-token-1234567890"),
+            ("DISABLE_TELEMETRY", "1"),
+        ]);
+        let live = env(&[
+            ("ANTHROPIC_AUTH_TOKEN", "new-token-0987654321"),
+            ("HTTPS_PROXY", "http://proxy:8080"),
+        ]);
+
+        let changes = diff_env(&backup, &live);
+        let described: Vec<String> = changes.iter().map(EnvChange::describe).collect();
+        assert_eq!(
+            described,
+            vec![
+                "~ ANTHROPIC_AUTH_TOKEN: old-...7890 → new-...4321",
+                "- DISABLE_TELEMETRY=1",
+                "+ HTTPS_PROXY=http://proxy:8080",
+            ]
+        );
+    }
+}
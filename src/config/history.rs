@@ -0,0 +1,176 @@
+use crate::config::Provider;
+use crate::utils::error::AppError;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+/// One JSON object per line, appended after every successful switch
+pub const HISTORY_FILE_NAME: &str = "history.jsonl";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub timestamp: DateTime<Utc>,
+    pub provider: String,
+    /// providers.toml profile, when it is not just the provider name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+}
+
+impl HistoryEntry {
+    pub fn now(provider: &Provider, profile: Option<&str>) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            provider: provider.as_str().to_string(),
+            profile: profile
+                .filter(|name| *name != provider.as_str())
+                .map(str::to_string),
+        }
+    }
+}
+
+pub fn append(path: &Path, entry: &HistoryEntry) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+/// Switching already succeeded by the time we record it, so a failure only warns
+pub fn record(path: &Path, entry: &HistoryEntry) {
+    if let Err(e) = append(path, entry) {
+        eprintln!("{}{}", "⚠️  Failed to record switch history: ".yellow(), e);
+    }
+}
+
+/// Entries in file order; a missing file is an empty history
+pub fn load(path: &Path) -> Result<Vec<HistoryEntry>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line).map_err(|e| {
+                AppError::Config(format!("{}:{}: {}", path.display(), index + 1, e)).into()
+            })
+        })
+        .collect()
+}
+
+/// `--since` value parser: `30m`, `24h`, `7d`, `2w`, a date or an RFC 3339 time
+pub fn parse_since(value: &str) -> Result<DateTime<Utc>, String> {
+    parse_since_at(value, Utc::now())
+}
+
+fn parse_since_at(value: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    let value = value.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc());
+    }
+
+    let invalid = || {
+        format!(
+            "invalid duration or date '{}' (expected e.g. 30m, 24h, 7d, 2w, 2024-01-31)",
+            value
+        )
+    };
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let (amount, unit) = value.split_at(split);
+    let amount: i64 = amount.parse().map_err(|_| invalid())?;
+    let duration = match unit {
+        "m" => Duration::try_minutes(amount),
+        "h" => Duration::try_hours(amount),
+        "d" => Duration::try_days(amount),
+        "w" => Duration::try_weeks(amount),
+        _ => None,
+    }
+    .ok_or_else(invalid)?;
+    now.checked_sub_signed(duration).ok_or_else(invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(timestamp: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(timestamp)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_parse_since() {
+        let now = at("2024-03-10T12:00:00Z");
+        assert_eq!(
+            parse_since_at("24h", now).unwrap(),
+            at("2024-03-09T12:00:00Z")
+        );
+        assert_eq!(
+            parse_since_at("7d", now).unwrap(),
+            at("2024-03-03T12:00:00Z")
+        );
+        assert_eq!(
+            parse_since_at("2024-03-01", now).unwrap(),
+            at("2024-03-01T00:00:00Z")
+        );
+        assert_eq!(
+            parse_since_at("2024-03-01T08:30:00+01:00", now).unwrap(),
+            at("2024-03-01T07:30:00Z")
+        );
+        for invalid in ["", "7", "d", "7y", "-7d", "seven days"] {
+            let error = parse_since_at(invalid, now).unwrap_err();
+            assert!(error.contains("invalid duration or date"), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_load_round_trips_appended_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(HISTORY_FILE_NAME);
+        assert!(load(&path).unwrap().is_empty());
+
+        let glm = HistoryEntry::now(&Provider::GLM, Some("fast"));
+        let anthropic = HistoryEntry::now(&Provider::Anthropic, Some("anthropic"));
+        append(&path, &glm).unwrap();
+        append(&path, &anthropic).unwrap();
+
+        assert_eq!(load(&path).unwrap(), vec![glm, anthropic.clone()]);
+        assert_eq!(anthropic.profile, None);
+    }
+
+    #[test]
+    fn test_load_reports_malformed_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(HISTORY_FILE_NAME);
+        fs::write(
+            &path,
+            "{\"timestamp\":\"2024-01-01T00:00:00Z\",\"provider\":\"glm\"}\nnot json\n",
+        )
+        .unwrap();
+
+        let error = load(&path).unwrap_err().to_string();
+        assert!(error.contains(":2:"));
+    }
+}
@@ -1,38 +1,483 @@
-use crate::config::{BackupConfig, BackupMetadata, Config, Provider};
+use crate::config::history::HISTORY_FILE_NAME;
+use crate::config::registry::{PROVIDERS_FILE_NAME, SYSTEM_PROVIDERS_FILE};
+use crate::config::{
+    BackupConfig, BackupMetadata, Config, Provider, RetentionPolicy, SwitchSettings,
+};
+use crate::utils::error::{redacted_json_error, AppError};
 use anyhow::{Context, Result};
-use chrono::Utc;
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use colored::*;
 use dirs::home_dir;
+use std::collections::HashSet;
 use std::fs;
+use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
+/// Overrides the config directory, as it does for Claude Code itself
+pub const CONFIG_DIR_ENV: &str = "CLAUDE_CONFIG_DIR";
+
+/// How to replace a token `stale_token_warning` complains about
+pub const STALE_TOKEN_HINT: &str =
+    "Re-enter it: claude-switch clear-token, then claude-switch glm --force";
+
+/// Overrides where the GLM token is saved (see `with_token_file`)
+pub const TOKEN_FILE_ENV: &str = "CLAUDE_TOKEN_FILE";
+
+/// File inside `~/.claude` recording where the config was migrated to
+const CONFIG_DIR_POINTER: &str = ".claude-switch-dir";
+
+const BACKUPS_DIR_NAME: &str = "backups";
+/// Rotated backups are named `settings-<BACKUP_TIMESTAMP_FORMAT>.json`
+const BACKUP_TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%S%3fZ";
+const PRUNE_LOCK_NAME: &str = ".prune.lock";
+/// File names of labelled backups already warned about as kept past the limits
+const KEPT_LABELLED_NAME: &str = ".kept-labelled";
+const PROFILES_DIR_NAME: &str = "profiles";
+
+/// How often to re-read a config file that vanished mid-write (see `load_config`)
+const LOAD_RETRY_ATTEMPTS: u32 = 5;
+const LOAD_RETRY_DELAY: std::time::Duration = std::time::Duration::from_millis(20);
+
+/// Claude Code's per-machine override next to `settings.json`; never written by us
+const LOCAL_SETTINGS_FILE_NAME: &str = "settings.local.json";
+
+/// Shell configs that received aliases from `install`
+const ALIASES_FILE_NAME: &str = "aliases.json";
+
+const TOKEN_FILE_NAME: &str = ".claude-switch-token";
+/// Pre-2.3 token file name, still read as a fallback for one release
+const LEGACY_TOKEN_FILE_NAME: &str = ".z_ai_token";
+
+/// What `ensure_backup` did
+#[derive(Debug)]
+pub enum EnsuredBackup {
+    Created,
+    /// A backup was already there and is left alone
+    Reused(Box<BackupConfig>),
+}
+
+/// Rotated backups over the retention limits
+struct PruneSelection {
+    /// Removed by `prune_backups`
+    prunable: Vec<PathBuf>,
+    /// Kept regardless because they are labelled
+    labelled: Vec<LabelledBackup>,
+}
+
+struct LabelledBackup {
+    path: PathBuf,
+    label: String,
+}
+
+/// What `settings.json.backup` was recognized as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackupFileKind {
+    Missing,
+    /// With metadata, inline (`_metadata`) or in the `.meta` sidecar
+    Current,
+    /// A plain settings.json copy without a sidecar, from before backups had metadata
+    Legacy,
+    /// Valid JSON, but neither a backup nor a settings file (e.g. an array)
+    UnrecognizedSchema,
+    /// Not JSON at all, e.g. a truncated write
+    Corrupt,
+}
+
+impl BackupFileKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BackupFileKind::Missing => "missing",
+            BackupFileKind::Current => "current",
+            BackupFileKind::Legacy => "legacy",
+            BackupFileKind::UnrecognizedSchema => "unrecognized_schema",
+            BackupFileKind::Corrupt => "corrupt",
+        }
+    }
+}
+
+/// A timestamped copy in the backup rotation
+#[derive(Debug, Clone)]
+pub struct BackupEntry {
+    pub path: PathBuf,
+    /// Parsed from the file name; `None` when the name is not ours
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+impl BackupEntry {
+    /// How the backup is named on the command line: the file name's timestamp
+    pub fn id(&self) -> String {
+        let stem = self
+            .path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        match stem.strip_prefix("settings-") {
+            Some(id) => id.to_string(),
+            None => stem,
+        }
+    }
+}
+
+/// Line ending of a config file, kept when the file is rewritten so a
+/// settings.json edited on Windows doesn't turn into a whole-file diff
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    /// How much of a file is looked at to tell its line ending
+    const SAMPLE_BYTES: u64 = 64 * 1024;
+
+    /// The predominant line ending at the start of `path`; `Lf` for a file
+    /// that is missing, unreadable or has no line breaks
+    pub fn detect(path: &Path) -> Self {
+        let mut sample = Vec::new();
+        match fs::File::open(path) {
+            Ok(file) => {
+                if file
+                    .take(Self::SAMPLE_BYTES)
+                    .read_to_end(&mut sample)
+                    .is_err()
+                {
+                    return Self::Lf;
+                }
+            }
+            Err(_) => return Self::Lf,
+        }
+        Self::of(&sample)
+    }
+
+    fn of(text: &[u8]) -> Self {
+        let breaks = text.iter().filter(|byte| **byte == b'\n').count();
+        let crlf = text.windows(2).filter(|pair| pair == b"\r\n").count();
+        if crlf * 2 > breaks {
+            Self::CrLf
+        } else {
+            Self::Lf
+        }
+    }
+}
+
+/// Writes every `\n` as `\r\n`. Only safe for output like serde_json's,
+/// which never has a raw newline inside a value.
+struct CrLfWriter<W: Write>(W);
+
+impl<W: Write> Write for CrLfWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        for line in buf.split_inclusive(|byte| *byte == b'\n') {
+            match line.strip_suffix(b"\n") {
+                Some(line) => {
+                    self.0.write_all(line)?;
+                    self.0.write_all(b"\r\n")?;
+                }
+                None => self.0.write_all(line)?,
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct ConfigManager {
+    config_dir: PathBuf,
+    /// `~/.claude`, where Claude Code looks and `new()` reads the pointer left
+    /// by `migrate_to`, wherever the config lives now
+    home_dir: PathBuf,
+    /// Holds `claude-switch.toml`; unlike `config_dir` never scoped to a profile
+    preferences_dir: PathBuf,
+    profile: Option<String>,
+    backup_dir: PathBuf,
     settings_file: PathBuf,
     backup_file: PathBuf,
     token_file: PathBuf,
+    /// Pre-2.3 token file; `None` when the token file was given explicitly
+    legacy_token_file: Option<PathBuf>,
+    providers_file: PathBuf,
+    /// Read before `providers_file`; `None` with `--no-system-profiles`
+    system_providers_file: Option<PathBuf>,
+    history_file: PathBuf,
+    aliases_file: PathBuf,
+    /// Recorded in the metadata of every backup this manager takes
+    backup_note: Option<String>,
 }
 
 impl ConfigManager {
     pub fn new() -> Result<Self> {
-        let home = home_dir().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
-        let config_dir = home.join(".claude");
+        let home = home_dir().ok_or(AppError::HomeDirNotFound)?;
+        let legacy_dir = home.join(".claude");
+
+        // CLAUDE_CONFIG_DIR (shared with Claude Code) wins; otherwise follow
+        // the pointer left behind by `config-dir migrate`
+        let pointer = legacy_dir.join(CONFIG_DIR_POINTER);
+        let config_dir = match std::env::var(CONFIG_DIR_ENV) {
+            Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => match fs::read_to_string(&pointer) {
+                Ok(content) if !content.trim().is_empty() => PathBuf::from(content.trim()),
+                _ => legacy_dir.clone(),
+            },
+        };
+
+        let mut manager = Self::with_config_dir(config_dir);
+        manager.home_dir = legacy_dir;
+        manager.system_providers_file = Some(PathBuf::from(SYSTEM_PROVIDERS_FILE));
+        if let Ok(backup_dir) = std::env::var("CLAUDE_BACKUP_DIR") {
+            if !backup_dir.is_empty() {
+                manager = manager.with_backup_dir(PathBuf::from(backup_dir));
+            }
+        }
+        if let Ok(token_file) = std::env::var(TOKEN_FILE_ENV) {
+            if !token_file.is_empty() {
+                manager = manager.with_token_file(PathBuf::from(token_file));
+            }
+        }
+        Ok(manager)
+    }
 
-        Ok(Self {
+    pub fn with_config_dir(config_dir: PathBuf) -> Self {
+        Self {
             settings_file: config_dir.join("settings.json"),
             backup_file: config_dir.join("settings.json.backup"),
-            token_file: config_dir.join(".z_ai_token"),
-        })
+            token_file: config_dir.join(TOKEN_FILE_NAME),
+            legacy_token_file: Some(config_dir.join(LEGACY_TOKEN_FILE_NAME)),
+            providers_file: config_dir.join(PROVIDERS_FILE_NAME),
+            system_providers_file: None,
+            history_file: config_dir.join(HISTORY_FILE_NAME),
+            aliases_file: config_dir.join(ALIASES_FILE_NAME),
+            backup_dir: config_dir.clone(),
+            preferences_dir: config_dir.clone(),
+            home_dir: config_dir.clone(),
+            config_dir,
+            profile: None,
+            backup_note: None,
+        }
     }
 
-    pub fn load_config(&self, path: &Path) -> Result<Config> {
-        if !path.exists() {
-            return Ok(Config::default());
+    /// Treat `settings_file`, which may have any name, as the live
+    /// settings.json; backups, token and history live next to it
+    pub fn with_settings_file(settings_file: PathBuf) -> Self {
+        let config_dir = match settings_file.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        Self {
+            settings_file,
+            system_providers_file: Some(PathBuf::from(SYSTEM_PROVIDERS_FILE)),
+            ..Self::with_config_dir(config_dir)
         }
+    }
 
-        let content = fs::read_to_string(path)
-            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    /// Scope settings and backups to `<config dir>/profiles/<name>`.
+    ///
+    /// The saved token, `providers.toml` and the alias manifest stay shared between profiles. A backup
+    /// location set before (`CLAUDE_BACKUP_DIR`, `with_backup_dir`) is kept.
+    pub fn with_profile(self, name: &str) -> Self {
+        let profile_dir = self.config_dir.join(PROFILES_DIR_NAME).join(name);
+        let (backup_dir, backup_file) = if self.backup_dir == self.config_dir {
+            (
+                profile_dir.clone(),
+                profile_dir.join("settings.json.backup"),
+            )
+        } else {
+            (self.backup_dir.clone(), self.backup_file.clone())
+        };
+        Self {
+            settings_file: profile_dir.join("settings.json"),
+            backup_file,
+            history_file: profile_dir.join(HISTORY_FILE_NAME),
+            backup_dir,
+            config_dir: profile_dir,
+            profile: Some(name.to_string()),
+            ..self
+        }
+    }
+
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    /// Store backups (primary, metadata and rotation) under `backup_dir`,
+    /// leaving settings and token where they are
+    pub fn with_backup_dir(mut self, backup_dir: PathBuf) -> Self {
+        self.backup_file = backup_dir.join("settings.json.backup");
+        self.backup_dir = backup_dir;
+        self
+    }
+
+    /// Read and write the saved token at `token_file` instead of the config
+    /// directory. There is no legacy fallback for an explicit file.
+    pub fn with_token_file(mut self, token_file: PathBuf) -> Self {
+        self.legacy_token_file = None;
+        self.token_file = token_file;
+        self
+    }
+
+    /// Record `note` in the metadata of the backups taken from now on
+    pub fn with_backup_note(mut self, note: Option<String>) -> Self {
+        self.backup_note = note;
+        self
+    }
+
+    /// Metadata for a backup of `provider` taken now, with the backup note
+    fn new_backup_metadata(&self, provider: &Provider) -> BackupMetadata {
+        BackupMetadata {
+            note: self.backup_note.clone(),
+            ..BackupMetadata::new(provider)
+        }
+    }
+
+    /// Default migration target: `$XDG_CONFIG_HOME/claude-switch` (or `~/.config/claude-switch`)
+    pub fn xdg_config_dir() -> Result<PathBuf> {
+        let base = match std::env::var("XDG_CONFIG_HOME") {
+            Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => home_dir().ok_or(AppError::HomeDirNotFound)?.join(".config"),
+        };
+        Ok(base.join("claude-switch"))
+    }
+
+    pub fn config_dir(&self) -> &Path {
+        &self.config_dir
+    }
+
+    /// Where `claude-switch.toml` is read from, the global config directory
+    /// even when a profile is selected
+    pub fn preferences_dir(&self) -> &Path {
+        &self.preferences_dir
+    }
+
+    /// The `settings.json` Claude Code reads
+    pub fn settings_file(&self) -> &Path {
+        &self.settings_file
+    }
+
+    /// `settings.local.json` next to the managed settings file
+    pub fn local_settings_file(&self) -> PathBuf {
+        self.settings_file.with_file_name(LOCAL_SETTINGS_FILE_NAME)
+    }
+
+    /// The Claude CLI's OAuth login, next to the managed settings file
+    pub fn credentials_file(&self) -> PathBuf {
+        self.settings_file
+            .with_file_name(crate::config::credentials::CREDENTIALS_FILE_NAME)
+    }
+
+    /// Saved GLM token
+    pub fn token_file(&self) -> &Path {
+        &self.token_file
+    }
+
+    /// `providers.toml`, shared by all profiles
+    pub fn providers_file(&self) -> &Path {
+        &self.providers_file
+    }
+
+    /// Team-wide `providers.toml` read before the user's, if enabled
+    pub fn system_providers_file(&self) -> Option<&Path> {
+        self.system_providers_file.as_deref()
+    }
 
-        let config: Config =
-            serde_json::from_str(&content).with_context(|| "Failed to parse config file")?;
+    /// Ignore the system `providers.toml` (`--no-system-profiles`)
+    pub fn without_system_profiles(mut self) -> Self {
+        self.system_providers_file = None;
+        self
+    }
+
+    /// Alias install manifest, shared by all profiles
+    pub fn aliases_file(&self) -> &Path {
+        &self.aliases_file
+    }
+
+    /// Switch log, kept per profile like settings
+    pub fn history_file(&self) -> &Path {
+        &self.history_file
+    }
+
+    /// Fail early, with an actionable message, if any directory a switch
+    /// writes to (config, backup, token) is read-only.
+    pub fn ensure_writable(&self) -> Result<()> {
+        let mut dirs = vec![self.config_dir.clone(), self.backup_dir.clone()];
+        if let Some(token_dir) = self.token_file.parent() {
+            dirs.push(token_dir.to_path_buf());
+        }
+        dirs.dedup();
+
+        for dir in dirs {
+            Self::probe_writable(&dir)?;
+        }
+        Ok(())
+    }
+
+    fn probe_writable(dir: &Path) -> Result<()> {
+        // Directories that don't exist yet are created in their nearest existing ancestor
+        let existing = dir
+            .ancestors()
+            .find(|ancestor| ancestor.exists())
+            .unwrap_or(dir);
+
+        let probe = existing.join(format!(".claude-switch-probe-{}", std::process::id()));
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&probe)
+        {
+            Ok(_) => {
+                let _ = fs::remove_file(&probe);
+                Ok(())
+            }
+            Err(e) => Err(AppError::FileSystem(format!(
+                "Directory {} is not writable ({}). Check its permissions (e.g. `chmod u+w {}`) \
+                 or whether it is on a read-only mount",
+                existing.display(),
+                e,
+                existing.display()
+            ))
+            .into()),
+        }
+    }
+
+    /// A missing file is an empty config, unless its `.tmp` sibling exists:
+    /// then a concurrent `save_config_atomic` is mid-rename and we retry briefly
+    pub fn load_config(&self, path: &Path) -> Result<Config> {
+        let temp_path = path.with_extension("tmp");
+        let mut attempt = 1;
+        let file = loop {
+            match fs::File::open(path) {
+                Ok(file) => break file,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    if attempt >= LOAD_RETRY_ATTEMPTS || !temp_path.exists() {
+                        return Ok(Config::default());
+                    }
+                    attempt += 1;
+                    std::thread::sleep(LOAD_RETRY_DELAY);
+                }
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("Failed to read config file: {}", path.display()))
+                }
+            }
+        };
+
+        // Streamed, so large `permissions` or MCP server trees never sit in
+        // memory twice
+        let config: Config = serde_json::from_reader(BufReader::new(file)).map_err(|e| {
+            if e.is_io() {
+                return anyhow::Error::new(std::io::Error::from(e))
+                    .context(format!("Failed to read config file: {}", path.display()));
+            }
+            AppError::Config(format!(
+                "Failed to parse {}: {}",
+                path.display(),
+                redacted_json_error(&e)
+            ))
+            .into()
+        })?;
 
         Ok(config)
     }
@@ -47,13 +492,15 @@ impl ConfigManager {
                 .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
         }
 
-        let content =
-            serde_json::to_string_pretty(config).with_context(|| "Failed to serialize config")?;
-
+        // A file being replaced keeps the line ending it was loaded with
+        let line_ending = LineEnding::detect(path);
+        // Written in full to a temp file first, so a failure halfway never
+        // leaves a truncated config behind
         let temp_path = path.with_extension("tmp");
-
-        fs::write(&temp_path, content)
-            .with_context(|| format!("Failed to write temp file: {}", temp_path.display()))?;
+        if let Err(e) = Self::write_config_to(&temp_path, config, line_ending) {
+            let _ = fs::remove_file(&temp_path);
+            return Err(e);
+        }
 
         fs::rename(&temp_path, path)
             .with_context(|| format!("Failed to rename temp file to: {}", path.display()))?;
@@ -61,51 +508,125 @@ impl ConfigManager {
         Ok(())
     }
 
+    /// Stream `config` as pretty JSON into `path` without building the whole
+    /// text in memory first
+    fn write_config_to(path: &Path, config: &Config, line_ending: LineEnding) -> Result<()> {
+        let write_error = || format!("Failed to write temp file: {}", path.display());
+        let mut writer = BufWriter::new(fs::File::create(path).with_context(write_error)?);
+        let written = match line_ending {
+            LineEnding::Lf => serde_json::to_writer_pretty(&mut writer, config),
+            LineEnding::CrLf => serde_json::to_writer_pretty(CrLfWriter(&mut writer), config),
+        };
+        written.map_err(|e| {
+            if e.is_io() {
+                return anyhow::Error::new(std::io::Error::from(e)).context(write_error());
+            }
+            AppError::Config(format!(
+                "Failed to serialize config: {}",
+                redacted_json_error(&e)
+            ))
+            .into()
+        })?;
+        writer.flush().with_context(write_error)
+    }
+
     pub fn save_current_config(&self, config: &Config) -> Result<()> {
+        self.migrate_legacy_token_or_warn();
         self.save_config_atomic(&self.settings_file, config)
     }
 
     pub fn has_valid_anthropic_backup(&self) -> Result<(bool, Option<BackupConfig>)> {
+        Ok(match self.read_backup_file()? {
+            (BackupFileKind::Current, Some(backup)) => {
+                let is_anthropic = backup.metadata.provider == Provider::Anthropic.as_str();
+                (is_anthropic, Some(backup))
+            }
+            (BackupFileKind::Legacy, backup) => (true, backup),
+            _ => (false, None),
+        })
+    }
+
+    /// What the Anthropic backup file holds, for status and doctor
+    pub fn classify_backup_file(&self) -> Result<BackupFileKind> {
+        Ok(self.read_backup_file()?.0)
+    }
+
+    /// The Anthropic backup and how it was recognized; legacy backups are
+    /// converted to the current format
+    fn read_backup_file(&self) -> Result<(BackupFileKind, Option<BackupConfig>)> {
         if !self.backup_file.exists() {
-            return Ok((false, None));
+            return Ok((BackupFileKind::Missing, None));
         }
 
         let content =
             fs::read_to_string(&self.backup_file).with_context(|| "Failed to read backup file")?;
 
+        let modified = Self::modified_at(&self.backup_file);
         // Try parsing as new format first
-        if let Ok(backup) = serde_json::from_str::<BackupConfig>(&content) {
-            let is_anthropic = backup.metadata.provider == Provider::Anthropic.as_str();
-            Ok((is_anthropic, Some(backup)))
+        if let Ok(mut backup) = serde_json::from_str::<BackupConfig>(&content) {
+            backup.metadata = backup.metadata.migrate(modified);
+            return Ok((BackupFileKind::Current, Some(backup)));
+        }
+        let Ok(value) = serde_json::from_str::<serde_json::Value>(&content) else {
+            return Ok((BackupFileKind::Corrupt, None));
+        };
+        // `_metadata` that doesn't parse is a damaged current backup, not a
+        // legacy one
+        if value.get("_metadata").is_some() {
+            return Ok((BackupFileKind::UnrecognizedSchema, None));
+        }
+        let Ok(config) = serde_json::from_value::<Config>(value) else {
+            return Ok((BackupFileKind::UnrecognizedSchema, None));
+        };
+        // A plain settings copy: current when `create_backup_with_metadata`
+        // left its sidecar, legacy (from before sidecars) otherwise
+        let sidecar = fs::read_to_string(self.backup_file.with_extension("meta"))
+            .ok()
+            .and_then(|content| serde_json::from_str::<BackupMetadata>(&content).ok());
+        let kind = if sidecar.is_some() {
+            BackupFileKind::Current
         } else {
-            // Try parsing as old format (without metadata)
-            if let Ok(old_config) = serde_json::from_str::<Config>(&content) {
-                let backup = BackupConfig {
-                    metadata: BackupMetadata {
-                        provider: Provider::Anthropic.as_str().to_string(),
-                        created_at: Some(Utc::now()),
-                        version: "2.2.0".to_string(),
-                    },
-                    env: old_config.env,
-                };
-                Ok((true, Some(backup)))
-            } else {
-                Ok((false, None))
+            BackupFileKind::Legacy
+        };
+        let backup = BackupConfig {
+            metadata: match sidecar {
+                Some(metadata) => metadata.migrate(modified),
+                None => BackupMetadata::synthesized(&Provider::Anthropic, modified),
+            },
+            env: config.env,
+            settings: Default::default(),
+        };
+        Ok((kind, Some(backup)))
+    }
+
+    /// Make sure there is a primary backup of `provider`: reuse a readable
+    /// one, otherwise back up `config`
+    pub fn ensure_backup(&self, config: &Config, provider: &Provider) -> Result<EnsuredBackup> {
+        match self.read_backup_file()? {
+            (_, Some(backup)) if backup.metadata.provider == provider.as_str() => {
+                Ok(EnsuredBackup::Reused(Box::new(backup)))
+            }
+            _ => {
+                self.create_backup_with_metadata(config, provider)?;
+                Ok(EnsuredBackup::Created)
             }
         }
     }
 
     pub fn create_backup_with_metadata(&self, config: &Config, provider: &Provider) -> Result<()> {
         let backup = BackupConfig {
-            metadata: BackupMetadata {
-                provider: provider.as_str().to_string(),
-                created_at: Some(Utc::now()),
-                version: "2.2.0".to_string(),
-            },
+            metadata: self.new_backup_metadata(provider),
             env: config.env.clone(),
+            settings: Default::default(),
         };
 
-        self.save_config_atomic(&self.backup_file, &Config { env: backup.env })?;
+        self.save_config_atomic(
+            &self.backup_file,
+            &Config {
+                env: backup.env.clone(),
+                ..Default::default()
+            },
+        )?;
 
         // Also save metadata separately for easier access
         let metadata_path = self.backup_file.with_extension("meta");
@@ -115,53 +636,1889 @@ impl ConfigManager {
         fs::write(&temp_metadata, metadata_content)?;
         fs::rename(&temp_metadata, &metadata_path)?;
 
+        self.add_to_rotation(&backup)?;
+        let policy = SwitchSettings::load(&self.preferences_dir)?.backups;
+        self.prune_backups(&policy)?;
+
         Ok(())
     }
 
-    pub fn save_token(&self, token: &str) -> Result<()> {
-        if let Some(parent) = self.token_file.parent() {
-            fs::create_dir_all(parent)?;
+    /// Keep the whole of `config` (env and other settings) in the rotation
+    /// only, leaving the primary Anthropic backup alone.
+    pub fn backup_to_rotation(&self, config: &Config, provider: &Provider) -> Result<PathBuf> {
+        let backup = BackupConfig {
+            metadata: self.new_backup_metadata(provider),
+            env: config.env.clone(),
+            settings: config.extra.clone(),
+        };
+
+        let path = self.add_to_rotation(&backup)?;
+        let policy = SwitchSettings::load(&self.preferences_dir)?.backups;
+        self.prune_backups(&policy)?;
+        Ok(path)
+    }
+
+    pub fn backups_dir(&self) -> PathBuf {
+        self.backup_dir.join(BACKUPS_DIR_NAME)
+    }
+
+    /// Keep a timestamped copy (with metadata) of every backup taken
+    fn add_to_rotation(&self, backup: &BackupConfig) -> Result<PathBuf> {
+        let mut created_at = backup.metadata.created_at.unwrap_or_else(Utc::now);
+        let mut path = self.rotation_path(created_at);
+        // Never overwrite another backup taken within the same millisecond
+        while path.exists() {
+            created_at += Duration::milliseconds(1);
+            path = self.rotation_path(created_at);
         }
 
-        fs::write(&self.token_file, token).context("Failed to save token")?;
+        fs::create_dir_all(self.backups_dir())?;
+        Self::write_rotated_backup(&path, backup)?;
+        Ok(path)
+    }
 
-        // Set restrictive permissions (600)
-        #[cfg(unix)]
+    fn write_rotated_backup(path: &Path, backup: &BackupConfig) -> Result<()> {
+        let content = serde_json::to_string_pretty(backup).map_err(|e| {
+            AppError::Config(format!(
+                "Failed to serialize backup: {}",
+                redacted_json_error(&e)
+            ))
+        })?;
+        let temp_path = path.with_extension("tmp");
+        fs::write(&temp_path, content)
+            .with_context(|| format!("Failed to write temp file: {}", temp_path.display()))?;
+        fs::rename(&temp_path, path)
+            .with_context(|| format!("Failed to rename temp file to: {}", path.display()))?;
+        Ok(())
+    }
+
+    pub fn load_rotated_backup(&self, path: &Path) -> Result<BackupConfig> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read backup: {}", path.display()))?;
+        let mut backup: BackupConfig = serde_json::from_str(&content).map_err(|e| {
+            AppError::Config(format!(
+                "Failed to parse {}: {}",
+                path.display(),
+                redacted_json_error(&e)
+            ))
+        })?;
+        backup.metadata = backup.metadata.migrate(Self::modified_at(path));
+        Ok(backup)
+    }
+
+    /// When `path` was last written, to date backups whose metadata doesn't say
+    fn modified_at(path: &Path) -> Option<DateTime<Utc>> {
+        fs::metadata(path).ok()?.modified().ok().map(DateTime::from)
+    }
+
+    /// A rotated backup by id (its timestamp) or by label
+    pub fn find_backup(&self, reference: &str) -> Result<(BackupEntry, BackupConfig)> {
+        let entries = self.list_backups()?;
+        if let Some(entry) = entries.iter().find(|entry| entry.id() == reference) {
+            let backup = self.load_rotated_backup(&entry.path)?;
+            return Ok((entry.clone(), backup));
+        }
+        for entry in entries {
+            // Unreadable backups can't carry the label we're looking for
+            if let Ok(backup) = self.load_rotated_backup(&entry.path) {
+                if backup.metadata.label.as_deref() == Some(reference) {
+                    return Ok((entry, backup));
+                }
+            }
+        }
+        Err(AppError::Config(format!("No backup with id or label '{}'", reference)).into())
+    }
+
+    /// A rotated backup by id, label or 1-based position in `list_backups`
+    /// (as numbered by `restore --select`). Unlike `find_backup` the backup
+    /// need not be readable.
+    pub fn find_backup_entry(&self, reference: &str) -> Result<BackupEntry> {
+        let entries = self.list_backups()?;
+        if let Some(entry) = entries.iter().find(|entry| entry.id() == reference) {
+            return Ok(entry.clone());
+        }
+        let labelled = entries.iter().find(|entry| {
+            self.load_rotated_backup(&entry.path)
+                .is_ok_and(|backup| backup.metadata.label.as_deref() == Some(reference))
+        });
+        let numbered = reference
+            .parse::<usize>()
+            .ok()
+            .and_then(|index| index.checked_sub(1))
+            .and_then(|index| entries.get(index));
+        labelled.or(numbered).cloned().ok_or_else(|| {
+            AppError::Config(format!("No backup with id, label or index '{}'", reference)).into()
+        })
+    }
+
+    /// Remove a rotated backup and its `.meta` sidecar, if it has one;
+    /// returns the files removed
+    pub fn delete_backup(&self, entry: &BackupEntry) -> Result<Vec<PathBuf>> {
+        let mut removed = Vec::new();
+        for path in [entry.path.clone(), entry.path.with_extension("meta")] {
+            match fs::remove_file(&path) {
+                Ok(()) => removed.push(path),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound && !removed.is_empty() => {}
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to remove {}", path.display()))
+                }
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Record `label` in the metadata of backup `id`. Labels are unique and
+    /// can't shadow another backup's id.
+    pub fn label_backup(&self, id: &str, label: &str) -> Result<PathBuf> {
+        let label = label.trim();
+        if label.is_empty() {
+            return Err(AppError::Config("Backup label cannot be empty".to_string()).into());
+        }
+
+        let entries = self.list_backups()?;
+        let entry = entries
+            .iter()
+            .find(|entry| entry.id() == id)
+            .ok_or_else(|| AppError::Config(format!("No backup with id '{}'", id)))?;
+
+        for other in entries.iter().filter(|other| other.path != entry.path) {
+            if other.id() == label {
+                return Err(AppError::Config(format!(
+                    "Label '{}' is the id of another backup",
+                    label
+                ))
+                .into());
+            }
+            if let Ok(backup) = self.load_rotated_backup(&other.path) {
+                if backup.metadata.label.as_deref() == Some(label) {
+                    return Err(AppError::Config(format!(
+                        "Label '{}' is already used by backup {}",
+                        label,
+                        other.id()
+                    ))
+                    .into());
+                }
+            }
+        }
+
+        let mut backup = self.load_rotated_backup(&entry.path)?;
+        backup.metadata.label = Some(label.to_string());
+        Self::write_rotated_backup(&entry.path, &backup)?;
+        Ok(entry.path.clone())
+    }
+
+    fn rotation_path(&self, created_at: DateTime<Utc>) -> PathBuf {
+        self.backups_dir().join(format!(
+            "settings-{}.json",
+            created_at.format(BACKUP_TIMESTAMP_FORMAT)
+        ))
+    }
+
+    /// Replace the primary backup with `config`, first snapshotting `previous`
+    /// into the rotation unless an identical copy is already there.
+    pub fn replace_backup(
+        &self,
+        previous: &BackupConfig,
+        config: &Config,
+        provider: &Provider,
+    ) -> Result<()> {
+        let already_rotated = previous
+            .metadata
+            .created_at
+            .is_some_and(|created_at| self.rotation_path(created_at).exists());
+        if !already_rotated {
+            self.add_to_rotation(previous)?;
+        }
+        self.create_backup_with_metadata(config, provider)
+    }
+
+    /// All rotated backups, newest first. Undatable files sort last.
+    pub fn list_backups(&self) -> Result<Vec<BackupEntry>> {
+        let dir = self.backups_dir();
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(&dir)
+            .with_context(|| format!("Failed to read directory: {}", dir.display()))?
         {
-            use std::os::unix::fs::PermissionsExt;
-            let mut perms = fs::metadata(&self.token_file)?.permissions();
-            perms.set_mode(0o600);
-            fs::set_permissions(&self.token_file, perms)?;
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let timestamp = Self::parse_backup_timestamp(&path);
+            entries.push(BackupEntry { path, timestamp });
         }
 
-        Ok(())
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.timestamp));
+        Ok(entries)
     }
 
-    pub fn load_saved_token(&self) -> Result<Option<String>> {
-        if !self.token_file.exists() {
-            return Ok(None);
+    fn parse_backup_timestamp(path: &Path) -> Option<DateTime<Utc>> {
+        let stem = path.file_stem()?.to_str()?;
+        let timestamp = stem.strip_prefix("settings-")?;
+        NaiveDateTime::parse_from_str(timestamp, BACKUP_TIMESTAMP_FORMAT)
+            .ok()
+            .map(|naive| naive.and_utc())
+    }
+
+    /// Remove rotated backups violating the count or age limit, oldest first.
+    ///
+    /// The newest backup is always kept, and files whose age can't be
+    /// determined are never removed. Returns the removed paths.
+    pub fn prune_backups(&self, policy: &RetentionPolicy) -> Result<Vec<PathBuf>> {
+        self.prune_backups_at(policy, Utc::now())
+    }
+
+    fn prune_backups_at(
+        &self,
+        policy: &RetentionPolicy,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<PathBuf>> {
+        let dir = self.backups_dir();
+        if !dir.exists() {
+            return Ok(Vec::new());
         }
 
-        let token = fs::read_to_string(&self.token_file)
-            .context("Failed to read saved token")?
-            .trim()
-            .to_string();
+        // Another claude-switch process is pruning; let it finish
+        let Some(_lock) = PruneLock::acquire(&dir.join(PRUNE_LOCK_NAME))? else {
+            return Ok(Vec::new());
+        };
 
-        if token.is_empty() {
-            return Ok(None);
+        let selection = self.select_for_pruning(policy, now)?;
+        for kept in Self::newly_kept(&dir, &selection.labelled) {
+            eprintln!(
+                "{}",
+                format!(
+                    "⚠️  Keeping backup '{}' ({}) beyond the retention policy because it is labelled",
+                    kept.label,
+                    kept.path.display()
+                )
+                .yellow()
+            );
         }
 
-        Ok(Some(token))
+        let mut removed = Vec::new();
+        for path in selection.prunable {
+            match fs::remove_file(&path) {
+                Ok(()) => removed.push(path),
+                // Already removed by a concurrent prune
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to remove {}", path.display()))
+                }
+            }
+        }
+
+        Ok(removed)
     }
 
-    pub fn remove_saved_token(&self) -> Result<()> {
-        if self.token_file.exists() {
-            fs::remove_file(&self.token_file).context("Failed to remove saved token")?;
+    /// Rotated backups `prune_backups` would remove right now
+    pub fn backups_to_prune(&self, policy: &RetentionPolicy) -> Result<Vec<PathBuf>> {
+        self.prunable_backups(policy, Utc::now())
+    }
+
+    fn prunable_backups(
+        &self,
+        policy: &RetentionPolicy,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<PathBuf>> {
+        Ok(self.select_for_pruning(policy, now)?.prunable)
+    }
+
+    /// The labelled backups kept past the limits that no earlier prune warned
+    /// about, remembering all of `labelled` in the backups directory so each
+    /// is only reported once
+    fn newly_kept<'a>(dir: &Path, labelled: &'a [LabelledBackup]) -> Vec<&'a LabelledBackup> {
+        let notice_file = dir.join(KEPT_LABELLED_NAME);
+        let warned = fs::read_to_string(&notice_file).unwrap_or_default();
+        let warned: HashSet<&str> = warned.lines().collect();
+        let name = |kept: &LabelledBackup| {
+            kept.path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        };
+
+        let newly_kept = labelled
+            .iter()
+            .filter(|kept| !warned.contains(name(kept).as_str()))
+            .collect();
+        // Best effort: at worst a warning is repeated
+        let _ = if labelled.is_empty() {
+            fs::remove_file(&notice_file)
+        } else {
+            let names: Vec<String> = labelled.iter().map(name).collect();
+            fs::write(&notice_file, names.join("\n") + "\n")
+        };
+        newly_kept
+    }
+
+    /// Backups over the retention limits, split into the ones to delete and
+    /// the labelled ones that are kept regardless
+    fn select_for_pruning(
+        &self,
+        policy: &RetentionPolicy,
+        now: DateTime<Utc>,
+    ) -> Result<PruneSelection> {
+        let dated: Vec<(PathBuf, DateTime<Utc>)> = self
+            .list_backups()?
+            .into_iter()
+            .filter_map(|entry| entry.timestamp.map(|ts| (entry.path, ts)))
+            .collect();
+
+        let mut prunable = Vec::new();
+        let mut labelled = Vec::new();
+        // Index 0 is the newest backup, which is always kept
+        for (index, (path, timestamp)) in dated.into_iter().enumerate().skip(1) {
+            let over_count = policy.max_count.is_some_and(|max| index >= max);
+            // Timestamps in the future (clock skew) count as brand new
+            let age = (now - timestamp).max(Duration::zero());
+            // An age limit too large for a Duration is no limit at all
+            let over_age = policy
+                .max_age_days
+                .and_then(|days| i64::try_from(days).ok())
+                .and_then(Duration::try_days)
+                .is_some_and(|max_age| age > max_age);
+
+            if over_count || over_age {
+                // Unreadable backups carry no label and are pruned as before
+                let label = self
+                    .load_rotated_backup(&path)
+                    .ok()
+                    .and_then(|backup| backup.metadata.label);
+                match label {
+                    Some(label) => labelled.push(LabelledBackup { path, label }),
+                    None => prunable.push(path),
+                }
+            }
         }
-        Ok(())
+        Ok(PruneSelection { prunable, labelled })
     }
 
-    pub fn backup_file(&self) -> &Path {
-        &self.backup_file
+    /// Every existing file holding claude-switch state: settings, backups,
+    /// tokens, history and stray temp files. Preferences (`claude-switch.toml`,
+    /// `providers.toml`) and the alias manifest are not included.
+    pub fn state_files(&self) -> Result<Vec<PathBuf>> {
+        let mut files: Vec<PathBuf> = [
+            self.settings_file.clone(),
+            self.backup_file.clone(),
+            self.backup_file.with_extension("meta"),
+            self.token_file.clone(),
+            self.history_file.clone(),
+            self.backups_dir().join(KEPT_LABELLED_NAME),
+        ]
+        .into_iter()
+        .chain(self.legacy_token_file.clone())
+        // settings.json may be a symlink left by `config-dir migrate`
+        .filter(|path| fs::symlink_metadata(path).is_ok_and(|meta| !meta.is_dir()))
+        .collect();
+        files.extend(
+            self.list_backups()?
+                .into_iter()
+                .filter(|entry| entry.timestamp.is_some())
+                .map(|entry| entry.path),
+        );
+        files.extend(self.stray_files()?);
+
+        files.sort();
+        files.dedup();
+        Ok(files)
+    }
+
+    /// Leftovers from interrupted writes: our own `*.tmp` files and a
+    /// `.meta` sidecar whose backup is gone.
+    ///
+    /// Only file names claude-switch writes are considered, since the config
+    /// directory is shared with Claude Code.
+    pub fn stray_files(&self) -> Result<Vec<PathBuf>> {
+        let mut stray = Vec::new();
+
+        let mut dirs = vec![self.config_dir.clone(), self.backup_dir.clone()];
+        dirs.dedup();
+        for dir in &dirs {
+            // settings.json -> settings.tmp, settings.json.backup/.meta -> settings.json.tmp
+            for name in ["settings.tmp", "settings.json.tmp"] {
+                let path = dir.join(name);
+                if path.is_file() {
+                    stray.push(path);
+                }
+            }
+        }
+
+        let backups_dir = self.backups_dir();
+        if backups_dir.is_dir() {
+            for entry in fs::read_dir(&backups_dir)
+                .with_context(|| format!("Failed to read directory: {}", backups_dir.display()))?
+            {
+                let path = entry?.path();
+                let is_rotation_temp = path.extension().and_then(|ext| ext.to_str()) == Some("tmp")
+                    && path
+                        .file_name()
+                        .and_then(|name| name.to_str())
+                        .is_some_and(|name| name.starts_with("settings-"));
+                if is_rotation_temp && path.is_file() {
+                    stray.push(path);
+                }
+            }
+        }
+
+        let metadata_path = self.backup_file.with_extension("meta");
+        if metadata_path.is_file() && !self.backup_file.exists() {
+            stray.push(metadata_path);
+        }
+
+        stray.sort();
+        Ok(stray)
+    }
+
+    pub fn save_token(&self, token: &str) -> Result<()> {
+        if let Some(parent) = self.token_file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        self.migrate_legacy_token_or_warn();
+
+        // Always written without a trailing newline
+        fs::write(&self.token_file, token.trim()).context("Failed to save token")?;
+
+        // Set restrictive permissions (600)
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mut perms = fs::metadata(&self.token_file)?.permissions();
+            perms.set_mode(0o600);
+            fs::set_permissions(&self.token_file, perms)?;
+        }
+
+        // Windows has no mode bits: replace the inherited ACL with one for the current user
+        #[cfg(windows)]
+        if let Err(e) = restrict_to_current_user(&self.token_file) {
+            eprintln!(
+                "{}{}",
+                "⚠️  Token file may be readable by other users: ".yellow(),
+                e
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Move `.z_ai_token` to the provider-neutral name, keeping its permissions.
+    ///
+    /// Returns whether a file was migrated.
+    pub fn migrate_legacy_token(&self) -> Result<bool> {
+        let Some(legacy_token_file) = &self.legacy_token_file else {
+            return Ok(false);
+        };
+        if self.token_file.exists() || !legacy_token_file.is_file() {
+            return Ok(false);
+        }
+
+        fs::rename(legacy_token_file, &self.token_file).with_context(|| {
+            format!(
+                "Failed to rename {} to {}",
+                legacy_token_file.display(),
+                self.token_file.display()
+            )
+        })?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&self.token_file, fs::Permissions::from_mode(0o600))?;
+        }
+
+        Ok(true)
+    }
+
+    /// Migrate on the way to a write, so read-only commands and
+    /// `--print-only` leave the config directory alone. A failed migration
+    /// is not fatal: load_saved_token falls back to the legacy file.
+    fn migrate_legacy_token_or_warn(&self) {
+        if let Err(e) = self.migrate_legacy_token() {
+            eprintln!(
+                "{}{}",
+                "⚠️  Failed to migrate legacy token file: ".yellow(),
+                e
+            );
+        }
+    }
+
+    pub fn load_saved_token(&self) -> Result<Option<String>> {
+        let Some(token_file) = self.saved_token_path() else {
+            return Ok(None);
+        };
+
+        if let Some(problem) = self.token_file_permission_problem() {
+            eprintln!("{}", format!("⚠️  {}", problem).yellow());
+            eprintln!("{}", "   Fix: claude-switch doctor --fix-perms".yellow());
+        }
+
+        let raw = fs::read_to_string(token_file).context("Failed to read saved token")?;
+        let token = Self::normalize_token(&raw);
+
+        if token.is_empty() {
+            return Ok(None);
+        }
+
+        if token != raw {
+            eprintln!(
+                "{}{}",
+                "⚠️  Normalized whitespace/quotes in token file: ".yellow(),
+                token_file.display()
+            );
+        }
+
+        Ok(Some(token))
+    }
+
+    /// The token file `load_saved_token` reads, if there is one
+    fn saved_token_path(&self) -> Option<&Path> {
+        std::iter::once(&self.token_file)
+            .chain(&self.legacy_token_file)
+            .find(|path| path.exists())
+            .map(PathBuf::as_path)
+    }
+
+    /// Whole days since the saved token file was last written
+    pub fn saved_token_age_days(&self, now: SystemTime) -> Option<u64> {
+        let modified = fs::metadata(self.saved_token_path()?)
+            .ok()?
+            .modified()
+            .ok()?;
+        Some(now.duration_since(modified).unwrap_or_default().as_secs() / 86_400)
+    }
+
+    /// A reminder to rotate the saved token once it is older than
+    /// `max_token_age_days` from `claude-switch.toml`
+    pub fn stale_token_warning(&self, now: SystemTime) -> Result<Option<String>> {
+        let Some(max_days) = SwitchSettings::load(&self.preferences_dir)?.max_token_age_days else {
+            return Ok(None);
+        };
+        Ok(self
+            .saved_token_age_days(now)
+            .filter(|age| *age > max_days)
+            .map(|age| {
+                format!(
+                    "Saved token is {} days old (max_token_age_days = {}) and may be stale",
+                    age, max_days
+                )
+            }))
+    }
+
+    /// Why the saved token file may be readable by others (group or world
+    /// bits set on Unix; on Windows inherited or other users' ACL entries,
+    /// e.g. a hand-made or copied file); `None` when it is private or absent
+    pub fn token_file_permission_problem(&self) -> Option<String> {
+        let path = self.saved_token_path()?;
+        let detail = Self::loose_permissions(path)?;
+        Some(format!(
+            "Token file {} may be readable by other users ({})",
+            path.display(),
+            detail
+        ))
+    }
+
+    /// Tighten the saved token file to 0600 (on Windows, an ACL for the
+    /// current user only); the file fixed, if it needed it
+    pub fn fix_token_file_permissions(&self) -> Result<Option<PathBuf>> {
+        let Some(path) = self.saved_token_path() else {
+            return Ok(None);
+        };
+        if Self::loose_permissions(path).is_none() {
+            return Ok(None);
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600)).map_err(|e| {
+                AppError::FileSystem(format!("Failed to chmod {}: {}", path.display(), e))
+            })?;
+        }
+        #[cfg(windows)]
+        restrict_to_current_user(path)?;
+        Ok(Some(path.to_path_buf()))
+    }
+
+    /// Why the saved token file needs normalizing (stray whitespace or
+    /// quotes around the token); `None` when it holds just the token, with
+    /// or without the trailing newline editors add
+    pub fn token_file_format_problem(&self) -> Option<String> {
+        let path = self.saved_token_path()?;
+        let raw = fs::read_to_string(path).ok()?;
+        Self::misformatted_token(&raw)?;
+        Some(format!(
+            "Token file {} has whitespace or quotes around the token",
+            path.display()
+        ))
+    }
+
+    /// The token in `raw` when more than a single trailing newline surrounds it
+    fn misformatted_token(raw: &str) -> Option<String> {
+        let token = Self::normalize_token(raw);
+        let line = raw
+            .strip_suffix('\n')
+            .map(|line| line.strip_suffix('\r').unwrap_or(line))
+            .unwrap_or(raw);
+        (!token.is_empty() && token != line).then_some(token)
+    }
+
+    /// Rewrite the saved token file with just the token, keeping its mode;
+    /// the file fixed, if it needed it
+    pub fn normalize_token_file(&self) -> Result<Option<PathBuf>> {
+        let Some(path) = self.saved_token_path() else {
+            return Ok(None);
+        };
+        let raw = fs::read_to_string(path).context("Failed to read saved token")?;
+        let Some(token) = Self::misformatted_token(&raw) else {
+            return Ok(None);
+        };
+        fs::write(path, token).map_err(|e| {
+            AppError::FileSystem(format!("Failed to rewrite {}: {}", path.display(), e))
+        })?;
+        Ok(Some(path.to_path_buf()))
+    }
+
+    #[cfg(unix)]
+    fn loose_permissions(path: &Path) -> Option<String> {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(path).ok()?.permissions().mode() & 0o777;
+        (mode & 0o077 != 0).then(|| format!("mode {:04o}, expected 0600", mode))
+    }
+
+    /// Entries of the file's ACL beyond a non-inherited one for the current
+    /// user; a file whose ACL can't be read counts as unverified, not private
+    #[cfg(windows)]
+    fn loose_permissions(path: &Path) -> Option<String> {
+        let unverified = || Some("permissions could not be verified".to_string());
+        let Ok(user) = std::env::var("USERNAME") else {
+            return unverified();
+        };
+        let output = match std::process::Command::new("icacls").arg(path).output() {
+            Ok(output) if output.status.success() => output,
+            _ => return unverified(),
+        };
+        let listing = String::from_utf8_lossy(&output.stdout);
+        let loose = icacls_loose_entries(&listing, path, &user);
+        (!loose.is_empty())
+            .then(|| format!("ACL grants {}, expected only {}", loose.join(", "), user))
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    fn loose_permissions(_path: &Path) -> Option<String> {
+        None
+    }
+
+    /// Clean up hand-written token files: CRLF line endings, surrounding
+    /// whitespace and a single pair of matching quotes.
+    fn normalize_token(raw: &str) -> String {
+        let token = raw.replace("\r\n", "\n");
+        let token = token.trim();
+        let token = ['"', '\'']
+            .iter()
+            .find_map(|quote| {
+                token
+                    .strip_prefix(*quote)
+                    .and_then(|rest| rest.strip_suffix(*quote))
+            })
+            .unwrap_or(token);
+        token.trim().to_string()
+    }
+
+    pub fn remove_saved_token(&self) -> Result<()> {
+        for token_file in std::iter::once(&self.token_file).chain(&self.legacy_token_file) {
+            if token_file.exists() {
+                fs::remove_file(token_file).context("Failed to remove saved token")?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn backup_file(&self) -> &Path {
+        &self.backup_file
+    }
+
+    /// Move every managed file into `new_dir` and record the new location in
+    /// `~/.claude`, however often the config has moved before.
+    ///
+    /// Claude Code keeps reading `~/.claude/settings.json`: on Unix it becomes
+    /// a symlink to the moved file, elsewhere settings.json is copied rather
+    /// than moved. Returns the files that were moved.
+    pub fn migrate_to(&self, new_dir: &Path) -> Result<Vec<PathBuf>> {
+        if new_dir == self.config_dir {
+            return Err(AppError::Config(format!(
+                "Config is already stored in {}",
+                new_dir.display()
+            ))
+            .into());
+        }
+
+        fs::create_dir_all(new_dir)
+            .with_context(|| format!("Failed to create directory: {}", new_dir.display()))?;
+
+        let mut managed = vec![
+            self.settings_file.clone(),
+            self.backup_file.clone(),
+            self.backup_file.with_extension("meta"),
+            self.token_file.clone(),
+            self.config_dir.join(crate::config::SETTINGS_FILE_NAME),
+            self.providers_file.clone(),
+            self.history_file.clone(),
+            self.aliases_file.clone(),
+        ];
+        managed.extend(self.legacy_token_file.clone());
+        managed.extend(self.list_backups()?.into_iter().map(|entry| entry.path));
+
+        // Backups kept in a separate --backup-dir stay where they are
+        managed.retain(|path| path.starts_with(&self.config_dir));
+
+        let mut moved = Vec::new();
+        for source in managed.iter().filter(|path| path.is_file()) {
+            let relative = source
+                .strip_prefix(&self.config_dir)
+                .map_err(|_| anyhow::anyhow!("Invalid file path: {}", source.display()))?;
+            let target = new_dir.join(relative);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            if *source == self.settings_file && cfg!(not(unix)) {
+                fs::copy(source, &target).with_context(|| {
+                    format!(
+                        "Failed to copy {} to {}",
+                        source.display(),
+                        target.display()
+                    )
+                })?;
+            } else {
+                Self::move_file(source, &target)?;
+            }
+            moved.push(target);
+        }
+
+        #[cfg(unix)]
+        if new_dir.join("settings.json").exists() {
+            let link = self.home_dir.join("settings.json");
+            // A link left by an earlier migration now points at the moved file
+            if fs::symlink_metadata(&link).is_ok_and(|meta| meta.file_type().is_symlink()) {
+                fs::remove_file(&link)
+                    .with_context(|| format!("Failed to remove {}", link.display()))?;
+            }
+            if !link.exists() {
+                fs::create_dir_all(&self.home_dir)?;
+                std::os::unix::fs::symlink(new_dir.join("settings.json"), &link)
+                    .with_context(|| format!("Failed to link {}", link.display()))?;
+            }
+        }
+
+        fs::create_dir_all(&self.home_dir)?;
+        fs::write(
+            self.home_dir.join(CONFIG_DIR_POINTER),
+            new_dir.to_string_lossy().as_bytes(),
+        )
+        .context("Failed to record new config directory")?;
+
+        Ok(moved)
+    }
+
+    fn move_file(source: &Path, target: &Path) -> Result<()> {
+        // rename keeps permissions; fall back to copy (which also copies mode bits)
+        // when the target lives on another filesystem
+        if fs::rename(source, target).is_err() {
+            fs::copy(source, target).with_context(|| {
+                format!(
+                    "Failed to copy {} to {}",
+                    source.display(),
+                    target.display()
+                )
+            })?;
+            fs::remove_file(source)
+                .with_context(|| format!("Failed to remove {}", source.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Exclusive marker file held while pruning; stale locks are broken after a minute
+struct PruneLock {
+    path: PathBuf,
+}
+
+impl PruneLock {
+    fn acquire(path: &Path) -> Result<Option<Self>> {
+        for _ in 0..2 {
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(path)
+            {
+                Ok(_) => {
+                    return Ok(Some(Self {
+                        path: path.to_path_buf(),
+                    }))
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    let stale = fs::metadata(path)
+                        .and_then(|meta| meta.modified())
+                        .ok()
+                        .and_then(|modified| modified.elapsed().ok())
+                        .is_some_and(|age| age > std::time::Duration::from_secs(60));
+                    if !stale {
+                        return Ok(None);
+                    }
+                    let _ = fs::remove_file(path);
+                }
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("Failed to create lock: {}", path.display()))
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl Drop for PruneLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Drop inherited ACEs from `path` and grant full access to the current user only
+#[cfg(windows)]
+fn restrict_to_current_user(path: &Path) -> Result<()> {
+    let user = std::env::var("USERNAME")
+        .map_err(|_| AppError::FileSystem("USERNAME is not set".to_string()))?;
+    let output = std::process::Command::new("icacls")
+        .args(icacls_restrict_args(path, &user))
+        .output()
+        .context("Failed to run icacls")?;
+    if !output.status.success() {
+        // icacls reports errors on stderr; stdout only if that is empty
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = match stderr.trim() {
+            "" => String::from_utf8_lossy(&output.stdout).trim().to_string(),
+            stderr => stderr.to_string(),
+        };
+        return Err(AppError::FileSystem(format!("icacls failed: {}", message)).into());
+    }
+    Ok(())
+}
+
+/// The ACEs in `icacls <path>` output that are inherited or name anyone but
+/// `user`, as printed (e.g. `BUILTIN\Users:(I)(RX)`)
+#[cfg(any(windows, test))]
+fn icacls_loose_entries(listing: &str, path: &Path, user: &str) -> Vec<String> {
+    let path = path.to_string_lossy();
+    listing
+        .lines()
+        .map(|line| line.strip_prefix(path.as_ref()).unwrap_or(line).trim())
+        .take_while(|entry| !entry.is_empty())
+        .filter(|entry| {
+            let Some((principal, rights)) = entry.split_once(":(") else {
+                return false;
+            };
+            let name = principal.rsplit('\\').next().unwrap_or(principal);
+            rights.contains("I)") || !name.eq_ignore_ascii_case(user)
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(any(windows, test))]
+fn icacls_restrict_args(path: &Path, user: &str) -> Vec<std::ffi::OsString> {
+    vec![
+        path.as_os_str().to_os_string(),
+        "/inheritance:r".into(),
+        "/grant:r".into(),
+        format!("{}:F", user).into(),
+    ]
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::config::{BACKUP_METADATA_VERSION, UNVERSIONED_BACKUP};
+    use chrono::SubsecRound;
+
+    /// A manager on `dir` whose settings.json holds `settings`
+    pub(crate) fn manager_with(dir: &Path, settings: &str) -> ConfigManager {
+        fs::write(dir.join("settings.json"), settings).unwrap();
+        ConfigManager::with_config_dir(dir.to_path_buf())
+    }
+
+    /// Backup file names only keep milliseconds
+    fn now() -> DateTime<Utc> {
+        Utc::now().trunc_subsecs(3)
+    }
+
+    fn migrated_backups(dir: &Path) -> usize {
+        ConfigManager::with_config_dir(dir.to_path_buf())
+            .list_backups()
+            .unwrap()
+            .len()
+    }
+
+    #[test]
+    fn test_label_backup_must_be_unique() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        let first = manager
+            .backup_to_rotation(&Config::default(), &Provider::Anthropic)
+            .unwrap();
+        manager
+            .backup_to_rotation(&Config::default(), &Provider::GLM)
+            .unwrap();
+        let entries = manager.list_backups().unwrap();
+        let (newest, oldest) = (entries[0].id(), entries[1].id());
+        assert_eq!(entries[1].path, first);
+
+        manager.label_backup(&oldest, "good-anthropic").unwrap();
+        let (found, backup) = manager.find_backup("good-anthropic").unwrap();
+        assert_eq!(found.path, first);
+        assert_eq!(backup.metadata.provider, "anthropic");
+        assert_eq!(backup.metadata.label.as_deref(), Some("good-anthropic"));
+
+        // Relabelling the same backup is fine, reusing the label elsewhere is not
+        manager.label_backup(&oldest, "good-anthropic").unwrap();
+        assert!(manager.label_backup(&newest, "good-anthropic").is_err());
+        assert!(manager.label_backup(&newest, &oldest).is_err());
+        assert!(manager.label_backup(&newest, " ").is_err());
+        assert!(manager.label_backup("20000101T000000000Z", "x").is_err());
+        assert!(manager.find_backup("missing").is_err());
+    }
+
+    #[test]
+    fn test_icacls_args_grant_only_current_user() {
+        let args = icacls_restrict_args(Path::new(r"C:\Users\me\.claude\token"), "me");
+        assert_eq!(
+            args,
+            [
+                r"C:\Users\me\.claude\token",
+                "/inheritance:r",
+                "/grant:r",
+                "me:F"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_icacls_loose_entries() {
+        let path = Path::new(r"C:\Users\me\.claude\token");
+        let private = "C:\\Users\\me\\.claude\\token DESKTOP-1\\me:(F)\r\n\r\n\
+                       Successfully processed 1 files; Failed processing 0 files\r\n";
+        assert!(icacls_loose_entries(private, path, "ME").is_empty());
+
+        let inherited = "C:\\Users\\me\\.claude\\token NT AUTHORITY\\SYSTEM:(I)(F)\n\
+                         \x20                         BUILTIN\\Users:(I)(RX)\n\
+                         \x20                         DESKTOP-1\\me:(I)(F)\n\n\
+                         Successfully processed 1 files; Failed processing 0 files\n";
+        assert_eq!(
+            icacls_loose_entries(inherited, path, "me"),
+            [
+                r"NT AUTHORITY\SYSTEM:(I)(F)",
+                r"BUILTIN\Users:(I)(RX)",
+                r"DESKTOP-1\me:(I)(F)"
+            ]
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_saved_token_acl_is_restricted() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        manager.save_token("sk-acl").unwrap();
+
+        let output = std::process::Command::new("icacls")
+            .arg(dir.path().join(TOKEN_FILE_NAME))
+            .output()
+            .unwrap();
+        let acl = String::from_utf8_lossy(&output.stdout);
+        assert!(acl.contains(&std::env::var("USERNAME").unwrap()));
+        assert!(!acl.contains("BUILTIN\\Users"));
+        assert!(!acl.contains("Everyone"));
+        assert_eq!(manager.token_file_permission_problem(), None);
+    }
+
+    #[test]
+    fn test_prune_keeps_labelled_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        for _ in 0..3 {
+            manager
+                .backup_to_rotation(&Config::default(), &Provider::Anthropic)
+                .unwrap();
+        }
+        let oldest = manager.list_backups().unwrap()[2].clone();
+        manager.label_backup(&oldest.id(), "keep-me").unwrap();
+
+        let policy = RetentionPolicy {
+            max_count: Some(1),
+            max_age_days: None,
+        };
+        let removed = manager.prune_backups(&policy).unwrap();
+        assert_eq!(removed.len(), 1);
+        assert!(!removed.contains(&oldest.path));
+
+        let remaining = manager.list_backups().unwrap();
+        assert_eq!(remaining.len(), 2);
+        assert!(oldest.path.exists());
+        assert!(manager.backups_to_prune(&policy).unwrap().is_empty());
+
+        // The kept backup is reported by the first prune only
+        let dir = manager.backups_dir();
+        let kept = [LabelledBackup {
+            path: oldest.path.clone(),
+            label: "keep-me".to_string(),
+        }];
+        assert!(ConfigManager::newly_kept(&dir, &kept).is_empty());
+        assert!(ConfigManager::newly_kept(&dir, &[]).is_empty());
+        assert!(!dir.join(KEPT_LABELLED_NAME).exists());
+        assert_eq!(ConfigManager::newly_kept(&dir, &kept).len(), 1);
+    }
+
+    #[test]
+    fn test_config_errors_never_contain_the_token() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        let token = "sk-ant-REDACTED";
+        let mut config = Config::default();
+        config
+            .env
+            .insert("ANTHROPIC_AUTH_TOKEN".to_string(), token.to_string());
+
+        // A file where the settings directory should be makes the save fail
+        let blocker = dir.path().join("blocked");
+        fs::write(&blocker, "").unwrap();
+        let save_error = manager
+            .save_config_atomic(&blocker.join("settings.json"), &config)
+            .unwrap_err();
+
+        // A token where the env map belongs makes the load fail
+        let settings = dir.path().join("settings.json");
+        fs::write(&settings, format!(r#"{{"env": "{}"}}"#, token)).unwrap();
+        let load_error = manager.load_config(&settings).unwrap_err();
+
+        for error in [save_error, load_error] {
+            for text in [format!("{:#}", error), format!("{:?}", error)] {
+                assert!(!text.contains("do-not-leak"), "{}", text);
+            }
+        }
+    }
+
+    #[test]
+    fn test_load_config_waits_for_pending_rename() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        let settings = dir.path().join("settings.json");
+        let temp = dir.path().join("settings.tmp");
+        fs::write(&temp, r#"{"env": {"DEBUG": "1"}}"#).unwrap();
+
+        // Writer is between writing the temp file and renaming it into place
+        let writer = std::thread::spawn({
+            let (temp, settings) = (temp.clone(), settings.clone());
+            move || {
+                std::thread::sleep(LOAD_RETRY_DELAY);
+                fs::rename(temp, settings).unwrap();
+            }
+        });
+        let config = manager.load_current_config().unwrap();
+        writer.join().unwrap();
+
+        assert_eq!(config.env["DEBUG"], "1");
+    }
+
+    #[test]
+    fn test_load_config_missing_without_temp_is_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        assert!(manager.load_current_config().unwrap().env.is_empty());
+    }
+
+    #[test]
+    fn test_migrate_moves_all_managed_files() {
+        let home = tempfile::tempdir().unwrap();
+        let legacy_dir = home.path().join(".claude");
+        let xdg_dir = home.path().join("xdg").join("claude-switch");
+
+        let manager = ConfigManager::with_config_dir(legacy_dir.clone());
+        let mut config = Config::default();
+        config
+            .env
+            .insert("ANTHROPIC_AUTH_TOKEN".to_string(), "token".to_string());
+        manager.save_current_config(&config).unwrap();
+        manager
+            .create_backup_with_metadata(&config, &Provider::Anthropic)
+            .unwrap();
+        manager.save_token("sk-test").unwrap();
+
+        let moved = manager.migrate_to(&xdg_dir).unwrap();
+        // settings, backup, metadata sidecar, token and the rotated backup copy
+        assert_eq!(moved.len(), 5);
+        assert_eq!(migrated_backups(&xdg_dir), 1);
+
+        for name in [
+            "settings.json",
+            "settings.json.backup",
+            "settings.json.meta",
+            ".claude-switch-token",
+        ] {
+            assert!(xdg_dir.join(name).is_file(), "{} was not moved", name);
+        }
+        assert!(!legacy_dir.join(".claude-switch-token").exists());
+        assert!(!legacy_dir.join("settings.json.backup").exists());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(xdg_dir.join(".claude-switch-token"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+            assert!(fs::symlink_metadata(legacy_dir.join("settings.json"))
+                .unwrap()
+                .file_type()
+                .is_symlink());
+        }
+
+        let pointer = fs::read_to_string(legacy_dir.join(CONFIG_DIR_POINTER)).unwrap();
+        assert_eq!(PathBuf::from(pointer), xdg_dir);
+
+        let migrated = ConfigManager::with_config_dir(xdg_dir.clone());
+        assert_eq!(
+            migrated.load_saved_token().unwrap().as_deref(),
+            Some("sk-test")
+        );
+        assert_eq!(
+            migrated
+                .load_current_config()
+                .unwrap()
+                .env
+                .get("ANTHROPIC_AUTH_TOKEN"),
+            Some(&"token".to_string())
+        );
+
+        // Moving again still records the pointer and link in ~/.claude
+        let second_dir = home.path().join("elsewhere");
+        let migrated = ConfigManager {
+            home_dir: legacy_dir.clone(),
+            ..ConfigManager::with_config_dir(xdg_dir.clone())
+        };
+        migrated.migrate_to(&second_dir).unwrap();
+        let pointer = fs::read_to_string(legacy_dir.join(CONFIG_DIR_POINTER)).unwrap();
+        assert_eq!(PathBuf::from(pointer), second_dir);
+        assert!(!xdg_dir.join(CONFIG_DIR_POINTER).exists());
+        assert!(second_dir.join(".claude-switch-token").is_file());
+        #[cfg(unix)]
+        assert_eq!(
+            fs::read_link(legacy_dir.join("settings.json")).unwrap(),
+            second_dir.join("settings.json")
+        );
+        // Without symlinks Claude Code still finds its settings
+        assert!(legacy_dir.join("settings.json").is_file());
+    }
+
+    #[test]
+    fn test_load_saved_token_normalizes_hand_written_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+
+        for content in [
+            "sk-clean\r\n",
+            "\"sk-clean\"",
+            "'sk-clean'\n",
+            "  sk-clean\n\n",
+        ] {
+            fs::write(&manager.token_file, content).unwrap();
+            assert_eq!(
+                manager.load_saved_token().unwrap().as_deref(),
+                Some("sk-clean"),
+                "failed for {:?}",
+                content
+            );
+        }
+    }
+
+    #[test]
+    fn test_save_token_has_no_trailing_newline() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+
+        manager.save_token("sk-clean\n").unwrap();
+        assert_eq!(fs::read_to_string(&manager.token_file).unwrap(), "sk-clean");
+    }
+
+    #[test]
+    fn test_a_trailing_newline_alone_is_not_a_format_problem() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        for content in ["sk-token", "sk-token\n", "sk-token\r\n"] {
+            fs::write(manager.token_file(), content).unwrap();
+            assert_eq!(manager.token_file_format_problem(), None, "{:?}", content);
+            assert_eq!(manager.normalize_token_file().unwrap(), None);
+            assert_eq!(fs::read_to_string(manager.token_file()).unwrap(), content);
+        }
+        for content in ["sk-token\n\n", " sk-token\n", "\"sk-token\"\n"] {
+            fs::write(manager.token_file(), content).unwrap();
+            assert!(
+                manager.token_file_format_problem().is_some(),
+                "{:?}",
+                content
+            );
+        }
+    }
+
+    #[test]
+    fn test_token_file_override_is_used_for_load_save_and_clear() {
+        let dir = tempfile::tempdir().unwrap();
+        let token_file = dir.path().join("project/secrets/glm-token");
+        let manager = ConfigManager::with_config_dir(dir.path().join("claude"))
+            .with_token_file(token_file.clone());
+        assert_eq!(manager.legacy_token_file, None);
+        // A legacy file in the config directory is neither read nor migrated
+        let legacy = dir.path().join("claude").join(LEGACY_TOKEN_FILE_NAME);
+        fs::create_dir_all(legacy.parent().unwrap()).unwrap();
+        fs::write(&legacy, "sk-legacy").unwrap();
+        assert_eq!(manager.load_saved_token().unwrap(), None);
+
+        manager.save_token("sk-project\n").unwrap();
+        assert_eq!(fs::read_to_string(&token_file).unwrap(), "sk-project");
+        assert!(!dir.path().join("claude").join(TOKEN_FILE_NAME).exists());
+        assert_eq!(
+            manager.load_saved_token().unwrap().as_deref(),
+            Some("sk-project")
+        );
+        assert!(!manager.migrate_legacy_token().unwrap());
+
+        manager.remove_saved_token().unwrap();
+        assert!(!token_file.exists());
+        assert_eq!(manager.load_saved_token().unwrap(), None);
+        assert!(legacy.exists());
+    }
+
+    #[test]
+    fn test_legacy_token_file_is_migrated() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        fs::write(manager.legacy_token_file.as_ref().unwrap(), "sk-legacy").unwrap();
+
+        assert!(manager.migrate_legacy_token().unwrap());
+        assert!(!manager.legacy_token_file.as_ref().unwrap().exists());
+        assert_eq!(
+            fs::read_to_string(&manager.token_file).unwrap(),
+            "sk-legacy"
+        );
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&manager.token_file)
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // Nothing left to migrate
+        assert!(!manager.migrate_legacy_token().unwrap());
+    }
+
+    #[test]
+    fn test_legacy_token_is_migrated_on_write_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        fs::write(manager.legacy_token_file.as_ref().unwrap(), "sk-legacy").unwrap();
+
+        assert_eq!(
+            manager.load_saved_token().unwrap().as_deref(),
+            Some("sk-legacy")
+        );
+        assert!(manager.legacy_token_file.as_ref().unwrap().exists());
+
+        manager.save_current_config(&Config::default()).unwrap();
+        assert!(!manager.legacy_token_file.as_ref().unwrap().exists());
+        assert_eq!(
+            fs::read_to_string(&manager.token_file).unwrap(),
+            "sk-legacy"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_world_readable_token_file_is_reported_and_fixed() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        manager.save_token("sk-private").unwrap();
+        assert_eq!(manager.token_file_permission_problem(), None);
+        assert_eq!(manager.fix_token_file_permissions().unwrap(), None);
+
+        // Copied in by hand
+        fs::set_permissions(manager.token_file(), fs::Permissions::from_mode(0o644)).unwrap();
+        let problem = manager.token_file_permission_problem().unwrap();
+        assert!(problem.contains("mode 0644"), "{}", problem);
+        assert_eq!(
+            manager.load_saved_token().unwrap().as_deref(),
+            Some("sk-private")
+        );
+
+        assert_eq!(
+            manager.fix_token_file_permissions().unwrap().as_deref(),
+            Some(manager.token_file())
+        );
+        let mode = fs::metadata(manager.token_file())
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(manager.token_file_permission_problem(), None);
+    }
+
+    #[test]
+    fn test_stale_token_warning_follows_token_mtime() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        manager.save_token("sk-rotating").unwrap();
+        let now = SystemTime::now();
+        let set_age = |days: u64| {
+            fs::File::options()
+                .write(true)
+                .open(manager.token_file())
+                .unwrap()
+                .set_modified(now - std::time::Duration::from_secs(days * 86_400))
+                .unwrap();
+        };
+
+        // No limit configured
+        set_age(400);
+        assert_eq!(manager.stale_token_warning(now).unwrap(), None);
+
+        fs::write(
+            dir.path().join(crate::config::SETTINGS_FILE_NAME),
+            "max_token_age_days = 90\n",
+        )
+        .unwrap();
+        let warning = manager.stale_token_warning(now).unwrap().unwrap();
+        assert!(warning.contains("400 days old"), "{}", warning);
+
+        set_age(10);
+        assert_eq!(manager.saved_token_age_days(now), Some(10));
+        assert_eq!(manager.stale_token_warning(now).unwrap(), None);
+    }
+
+    #[test]
+    fn test_load_saved_token_falls_back_to_legacy_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        fs::write(manager.legacy_token_file.as_ref().unwrap(), "sk-legacy").unwrap();
+
+        assert_eq!(
+            manager.load_saved_token().unwrap().as_deref(),
+            Some("sk-legacy")
+        );
+
+        // The new file takes priority once it exists
+        manager.save_token("sk-new").unwrap();
+        assert_eq!(
+            manager.load_saved_token().unwrap().as_deref(),
+            Some("sk-new")
+        );
+
+        manager.remove_saved_token().unwrap();
+        assert!(manager.load_saved_token().unwrap().is_none());
+    }
+
+    fn seed_backups(manager: &ConfigManager, now: DateTime<Utc>, ages_in_days: &[i64]) {
+        fs::create_dir_all(manager.backups_dir()).unwrap();
+        for days in ages_in_days {
+            let timestamp = now - Duration::days(*days);
+            let name = format!(
+                "settings-{}.json",
+                timestamp.format(BACKUP_TIMESTAMP_FORMAT)
+            );
+            fs::write(manager.backups_dir().join(name), "{}").unwrap();
+        }
+    }
+
+    fn remaining_ages(manager: &ConfigManager, now: DateTime<Utc>) -> Vec<i64> {
+        manager
+            .list_backups()
+            .unwrap()
+            .iter()
+            .filter_map(|entry| entry.timestamp.map(|ts| (now - ts).num_days()))
+            .collect()
+    }
+
+    #[test]
+    fn test_prune_by_count() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        let now = now();
+        seed_backups(&manager, now, &[0, 1, 2, 3, 4]);
+
+        let policy = RetentionPolicy {
+            max_count: Some(2),
+            max_age_days: None,
+        };
+        let removed = manager.prune_backups_at(&policy, now).unwrap();
+        assert_eq!(removed.len(), 3);
+        assert_eq!(remaining_ages(&manager, now), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_prune_by_age_keeps_newest() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        let now = now();
+        seed_backups(&manager, now, &[40, 50, 60]);
+        // Undatable files are never removed
+        fs::write(manager.backups_dir().join("hand-made.json"), "{}").unwrap();
+
+        let policy = RetentionPolicy {
+            max_count: None,
+            max_age_days: Some(30),
+        };
+        manager.prune_backups_at(&policy, now).unwrap();
+        assert_eq!(remaining_ages(&manager, now), vec![40]);
+        assert!(manager.backups_dir().join("hand-made.json").exists());
+    }
+
+    #[test]
+    fn test_prune_with_huge_age_limit_keeps_everything() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        let now = now();
+        seed_backups(&manager, now, &[0, 400, 4000]);
+
+        for max_age_days in [u64::MAX, i64::MAX as u64] {
+            let policy = RetentionPolicy {
+                max_count: None,
+                max_age_days: Some(max_age_days),
+            };
+            assert!(manager.prune_backups_at(&policy, now).unwrap().is_empty());
+        }
+        assert_eq!(remaining_ages(&manager, now), vec![0, 400, 4000]);
+    }
+
+    #[test]
+    fn test_prune_by_count_and_age() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        let now = now();
+        // One backup is in the future (clock skew) and must not be aged out
+        seed_backups(&manager, now, &[-2, 1, 5, 20, 45]);
+
+        let policy = RetentionPolicy {
+            max_count: Some(4),
+            max_age_days: Some(10),
+        };
+        manager.prune_backups_at(&policy, now).unwrap();
+        assert_eq!(remaining_ages(&manager, now), vec![-2, 1, 5]);
+    }
+
+    #[test]
+    fn test_backup_creation_adds_to_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+
+        manager
+            .create_backup_with_metadata(&Config::default(), &Provider::Anthropic)
+            .unwrap();
+
+        let backups = manager.list_backups().unwrap();
+        assert_eq!(backups.len(), 1);
+        let content = fs::read_to_string(&backups[0].path).unwrap();
+        let backup: BackupConfig = serde_json::from_str(&content).unwrap();
+        assert_eq!(backup.metadata.provider, "anthropic");
+    }
+
+    #[test]
+    fn test_legacy_backup_is_dated_by_its_mtime() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        fs::write(
+            manager.backup_file(),
+            r#"{"env": {"ANTHROPIC_AUTH_TOKEN": "old"}}"#,
+        )
+        .unwrap();
+        let written = DateTime::parse_from_rfc3339("2023-05-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        fs::File::options()
+            .write(true)
+            .open(manager.backup_file())
+            .unwrap()
+            .set_modified(written.into())
+            .unwrap();
+
+        let (valid, backup) = manager.has_valid_anthropic_backup().unwrap();
+        assert!(valid);
+        let metadata = backup.unwrap().metadata;
+        assert_eq!(metadata.created_at, Some(written));
+        assert_eq!(metadata.version, BACKUP_METADATA_VERSION);
+        assert_eq!(metadata.migrated_from.as_deref(), Some(UNVERSIONED_BACKUP));
+    }
+
+    #[test]
+    fn test_older_metadata_is_migrated_on_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        fs::create_dir_all(manager.backups_dir()).unwrap();
+        let path = manager
+            .backups_dir()
+            .join("settings-20230501T120000000Z.json");
+        fs::write(
+            &path,
+            r#"{"_metadata": {"provider": "glm", "created_at": null, "version": "2.0.1"}}"#,
+        )
+        .unwrap();
+
+        let metadata = manager.load_rotated_backup(&path).unwrap().metadata;
+        assert_eq!(metadata.version, BACKUP_METADATA_VERSION);
+        assert_eq!(metadata.migrated_from.as_deref(), Some("2.0.1"));
+        let modified: DateTime<Utc> = fs::metadata(&path).unwrap().modified().unwrap().into();
+        assert_eq!(metadata.created_at, Some(modified));
+
+        // Current metadata is left as written
+        fs::write(
+            &path,
+            r#"{"_metadata": {"provider": "glm", "created_at": 1700000000, "version": "2.2.0"}}"#,
+        )
+        .unwrap();
+        let metadata = manager.load_rotated_backup(&path).unwrap().metadata;
+        assert_eq!(metadata.created_at.unwrap().timestamp(), 1_700_000_000);
+        assert!(metadata.migrated_from.is_none());
+    }
+
+    #[test]
+    fn test_line_ending_follows_the_majority() {
+        assert_eq!(LineEnding::of(b"{\r\n  \"env\": {}\r\n}"), LineEnding::CrLf);
+        assert_eq!(LineEnding::of(b"{\n  \"env\": {}\n}"), LineEnding::Lf);
+        assert_eq!(LineEnding::of(b"{\r\n\r\n\n}"), LineEnding::CrLf);
+        assert_eq!(LineEnding::of(b"{\r\n\n\n}"), LineEnding::Lf);
+        assert_eq!(LineEnding::of(b"{}"), LineEnding::Lf);
+
+        let mut out = Vec::new();
+        CrLfWriter(&mut out)
+            .write_all(b"{\n  \"a\": \"b\\nc\"\n}")
+            .unwrap();
+        assert_eq!(out, b"{\r\n  \"a\": \"b\\nc\"\r\n}");
+    }
+
+    #[test]
+    fn test_large_config_round_trips_through_the_streaming_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        let mut config = Config::from_env([(
+            "ANTHROPIC_BASE_URL".to_string(),
+            "https://api.z.ai/api/anthropic".to_string(),
+        )]);
+        let allow: Vec<String> = (0..40_000)
+            .map(|i| format!("Bash(scripts/generated/task-{:05}.sh --verbose)", i))
+            .collect();
+        config.extra.insert(
+            "permissions".to_string(),
+            serde_json::json!({ "allow": allow }),
+        );
+        let servers: serde_json::Map<String, serde_json::Value> = (0..2_000)
+            .map(|i| {
+                (
+                    format!("server-{}", i),
+                    serde_json::json!({"command": "npx", "args": ["-y", format!("mcp-{}", i)]}),
+                )
+            })
+            .collect();
+        config
+            .extra
+            .insert("mcpServers".to_string(), servers.into());
+
+        manager.save_current_config(&config).unwrap();
+        let size = fs::metadata(manager.settings_file()).unwrap().len();
+        assert!(size > 2_000_000, "{} bytes", size);
+        assert!(!manager.settings_file().with_extension("tmp").exists());
+
+        let loaded = manager.load_current_config().unwrap();
+        assert_eq!(loaded.env, config.env);
+        assert_eq!(loaded.extra, config.extra);
+    }
+
+    #[test]
+    fn test_classify_backup_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        assert_eq!(
+            manager.classify_backup_file().unwrap(),
+            BackupFileKind::Missing
+        );
+
+        for (content, kind, restorable) in [
+            (
+                r#"{"_metadata": {"provider": "anthropic", "created_at": 1700000000, "version": "2.2.0"}}"#,
+                BackupFileKind::Current,
+                true,
+            ),
+            (
+                r#"{"env": {"ANTHROPIC_AUTH_TOKEN": "old"}}"#,
+                BackupFileKind::Legacy,
+                true,
+            ),
+            ("[1, 2]", BackupFileKind::UnrecognizedSchema, false),
+            (
+                r#"{"env": {"ANTHROPIC_AUTH_TOKEN": 42}}"#,
+                BackupFileKind::UnrecognizedSchema,
+                false,
+            ),
+            (
+                r#"{"_metadata": "damaged", "env": {}}"#,
+                BackupFileKind::UnrecognizedSchema,
+                false,
+            ),
+            (r#"{"env": {"ANTHROPIC_AU"#, BackupFileKind::Corrupt, false),
+        ] {
+            fs::write(manager.backup_file(), content).unwrap();
+            assert_eq!(manager.classify_backup_file().unwrap(), kind, "{}", content);
+            let (valid, backup) = manager.has_valid_anthropic_backup().unwrap();
+            assert_eq!(valid, restorable, "{}", content);
+            assert_eq!(backup.is_some(), restorable, "{}", content);
+        }
+
+        // What `create_backup_with_metadata` writes: a plain copy plus its sidecar
+        manager
+            .create_backup_with_metadata(&Config::default(), &Provider::Anthropic)
+            .unwrap();
+        assert_eq!(
+            manager.classify_backup_file().unwrap(),
+            BackupFileKind::Current
+        );
+    }
+
+    #[test]
+    fn test_ensure_backup_creates_once_then_reuses() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        let mut first = Config::default();
+        first
+            .env
+            .insert("ANTHROPIC_AUTH_TOKEN".to_string(), "first".to_string());
+        let mut second = Config::default();
+        second
+            .env
+            .insert("ANTHROPIC_AUTH_TOKEN".to_string(), "second".to_string());
+
+        let created = manager.ensure_backup(&first, &Provider::Anthropic).unwrap();
+        assert!(matches!(created, EnsuredBackup::Created));
+
+        match manager
+            .ensure_backup(&second, &Provider::Anthropic)
+            .unwrap()
+        {
+            EnsuredBackup::Reused(backup) => {
+                assert_eq!(backup.env["ANTHROPIC_AUTH_TOKEN"], "first")
+            }
+            EnsuredBackup::Created => panic!("second call replaced the backup"),
+        }
+        assert_eq!(manager.list_backups().unwrap().len(), 1);
+
+        // An unreadable backup is replaced
+        fs::write(manager.backup_file(), "{ torn").unwrap();
+        let created = manager
+            .ensure_backup(&second, &Provider::Anthropic)
+            .unwrap();
+        assert!(matches!(created, EnsuredBackup::Created));
+        let (_, backup) = manager.has_valid_anthropic_backup().unwrap();
+        assert_eq!(backup.unwrap().env["ANTHROPIC_AUTH_TOKEN"], "second");
+    }
+
+    #[test]
+    fn test_replace_backup_keeps_previous_in_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+
+        let mut old = Config::default();
+        old.env
+            .insert("ANTHROPIC_AUTH_TOKEN".to_string(), "old".to_string());
+        let mut new = Config::default();
+        new.env
+            .insert("ANTHROPIC_AUTH_TOKEN".to_string(), "new".to_string());
+
+        // A legacy backup that never made it into the rotation
+        manager
+            .save_config_atomic(manager.backup_file(), &old)
+            .unwrap();
+        let (_, previous) = manager.has_valid_anthropic_backup().unwrap();
+        manager
+            .replace_backup(&previous.unwrap(), &new, &Provider::Anthropic)
+            .unwrap();
+
+        let (_, current) = manager.has_valid_anthropic_backup().unwrap();
+        assert_eq!(
+            current.unwrap().env.get("ANTHROPIC_AUTH_TOKEN").unwrap(),
+            "new"
+        );
+
+        let rotated: Vec<String> = manager
+            .list_backups()
+            .unwrap()
+            .iter()
+            .map(|entry| {
+                let content = fs::read_to_string(&entry.path).unwrap();
+                let backup: BackupConfig = serde_json::from_str(&content).unwrap();
+                backup.env["ANTHROPIC_AUTH_TOKEN"].clone()
+            })
+            .collect();
+        assert_eq!(rotated, vec!["new".to_string(), "old".to_string()]);
+    }
+
+    #[test]
+    fn test_settings_file_override() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_settings_file(dir.path().join("colleague.json"));
+        assert_eq!(manager.settings_file(), dir.path().join("colleague.json"));
+        assert_eq!(manager.config_dir(), dir.path());
+        assert_eq!(
+            manager.backup_file(),
+            dir.path().join("settings.json.backup")
+        );
+
+        let relative = ConfigManager::with_settings_file(PathBuf::from("settings.json"));
+        assert_eq!(relative.config_dir(), Path::new("."));
+    }
+
+    #[test]
+    fn test_backup_dir_override() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let backup_dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(config_dir.path().to_path_buf())
+            .with_backup_dir(backup_dir.path().to_path_buf());
+
+        let mut config = Config::default();
+        config
+            .env
+            .insert("ANTHROPIC_AUTH_TOKEN".to_string(), "token".to_string());
+        manager.save_current_config(&config).unwrap();
+        manager
+            .create_backup_with_metadata(&config, &Provider::Anthropic)
+            .unwrap();
+        manager.save_token("sk-test").unwrap();
+
+        assert!(backup_dir.path().join("settings.json.backup").is_file());
+        assert!(backup_dir.path().join("settings.json.meta").is_file());
+        assert_eq!(manager.list_backups().unwrap().len(), 1);
+        assert!(manager.list_backups().unwrap()[0]
+            .path
+            .starts_with(backup_dir.path()));
+
+        assert!(config_dir.path().join("settings.json").is_file());
+        assert!(config_dir.path().join(TOKEN_FILE_NAME).is_file());
+        assert!(!config_dir.path().join("settings.json.backup").exists());
+        assert!(!config_dir.path().join(BACKUPS_DIR_NAME).exists());
+
+        let (has_backup, _) = manager.has_valid_anthropic_backup().unwrap();
+        assert!(has_backup);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_read_only_config_dir_is_reported_clearly() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let config_dir = dir.path().join(".claude");
+        fs::create_dir_all(&config_dir).unwrap();
+        fs::set_permissions(&config_dir, fs::Permissions::from_mode(0o555)).unwrap();
+
+        // Privileged users (e.g. root in CI containers) bypass permission bits
+        let privileged = fs::write(config_dir.join("probe"), "").is_ok();
+        let result = ConfigManager::with_config_dir(config_dir.clone()).ensure_writable();
+        fs::set_permissions(&config_dir, fs::Permissions::from_mode(0o755)).unwrap();
+        if privileged {
+            return;
+        }
+
+        let error = result.unwrap_err();
+        let app_error = error.downcast_ref::<AppError>().unwrap();
+        assert!(matches!(app_error, AppError::FileSystem(_)));
+        assert!(error
+            .to_string()
+            .contains(&config_dir.display().to_string()));
+        assert!(error.to_string().contains("chmod u+w"));
+    }
+
+    #[test]
+    fn test_writable_dirs_pass_including_missing_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().join("not-yet-created"))
+            .with_backup_dir(dir.path().join("backups-elsewhere"));
+        assert!(manager.ensure_writable().is_ok());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_profile_scopes_settings_and_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let global = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        global.save_token("sk-shared").unwrap();
+
+        let profile = ConfigManager::with_config_dir(dir.path().to_path_buf()).with_profile("work");
+        profile.save_current_config(&Config::default()).unwrap();
+        profile
+            .create_backup_with_metadata(&Config::default(), &Provider::Anthropic)
+            .unwrap();
+
+        let profile_dir = dir.path().join("profiles/work");
+        assert!(profile_dir.join("settings.json").is_file());
+        assert!(profile_dir.join("settings.json.backup").is_file());
+        assert!(!dir.path().join("settings.json").exists());
+        assert_eq!(profile.profile(), Some("work"));
+        assert_eq!(
+            profile.load_saved_token().unwrap().as_deref(),
+            Some("sk-shared")
+        );
+    }
+
+    #[test]
+    fn test_profile_honors_the_global_retention_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join(crate::config::settings::SETTINGS_FILE_NAME),
+            "[backups]\nmax_count = 2\n",
+        )
+        .unwrap();
+
+        let profile = ConfigManager::with_config_dir(dir.path().to_path_buf()).with_profile("work");
+        assert_eq!(profile.preferences_dir(), dir.path());
+        for _ in 0..4 {
+            profile
+                .backup_to_rotation(&Config::default(), &Provider::Anthropic)
+                .unwrap();
+        }
+        assert_eq!(profile.list_backups().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_profile_keeps_an_explicit_backup_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let elsewhere = dir.path().join("backups-elsewhere");
+        let profile = ConfigManager::with_config_dir(dir.path().to_path_buf())
+            .with_backup_dir(elsewhere.clone())
+            .with_profile("work");
+        profile
+            .create_backup_with_metadata(&Config::default(), &Provider::Anthropic)
+            .unwrap();
+
+        assert!(elsewhere.join("settings.json.backup").is_file());
+        assert!(!dir
+            .path()
+            .join("profiles/work/settings.json.backup")
+            .exists());
+    }
+
+    #[test]
+    fn test_migrate_rejects_same_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        assert!(manager.migrate_to(dir.path()).is_err());
     }
 }
@@ -0,0 +1,78 @@
+use crate::utils::error::AppError;
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+/// Parse one `--merge-env KEY=VALUE` argument (used as a clap value parser)
+pub fn parse_env_assignment(arg: &str) -> Result<(String, String), String> {
+    let (key, value) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got '{}'", arg))?;
+
+    let valid_key = key
+        .chars()
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid_key {
+        return Err(format!("invalid environment variable name '{}'", key));
+    }
+
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// Collect `--merge-env` pairs, rejecting a repeated key unless `last_wins`
+pub fn collect_merge_env(
+    pairs: Vec<(String, String)>,
+    last_wins: bool,
+) -> Result<BTreeMap<String, String>> {
+    let mut merged = BTreeMap::new();
+    for (key, value) in pairs {
+        if merged.insert(key.clone(), value).is_some() && !last_wins {
+            return Err(AppError::Config(format!(
+                "--merge-env sets {} more than once (use --last-wins to allow this)",
+                key
+            ))
+            .into());
+        }
+    }
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_env_assignment() {
+        assert_eq!(
+            parse_env_assignment("HTTPS_PROXY=http://proxy:8080").unwrap(),
+            ("HTTPS_PROXY".to_string(), "http://proxy:8080".to_string())
+        );
+        // Only the first '=' separates key and value; empty values are allowed
+        assert_eq!(
+            parse_env_assignment("OPTS=a=b").unwrap().1,
+            "a=b".to_string()
+        );
+        assert_eq!(parse_env_assignment("DEBUG=").unwrap().1, "");
+
+        assert!(parse_env_assignment("NOVALUE").is_err());
+        assert!(parse_env_assignment("=value").is_err());
+        assert!(parse_env_assignment("1KEY=value").is_err());
+        assert!(parse_env_assignment("BAD-KEY=value").is_err());
+    }
+
+    #[test]
+    fn test_collect_merge_env_duplicates() {
+        let pairs = vec![
+            ("DEBUG".to_string(), "1".to_string()),
+            ("HTTPS_PROXY".to_string(), "http://proxy".to_string()),
+            ("DEBUG".to_string(), "2".to_string()),
+        ];
+
+        assert!(collect_merge_env(pairs.clone(), false).is_err());
+
+        let merged = collect_merge_env(pairs, true).unwrap();
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged["DEBUG"], "2");
+    }
+}
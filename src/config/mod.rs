@@ -1,5 +1,13 @@
-pub mod types;
+pub mod credentials;
+pub mod diff;
+pub mod history;
 pub mod manager;
+pub mod merge;
+pub mod profile;
+pub mod registry;
+pub mod settings;
+pub mod types;
 
+pub use manager::*;
+pub use settings::*;
 pub use types::*;
-pub use manager::*;
\ No newline at end of file
@@ -0,0 +1,117 @@
+use crate::utils::error::AppError;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File in a project directory naming the profile to use there
+pub const PROJECT_PROFILE_FILE: &str = ".claude-switch";
+
+/// Where the active profile came from
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProfileSource {
+    Flag,
+    ProjectFile(PathBuf),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileSelection {
+    pub name: String,
+    pub source: ProfileSource,
+}
+
+/// Resolve the profile: explicit flag, then `.claude-switch` in `cwd`, then
+/// none (the global config).
+pub fn resolve_profile(flag: Option<&str>, cwd: &Path) -> Result<Option<ProfileSelection>> {
+    if let Some(name) = flag {
+        return Ok(Some(ProfileSelection {
+            name: validate_profile_name(name)?,
+            source: ProfileSource::Flag,
+        }));
+    }
+
+    let project_file = cwd.join(PROJECT_PROFILE_FILE);
+    if !project_file.is_file() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(&project_file)
+        .with_context(|| format!("Failed to read {}", project_file.display()))?;
+    let name = content
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .ok_or_else(|| {
+            AppError::Config(format!(
+                "{} does not name a profile",
+                project_file.display()
+            ))
+        })?;
+
+    Ok(Some(ProfileSelection {
+        name: validate_profile_name(name)?,
+        source: ProfileSource::ProjectFile(project_file),
+    }))
+}
+
+/// Profile names become directory names, so keep them to a safe alphabet
+fn validate_profile_name(name: &str) -> Result<String> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+        && name != "."
+        && name != "..";
+    if !valid {
+        return Err(AppError::Config(format!(
+            "Invalid profile name '{}' (use letters, digits, '-', '_' or '.')",
+            name
+        ))
+        .into());
+    }
+    Ok(name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flag_wins_over_project_file() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(PROJECT_PROFILE_FILE), "project\n").unwrap();
+
+        let selection = resolve_profile(Some("work"), dir.path()).unwrap().unwrap();
+        assert_eq!(selection.name, "work");
+        assert_eq!(selection.source, ProfileSource::Flag);
+    }
+
+    #[test]
+    fn test_project_file_used_without_flag() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join(PROJECT_PROFILE_FILE),
+            "# provider for this repo\nclient-a\n",
+        )
+        .unwrap();
+
+        let selection = resolve_profile(None, dir.path()).unwrap().unwrap();
+        assert_eq!(selection.name, "client-a");
+        assert_eq!(
+            selection.source,
+            ProfileSource::ProjectFile(dir.path().join(PROJECT_PROFILE_FILE))
+        );
+    }
+
+    #[test]
+    fn test_global_without_flag_or_project_file() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(resolve_profile(None, dir.path()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_rejects_path_like_names() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(resolve_profile(Some("../other"), dir.path()).is_err());
+        assert!(resolve_profile(Some(".."), dir.path()).is_err());
+    }
+}
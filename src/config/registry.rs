@@ -0,0 +1,618 @@
+use crate::config::{Config, Provider};
+use crate::provider::detector::ProviderDetector;
+use crate::utils::error::AppError;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const PROVIDERS_FILE_NAME: &str = "providers.toml";
+
+/// Team-wide profiles shipped by an administrator, read before the user's
+#[cfg(not(windows))]
+pub const SYSTEM_PROVIDERS_FILE: &str = "/etc/claude-switch/providers.toml";
+#[cfg(windows)]
+pub const SYSTEM_PROVIDERS_FILE: &str = r"C:\ProgramData\claude-switch\providers.toml";
+
+/// `API_TIMEOUT_MS` written for GLM when no profile or flag says otherwise
+pub const GLM_DEFAULT_TIMEOUT_MS: u64 = 3_000_000;
+
+/// `--timeout-ms` value parser: `500ms`, `30s`, `5m`, `1h`, or a bare number
+/// of milliseconds
+pub fn parse_timeout_ms(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let invalid = || {
+        format!(
+            "invalid timeout '{}' (expected milliseconds or e.g. 500ms, 30s, 5m, 1h)",
+            value
+        )
+    };
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount.parse().map_err(|_| invalid())?;
+    let factor = match unit {
+        "" | "ms" => 1,
+        "s" => 1_000,
+        "m" => 60_000,
+        "h" => 3_600_000,
+        _ => return Err(invalid()),
+    };
+    amount.checked_mul(factor).ok_or_else(invalid)
+}
+
+/// Expand `${VAR}` in a profile value with `lookup`. `${VAR:-fallback}` uses
+/// `fallback` when `VAR` is unset or empty, and `$$` is a literal `$`.
+pub fn interpolate(value: &str, lookup: &dyn Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        if let Some(after) = after.strip_prefix('$') {
+            expanded.push('$');
+            rest = after;
+            continue;
+        }
+        let Some(reference) = after.strip_prefix('{') else {
+            // A lone `$` is kept as it is
+            expanded.push('$');
+            rest = after;
+            continue;
+        };
+        let end = reference
+            .find('}')
+            .ok_or_else(|| format!("unterminated '${{' in '{}'", value))?;
+        let (name, fallback) = match reference[..end].split_once(":-") {
+            Some((name, fallback)) => (name, Some(fallback)),
+            None => (&reference[..end], None),
+        };
+        let resolved = match (lookup(name), fallback) {
+            (Some(set), Some(fallback)) if set.is_empty() => fallback.to_string(),
+            (Some(set), _) => set,
+            (None, Some(fallback)) => fallback.to_string(),
+            (None, None) => return Err(format!("environment variable '{}' is not set", name)),
+        };
+        expanded.push_str(&resolved);
+        rest = &reference[end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// A named provider setup from `providers.toml`, e.g.
+///
+/// ```toml
+/// [profiles.zai-slow]
+/// provider = "glm"
+/// timeout_ms = 6000000
+/// ```
+///
+/// A profile named after a built-in provider (`glm`, `anthropic`) may omit
+/// `provider` and changes what the plain `glm`/`anthropic` commands write.
+/// String values may reference the environment as `${VAR}`, expanded when
+/// the profile is resolved (see `interpolate`).
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct ProviderProfile {
+    /// Built-in provider this profile is based on
+    pub provider: Option<String>,
+    /// Value for `ANTHROPIC_BASE_URL`
+    pub base_url: Option<String>,
+    /// Value for `API_TIMEOUT_MS`
+    pub timeout_ms: Option<u64>,
+    /// Env var names to write the token and models under (GLM profiles only)
+    pub env_keys: Option<EnvKeys>,
+}
+
+/// Env var names a profile's token and models are written under, for
+/// gateways that don't read the `ANTHROPIC_*` ones:
+///
+/// ```toml
+/// [profiles.gateway.env_keys]
+/// auth = "OPENAI_API_KEY"
+/// ```
+///
+/// Names left out keep their `ANTHROPIC_*` default.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EnvKeys {
+    pub auth: String,
+    pub opus: String,
+    pub sonnet: String,
+    pub haiku: String,
+}
+
+impl Default for EnvKeys {
+    fn default() -> Self {
+        Self {
+            auth: "ANTHROPIC_AUTH_TOKEN".to_string(),
+            opus: "ANTHROPIC_DEFAULT_OPUS_MODEL".to_string(),
+            sonnet: "ANTHROPIC_DEFAULT_SONNET_MODEL".to_string(),
+            haiku: "ANTHROPIC_DEFAULT_HAIKU_MODEL".to_string(),
+        }
+    }
+}
+
+impl EnvKeys {
+    /// Whether `key` is one of the model names
+    pub fn is_model_key(&self, key: &str) -> bool {
+        [&self.opus, &self.sonnet, &self.haiku]
+            .iter()
+            .any(|name| name.as_str() == key)
+    }
+
+    /// Names must look like env vars: `A-Z`, `0-9` and `_`, not starting with a digit
+    fn validate(&self, profile: &str) -> Result<()> {
+        for name in [&self.auth, &self.opus, &self.sonnet, &self.haiku] {
+            let valid = name
+                .chars()
+                .next()
+                .is_some_and(|first| !first.is_ascii_digit())
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+            if !valid {
+                return Err(AppError::Config(format!(
+                    "Profile '{}': '{}' is not a valid env var name",
+                    profile, name
+                ))
+                .into());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ProfileRegistry {
+    profiles: BTreeMap<String, ProviderProfile>,
+    /// Profiles of the user file that replaced a system profile of the same
+    /// name, with the system file they replaced
+    #[serde(skip)]
+    overridden: BTreeMap<String, PathBuf>,
+}
+
+/// A registry profile with its base provider and timeout worked out
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedProfile {
+    pub name: String,
+    pub provider: Provider,
+    pub base_url: Option<String>,
+    /// Only set by the profile; `None` leaves the provider's default (GLM
+    /// writes `GLM_DEFAULT_TIMEOUT_MS` on a full switch)
+    pub timeout_ms: Option<u64>,
+    pub env_keys: EnvKeys,
+    /// The system file whose profile of this name the user file replaced
+    pub overrides: Option<PathBuf>,
+}
+
+impl ProfileRegistry {
+    /// The system file (if any) extended by the user file: a profile defined
+    /// in both comes entirely from the user file
+    pub fn load_layered(system: Option<&Path>, user: &Path) -> Result<Self> {
+        let mut registry = match system {
+            Some(system) => Self::load(system)?,
+            None => Self::default(),
+        };
+        for (name, profile) in Self::load(user)?.profiles {
+            if let (Some(system), Some(_)) = (system, registry.profiles.get(&name)) {
+                registry
+                    .overridden
+                    .insert(name.clone(), system.to_path_buf());
+            }
+            registry.profiles.insert(name, profile);
+        }
+        Ok(registry)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        toml::from_str(&content).map_err(|e| {
+            AppError::Config(format!("Failed to parse {}: {}", path.display(), e)).into()
+        })
+    }
+
+    /// Look up `name`, falling back to the built-in `glm`/`anthropic` defaults,
+    /// with `${VAR}` references expanded from the process environment
+    pub fn resolve(&self, name: &str) -> Result<ResolvedProfile> {
+        self.resolve_with(name, &|var| std::env::var(var).ok())
+    }
+
+    /// `resolve` with `${VAR}` references expanded from `lookup`
+    pub fn resolve_with(
+        &self,
+        name: &str,
+        lookup: &dyn Fn(&str) -> Option<String>,
+    ) -> Result<ResolvedProfile> {
+        let builtin = match name {
+            "glm" | "anthropic" => name.parse::<Provider>().ok(),
+            _ => None,
+        };
+
+        let profile = match (self.profiles.get(name), &builtin) {
+            (Some(profile), _) => Self::expand(name, profile, lookup)?,
+            (None, Some(_)) => ProviderProfile::default(),
+            (None, None) => {
+                return Err(AppError::Config(format!(
+                    "Unknown profile '{}' (not defined in {})",
+                    name, PROVIDERS_FILE_NAME
+                ))
+                .into())
+            }
+        };
+
+        let provider = match (&profile.provider, builtin) {
+            (Some(provider), _) => provider.parse::<Provider>()?,
+            (None, Some(builtin)) => builtin,
+            (None, None) => {
+                return Err(AppError::Config(format!(
+                    "Profile '{}' must set provider = \"glm\" or \"anthropic\"",
+                    name
+                ))
+                .into())
+            }
+        };
+        if !matches!(provider, Provider::GLM | Provider::Anthropic) {
+            return Err(AppError::Config(format!(
+                "Profile '{}': provider '{}' cannot be switched to yet",
+                name,
+                provider.as_str()
+            ))
+            .into());
+        }
+
+        let env_keys = match (profile.env_keys, &provider) {
+            (None, _) => EnvKeys::default(),
+            (Some(env_keys), Provider::GLM) => {
+                env_keys.validate(name)?;
+                env_keys
+            }
+            (Some(_), _) => {
+                return Err(AppError::Config(format!(
+                    "Profile '{}': env_keys only apply to glm profiles",
+                    name
+                ))
+                .into())
+            }
+        };
+
+        Ok(ResolvedProfile {
+            name: name.to_string(),
+            provider,
+            base_url: profile.base_url,
+            timeout_ms: profile.timeout_ms,
+            env_keys,
+            overrides: self.overridden.get(name).cloned(),
+        })
+    }
+
+    /// The profile, first by name, whose `base_url` has exactly the host of
+    /// `base_url`; subdomains and look-alike hosts never match
+    pub fn profile_for_base_url(&self, base_url: &str) -> Option<ResolvedProfile> {
+        let host = ProviderDetector::base_url_host(base_url)?;
+        self.profiles
+            .keys()
+            .filter_map(|name| self.resolve(name).ok())
+            .find(|profile| {
+                profile
+                    .base_url
+                    .as_deref()
+                    .and_then(ProviderDetector::base_url_host)
+                    .is_some_and(|profile_host| profile_host == host)
+            })
+    }
+
+    /// The `env_keys` of the profile that wrote `config`: the one on exactly
+    /// the host of its base URL, else the first (by name) with its own token
+    /// variable set in `config`
+    pub fn env_keys_for(&self, config: &Config) -> Option<EnvKeys> {
+        let by_host = config
+            .env
+            .get("ANTHROPIC_BASE_URL")
+            .and_then(|base_url| self.profile_for_base_url(base_url));
+        if let Some(profile) = by_host {
+            return Some(profile.env_keys);
+        }
+        let default = EnvKeys::default();
+        self.profiles
+            .keys()
+            .filter_map(|name| self.resolve(name).ok())
+            .map(|profile| profile.env_keys)
+            .find(|keys| *keys != default && config.env.contains_key(&keys.auth))
+    }
+
+    /// `profile` with `${VAR}` references in its string values expanded
+    fn expand(
+        name: &str,
+        profile: &ProviderProfile,
+        lookup: &dyn Fn(&str) -> Option<String>,
+    ) -> Result<ProviderProfile> {
+        let expand = |value: &String| {
+            interpolate(value, lookup)
+                .map_err(|e| AppError::Config(format!("Profile '{}': {}", name, e)))
+        };
+        let mut profile = profile.clone();
+        if let Some(provider) = &profile.provider {
+            profile.provider = Some(expand(provider)?);
+        }
+        if let Some(base_url) = &profile.base_url {
+            profile.base_url = Some(expand(base_url)?);
+        }
+        if let Some(env_keys) = &mut profile.env_keys {
+            for key in [
+                &mut env_keys.auth,
+                &mut env_keys.opus,
+                &mut env_keys.sonnet,
+                &mut env_keys.haiku,
+            ] {
+                *key = expand(key)?;
+            }
+        }
+        Ok(profile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry(toml: &str) -> ProfileRegistry {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(PROVIDERS_FILE_NAME), toml).unwrap();
+        ProfileRegistry::load(&dir.path().join(PROVIDERS_FILE_NAME)).unwrap()
+    }
+
+    #[test]
+    fn test_builtin_defaults_without_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = ProfileRegistry::load(&dir.path().join(PROVIDERS_FILE_NAME)).unwrap();
+
+        let glm = registry.resolve("glm").unwrap();
+        assert_eq!(glm.provider, Provider::GLM);
+        assert_eq!(glm.timeout_ms, None);
+
+        let anthropic = registry.resolve("anthropic").unwrap();
+        assert_eq!(anthropic.timeout_ms, None);
+
+        assert!(registry.resolve("missing").is_err());
+    }
+
+    #[test]
+    fn test_profiles_carry_their_own_timeouts() {
+        let registry = registry(
+            "[profiles.fast]\nprovider = \"glm\"\ntimeout_ms = 60000\n\n\
+             [profiles.slow]\nprovider = \"glm\"\ntimeout_ms = 9000000\n\n\
+             [profiles.glm]\ntimeout_ms = 120000\n",
+        );
+
+        assert_eq!(registry.resolve("fast").unwrap().timeout_ms, Some(60000));
+        assert_eq!(registry.resolve("slow").unwrap().timeout_ms, Some(9000000));
+        // Overriding the built-in keeps its provider
+        let glm = registry.resolve("glm").unwrap();
+        assert_eq!(glm.provider, Provider::GLM);
+        assert_eq!(glm.timeout_ms, Some(120000));
+    }
+
+    #[test]
+    fn test_profile_requires_known_provider() {
+        let registry = registry(
+            "[profiles.nobase]\ntimeout_ms = 1\n\n[profiles.bad]\nprovider = \"custom\"\n",
+        );
+        assert!(registry.resolve("nobase").is_err());
+        assert!(registry.resolve("bad").is_err());
+    }
+
+    #[test]
+    fn test_profile_env_keys() {
+        let registry = registry(
+            "[profiles.gateway]\nprovider = \"glm\"\n\
+             [profiles.gateway.env_keys]\nauth = \"OPENAI_API_KEY\"\nopus = \"GATEWAY_MODEL\"\n\n\
+             [profiles.lower]\nprovider = \"glm\"\nenv_keys = { auth = \"api_key\" }\n\n\
+             [profiles.work]\nprovider = \"anthropic\"\nenv_keys = { auth = \"KEY\" }\n",
+        );
+
+        let gateway = registry.resolve("gateway").unwrap();
+        assert_eq!(gateway.env_keys.auth, "OPENAI_API_KEY");
+        assert_eq!(gateway.env_keys.opus, "GATEWAY_MODEL");
+        assert_eq!(gateway.env_keys.haiku, "ANTHROPIC_DEFAULT_HAIKU_MODEL");
+        assert_eq!(
+            registry.resolve("glm").unwrap().env_keys,
+            EnvKeys::default()
+        );
+
+        assert!(registry.resolve("lower").is_err());
+        assert!(registry.resolve("work").is_err());
+    }
+
+    #[test]
+    fn test_user_file_overrides_and_extends_system_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let system = dir.path().join("system.toml");
+        let user = dir.path().join(PROVIDERS_FILE_NAME);
+        fs::write(
+            &system,
+            "[profiles.team]\nprovider = \"glm\"\ntimeout_ms = 1000\n\n\
+             [profiles.shared]\nprovider = \"glm\"\ntimeout_ms = 2000\n",
+        )
+        .unwrap();
+        fs::write(
+            &user,
+            "[profiles.shared]\nprovider = \"anthropic\"\n\n\
+             [profiles.mine]\nprovider = \"glm\"\ntimeout_ms = 3000\n",
+        )
+        .unwrap();
+
+        let registry = ProfileRegistry::load_layered(Some(&system), &user).unwrap();
+        let team = registry.resolve("team").unwrap();
+        assert_eq!(team.timeout_ms, Some(1000));
+        assert_eq!(team.overrides, None);
+        assert_eq!(registry.resolve("mine").unwrap().timeout_ms, Some(3000));
+        // The whole user profile wins, not field by field
+        let shared = registry.resolve("shared").unwrap();
+        assert_eq!(shared.provider, Provider::Anthropic);
+        assert_eq!(shared.timeout_ms, None);
+        assert_eq!(shared.overrides, Some(system.clone()));
+
+        let user_only = ProfileRegistry::load_layered(None, &user).unwrap();
+        assert!(user_only.resolve("team").is_err());
+        assert_eq!(user_only.resolve("shared").unwrap().overrides, None);
+
+        // A missing system file is no error
+        let missing = dir.path().join("missing.toml");
+        assert!(ProfileRegistry::load_layered(Some(&missing), &user).is_ok());
+    }
+
+    fn lookup(var: &str) -> Option<String> {
+        match var {
+            "GATEWAY_URL" => Some("https://gateway.example.com".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_interpolate() {
+        assert_eq!(
+            interpolate("${GATEWAY_URL}/api", &lookup).unwrap(),
+            "https://gateway.example.com/api"
+        );
+        assert_eq!(
+            interpolate("${MISSING:-https://fallback}", &lookup).unwrap(),
+            "https://fallback"
+        );
+        assert_eq!(interpolate("${EMPTY:-used}", &lookup).unwrap(), "used");
+        assert_eq!(interpolate("${EMPTY}", &lookup).unwrap(), "");
+        assert_eq!(interpolate("${MISSING:-}", &lookup).unwrap(), "");
+        assert_eq!(
+            interpolate("$${GATEWAY_URL} costs $5", &lookup).unwrap(),
+            "${GATEWAY_URL} costs $5"
+        );
+        assert_eq!(interpolate("$$$$", &lookup).unwrap(), "$$");
+
+        let missing = interpolate("${MISSING}", &lookup).unwrap_err();
+        assert!(missing.contains("'MISSING' is not set"), "{}", missing);
+        assert!(interpolate("${GATEWAY_URL", &lookup).is_err());
+    }
+
+    #[test]
+    fn test_profile_values_are_interpolated_on_resolve() {
+        let registry = registry(
+            "[profiles.gateway]
+provider = \"${KIND:-glm}\"
+base_url = \"${GATEWAY_URL}\"
+             env_keys = { auth = \"${AUTH_VAR:-GATEWAY_KEY}\" }
+
+             [profiles.unset]
+provider = \"glm\"
+base_url = \"${MISSING}\"
+",
+        );
+
+        let gateway = registry.resolve_with("gateway", &lookup).unwrap();
+        assert_eq!(gateway.provider, Provider::GLM);
+        assert_eq!(
+            gateway.base_url.as_deref(),
+            Some("https://gateway.example.com")
+        );
+        assert_eq!(gateway.env_keys.auth, "GATEWAY_KEY");
+
+        let error = registry.resolve_with("unset", &lookup).unwrap_err();
+        assert!(error.to_string().contains("Profile 'unset'"), "{}", error);
+        // Profiles that are not used never need their variables
+        assert!(registry.resolve_with("glm", &lookup).is_ok());
+    }
+
+    #[test]
+    fn test_profile_for_base_url_matches_the_exact_host() {
+        let registry = registry(
+            "[profiles.gateway]\nprovider = \"glm\"\nbase_url = \"https://gateway.example.com/anthropic\"\n",
+        );
+        for base_url in [
+            "https://gateway.example.com",
+            "https://Gateway.Example.com:8443/v1/",
+        ] {
+            let profile = registry.profile_for_base_url(base_url);
+            assert_eq!(
+                profile.map(|p| p.name).as_deref(),
+                Some("gateway"),
+                "{}",
+                base_url
+            );
+        }
+        for base_url in [
+            "https://eu.gateway.example.com",
+            "https://gateway.example.com.evil.io",
+            "https://notgateway.example.com",
+            "",
+        ] {
+            assert!(
+                registry.profile_for_base_url(base_url).is_none(),
+                "{}",
+                base_url
+            );
+        }
+    }
+
+    #[test]
+    fn test_env_keys_come_from_the_live_config() {
+        let registry = registry(
+            "[profiles.gateway]\nprovider = \"glm\"\nbase_url = \"https://gateway.example.com\"\n\
+             env_keys = { auth = \"GATEWAY_KEY\" }\n\
+             [profiles.openai]\nprovider = \"glm\"\nenv_keys = { auth = \"OPENAI_API_KEY\" }\n",
+        );
+        let config = |entries: &[(&str, &str)]| {
+            Config::from_env(
+                entries
+                    .iter()
+                    .map(|(key, value)| (key.to_string(), value.to_string())),
+            )
+        };
+
+        let on_gateway = config(&[("ANTHROPIC_BASE_URL", "https://gateway.example.com/v1")]);
+        let keys = registry.env_keys_for(&on_gateway).unwrap();
+        assert_eq!(keys.auth, "GATEWAY_KEY");
+
+        let openai = config(&[("OPENAI_API_KEY", "sk-x")]);
+        let keys = registry.env_keys_for(&openai).unwrap();
+        assert_eq!(keys.auth, "OPENAI_API_KEY");
+
+        let plain = config(&[("ANTHROPIC_AUTH_TOKEN", "sk-x")]);
+        assert_eq!(registry.env_keys_for(&plain), None);
+    }
+
+    #[test]
+    fn test_parse_timeout_ms() {
+        for (value, ms) in [
+            ("3000000", 3_000_000),
+            ("250ms", 250),
+            ("30s", 30_000),
+            ("5m", 300_000),
+            ("50m", GLM_DEFAULT_TIMEOUT_MS),
+            ("2h", 7_200_000),
+            (" 10s ", 10_000),
+        ] {
+            assert_eq!(parse_timeout_ms(value), Ok(ms), "{}", value);
+        }
+        for value in [
+            "",
+            "5x",
+            "m",
+            "5 m",
+            "-5s",
+            "1.5m",
+            "5mm",
+            "99999999999999999999h",
+        ] {
+            let error = parse_timeout_ms(value).unwrap_err();
+            assert!(error.contains("invalid timeout"), "{}", value);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use crate::config::Provider;
+use crate::provider::detector::ProviderOrder;
+use crate::utils::error::AppError;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+pub const SETTINGS_FILE_NAME: &str = "claude-switch.toml";
+
+/// Preferences for claude-switch itself, read from `claude-switch.toml`
+/// in the config directory. Every section is optional.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SwitchSettings {
+    pub backups: RetentionPolicy,
+    /// User command aliases: `z = "glm"` makes `claude-switch z` switch to GLM.
+    /// A target that is not a switchable provider is a `providers.toml` profile.
+    pub aliases: BTreeMap<String, String>,
+    /// Tiebreak for `status` when a config's signals disagree
+    pub provider_order: ProviderOrder,
+    /// Warn when the saved token file was last written longer ago than this
+    pub max_token_age_days: Option<u64>,
+}
+
+/// Limits applied to the rotating backups after each new backup
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    /// Keep at most this many backups
+    pub max_count: Option<usize>,
+    /// Remove backups older than this many days
+    pub max_age_days: Option<u64>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            max_count: Some(10),
+            max_age_days: None,
+        }
+    }
+}
+
+impl SwitchSettings {
+    pub fn load(config_dir: &Path) -> Result<Self> {
+        let path = config_dir.join(SETTINGS_FILE_NAME);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        toml::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Fail if a user alias would be shadowed by one of `reserved`, the
+    /// built-in command names and aliases
+    pub fn check_aliases(&self, reserved: &[String]) -> Result<()> {
+        for (name, target) in &self.aliases {
+            if reserved.iter().any(|builtin| builtin == name) {
+                return Err(AppError::Config(format!(
+                    "Alias '{}' in {} conflicts with the built-in command '{}'",
+                    name, SETTINGS_FILE_NAME, name
+                ))
+                .into());
+            }
+            if name.is_empty() || name.starts_with('-') || target.trim().is_empty() {
+                return Err(AppError::Config(format!(
+                    "Invalid alias '{}' = '{}' in {}",
+                    name, target, SETTINGS_FILE_NAME
+                ))
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Command line words `name` stands for: the provider's own command for
+    /// `anthropic`, `glm` and `local`, `use <profile>` for anything else
+    pub fn expand_alias(&self, name: &str) -> Option<Vec<String>> {
+        let target = self.aliases.get(name)?.trim();
+        let words = match target.parse::<Provider>() {
+            Ok(provider @ (Provider::Anthropic | Provider::GLM | Provider::Local)) => {
+                vec![provider.as_str().to_string()]
+            }
+            _ => vec!["use".to_string(), target.to_string()],
+        };
+        Some(words)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_retention_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join(SETTINGS_FILE_NAME),
+            "[backups]\nmax_count = 3\nmax_age_days = 30\n",
+        )
+        .unwrap();
+
+        let settings = SwitchSettings::load(dir.path()).unwrap();
+        assert_eq!(settings.backups.max_count, Some(3));
+        assert_eq!(settings.backups.max_age_days, Some(30));
+    }
+
+    #[test]
+    fn test_missing_file_uses_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = SwitchSettings::load(dir.path()).unwrap();
+        assert_eq!(settings.backups, RetentionPolicy::default());
+    }
+
+    #[test]
+    fn test_aliases_expand_to_provider_or_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join(SETTINGS_FILE_NAME),
+            "[aliases]\nz = \"glm\"\nzai = \"Z_AI\"\nbatch = \"zai-batch\"\n",
+        )
+        .unwrap();
+
+        let settings = SwitchSettings::load(dir.path()).unwrap();
+        assert_eq!(settings.expand_alias("z").unwrap(), ["glm"]);
+        assert_eq!(settings.expand_alias("zai").unwrap(), ["glm"]);
+        assert_eq!(
+            settings.expand_alias("batch").unwrap(),
+            ["use", "zai-batch"]
+        );
+        assert_eq!(settings.expand_alias("nope"), None);
+        assert!(settings.check_aliases(&["glm".to_string()]).is_ok());
+    }
+
+    #[test]
+    fn test_alias_conflicting_with_builtin_is_rejected() {
+        let settings = SwitchSettings {
+            aliases: BTreeMap::from([("s".to_string(), "glm".to_string())]),
+            ..Default::default()
+        };
+        let error = settings
+            .check_aliases(&["status".to_string(), "s".to_string()])
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("conflicts with the built-in command"));
+    }
+}
@@ -1,41 +1,231 @@
+use crate::provider::detector::ProviderDetector;
+use crate::utils::error::AppError;
+use crate::utils::update::is_newer;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Top-level settings.json keys claude-switch reads or writes itself
+pub const MANAGED_SETTINGS_KEYS: &[&str] = &["env", "$schema"];
+
+/// Top-level settings.json keys through which Claude Code gets credentials
+/// outside `env`. Recognized by detection and status, preserved on switch.
+pub const AUTH_SETTINGS_KEYS: &[&str] = &["apiKeyHelper", "awsAuthRefresh", "awsCredentialExport"];
+
+/// Which side of `Config::merge` wins a key set by both
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precedence {
+    /// `other` only fills in missing keys; no switch or restore merges this way
+    #[allow(dead_code)]
+    SelfWins,
+    OtherWins,
+}
+
+/// `Debug` is implemented by hand so secrets in `env` are masked
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct Config {
     #[serde(default, skip_serializing_if = "std::collections::HashMap::is_empty")]
     pub env: std::collections::HashMap<String, String>,
+    /// Every other top-level field of settings.json, kept verbatim
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
-impl Default for Config {
-    fn default() -> Self {
+impl Config {
+    /// Same top-level settings, new `env`
+    pub fn with_env(&self, env: std::collections::HashMap<String, String>) -> Self {
         Self {
-            env: std::collections::HashMap::new(),
+            env,
+            extra: self.extra.clone(),
         }
     }
+
+    /// Just `env`, no other settings
+    pub fn from_env(env: impl IntoIterator<Item = (String, String)>) -> Self {
+        Self {
+            env: env.into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Union of both configs' `env` and top-level settings, as a new config.
+    ///
+    /// Top-level settings merge shallowly: an overlapping key takes the winning
+    /// side's whole value.
+    pub fn merge(&self, other: &Config, precedence: Precedence) -> Config {
+        let (base, winner) = match precedence {
+            Precedence::SelfWins => (other, self),
+            Precedence::OtherWins => (self, other),
+        };
+        let mut merged = base.clone();
+        merged
+            .env
+            .extend(winner.env.iter().map(|(k, v)| (k.clone(), v.clone())));
+        merged
+            .extra
+            .extend(winner.extra.iter().map(|(k, v)| (k.clone(), v.clone())));
+        merged
+    }
+
+    /// The `$schema` marker, if the settings file declares one
+    pub fn schema(&self) -> Option<&str> {
+        self.extra.get("$schema").and_then(|value| value.as_str())
+    }
+
+    /// Script whose output Claude Code uses as the API key
+    pub fn api_key_helper(&self) -> Option<&str> {
+        self.extra
+            .get("apiKeyHelper")
+            .and_then(|value| value.as_str())
+            .filter(|helper| !helper.trim().is_empty())
+    }
+
+    /// `AUTH_SETTINGS_KEYS` present in this config
+    pub fn auth_settings(&self) -> Vec<String> {
+        AUTH_SETTINGS_KEYS
+            .iter()
+            .filter(|key| self.extra.contains_key(**key))
+            .map(|key| key.to_string())
+            .collect()
+    }
+
+    /// Top-level keys this tool preserves but doesn't interpret
+    pub fn unmanaged_keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self
+            .extra
+            .keys()
+            .filter(|key| {
+                !MANAGED_SETTINGS_KEYS.contains(&key.as_str())
+                    && !AUTH_SETTINGS_KEYS.contains(&key.as_str())
+            })
+            .cloned()
+            .collect();
+        keys.sort();
+        keys
+    }
+}
+
+impl fmt::Debug for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Config")
+            .field("env", &redacted_env(&self.env))
+            .field("extra", &redacted_settings(&self.extra))
+            .finish()
+    }
+}
+
+/// `env` sorted by key, with secret values (`*_AUTH_TOKEN`, `*_API_KEY`) masked
+fn redacted_env(env: &HashMap<String, String>) -> BTreeMap<&str, String> {
+    env.iter()
+        .map(|(key, value)| (key.as_str(), ProviderDetector::display_value(key, value)))
+        .collect()
 }
 
+/// Top-level setting names only: values such as `apiKeyHelper` commands or
+/// MCP server env can hold credentials of any shape
+fn redacted_settings(
+    settings: &serde_json::Map<String, serde_json::Value>,
+) -> BTreeMap<&str, &str> {
+    settings
+        .keys()
+        .map(|key| (key.as_str(), "<redacted>"))
+        .collect()
+}
+
+/// `version` written into new backup metadata. Metadata from an older
+/// version is migrated when it is read (see `BackupMetadata::migrate`).
+pub const BACKUP_METADATA_VERSION: &str = "2.2.0";
+
+/// `migrated_from` of a backup written before backups had metadata
+pub const UNVERSIONED_BACKUP: &str = "unversioned";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BackupMetadata {
     pub provider: String,
     #[serde(with = "chrono::serde::ts_seconds_option")]
     pub created_at: Option<DateTime<Utc>>,
     pub version: String,
+    /// Name given with `rename-backup`, unique among rotated backups
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// The version this metadata was migrated from when it was read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub migrated_from: Option<String>,
+    /// Free-form context, e.g. the dotfiles commit from `backup --note-from-git`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl BackupMetadata {
+    /// Metadata for a backup of `provider` taken now
+    pub fn new(provider: &Provider) -> Self {
+        Self {
+            provider: provider.as_str().to_string(),
+            created_at: Some(Utc::now()),
+            version: BACKUP_METADATA_VERSION.to_string(),
+            label: None,
+            migrated_from: None,
+            note: None,
+        }
+    }
+
+    /// Metadata for a backup written before backups had any, dated by the
+    /// file's modification time
+    pub fn synthesized(provider: &Provider, modified: Option<DateTime<Utc>>) -> Self {
+        Self {
+            created_at: modified,
+            migrated_from: Some(UNVERSIONED_BACKUP.to_string()),
+            ..Self::new(provider)
+        }
+    }
+
+    /// Bring metadata from an older version up to `BACKUP_METADATA_VERSION`,
+    /// recording the original in `migrated_from`. A missing `created_at` is
+    /// estimated from the file's modification time. Metadata from this or a
+    /// newer version is returned as is.
+    pub fn migrate(mut self, modified: Option<DateTime<Utc>>) -> Self {
+        if !is_newer(BACKUP_METADATA_VERSION, &self.version) {
+            return self;
+        }
+        self.created_at = self.created_at.or(modified);
+        self.migrated_from = Some(std::mem::replace(
+            &mut self.version,
+            BACKUP_METADATA_VERSION.to_string(),
+        ));
+        self
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct BackupConfig {
     #[serde(rename = "_metadata")]
     pub metadata: BackupMetadata,
     #[serde(default, skip_serializing_if = "std::collections::HashMap::is_empty")]
     pub env: std::collections::HashMap<String, String>,
+    /// Other top-level settings, for configs backed up as a whole
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub settings: serde_json::Map<String, serde_json::Value>,
+}
+
+impl fmt::Debug for BackupConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BackupConfig")
+            .field("metadata", &self.metadata)
+            .field("env", &redacted_env(&self.env))
+            .field("settings", &redacted_settings(&self.settings))
+            .finish()
+    }
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Clone, PartialEq)]
 pub enum Provider {
     Anthropic,
     GLM,
     Custom,
+    /// An Anthropic-compatible endpoint on this machine, e.g. an Ollama proxy
+    Local,
     Unknown,
 }
 
@@ -45,14 +235,190 @@ impl Provider {
             Provider::Anthropic => "anthropic",
             Provider::GLM => "glm",
             Provider::Custom => "custom",
+            Provider::Local => "local",
             Provider::Unknown => "unknown",
         }
     }
 }
 
+impl std::str::FromStr for Provider {
+    type Err = AppError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.trim().to_lowercase().as_str() {
+            "anthropic" => Ok(Provider::Anthropic),
+            "glm" | "z_ai" => Ok(Provider::GLM),
+            "custom" => Ok(Provider::Custom),
+            "local" => Ok(Provider::Local),
+            _ => Err(AppError::Provider(format!(
+                "Unknown provider '{}' (expected anthropic, glm, custom or local)",
+                name
+            ))),
+        }
+    }
+}
+
+impl TryFrom<&str> for Provider {
+    type Error = AppError;
+
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        name.parse()
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenType {
     GLM,
     Anthropic,
     Unknown,
 }
+
+impl TokenType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenType::GLM => "glm",
+            TokenType::Anthropic => "anthropic",
+            TokenType::Unknown => "unknown",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(env: &[(&str, &str)], extra: serde_json::Value) -> Config {
+        Config {
+            env: env
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            extra: extra.as_object().cloned().unwrap_or_default(),
+        }
+    }
+
+    #[test]
+    fn test_merge_precedence() {
+        let live = config(
+            &[("ANTHROPIC_BASE_URL", "https://api.z.ai"), ("DEBUG", "1")],
+            serde_json::json!({"model": "opus", "theme": "dark"}),
+        );
+        let other = config(
+            &[("ANTHROPIC_BASE_URL", "https://gateway.example.com")],
+            serde_json::json!({"model": "sonnet", "verbose": true}),
+        );
+
+        let merged = live.merge(&other, Precedence::OtherWins);
+        assert_eq!(
+            merged.env["ANTHROPIC_BASE_URL"],
+            "https://gateway.example.com"
+        );
+        assert_eq!(merged.env["DEBUG"], "1");
+        assert_eq!(merged.extra["model"], "sonnet");
+        assert_eq!(merged.extra["theme"], "dark");
+        assert_eq!(merged.extra["verbose"], true);
+
+        let merged = live.merge(&other, Precedence::SelfWins);
+        assert_eq!(merged.env["ANTHROPIC_BASE_URL"], "https://api.z.ai");
+        assert_eq!(merged.env["DEBUG"], "1");
+        assert_eq!(merged.extra["model"], "opus");
+        assert_eq!(merged.extra["verbose"], true);
+
+        // Neither input changes
+        assert_eq!(live.env.len(), 2);
+        assert_eq!(other.extra.len(), 2);
+    }
+
+    #[test]
+    fn test_merge_disjoint_is_union_either_way() {
+        let left = config(&[("A", "1")], serde_json::json!({"x": 1}));
+        let right = config(&[("B", "2")], serde_json::json!({"y": 2}));
+
+        for precedence in [Precedence::SelfWins, Precedence::OtherWins] {
+            let merged = left.merge(&right, precedence);
+            assert_eq!(merged.env.len(), 2);
+            assert_eq!(merged.extra.len(), 2);
+        }
+        assert!(Config::default()
+            .merge(&Config::default(), Precedence::OtherWins)
+            .env
+            .is_empty());
+    }
+
+    #[test]
+    fn test_provider_from_str() {
+        for (name, provider) in [
+            ("anthropic", Provider::Anthropic),
+            ("Anthropic", Provider::Anthropic),
+            ("glm", Provider::GLM),
+            ("GLM", Provider::GLM),
+            ("z_ai", Provider::GLM),
+            ("Z_AI", Provider::GLM),
+            ("custom", Provider::Custom),
+            (" CUSTOM ", Provider::Custom),
+            ("local", Provider::Local),
+        ] {
+            assert_eq!(name.parse::<Provider>().unwrap(), provider, "{}", name);
+        }
+        assert_eq!(Provider::try_from("glm").unwrap(), Provider::GLM);
+    }
+
+    #[test]
+    fn test_provider_from_str_rejects_unknown() {
+        let error = "openai".parse::<Provider>().unwrap_err();
+        assert!(matches!(error, AppError::Provider(_)));
+        assert!(error.to_string().contains("openai"));
+        assert!("unknown".parse::<Provider>().is_err());
+    }
+
+    #[test]
+    fn test_debug_masks_secrets_but_serialization_does_not() {
+        let mut config = Config::default();
+        config.env.insert(
+            "ANTHROPIC_AUTH_TOKEN".to_string(),
+            "sk-supersecret-1234".to_string(),
+        );
+        config.env.insert(
+            "OPENAI_API_KEY".to_string(),
+            "key-abcdefgh-5678".to_string(),
+        );
+        config
+            .env
+            .insert("API_TIMEOUT_MS".to_string(), "3000000".to_string());
+
+        config.extra.insert(
+            "apiKeyHelper".to_string(),
+            serde_json::json!("echo sk-supersecret-helper"),
+        );
+        config.extra.insert(
+            "mcpServers".to_string(),
+            serde_json::json!({"db": {"env": {"DB_PASSWORD": "mcp-secret"}}}),
+        );
+
+        let debug = format!("{:?}", config);
+        assert!(debug.contains("apiKeyHelper"));
+        assert!(!debug.contains("mcp-secret"));
+        assert!(debug.contains("sk-s...1234"));
+        assert!(debug.contains("key-...5678"));
+        assert!(debug.contains("3000000"));
+        assert!(!debug.contains("supersecret"));
+        assert!(!debug.contains("abcdefgh"));
+
+        let backup = BackupConfig {
+            metadata: BackupMetadata {
+                created_at: None,
+                ..BackupMetadata::new(&Provider::Anthropic)
+            },
+            env: config.env.clone(),
+            settings: config.extra.clone(),
+        };
+        let debug = format!("{:?}", backup);
+        assert!(!debug.contains("supersecret"));
+        assert!(!debug.contains("mcp-secret"));
+        assert!(debug.contains("mcpServers"));
+
+        let json = serde_json::to_string(&config).unwrap();
+        assert!(json.contains("sk-supersecret-1234"));
+    }
+}
@@ -1,14 +1,38 @@
-use anyhow::Result;
-use clap::{Parser, Subcommand};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use clap::error::{ContextKind, ContextValue};
+use clap::{Args, CommandFactory, Parser, Subcommand};
 use colored::*;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::PathBuf;
 
 mod config;
 mod provider;
 mod utils;
 
-use config::ConfigManager;
-use provider::{AnthropicSwitcher, GLMSwitcher, StatusDisplay};
-use utils::{Installer, TokenManager};
+use config::history::parse_since;
+use config::merge::{collect_merge_env, parse_env_assignment};
+use config::profile::resolve_profile;
+use config::registry::parse_timeout_ms;
+use config::{Config, ConfigManager, Provider, SwitchSettings};
+use provider::bundle::BackupFormat;
+use provider::detector::ProviderDetector;
+use provider::picker::Picker;
+use provider::print_env::EnvFormat;
+use provider::report::StatusField;
+use provider::{
+    AuthPreference, BackupCommand, CleanCommand, CustomOptions, CustomSwitcher,
+    DeleteBackupCommand, DoctorCommand, GLMOptions, GlmRegion, HistoryCommand, ModelSelection,
+    NotifyWatcher, PrintBackupCommand, PrintEnvCommand, ProfileSwitcher, ProviderMenu,
+    RenameBackupCommand, ResetCommand, RestoreCommand, SetModelsCommand, StatusDisplay,
+    TerminalPicker, VerifyTokenCommand, WatchCommand, LOCAL_DEFAULT_PORT,
+};
+use std::io::IsTerminal;
+use utils::{
+    error, unicode_supported, ClaudeInstallation, Installer, OutputFormat, Renderer, SavePolicy,
+    SelfUpdater, TokenManager,
+};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -19,25 +43,456 @@ const VERSION: &str = env!("CARGO_PKG_VERSION");
 )]
 #[command(version = VERSION)]
 struct Cli {
+    /// Output format for commands that report data
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Human)]
+    output: OutputFormat,
+
+    /// Print JSON output on a single line instead of pretty-printed
+    #[arg(long, global = true)]
+    compact: bool,
+
+    /// Draw status panels with plain ASCII (automatic on terminals without UTF-8)
+    #[arg(long, global = true)]
+    ascii: bool,
+
+    /// Before each step of a switch, explain what it does and which file it touches
+    #[arg(long, global = true)]
+    explain: bool,
+
+    /// Use a named profile (overrides a .claude-switch file in the current directory)
+    #[arg(long, short = 'p', global = true)]
+    profile: Option<String>,
+
+    /// Operate on this settings file instead of settings.json; backups and the
+    /// token are kept in its directory
+    #[arg(long, global = true, value_name = "PATH", conflicts_with = "profile")]
+    config: Option<PathBuf>,
+
+    /// Store backups in this directory instead of the config directory
+    /// (overrides CLAUDE_BACKUP_DIR)
+    #[arg(long, global = true)]
+    backup_dir: Option<PathBuf>,
+
+    /// Read and write the saved GLM token at this path (overrides CLAUDE_TOKEN_FILE)
+    #[arg(long, global = true)]
+    token_file: Option<PathBuf>,
+
+    /// Don't check that Claude Code is installed before switching or in doctor
+    #[arg(long, global = true)]
+    ignore_missing_claude: bool,
+
+    /// Don't read the team-wide /etc/claude-switch/providers.toml
+    #[arg(long, global = true)]
+    no_system_profiles: bool,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Subcommand)]
 enum Commands {
     /// Switch to Anthropic API (restore configuration)
     #[command(alias = "a")]
-    Anthropic,
+    Anthropic {
+        /// Without a backup, rebuild the config from the Claude CLI login
+        /// (.credentials.json) instead of writing an empty one
+        #[arg(long)]
+        from_credentials: bool,
+        #[command(flatten)]
+        merge: MergeEnvArgs,
+        #[command(flatten)]
+        print: PrintOnlyArgs,
+        #[command(flatten)]
+        confirm: ConfirmProviderArgs,
+    },
     /// Switch to GLM API (use API key)
     #[command(alias = "g")]
-    GLM,
+    GLM {
+        /// Read the API token from a single line on stdin (never prompts)
+        #[arg(long)]
+        token_stdin: bool,
+        /// Run this shell command and use its stdout as the API token
+        /// (e.g. "op read op://vault/glm/token")
+        #[arg(long, value_name = "COMMAND", conflicts_with = "token_stdin")]
+        token_command: Option<String>,
+        /// Use the token on the system clipboard (e.g. copied from the Z.AI dashboard)
+        #[cfg(feature = "clipboard")]
+        #[arg(long, conflicts_with_all = ["token_stdin", "token_command"])]
+        from_clipboard: bool,
+        /// Save the entered token without asking
+        #[arg(long, conflicts_with = "no_save_token")]
+        save_token: bool,
+        /// Do not save the entered token and do not ask
+        #[arg(long)]
+        no_save_token: bool,
+        /// Never prompt (token must come from env, saved file or stdin)
+        #[arg(long)]
+        non_interactive: bool,
+        /// Replace an existing Anthropic backup with the current config
+        #[arg(long)]
+        force_backup: bool,
+        /// Do not back up the current config (it cannot be restored later)
+        #[arg(long, conflicts_with = "force_backup")]
+        no_backup: bool,
+        /// Z.AI endpoint: global (api.z.ai, the default) or cn (open.bigmodel.cn)
+        #[arg(long, value_enum)]
+        region: Option<GlmRegion>,
+        /// API_TIMEOUT_MS: milliseconds, or 30s, 5m, 1h (overrides providers.toml)
+        #[arg(long, alias = "timeout", value_parser = parse_timeout_ms)]
+        timeout_ms: Option<u64>,
+        /// Re-apply the token and models even if GLM is already active (implied by
+        /// --token-stdin, --token-command and --from-clipboard)
+        #[arg(long)]
+        force: bool,
+        /// Check the token against the endpoint after switching; a rejected token
+        /// can be re-entered once, otherwise the previous settings are put back
+        #[arg(long, conflicts_with = "print_only")]
+        verify: bool,
+        #[command(flatten)]
+        merge: MergeEnvArgs,
+        #[command(flatten)]
+        print: PrintOnlyArgs,
+        #[command(flatten)]
+        confirm: ConfirmProviderArgs,
+    },
+    /// Point Claude Code at any Anthropic-compatible base URL
+    Custom {
+        /// Base URL of the endpoint (http:// or https://)
+        #[arg(long)]
+        base_url: String,
+        /// Auth token for the endpoint
+        #[arg(long, conflicts_with = "token_stdin")]
+        token: Option<String>,
+        /// Read the auth token from a single line on stdin
+        #[arg(long)]
+        token_stdin: bool,
+        /// Model name for the opus, sonnet and haiku slots
+        #[arg(long)]
+        model: Option<String>,
+        #[command(flatten)]
+        merge: MergeEnvArgs,
+        #[command(flatten)]
+        print: PrintOnlyArgs,
+        #[command(flatten)]
+        confirm: ConfirmProviderArgs,
+    },
+    /// Point Claude Code at an Anthropic-compatible endpoint on localhost (e.g. Ollama)
+    Local {
+        /// Port the local endpoint listens on
+        #[arg(long, default_value_t = LOCAL_DEFAULT_PORT)]
+        port: u16,
+        /// Model name for the opus, sonnet and haiku slots
+        #[arg(long)]
+        model: Option<String>,
+        #[command(flatten)]
+        merge: MergeEnvArgs,
+        #[command(flatten)]
+        print: PrintOnlyArgs,
+        #[command(flatten)]
+        confirm: ConfirmProviderArgs,
+    },
+    /// Switch to a profile defined in providers.toml
+    Use {
+        /// Profile name
+        name: String,
+        /// Read the API token from a single line on stdin (never prompts)
+        #[arg(long)]
+        token_stdin: bool,
+        /// Never prompt (token must come from env, saved file or stdin)
+        #[arg(long)]
+        non_interactive: bool,
+        /// API_TIMEOUT_MS: milliseconds, or 30s, 5m, 1h (overrides the profile)
+        #[arg(long, alias = "timeout", value_parser = parse_timeout_ms)]
+        timeout_ms: Option<u64>,
+        #[command(flatten)]
+        merge: MergeEnvArgs,
+        #[command(flatten)]
+        print: PrintOnlyArgs,
+        #[command(flatten)]
+        confirm: ConfirmProviderArgs,
+    },
+    /// List the model IDs the active GLM, custom or local endpoint serves
+    #[cfg(feature = "list-models")]
+    ListModels,
+    /// Change the models of the active GLM, custom or local config, keeping its token
+    #[command(group(clap::ArgGroup::new("models").required(true).multiple(true)))]
+    SetModels {
+        /// ANTHROPIC_DEFAULT_OPUS_MODEL
+        #[arg(long, group = "models")]
+        opus: Option<String>,
+        /// ANTHROPIC_DEFAULT_SONNET_MODEL
+        #[arg(long, group = "models")]
+        sonnet: Option<String>,
+        /// ANTHROPIC_DEFAULT_HAIKU_MODEL
+        #[arg(long, group = "models")]
+        haiku: Option<String>,
+    },
+    /// Apply a profile and re-apply it whenever settings.json drifts away from it
+    Watch {
+        /// Profile name
+        name: String,
+        /// Wait for writes to settle this long before acting (milliseconds, or 2s, ...)
+        #[arg(long, default_value_t = provider::watch::DEFAULT_DEBOUNCE_MS, value_parser = parse_timeout_ms)]
+        debounce_ms: u64,
+    },
+    /// Back up the current Anthropic configuration
+    Backup {
+        /// Replace an existing backup (the old one is kept in rotation; JSON
+        /// backups only)
+        #[arg(long, conflicts_with = "format")]
+        force_backup: bool,
+        /// `tar` bundles settings.json and the saved token into a portable .tar.gz
+        #[arg(long, value_enum, default_value_t = BackupFormat::Json)]
+        format: BackupFormat,
+        /// Where to write the tar bundle (implies --format tar; default:
+        /// ./claude-switch-<timestamp>.tar.gz)
+        #[arg(long, value_name = "PATH", conflicts_with = "force_backup")]
+        archive: Option<PathBuf>,
+        /// Note the git branch and commit of the config directory in the backup
+        /// (JSON backups; nothing is noted outside a git checkout)
+        #[arg(long, alias = "backup-note-from-git", conflicts_with = "archive")]
+        note_from_git: bool,
+    },
+    /// Make sure an Anthropic backup exists, creating one only if there is none
+    EnsureBackup,
+    /// Restore a rotated backup by id (timestamp) or label
+    Restore {
+        /// Backup id, e.g. 20240131T120000000Z, or a label from rename-backup
+        #[arg(required_unless_present_any = ["from", "select"])]
+        backup: Option<String>,
+        /// Write the backup to this file instead, leaving settings.json alone
+        #[arg(long, value_name = "PATH")]
+        to: Option<PathBuf>,
+        /// Restore a bundle written by `backup --format tar`
+        #[arg(long, value_name = "ARCHIVE", conflicts_with_all = ["backup", "to"])]
+        from: Option<PathBuf>,
+        /// Choose the backup from an interactive list (needs a terminal)
+        #[arg(long, conflicts_with_all = ["backup", "to", "from"])]
+        select: bool,
+    },
+    /// Print a rotated backup's JSON, tokens masked
+    PrintBackup {
+        /// Backup id or label, as for restore; the newest backup when omitted
+        backup: Option<String>,
+        /// Show tokens and API keys unmasked
+        #[arg(long)]
+        include_secrets: bool,
+    },
+    /// Give a rotated backup a memorable label
+    RenameBackup {
+        /// Backup id (the timestamp in its file name)
+        id: String,
+        /// Unique label, usable with restore
+        label: String,
+    },
+    /// Remove one rotated backup
+    DeleteBackup {
+        /// Backup id, label, or its number in the `restore --select` list
+        backup: String,
+        /// Don't ask for confirmation (required when not in a terminal)
+        #[arg(long)]
+        confirm: bool,
+        /// Delete the backup even when it is the last one left
+        #[arg(long)]
+        force: bool,
+    },
     /// Show current configuration
     #[command(alias = "s")]
-    Status,
+    Status {
+        /// Show the live config and the Anthropic backup side by side
+        #[arg(long)]
+        compare_providers: bool,
+        /// Print the auth token unmasked (asks first; needs a terminal)
+        #[arg(long)]
+        show_token: bool,
+        /// With --show-token: allow non-terminal use; the answer is still read
+        /// from stdin (e.g. `echo y | claude-switch status --show-token --force`)
+        #[arg(long, requires = "show_token")]
+        force: bool,
+        /// Print only this value, unformatted
+        #[arg(long, value_enum, conflicts_with_all = ["compare_providers", "show_token"])]
+        field: Option<StatusField>,
+        /// Exit 1 if the live env differs from the Anthropic backup (0 if equal or no backup)
+        #[arg(long, conflicts_with_all = ["compare_providers", "show_token", "field"])]
+        diff_backup: bool,
+        /// List every rotated backup with its provider, age and token health
+        #[arg(long, conflicts_with_all = ["compare_providers", "show_token", "field", "diff_backup"])]
+        all_backups: bool,
+        /// List each provider env key (token, base URL, timeout, models) as set
+        /// or not, tokens masked
+        #[arg(long, conflicts_with_all = ["compare_providers", "show_token", "field", "diff_backup", "all_backups"])]
+        provider_env: bool,
+        /// One `key=value` line (provider, url, masked token, backup) for shell prompts
+        #[arg(long, conflicts_with_all = ["compare_providers", "show_token", "field", "diff_backup", "all_backups", "provider_env"])]
+        oneline: bool,
+    },
+    /// Show past switches
+    History {
+        /// Only switches since a duration ago (30m, 24h, 7d, 2w) or a date
+        #[arg(long, value_name = "DURATION|DATE", value_parser = parse_since)]
+        since: Option<DateTime<Utc>>,
+    },
+    /// Print the active env as shell exports (for `eval`)
+    PrintEnv {
+        /// Export tokens and API keys instead of commenting them out
+        #[arg(long)]
+        include_secrets: bool,
+        /// `export` lines for eval, or plain `KEY='value'` lines for an env file
+        #[arg(long, value_enum, default_value_t = EnvFormat::Export)]
+        format: EnvFormat,
+    },
+    /// Check the live config for problems Claude Code may trip over
+    Doctor {
+        /// When both ANTHROPIC_AUTH_TOKEN and ANTHROPIC_API_KEY are set, keep this one
+        #[arg(long, value_enum, value_name = "auth|key")]
+        prefer: Option<AuthPreference>,
+        /// Tighten a saved token file other users can read to mode 0600
+        #[arg(long)]
+        fix_perms: bool,
+        /// Apply the safe repairs: token file mode and whitespace, leftover
+        /// files, a missing config directory
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Remove leftover temp files and orphaned backup metadata
+    Clean {
+        /// Also prune rotated backups per the retention policy
+        #[arg(long)]
+        backups: bool,
+        /// Only report what would be removed
+        #[arg(long)]
+        dry_run: bool,
+    },
     /// Remove saved GLM API token
-    ClearToken,
+    ClearToken {
+        /// Only report whether a token is saved and where
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Copy the saved token to the clipboard (only a masked form is printed)
+    #[cfg(feature = "clipboard")]
+    CopyToken,
+    /// Delete settings, backups, tokens and history managed by claude-switch
+    Reset {
+        /// Don't ask for confirmation (required when not in a terminal)
+        #[arg(long)]
+        confirm: bool,
+    },
+    /// Check a token's format without switching (token from --token, env or stdin)
+    VerifyToken {
+        /// Token to check (otherwise Z_AI_AUTH_TOKEN or a line on stdin)
+        #[arg(long)]
+        token: Option<String>,
+        /// Provider the token is meant for (anthropic, glm, custom)
+        #[arg(long = "for")]
+        for_provider: Option<Provider>,
+        /// Exit with an error if the token does not match --for
+        #[arg(long, requires = "for_provider")]
+        strict: bool,
+    },
     /// Install aliases to shell
-    Install,
+    Install {
+        /// Keep the binary where it is and only install aliases
+        #[arg(long)]
+        skip_binary: bool,
+        /// Show the binary path, shell configs and alias block without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Remove the aliases written by install
+    Uninstall,
+    /// Download and install the latest release
+    SelfUpdate {
+        /// Only report whether an update is available
+        #[arg(long)]
+        check: bool,
+    },
+    /// Manage where claude-switch stores its files
+    ConfigDir {
+        #[command(subcommand)]
+        action: ConfigDirAction,
+    },
+}
+
+/// Extra env entries for the switch commands
+#[derive(Args)]
+struct MergeEnvArgs {
+    /// Also set KEY=VALUE in the written config (repeatable)
+    #[arg(long = "merge-env", value_name = "KEY=VALUE", value_parser = parse_env_assignment)]
+    merge_env: Vec<(String, String)>,
+    /// Let a repeated --merge-env key take the last value instead of failing
+    #[arg(long)]
+    last_wins: bool,
+}
+
+/// `--print-only` for the switch commands
+#[derive(Args)]
+struct PrintOnlyArgs {
+    /// Print the config the switch would write as JSON and exit without touching any file
+    #[arg(long)]
+    print_only: bool,
+    /// With --print-only: show tokens and API keys unmasked
+    #[arg(long, requires = "print_only")]
+    include_secrets: bool,
+}
+
+/// `--confirm-provider` for the switch commands
+#[derive(Args)]
+struct ConfirmProviderArgs {
+    /// Fail unless the resulting config is detected as this provider
+    /// (anthropic, glm, custom or local)
+    #[arg(long, value_name = "PROVIDER")]
+    confirm_provider: Option<Provider>,
+}
+
+impl ConfirmProviderArgs {
+    /// Fail unless `config` is detected as the expected provider
+    fn check(&self, config_manager: &ConfigManager, config: &Config) -> Result<()> {
+        let Some(expected) = &self.confirm_provider else {
+            return Ok(());
+        };
+        let order = SwitchSettings::load(config_manager.preferences_dir())?.provider_order;
+        ProviderDetector::confirm_provider(config, expected, &order)
+    }
+
+    /// `check` the config a switch just wrote
+    fn check_written(&self, config_manager: &ConfigManager) -> Result<()> {
+        let Some(expected) = &self.confirm_provider else {
+            return Ok(());
+        };
+        self.check(config_manager, &config_manager.load_current_config()?)?;
+        println!("{}{}", "✅ Confirmed provider: ".green(), expected.as_str());
+        Ok(())
+    }
+}
+
+impl PrintOnlyArgs {
+    fn print(&self, config: &Config) -> Result<()> {
+        println!(
+            "{}",
+            provider::print_env::config_json(config, self.include_secrets)?
+        );
+        Ok(())
+    }
+}
+
+impl MergeEnvArgs {
+    fn collect(self) -> Result<BTreeMap<String, String>> {
+        collect_merge_env(self.merge_env, self.last_wins)
+    }
+}
+
+#[derive(Subcommand)]
+enum ConfigDirAction {
+    /// Show the directory currently in use
+    Show,
+    /// Move settings, backups and token to $XDG_CONFIG_HOME/claude-switch
+    Migrate {
+        /// Target directory (defaults to $XDG_CONFIG_HOME/claude-switch)
+        #[arg(long)]
+        to: Option<PathBuf>,
+    },
 }
 
 fn print_header() {
@@ -50,13 +505,53 @@ fn print_usage() {
     println!("{}", "Usage:".cyan());
     println!();
     println!("  claude-switch [command]");
+    println!("  (run without a command in a terminal for an interactive menu)");
     println!();
     println!("{}", "Commands:".cyan());
     println!("  -a, --anthropic  Switch to Anthropic API (restore configuration)");
     println!("  -g, --glm        Switch to GLM API (use API key)");
     println!("  -s, --status     Show current configuration");
-    println!("  --clear-token    Remove saved GLM API token");
+    println!("  status --compare-providers  Live config vs Anthropic backup, side by side");
+    println!("  status --show-token  Print the auth token unmasked (asks first)");
+    println!("  status --field <name>  Print one value (provider, base_url, token_type, ...)");
+    println!("  status --all-backups  Provider, age and token health of every backup");
+    println!("  status --provider-env  Which provider env vars are set, tokens masked");
+    println!("  status --oneline  provider=... url=... token=... backup=... for prompts");
+    println!("  use <profile>    Switch to a profile from providers.toml");
+    println!("  list-models      List the model IDs the active endpoint serves");
+    println!("  set-models --opus <m> --sonnet <m> --haiku <m>  Change models, keep the token");
+    println!("  watch <profile>  Re-apply a profile whenever settings.json drifts from it");
+    println!("  custom --base-url <url>  Switch to any Anthropic-compatible endpoint");
+    println!("  local [--port <port>]    Switch to a local endpoint such as Ollama (no token)");
+    println!("  --clear-token    Remove saved GLM API token (--dry-run to preview)");
+    println!("  copy-token       Copy the saved token to the clipboard");
+    println!("  reset --confirm  Delete all settings, backups, tokens and history");
     println!("  --install        Install aliases to shell");
+    println!("  install --skip-binary  Only install aliases (binary already on PATH)");
+    println!("  uninstall        Remove the installed shell aliases");
+    println!("  verify-token     Check a token's format without switching");
+    println!("  backup           Back up the current Anthropic configuration");
+    println!("  backup --format tar  Bundle settings and token into a .tar.gz");
+    println!("  backup --note-from-git  Note the config dir's git branch and commit");
+    println!("  ensure-backup    Create an Anthropic backup only if there is none");
+    println!("  restore <backup> Restore a rotated backup by id or label (--to <path>)");
+    println!("  restore --from <archive>  Restore a backup --format tar bundle");
+    println!("  restore --select  Pick the backup to restore from a list");
+    println!("  print-backup [backup]  Print a backup's JSON (--include-secrets)");
+    println!("  rename-backup <id> <label>  Label a rotated backup");
+    println!(
+        "  delete-backup <backup>  Remove one rotated backup (--confirm, --force for the last)"
+    );
+    println!("  history          Show past switches (--since 7d|24h|<date>)");
+    println!("  print-env        Print the active env as shell exports (--include-secrets)");
+    println!("  print-env --format env  Print KEY='value' lines for an env file to source");
+    println!("  doctor           Check the config (--prefer auth|key fixes doubled auth vars)");
+    println!("  doctor --fix-perms  Make a token file other users can read private (0600)");
+    println!("  doctor --fix     Apply the safe repairs doctor finds");
+    println!("  clean            Remove leftover temp files (--backups also prunes)");
+    println!("  self-update      Download and install the latest release");
+    println!("  config-dir show  Show the directory in use");
+    println!("  config-dir migrate  Move files to $XDG_CONFIG_HOME/claude-switch");
     println!("  -v, --version    Show version");
     println!("  -h, --help       Show this help message");
     println!();
@@ -66,11 +561,43 @@ fn print_usage() {
     println!();
     println!("{}", "Environment Variables:".cyan());
     println!("  GLM_AUTH_TOKEN  GLM API key (optional)");
+    println!("  CLAUDE_CONFIG_DIR  Config directory (default: ~/.claude)");
+    println!("  CLAUDE_BACKUP_DIR  Directory for backups (default: config directory)");
+    println!("  CLAUDE_TOKEN_FILE  Saved token file (default: <config dir>/.claude-switch-token)");
+    println!();
+    println!("{}", "Options:".cyan());
+    println!("  --output <human|json>  Output format for status (default: human)");
+    println!("  --backup-dir <path>    Store backups outside the config directory");
+    println!("  --config <path>        Operate on this settings file instead of settings.json");
+    println!("  --no-system-profiles   Ignore /etc/claude-switch/providers.toml");
+    println!("  -p, --profile <name>   Use a profile (default: .claude-switch in the current dir)");
+    println!("  --merge-env KEY=VALUE  Also set an env var when switching (repeatable)");
+    println!("  --print-only           Print the config a switch would write, write nothing");
+    println!("  --confirm-provider <p> Fail unless the switch results in this provider");
+    println!("  glm --verify       Check the token with the endpoint after switching");
+    println!("  glm --token-command <cmd>  Use a command's stdout as the GLM API key");
+    println!("  glm --region cn  Use Z.AI's mainland China endpoint (open.bigmodel.cn)");
+    #[cfg(feature = "clipboard")]
+    println!("  glm --from-clipboard  Use the GLM API key on the clipboard");
+    println!(
+        "  glm --token-stdin  Read the GLM API key from stdin (e.g. piped from a secret store)"
+    );
+    println!();
+    println!("{}", "Exit Codes:".cyan());
+    println!("  0   Success");
+    println!("  1   Generic error");
+    println!("  2   Configuration error (unreadable or invalid settings)");
+    println!("  3   Token error (missing or empty token)");
+    println!("  4   Filesystem error (permission denied, missing home)");
+    println!("  5   Provider error");
+    println!("  6   Installation error");
+    println!("  64  Invalid command line usage");
     println!();
     println!("{}", "Examples:".cyan());
     println!("  claude-switch --glm        # Backup Anthropic config, switch to GLM");
     println!("  claude-switch --anthropic  # Restore Anthropic config from backup");
     println!("  claude-switch --status     # Check current provider");
+    println!("  echo $TOKEN | claude-switch glm --token-stdin");
     println!();
     println!(
         "{}",
@@ -83,64 +610,537 @@ fn print_usage() {
     println!();
 }
 
-fn main() -> Result<()> {
-    // Parse command line arguments using clap for better compatibility
-    let cli = Cli::parse();
+/// What running `claude-switch` without a subcommand does
+#[derive(Debug, PartialEq)]
+enum NoCommandAction {
+    Interactive,
+    Usage,
+}
+
+fn no_command_action(is_tty: bool) -> NoCommandAction {
+    if is_tty {
+        NoCommandAction::Interactive
+    } else {
+        NoCommandAction::Usage
+    }
+}
+
+fn migrate_config_dir(config_manager: &ConfigManager, to: Option<PathBuf>) -> Result<()> {
+    let target = match to {
+        Some(dir) => dir,
+        None => ConfigManager::xdg_config_dir()?,
+    };
+
+    println!(
+        "{}{}",
+        "📦 Migrating configuration to ".cyan(),
+        target.display()
+    );
+
+    let moved = config_manager.migrate_to(&target)?;
+    for path in &moved {
+        println!("  {}{}", "✅ Moved ".green(), path.display());
+    }
+    if moved.is_empty() {
+        println!("{}", "⚠️  No managed files found to migrate".yellow());
+    }
+
+    println!(
+        "{}{}",
+        "💾 New config directory recorded: ".cyan(),
+        target.display()
+    );
+    Ok(())
+}
+
+/// Built-in subcommand names and their aliases, which user aliases may not shadow
+fn builtin_command_names() -> Vec<String> {
+    Cli::command()
+        .get_subcommands()
+        .flat_map(|command| std::iter::once(command.get_name()).chain(command.get_all_aliases()))
+        .map(str::to_string)
+        .collect()
+}
+
+/// `claude-switch.toml` from the global config directory, with its `[aliases]` checked
+fn load_user_aliases(config_manager: &ConfigManager) -> Result<SwitchSettings> {
+    let settings = SwitchSettings::load(config_manager.preferences_dir())?;
+    settings.check_aliases(&builtin_command_names())?;
+    Ok(settings)
+}
+
+/// `args` with the user alias `name` replaced by what it stands for, or
+/// `None` when `name` is not an alias
+fn expand_user_alias(args: &[OsString], name: &str) -> Result<Option<Vec<OsString>>> {
+    let settings = load_user_aliases(&ConfigManager::new()?)?;
+    let Some(words) = settings.expand_alias(name) else {
+        return Ok(None);
+    };
+    let Some(position) = subcommand_position(args).filter(|i| args[*i] == *name) else {
+        return Ok(None);
+    };
+
+    let mut expanded = args[..position].to_vec();
+    expanded.extend(words.into_iter().map(OsString::from));
+    expanded.extend_from_slice(&args[position + 1..]);
+    Ok(Some(expanded))
+}
+
+/// Index of the first argument that is neither a global option nor its value
+fn subcommand_position(args: &[OsString]) -> Option<usize> {
+    let command = Cli::command();
+    let takes_value = |flag: &str| {
+        command.get_arguments().any(|arg| {
+            let named = match flag.strip_prefix("--") {
+                Some(long) => arg.get_long() == Some(long),
+                None => flag.len() == 2 && arg.get_short() == flag.chars().nth(1),
+            };
+            named && arg.get_action().takes_values()
+        })
+    };
 
-    // Initialize config manager
-    let config_manager = match ConfigManager::new() {
-        Ok(cm) => cm,
-        Err(e) => {
-            eprintln!("{}{}", "Error: ".red(), e);
-            std::process::exit(1);
+    let mut index = 1;
+    while index < args.len() {
+        let arg = args[index].to_string_lossy();
+        if !arg.starts_with('-') {
+            return Some(index);
         }
+        if !arg.contains('=') && takes_value(&arg) {
+            index += 1;
+        }
+        index += 1;
+    }
+    None
+}
+
+fn run(cli: Cli, renderer: Renderer) -> Result<()> {
+    let mut config_manager = match &cli.config {
+        Some(settings_file) => ConfigManager::with_settings_file(settings_file.clone()),
+        None => ConfigManager::new()?,
     };
+    // An explicit settings file is not scoped by a .claude-switch file
+    if cli.config.is_none() {
+        let cwd = std::env::current_dir().context("Failed to get current directory")?;
+        if let Some(selection) = resolve_profile(cli.profile.as_deref(), &cwd)? {
+            config_manager = config_manager.with_profile(&selection.name);
+        }
+    }
+    if cli.no_system_profiles {
+        config_manager = config_manager.without_system_profiles();
+    }
+    if let Some(backup_dir) = cli.backup_dir {
+        config_manager = config_manager.with_backup_dir(backup_dir);
+    }
+    if let Some(token_file) = cli.token_file {
+        config_manager = config_manager.with_token_file(token_file);
+    }
+
+    let switches = matches!(
+        cli.command,
+        Some(
+            Commands::Anthropic { .. }
+                | Commands::GLM { .. }
+                | Commands::Custom { .. }
+                | Commands::Local { .. }
+                | Commands::Use { .. }
+                | Commands::Watch { .. }
+        )
+    );
+    if switches && !cli.ignore_missing_claude {
+        ClaudeInstallation::detect().warn_if_missing();
+    }
 
-    // Handle the command
     match cli.command {
-        Some(Commands::Anthropic) => {
-            let switcher = AnthropicSwitcher::new(config_manager);
-            if let Err(e) = switcher.switch_to_anthropic() {
-                eprintln!("{}{}", "Error: ".red(), e);
-                std::process::exit(1);
+        Some(Commands::Anthropic {
+            from_credentials,
+            merge,
+            print,
+            confirm,
+        }) => {
+            let options = GLMOptions {
+                merge_env: merge.collect()?,
+                explain: cli.explain,
+                ..Default::default()
+            };
+            let switcher = ProfileSwitcher::new(config_manager.clone(), options)
+                .with_credentials_fallback(from_credentials);
+            if print.print_only {
+                let config = switcher.target_config("anthropic")?;
+                confirm.check(&config_manager, &config)?;
+                print.print(&config)?;
+            } else {
+                switcher.switch_to("anthropic")?;
+                confirm.check_written(&config_manager)?;
+            }
+        }
+        Some(Commands::GLM {
+            token_stdin,
+            token_command,
+            #[cfg(feature = "clipboard")]
+            from_clipboard,
+            save_token,
+            no_save_token,
+            non_interactive,
+            force_backup,
+            no_backup,
+            region,
+            timeout_ms,
+            force,
+            verify,
+            merge,
+            print,
+            confirm,
+        }) => {
+            let save_policy = if save_token {
+                SavePolicy::Always
+            } else if no_save_token || non_interactive {
+                SavePolicy::Never
+            } else {
+                SavePolicy::Ask
+            };
+            let options = GLMOptions {
+                token_stdin,
+                token_command,
+                #[cfg(feature = "clipboard")]
+                from_clipboard,
+                save_policy,
+                non_interactive,
+                force_backup,
+                no_backup,
+                region,
+                timeout_ms,
+                merge_env: merge.collect()?,
+                explain: cli.explain,
+                force,
+                verify,
+                ..Default::default()
+            };
+            let switcher = ProfileSwitcher::new(config_manager.clone(), options);
+            if print.print_only {
+                let config = switcher.target_config("glm")?;
+                confirm.check(&config_manager, &config)?;
+                print.print(&config)?;
+            } else {
+                switcher.switch_to("glm")?;
+                confirm.check_written(&config_manager)?;
+            }
+        }
+        Some(Commands::Custom {
+            base_url,
+            token,
+            token_stdin,
+            model,
+            merge,
+            print,
+            confirm,
+        }) => {
+            let options = CustomOptions {
+                base_url,
+                token,
+                token_stdin,
+                model,
+                merge_env: merge.collect()?,
+                explain: cli.explain,
+            };
+            let switcher = CustomSwitcher::new(config_manager.clone(), options);
+            if print.print_only {
+                let config = switcher.target_config()?;
+                confirm.check(&config_manager, &config)?;
+                print.print(&config)?;
+            } else {
+                switcher.switch_to_custom()?;
+                confirm.check_written(&config_manager)?;
             }
         }
-        Some(Commands::GLM) => {
-            let switcher = GLMSwitcher::new(config_manager);
-            if let Err(e) = switcher.switch_to_glm() {
-                eprintln!("{}{}", "Error: ".red(), e);
-                std::process::exit(1);
+        Some(Commands::Local {
+            port,
+            model,
+            merge,
+            print,
+            confirm,
+        }) => {
+            let options = CustomOptions {
+                merge_env: merge.collect()?,
+                explain: cli.explain,
+                ..CustomOptions::local(port, model)
+            };
+            let switcher = CustomSwitcher::new(config_manager.clone(), options);
+            if print.print_only {
+                let config = switcher.target_config()?;
+                confirm.check(&config_manager, &config)?;
+                print.print(&config)?;
+            } else {
+                switcher.switch_to_custom()?;
+                confirm.check_written(&config_manager)?;
             }
         }
-        Some(Commands::Status) => {
-            let display = StatusDisplay::new(config_manager);
-            if let Err(e) = display.show_status() {
-                eprintln!("{}{}", "Error: ".red(), e);
-                std::process::exit(1);
+        Some(Commands::Use {
+            name,
+            token_stdin,
+            non_interactive,
+            timeout_ms,
+            merge,
+            print,
+            confirm,
+        }) => {
+            let options = GLMOptions {
+                token_stdin,
+                save_policy: if non_interactive {
+                    SavePolicy::Never
+                } else {
+                    SavePolicy::Ask
+                },
+                non_interactive,
+                timeout_ms,
+                merge_env: merge.collect()?,
+                explain: cli.explain,
+                ..Default::default()
+            };
+            let switcher = ProfileSwitcher::new(config_manager.clone(), options);
+            if print.print_only {
+                let config = switcher.target_config(&name)?;
+                confirm.check(&config_manager, &config)?;
+                print.print(&config)?;
+            } else {
+                switcher.switch_to(&name)?;
+                confirm.check_written(&config_manager)?;
             }
         }
-        Some(Commands::ClearToken) => {
-            if let Err(e) = TokenManager::clear_saved_token(&config_manager) {
-                eprintln!("{}{}", "Error: ".red(), e);
-                std::process::exit(1);
+        #[cfg(feature = "list-models")]
+        Some(Commands::ListModels) => {
+            provider::ListModelsCommand::new(config_manager, renderer).run()?;
+        }
+        Some(Commands::SetModels {
+            opus,
+            sonnet,
+            haiku,
+        }) => {
+            SetModelsCommand::new(config_manager).run(&ModelSelection {
+                opus,
+                sonnet,
+                haiku,
+            })?;
+        }
+        Some(Commands::Watch { name, debounce_ms }) => {
+            let options = GLMOptions {
+                explain: cli.explain,
+                ..Default::default()
+            };
+            let mut watcher = NotifyWatcher::new(
+                config_manager.settings_file().to_path_buf(),
+                std::time::Duration::from_millis(debounce_ms),
+            )?
+            .stop_on_ctrl_c()?;
+            WatchCommand::new(config_manager, name, options).run(&mut watcher)?;
+        }
+        Some(Commands::Backup {
+            force_backup,
+            format,
+            archive,
+            note_from_git,
+        }) => match (format, archive) {
+            (BackupFormat::Json, None) => BackupCommand::new(config_manager)
+                .with_note_from_git(note_from_git)
+                .run(force_backup)?,
+            (_, archive) => BackupCommand::new(config_manager).run_bundle(archive)?,
+        },
+        Some(Commands::EnsureBackup) => {
+            BackupCommand::new(config_manager).run_ensure()?;
+        }
+        Some(Commands::Status {
+            compare_providers,
+            show_token,
+            force,
+            field,
+            diff_backup,
+            all_backups,
+            provider_env,
+            oneline,
+        }) => {
+            let display = StatusDisplay::new(config_manager, renderer)
+                .with_token_reveal(show_token, force)?;
+            if let Some(field) = field {
+                display.show_field(field)?;
+            } else if diff_backup {
+                display.show_backup_diff()?;
+            } else if all_backups {
+                display.show_all_backups()?;
+            } else if provider_env {
+                display.show_provider_env()?;
+            } else if oneline {
+                display.show_oneline()?;
+            } else if compare_providers {
+                display.show_comparison()?;
+            } else {
+                display.show_status()?;
+            }
+        }
+        Some(Commands::Restore {
+            backup,
+            to,
+            from,
+            select,
+        }) => {
+            let command = RestoreCommand::new(config_manager);
+            match (from, backup, to) {
+                (Some(archive), _, _) => command.run_from(&archive)?,
+                (None, Some(backup), Some(path)) => command.run_to(&backup, &path)?,
+                (None, Some(backup), None) => command.run(&backup)?,
+                (None, None, _) if select => {
+                    let is_tty = std::io::stdin().is_terminal() && std::io::stdout().is_terminal();
+                    command.run_select(is_tty.then_some(&TerminalPicker as &dyn Picker))?
+                }
+                (None, None, _) => unreachable!("clap requires a backup, --from or --select"),
+            }
+        }
+        Some(Commands::PrintBackup {
+            backup,
+            include_secrets,
+        }) => {
+            PrintBackupCommand::new(config_manager).run(backup.as_deref(), include_secrets)?;
+        }
+        Some(Commands::RenameBackup { id, label }) => {
+            RenameBackupCommand::new(config_manager).run(&id, &label)?;
+        }
+        Some(Commands::DeleteBackup {
+            backup,
+            confirm,
+            force,
+        }) => {
+            DeleteBackupCommand::new(config_manager).run(&backup, confirm, force)?;
+        }
+        Some(Commands::History { since }) => {
+            HistoryCommand::new(config_manager, renderer).run(since)?;
+        }
+        Some(Commands::PrintEnv {
+            include_secrets,
+            format,
+        }) => {
+            PrintEnvCommand::new(config_manager)
+                .with_format(format)
+                .run(include_secrets)?;
+        }
+        Some(Commands::Doctor {
+            prefer,
+            fix_perms,
+            fix,
+        }) => {
+            let problems = DoctorCommand::new(config_manager)
+                .with_prefer(prefer)
+                .with_fix_perms(fix_perms)
+                .with_fix(fix)
+                .with_installation((!cli.ignore_missing_claude).then(ClaudeInstallation::detect))
+                .run()?;
+            if !problems.is_empty() {
+                return Err(anyhow::anyhow!("{} problem(s) found", problems.len()));
             }
         }
-        Some(Commands::Install) => {
-            let installer = Installer::new()?;
-            if let Err(e) = installer.install() {
-                eprintln!("{}{}", "Error: ".red(), e);
-                std::process::exit(1);
+        Some(Commands::Clean { backups, dry_run }) => {
+            CleanCommand::new(config_manager).run(backups, dry_run)?;
+        }
+        Some(Commands::ClearToken { dry_run }) => {
+            TokenManager::clear_saved_token(&config_manager, dry_run)?;
+        }
+        #[cfg(feature = "clipboard")]
+        Some(Commands::CopyToken) => {
+            provider::CopyTokenCommand::new(&config_manager).run(&mut utils::SystemClipboard)?;
+        }
+        Some(Commands::Reset { confirm }) => {
+            ResetCommand::new(config_manager).run(confirm)?;
+        }
+        Some(Commands::VerifyToken {
+            token,
+            for_provider,
+            strict,
+        }) => {
+            VerifyTokenCommand::new(renderer).run(token, for_provider, strict)?;
+        }
+        Some(Commands::Install {
+            skip_binary,
+            dry_run,
+        }) => {
+            let installer =
+                Installer::new(config_manager.aliases_file())?.with_skip_binary(skip_binary);
+            if dry_run {
+                installer.dry_run()?;
+            } else {
+                installer.install()?;
             }
         }
+        Some(Commands::Uninstall) => {
+            Installer::new(config_manager.aliases_file())?.uninstall()?;
+        }
+        Some(Commands::SelfUpdate { check }) => {
+            SelfUpdater::new(VERSION).update(check)?;
+        }
+        Some(Commands::ConfigDir { action }) => match action {
+            ConfigDirAction::Show => {
+                println!("{}", config_manager.config_dir().display());
+            }
+            ConfigDirAction::Migrate { to } => {
+                migrate_config_dir(&config_manager, to)?;
+            }
+        },
         None => {
-            // No command provided, show usage
-            print_usage();
+            // No command provided: menu on a terminal, usage otherwise
+            let is_tty = std::io::stdin().is_terminal() && std::io::stdout().is_terminal();
+            match no_command_action(is_tty) {
+                NoCommandAction::Interactive => {
+                    ProviderMenu::new(config_manager, TerminalPicker).run()?;
+                }
+                NoCommandAction::Usage => print_usage(),
+            }
         }
     }
 
     Ok(())
 }
 
+fn exit_with_error(e: anyhow::Error, renderer: &Renderer) -> ! {
+    renderer.error(&e);
+    std::process::exit(error::exit_code_for(&e));
+}
+
+fn exit_with_usage_error(e: clap::Error) -> ! {
+    if e.use_stderr() {
+        let _ = e.print();
+        std::process::exit(error::EXIT_USAGE);
+    }
+    // --help / --version
+    e.exit()
+}
+
+/// Parse the command line, retrying with a user alias expanded when clap
+/// doesn't know the subcommand
+fn parse_cli() -> Cli {
+    let args: Vec<OsString> = std::env::args_os().collect();
+    let error = match Cli::try_parse_from(&args) {
+        Ok(cli) => return cli,
+        Err(error) => error,
+    };
+
+    if let Some(ContextValue::String(name)) = error.get(ContextKind::InvalidSubcommand) {
+        match expand_user_alias(&args, name) {
+            Ok(Some(expanded)) => {
+                return Cli::try_parse_from(expanded).unwrap_or_else(|e| exit_with_usage_error(e))
+            }
+            Ok(None) => {}
+            Err(e) => exit_with_error(e, &Renderer::default()),
+        }
+    }
+    exit_with_usage_error(error)
+}
+
+fn main() {
+    let cli = parse_cli();
+    let renderer = Renderer::new(cli.output)
+        .with_compact(cli.compact)
+        .with_ascii(cli.ascii || !unicode_supported());
+    if let Err(e) = run(cli, renderer) {
+        exit_with_error(e, &renderer);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -149,4 +1149,34 @@ mod tests {
     fn test_version_constant() {
         assert_eq!(VERSION, env!("CARGO_PKG_VERSION"));
     }
+
+    #[test]
+    fn test_no_command_action() {
+        assert_eq!(no_command_action(true), NoCommandAction::Interactive);
+        assert_eq!(no_command_action(false), NoCommandAction::Usage);
+    }
+
+    #[test]
+    fn test_subcommand_position_skips_global_option_values() {
+        let position = |args: &[&str]| {
+            let args: Vec<OsString> = args.iter().map(OsString::from).collect();
+            subcommand_position(&args)
+        };
+        assert_eq!(position(&["claude-switch", "z"]), Some(1));
+        assert_eq!(position(&["claude-switch", "-p", "z", "z"]), Some(3));
+        assert_eq!(
+            position(&["claude-switch", "--output", "json", "--compact", "z"]),
+            Some(4)
+        );
+        assert_eq!(position(&["claude-switch", "--profile=work", "z"]), Some(2));
+        assert_eq!(position(&["claude-switch", "--explain"]), None);
+    }
+
+    #[test]
+    fn test_builtin_command_names_include_aliases() {
+        let names = builtin_command_names();
+        for name in ["glm", "g", "anthropic", "a", "status", "s"] {
+            assert!(names.iter().any(|builtin| builtin == name), "{}", name);
+        }
+    }
 }
@@ -1,21 +1,65 @@
+use crate::config::credentials;
 use crate::config::manager::ConfigManager;
-use crate::config::Config;
+use crate::config::{BackupConfig, Config, Precedence};
 use crate::provider::detector::ProviderDetector;
+use crate::utils::Explainer;
 use anyhow::{Context, Result};
+use chrono::Utc;
 use colored::*;
+use std::collections::{BTreeMap, HashMap};
 
 pub struct AnthropicSwitcher {
     config_manager: ConfigManager,
+    timeout_ms: Option<u64>,
+    merge_env: BTreeMap<String, String>,
+    explainer: Explainer,
+    from_credentials: bool,
 }
 
 impl AnthropicSwitcher {
     pub fn new(config_manager: ConfigManager) -> Self {
-        Self { config_manager }
+        Self {
+            config_manager,
+            timeout_ms: None,
+            merge_env: BTreeMap::new(),
+            explainer: Explainer::default(),
+            from_credentials: false,
+        }
+    }
+
+    /// Also write `API_TIMEOUT_MS` into the restored config
+    pub fn with_timeout_ms(mut self, timeout_ms: Option<u64>) -> Self {
+        self.timeout_ms = timeout_ms;
+        self
+    }
+
+    /// Extra env entries from `--merge-env`, applied over the restored config
+    pub fn with_merge_env(mut self, merge_env: BTreeMap<String, String>) -> Self {
+        self.merge_env = merge_env;
+        self
+    }
+
+    /// Describe each step before it happens (`--explain`)
+    pub fn with_explain(mut self, explain: bool) -> Self {
+        self.explainer = Explainer::new(explain);
+        self
+    }
+
+    /// Without a backup, rebuild the config from the Claude CLI's OAuth login
+    /// instead of writing an empty one
+    pub fn with_credentials_fallback(mut self, from_credentials: bool) -> Self {
+        self.from_credentials = from_credentials;
+        self
     }
 
     pub fn switch_to_anthropic(&self) -> Result<()> {
         println!("{}", "🔄 Switching to Anthropic API...".green());
 
+        ProviderDetector::check_model_names(&self.merge_env)?;
+        self.config_manager.ensure_writable()?;
+
+        self.explainer
+            .reading_settings(self.config_manager.settings_file());
         // Load current config to check if already using Anthropic
         let current_config = self
             .config_manager
@@ -23,11 +67,32 @@ impl AnthropicSwitcher {
             .context("Failed to load current config")?;
 
         if ProviderDetector::is_anthropic_config(&current_config) {
-            println!("{}", "⚠️  Already using Anthropic configuration".yellow());
-            println!("{}", "   Use --status to check current settings".cyan());
+            let updates = self.pending_updates(&current_config);
+
+            if updates.is_empty() {
+                println!("{}", "⚠️  Already using Anthropic configuration".yellow());
+                println!("{}", "   Use --status to check current settings".cyan());
+                return Ok(());
+            }
+
+            let mut env = current_config.env.clone();
+            for (key, value) in updates {
+                println!("{}{}={}", "✏️  Updated ".green(), key, value);
+                env.insert(key, value);
+            }
+            self.explainer
+                .updating_active("Anthropic", self.config_manager.settings_file());
+            self.config_manager
+                .save_current_config(&current_config.with_env(env))
+                .context("Failed to save Anthropic configuration")?;
             return Ok(());
         }
 
+        self.explainer.step(
+            "Looking for the Anthropic settings saved when you last switched away from \
+             Anthropic.",
+            self.config_manager.backup_file(),
+        );
         // Check if valid Anthropic backup exists
         let (has_backup, backup) = self
             .config_manager
@@ -36,6 +101,26 @@ impl AnthropicSwitcher {
 
         if !has_backup || backup.is_none() {
             println!("{}", "❌ No valid Anthropic backup found!".red());
+
+            if self.from_credentials {
+                if let Some(env) = self.env_from_credentials() {
+                    self.explainer.step(
+                        "There is no backup, so the access token from the Claude CLI login is \
+                         written into `env` instead.",
+                        self.config_manager.settings_file(),
+                    );
+                    let config = current_config.with_env(env);
+                    self.config_manager
+                        .save_current_config(&config)
+                        .context("Failed to save Anthropic configuration")?;
+                    println!(
+                        "{}",
+                        "✅ Anthropic configuration rebuilt from Claude CLI credentials".green()
+                    );
+                    return Ok(());
+                }
+            }
+
             println!(
                 "{}",
                 "⚠️  Cannot restore Anthropic configuration without backup.".yellow()
@@ -43,8 +128,13 @@ impl AnthropicSwitcher {
             println!("{}", "   You may need to reconfigure Claude Code.".yellow());
             println!();
 
+            self.explainer.step(
+                "There is no backup to restore, so the provider keys are removed from `env` \
+                 and Claude Code will ask you to log in again.",
+                self.config_manager.settings_file(),
+            );
             // Create empty config without GLM keys
-            let config = Config::default();
+            let config = current_config.with_env(self.merge_env.clone().into_iter().collect());
             self.config_manager
                 .save_current_config(&config)
                 .context("Failed to save empty config")?;
@@ -57,6 +147,7 @@ impl AnthropicSwitcher {
         }
 
         let backup = backup.unwrap();
+        ProviderDetector::warn_unmanaged_settings(&current_config);
 
         // Show backup info
         if let Some(created_at) = backup.metadata.created_at {
@@ -66,22 +157,13 @@ impl AnthropicSwitcher {
                 created_at.format("%Y-%m-%d %H:%M:%S UTC")
             );
         }
+        let restored_config = self.restored_config(&current_config, backup);
 
-        // Create config from backup
-        let mut restored_config = Config { env: backup.env };
-
-        // Remove any GLM specific keys that might be in backup
-        let keys_to_remove: Vec<String> = restored_config
-            .env
-            .keys()
-            .filter(|key| ProviderDetector::is_glm_key(key))
-            .cloned()
-            .collect();
-
-        for key in keys_to_remove {
-            restored_config.env.remove(&key);
-        }
-
+        self.explainer.step(
+            "Writing the restored Anthropic settings back. GLM-specific keys are dropped and \
+             settings outside `env` are kept as they are.",
+            self.config_manager.settings_file(),
+        );
         self.config_manager
             .save_current_config(&restored_config)
             .context("Failed to restore config")?;
@@ -92,4 +174,257 @@ impl AnthropicSwitcher {
         );
         Ok(())
     }
+
+    /// The config `switch_to_anthropic` would write, without writing anything
+    /// (`--print-only`)
+    pub fn target_config(&self) -> Result<Config> {
+        ProviderDetector::check_model_names(&self.merge_env)?;
+        let current_config = self
+            .config_manager
+            .load_current_config()
+            .context("Failed to load current config")?;
+
+        if ProviderDetector::is_anthropic_config(&current_config) {
+            let mut env = current_config.env.clone();
+            env.extend(self.pending_updates(&current_config));
+            return Ok(current_config.with_env(env));
+        }
+
+        let (_, backup) = self
+            .config_manager
+            .has_valid_anthropic_backup()
+            .context("Failed to check for backup")?;
+        let config = match backup {
+            Some(backup) => self.restored_config(&current_config, backup),
+            None => {
+                let env = self
+                    .from_credentials
+                    .then(|| self.env_from_credentials())
+                    .flatten()
+                    .unwrap_or_else(|| self.merge_env.clone().into_iter().collect());
+                current_config.with_env(env)
+            }
+        };
+        Ok(config)
+    }
+
+    /// Timeout and `--merge-env` values that differ from `current`
+    fn pending_updates(&self, current: &Config) -> BTreeMap<String, String> {
+        let mut updates = BTreeMap::new();
+        if let Some(timeout_ms) = self.timeout_ms {
+            updates.insert("API_TIMEOUT_MS".to_string(), timeout_ms.to_string());
+        }
+        updates.extend(self.merge_env.clone());
+        updates.retain(|key, value| current.env.get(key) != Some(value));
+        updates
+    }
+
+    /// `current` with the backup's env; settings recorded in the backup win,
+    /// other live settings are kept (as `restore` does)
+    fn restored_config(&self, current: &Config, backup: BackupConfig) -> Config {
+        let backup_settings = Config {
+            extra: backup.settings.clone(),
+            ..Default::default()
+        };
+        let mut restored_config = current
+            .with_env(backup.env)
+            .merge(&backup_settings, Precedence::OtherWins);
+
+        // Remove any GLM specific keys that might be in backup
+        restored_config
+            .env
+            .retain(|key, _| !ProviderDetector::is_glm_key(key));
+
+        if let Some(timeout_ms) = self.timeout_ms {
+            restored_config
+                .env
+                .insert("API_TIMEOUT_MS".to_string(), timeout_ms.to_string());
+        }
+        restored_config.env.extend(self.merge_env.clone());
+        restored_config
+    }
+
+    /// `env` for the OAuth access token, or `None` (with the reason on
+    /// stderr, keeping `--print-only` output clean) when the credentials file
+    /// can't be used
+    fn env_from_credentials(&self) -> Option<HashMap<String, String>> {
+        let path = self.config_manager.credentials_file();
+        let Some(oauth) = credentials::load(&path) else {
+            eprintln!(
+                "{}{}",
+                "⚠️  No usable Claude CLI credentials in ".yellow(),
+                path.display()
+            );
+            return None;
+        };
+        if oauth.is_expired(Utc::now()) {
+            eprintln!(
+                "{}",
+                "⚠️  The Claude CLI login has expired; run `claude` to log in again".yellow()
+            );
+            return None;
+        }
+        // The Claude CLI refreshes its own copy; the one written here goes stale
+        let expiry = match oauth.expires_at() {
+            Some(expires_at) => format!("expires {}", expires_at.format("%Y-%m-%d %H:%M:%S UTC")),
+            None => "will expire".to_string(),
+        };
+        eprintln!(
+            "{}",
+            format!(
+                "⚠️  This access token {} and is not refreshed in settings.json; run \
+                 `claude-switch anthropic --from-credentials` again after it does",
+                expiry
+            )
+            .yellow()
+        );
+
+        let mut env = HashMap::from([(
+            "ANTHROPIC_AUTH_TOKEN".to_string(),
+            oauth.access_token.trim().to_string(),
+        )]);
+        if let Some(timeout_ms) = self.timeout_ms {
+            env.insert("API_TIMEOUT_MS".to_string(), timeout_ms.to_string());
+        }
+        env.extend(self.merge_env.clone());
+        Some(env)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{BackupMetadata, Provider};
+    use std::fs;
+
+    fn glm_manager(dir: &std::path::Path) -> ConfigManager {
+        let manager = ConfigManager::with_config_dir(dir.to_path_buf());
+        let glm = Config::from_env([
+            (
+                "ANTHROPIC_AUTH_TOKEN".to_string(),
+                "sk-glm-token".to_string(),
+            ),
+            (
+                "ANTHROPIC_BASE_URL".to_string(),
+                "https://api.z.ai/api/anthropic".to_string(),
+            ),
+        ]);
+        manager.save_current_config(&glm).unwrap();
+        manager
+    }
+
+    #[test]
+    fn test_restored_settings_from_the_backup_win() {
+        let dir = tempfile::tempdir().unwrap();
+        let switcher = AnthropicSwitcher::new(glm_manager(dir.path()));
+        let mut current = Config::default();
+        current.extra.insert("model".to_string(), "glm-4.6".into());
+        current.extra.insert("theme".to_string(), "dark".into());
+        let mut backup = BackupConfig {
+            metadata: BackupMetadata::new(&Provider::Anthropic),
+            env: [("ANTHROPIC_AUTH_TOKEN".to_string(), "sk-ant-x".to_string())].into(),
+            settings: Default::default(),
+        };
+        backup.settings.insert("model".to_string(), "opus".into());
+
+        let restored = switcher.restored_config(&current, backup);
+        assert_eq!(restored.extra["model"], "opus");
+        assert_eq!(restored.extra["theme"], "dark");
+        assert_eq!(restored.env["ANTHROPIC_AUTH_TOKEN"], "sk-ant-x");
+    }
+
+    #[test]
+    fn test_restore_from_credentials_without_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = glm_manager(dir.path());
+        fs::write(
+            manager.credentials_file(),
+            r#"{"claudeAiOauth": {"accessToken": "sk-ant-oat01-fixture", "refreshToken": "sk-ant-ort01-x",
+                "expiresAt": 4102444800000, "scopes": ["user:inference"]}}"#,
+        )
+        .unwrap();
+
+        AnthropicSwitcher::new(manager)
+            .with_credentials_fallback(true)
+            .switch_to_anthropic()
+            .unwrap();
+
+        let config = ConfigManager::with_config_dir(dir.path().to_path_buf())
+            .load_current_config()
+            .unwrap();
+        assert_eq!(config.env["ANTHROPIC_AUTH_TOKEN"], "sk-ant-oat01-fixture");
+        assert!(!config.env.contains_key("ANTHROPIC_BASE_URL"));
+        assert!(ProviderDetector::is_anthropic_config(&config));
+    }
+
+    #[test]
+    fn test_unusable_credentials_fall_back_to_empty_config() {
+        for credentials in [
+            None,
+            Some("{ not json"),
+            Some(r#"{"claudeAiOauth": {"accessToken": "sk-ant-oat01-old", "expiresAt": 1000}}"#),
+        ] {
+            let dir = tempfile::tempdir().unwrap();
+            let manager = glm_manager(dir.path());
+            if let Some(content) = credentials {
+                fs::write(manager.credentials_file(), content).unwrap();
+            }
+
+            AnthropicSwitcher::new(manager)
+                .with_credentials_fallback(true)
+                .switch_to_anthropic()
+                .unwrap();
+
+            let config = ConfigManager::with_config_dir(dir.path().to_path_buf())
+                .load_current_config()
+                .unwrap();
+            assert!(config.env.is_empty(), "{:?}", credentials);
+        }
+    }
+
+    #[test]
+    fn test_credentials_are_ignored_without_the_option() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = glm_manager(dir.path());
+        fs::write(
+            manager.credentials_file(),
+            r#"{"claudeAiOauth": {"accessToken": "sk-ant-oat01-fixture"}}"#,
+        )
+        .unwrap();
+
+        AnthropicSwitcher::new(manager)
+            .switch_to_anthropic()
+            .unwrap();
+
+        let config = ConfigManager::with_config_dir(dir.path().to_path_buf())
+            .load_current_config()
+            .unwrap();
+        assert!(config.env.is_empty());
+    }
+
+    #[test]
+    fn test_target_config_matches_the_switch_without_writing() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = glm_manager(dir.path());
+        let backup = Config::from_env([(
+            "ANTHROPIC_AUTH_TOKEN".to_string(),
+            "web-login-token".to_string(),
+        )]);
+        manager
+            .create_backup_with_metadata(&backup, &crate::config::Provider::Anthropic)
+            .unwrap();
+        let settings = fs::read_to_string(manager.settings_file()).unwrap();
+
+        let switcher = AnthropicSwitcher::new(manager.clone()).with_timeout_ms(Some(1000));
+        let target = switcher.target_config().unwrap();
+        assert_eq!(
+            fs::read_to_string(manager.settings_file()).unwrap(),
+            settings
+        );
+        assert_eq!(target.env["ANTHROPIC_AUTH_TOKEN"], "web-login-token");
+        assert!(!target.env.contains_key("ANTHROPIC_BASE_URL"));
+
+        switcher.switch_to_anthropic().unwrap();
+        assert_eq!(manager.load_current_config().unwrap().env, target.env);
+    }
 }
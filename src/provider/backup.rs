@@ -0,0 +1,342 @@
+use crate::config::diff::{diff_env, EnvChange};
+use crate::config::manager::{ConfigManager, EnsuredBackup};
+use crate::config::{BackupConfig, Config, Provider};
+use crate::provider::bundle;
+use crate::provider::detector::ProviderDetector;
+use crate::utils::error::AppError;
+use crate::utils::git;
+use crate::utils::Explainer;
+use anyhow::{Context, Result};
+use colored::*;
+use std::path::PathBuf;
+
+pub struct BackupCommand {
+    config_manager: ConfigManager,
+    note_from_git: bool,
+}
+
+impl BackupCommand {
+    pub fn new(config_manager: ConfigManager) -> Self {
+        Self {
+            config_manager,
+            note_from_git: false,
+        }
+    }
+
+    /// `--note-from-git`: note the branch and commit of the config directory
+    /// in the backup when it is a git checkout; nothing happens otherwise
+    pub fn with_note_from_git(mut self, note_from_git: bool) -> Self {
+        self.note_from_git = note_from_git;
+        self
+    }
+
+    pub fn run(&self, force: bool) -> Result<()> {
+        self.config_manager.ensure_writable()?;
+
+        let config = self
+            .config_manager
+            .load_current_config()
+            .context("Failed to load current config")?;
+
+        let provider = ProviderDetector::detect_provider(&config);
+        if provider != Provider::Anthropic {
+            return Err(AppError::Provider(format!(
+                "Current configuration is {}, only Anthropic configurations can be backed up",
+                provider.as_str()
+            ))
+            .into());
+        }
+
+        let note = self
+            .note_from_git
+            .then(|| git::describe_checkout(self.config_manager.config_dir()))
+            .flatten();
+        let config_manager = self.config_manager.clone().with_backup_note(note.clone());
+        let created =
+            backup_anthropic_config(&config_manager, &config, force, &Explainer::default())?;
+        if let (true, Some(note)) = (created, note) {
+            println!("{}{}", "📝 Note: ".cyan(), note);
+        }
+        Ok(())
+    }
+
+    /// `ensure-backup`: make sure an Anthropic backup exists, creating one
+    /// from the live config only if needed. Reusing one works whatever the
+    /// current provider is.
+    pub fn run_ensure(&self) -> Result<EnsuredBackup> {
+        let config = self
+            .config_manager
+            .load_current_config()
+            .context("Failed to load current config")?;
+        let (has_backup, _) = self.config_manager.has_valid_anthropic_backup()?;
+        let provider = ProviderDetector::detect_provider(&config);
+        if !has_backup && provider != Provider::Anthropic {
+            return Err(AppError::Provider(format!(
+                "No Anthropic backup, and the current configuration is {}",
+                provider.as_str()
+            ))
+            .into());
+        }
+        self.config_manager.ensure_writable()?;
+
+        let ensured = self
+            .config_manager
+            .ensure_backup(&config, &Provider::Anthropic)
+            .context("Failed to backup Anthropic configuration")?;
+        match &ensured {
+            EnsuredBackup::Created => {
+                println!("{}", "✅ Created Anthropic backup".green())
+            }
+            EnsuredBackup::Reused(backup) => {
+                println!("{}", "💾 Reusing existing Anthropic backup".cyan());
+                print_backed_up_at(backup);
+            }
+        }
+        Ok(ensured)
+    }
+
+    /// `backup --format tar`: bundle the whole setup, whatever the provider
+    pub fn run_bundle(&self, archive: Option<PathBuf>) -> Result<()> {
+        let path = archive.unwrap_or_else(|| bundle::default_bundle_path(chrono::Utc::now()));
+        let metadata = bundle::write_bundle(&self.config_manager, &path)?;
+
+        println!(
+            "{}{} ({})",
+            "📦 Bundled setup into ".green(),
+            path.display(),
+            metadata.files.join(", ")
+        );
+        if metadata.contains_secrets {
+            println!(
+                "{}",
+                "⚠️  The archive contains your API credentials; keep it private".yellow()
+            );
+        }
+        println!(
+            "{}",
+            format!(
+                "💡 Restore it with: claude-switch restore --from {}",
+                path.display()
+            )
+            .cyan()
+        );
+        Ok(())
+    }
+}
+
+/// Back up the live Anthropic config unless a backup already exists.
+///
+/// With `force`, the existing backup is snapshotted into the rotation and then
+/// replaced by `config`. Returns whether a backup was written; the step is
+/// only explained when one is.
+pub fn backup_anthropic_config(
+    config_manager: &ConfigManager,
+    config: &Config,
+    force: bool,
+    explainer: &Explainer,
+) -> Result<bool> {
+    let (has_backup, existing_backup) = config_manager
+        .has_valid_anthropic_backup()
+        .context("Failed to check existing backup")?;
+    let existing_backup = existing_backup.filter(|_| has_backup);
+
+    match existing_backup {
+        Some(backup) if force => {
+            explainer.anthropic_backup(config_manager.backup_file());
+            config_manager
+                .replace_backup(&backup, config, &Provider::Anthropic)
+                .context("Failed to replace Anthropic backup")?;
+            println!(
+                "{}",
+                "✅ Anthropic backup replaced (previous backup kept in rotation)".green()
+            );
+            Ok(true)
+        }
+        Some(backup) => {
+            // Backup already exists - don't overwrite
+            println!(
+                "{}",
+                "💾 Existing Anthropic backup found (preserving configuration)".cyan()
+            );
+            print_backed_up_at(&backup);
+            warn_if_backup_is_stale(&diff_env(&backup.env, &config.env));
+            Ok(false)
+        }
+        None => {
+            explainer.anthropic_backup(config_manager.backup_file());
+            config_manager
+                .create_backup_with_metadata(config, &Provider::Anthropic)
+                .context("Failed to backup Anthropic configuration")?;
+            println!("{}", "✅ Anthropic configuration backed up".green());
+            Ok(true)
+        }
+    }
+}
+
+/// Keep `config`, detected as `provider`, in the rotation so it can be
+/// restored later; an empty config has nothing worth keeping.
+pub fn backup_to_rotation(
+    config_manager: &ConfigManager,
+    config: &Config,
+    provider: &Provider,
+    explainer: &Explainer,
+) -> Result<()> {
+    if config.env.is_empty() && config.extra.is_empty() {
+        return Ok(());
+    }
+    explainer.rotated_backup(&config_manager.backups_dir());
+    let path = config_manager
+        .backup_to_rotation(config, provider)
+        .context("Failed to back up current configuration")?;
+    println!(
+        "{}{}",
+        "💾 Current configuration backed up to ".cyan(),
+        path.display()
+    );
+    Ok(())
+}
+
+fn print_backed_up_at(backup: &BackupConfig) {
+    if let Some(created_at) = backup.metadata.created_at {
+        println!(
+            "{}{}",
+            "   Backed up at: ".cyan(),
+            created_at.format("%Y-%m-%d %H:%M:%S UTC")
+        );
+    }
+}
+
+fn warn_if_backup_is_stale(changes: &[EnvChange]) {
+    if changes.is_empty() {
+        return;
+    }
+
+    println!(
+        "{}",
+        "⚠️  Backup is older than your current Anthropic config:".yellow()
+    );
+    for change in changes {
+        let line = change.describe();
+        let line = match change {
+            EnvChange::Added { .. } => line.green(),
+            EnvChange::Removed { .. } => line.red(),
+            EnvChange::Changed { .. } => line.yellow(),
+        };
+        println!("     {}", line);
+    }
+    println!(
+        "{}",
+        "   Use --force-backup to replace it with the current config".cyan()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_ensure_backup_reports_created_then_reused() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("settings.json"),
+            r#"{"env": {"ANTHROPIC_AUTH_TOKEN": "sk-ant-oat01-web"}}"#,
+        )
+        .unwrap();
+        let command = BackupCommand::new(ConfigManager::with_config_dir(dir.path().to_path_buf()));
+
+        assert!(matches!(
+            command.run_ensure().unwrap(),
+            EnsuredBackup::Created
+        ));
+        assert!(matches!(
+            command.run_ensure().unwrap(),
+            EnsuredBackup::Reused(_)
+        ));
+    }
+
+    #[test]
+    fn test_backup_to_rotation_skips_empty_configs() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        let explainer = Explainer::default();
+
+        backup_to_rotation(&manager, &Config::default(), &Provider::Custom, &explainer).unwrap();
+        assert!(manager.list_backups().unwrap().is_empty());
+
+        let custom = Config::from_env([(
+            "ANTHROPIC_BASE_URL".to_string(),
+            "https://gateway.example.com".to_string(),
+        )]);
+        backup_to_rotation(&manager, &custom, &Provider::Custom, &explainer).unwrap();
+        assert_eq!(manager.list_backups().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_ensure_backup_needs_anthropic_to_create() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("settings.json"),
+            r#"{"env": {"ANTHROPIC_BASE_URL": "https://api.z.ai/api/anthropic"}}"#,
+        )
+        .unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+
+        let err = BackupCommand::new(manager.clone())
+            .run_ensure()
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AppError>(),
+            Some(AppError::Provider(_))
+        ));
+        assert!(!manager.backup_file().exists());
+    }
+
+    #[test]
+    fn test_note_from_git_records_the_short_commit() {
+        use crate::utils::git::tests::git;
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("settings.json"),
+            r#"{"env": {"ANTHROPIC_AUTH_TOKEN": "sk-ant-oat01-web"}}"#,
+        )
+        .unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+
+        // Not a repository: the backup is taken without a note
+        BackupCommand::new(manager.clone())
+            .with_note_from_git(true)
+            .run(false)
+            .unwrap();
+        let (_, backup) = manager.has_valid_anthropic_backup().unwrap();
+        assert_eq!(backup.unwrap().metadata.note, None);
+
+        if !git(dir.path(), &["init", "-q", "-b", "main"]) {
+            return;
+        }
+        assert!(git(dir.path(), &["add", "settings.json"]));
+        assert!(git(dir.path(), &["commit", "-q", "-m", "dotfiles"]));
+        let commit = std::process::Command::new("git")
+            .arg("-C")
+            .arg(dir.path())
+            .args(["rev-parse", "--short", "HEAD"])
+            .output()
+            .unwrap();
+        let commit = String::from_utf8(commit.stdout).unwrap().trim().to_string();
+
+        BackupCommand::new(manager.clone())
+            .with_note_from_git(true)
+            .run(true)
+            .unwrap();
+        let (_, backup) = manager.has_valid_anthropic_backup().unwrap();
+        let expected = format!("git main@{}", commit);
+        assert_eq!(
+            backup.unwrap().metadata.note.as_deref(),
+            Some(expected.as_str())
+        );
+        let newest = &manager.list_backups().unwrap()[0];
+        let rotated = manager.load_rotated_backup(&newest.path).unwrap();
+        assert_eq!(rotated.metadata.note.as_deref(), Some(expected.as_str()));
+    }
+}
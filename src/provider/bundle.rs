@@ -0,0 +1,244 @@
+use crate::config::history::{self, HistoryEntry};
+use crate::config::manager::ConfigManager;
+use crate::config::Config;
+use crate::provider::detector::ProviderDetector;
+use crate::utils::archive::{self, ArchiveEntry};
+use crate::utils::error::{redacted_json_error, AppError};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SETTINGS_ENTRY: &str = "settings.json";
+const TOKEN_ENTRY: &str = "token";
+const METADATA_ENTRY: &str = "metadata.json";
+
+/// What `backup` writes
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum BackupFormat {
+    /// The Anthropic backup next to settings.json
+    #[default]
+    Json,
+    /// A portable `.tar.gz` with settings.json, the saved token and metadata
+    Tar,
+}
+
+/// `metadata.json` inside a bundle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleMetadata {
+    pub version: String,
+    pub created_at: DateTime<Utc>,
+    /// Provider of the bundled settings.json
+    pub provider: String,
+    pub files: Vec<String>,
+    /// Whether settings.json or the token file hold credentials
+    #[serde(default)]
+    pub contains_secrets: bool,
+}
+
+impl BundleMetadata {
+    pub fn has_token(&self) -> bool {
+        self.files.iter().any(|file| file == TOKEN_ENTRY)
+    }
+}
+
+/// `claude-switch-<timestamp>.tar.gz` in the current directory
+pub fn default_bundle_path(now: DateTime<Utc>) -> PathBuf {
+    PathBuf::from(format!(
+        "claude-switch-{}.tar.gz",
+        now.format("%Y%m%dT%H%M%SZ")
+    ))
+}
+
+/// Bundle the live settings and saved token into `path`
+pub fn write_bundle(config_manager: &ConfigManager, path: &Path) -> Result<BundleMetadata> {
+    let config = config_manager
+        .load_current_config()
+        .context("Failed to load current config")?;
+    // A missing settings.json bundles as an empty config
+    let settings = fs::read(config_manager.settings_file()).unwrap_or_else(|_| b"{}".to_vec());
+
+    let mut entries = vec![ArchiveEntry {
+        name: SETTINGS_ENTRY.to_string(),
+        data: settings,
+        mode: 0o600,
+    }];
+    let saved_token = config_manager.load_saved_token()?;
+    let contains_secrets = saved_token.is_some()
+        || config
+            .env
+            .keys()
+            .any(|key| ProviderDetector::is_secret_key(key));
+    if let Some(token) = saved_token {
+        entries.push(ArchiveEntry {
+            name: TOKEN_ENTRY.to_string(),
+            data: token.into_bytes(),
+            mode: 0o600,
+        });
+    }
+
+    let metadata = BundleMetadata {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: Utc::now(),
+        provider: ProviderDetector::detect_provider(&config)
+            .as_str()
+            .to_string(),
+        files: entries.iter().map(|entry| entry.name.clone()).collect(),
+        contains_secrets,
+    };
+    entries.push(ArchiveEntry {
+        name: METADATA_ENTRY.to_string(),
+        data: serde_json::to_vec_pretty(&metadata)?,
+        mode: 0o644,
+    });
+
+    archive::write_tar_gz(path, &entries)?;
+    Ok(metadata)
+}
+
+/// Make a bundle's settings.json live (the current config goes to the
+/// rotation first) and save its token, if it has one, saying so when that
+/// replaces a different saved token
+pub fn restore_bundle(config_manager: &ConfigManager, path: &Path) -> Result<BundleMetadata> {
+    let entries = archive::read_tar_gz(path)?;
+    let entry = |name: &str| entries.iter().find(|entry| entry.name == name);
+    let missing = |name: &str| AppError::Config(format!("{} has no {}", path.display(), name));
+
+    let metadata: BundleMetadata = serde_json::from_slice(
+        &entry(METADATA_ENTRY)
+            .ok_or_else(|| missing(METADATA_ENTRY))?
+            .data,
+    )
+    .map_err(|e| {
+        AppError::Config(format!(
+            "Invalid {} in {}: {}",
+            METADATA_ENTRY,
+            path.display(),
+            e
+        ))
+    })?;
+    let settings: Config = serde_json::from_slice(
+        &entry(SETTINGS_ENTRY)
+            .ok_or_else(|| missing(SETTINGS_ENTRY))?
+            .data,
+    )
+    .map_err(|e| {
+        AppError::Config(format!(
+            "Invalid {} in {}: {}",
+            SETTINGS_ENTRY,
+            path.display(),
+            redacted_json_error(&e)
+        ))
+    })?;
+    let token = entry(TOKEN_ENTRY)
+        .map(|entry| String::from_utf8_lossy(&entry.data).trim().to_string())
+        .filter(|token| !token.is_empty());
+
+    config_manager.ensure_writable()?;
+    let current = config_manager
+        .load_current_config()
+        .context("Failed to load current config")?;
+    if !current.env.is_empty() || !current.extra.is_empty() {
+        let backup = config_manager
+            .backup_to_rotation(&current, &ProviderDetector::detect_provider(&current))
+            .context("Failed to back up current configuration")?;
+        println!(
+            "{}{}",
+            "💾 Current configuration backed up to ".cyan(),
+            backup.display()
+        );
+    }
+
+    config_manager
+        .save_current_config(&settings)
+        .context("Failed to restore config")?;
+    if let Some(token) = token {
+        let previous = config_manager.load_saved_token()?;
+        config_manager.save_token(&token)?;
+        if previous.is_some_and(|previous| previous != token) {
+            println!(
+                "{}{}",
+                "🔑 Replaced the previous saved token with the bundle's in ".yellow(),
+                config_manager.token_file().display()
+            );
+        }
+    }
+
+    let provider = ProviderDetector::detect_provider(&settings);
+    history::record(
+        config_manager.history_file(),
+        &HistoryEntry::now(&provider, None),
+    );
+    Ok(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let source = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(source.path().to_path_buf());
+        fs::write(
+            manager.settings_file(),
+            r#"{"env": {"ANTHROPIC_BASE_URL": "https://api.z.ai/api/anthropic", "ANTHROPIC_AUTH_TOKEN": "sk-bundle"}, "model": "opus"}"#,
+        )
+        .unwrap();
+        manager.save_token("sk-bundle").unwrap();
+        let bundle = source.path().join("setup.tar.gz");
+
+        let written = write_bundle(&manager, &bundle).unwrap();
+        assert_eq!(written.provider, "glm");
+        assert!(written.has_token());
+        assert!(written.contains_secrets);
+
+        let target = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(target.path().to_path_buf());
+        fs::write(
+            manager.settings_file(),
+            r#"{"env": {"ANTHROPIC_AUTH_TOKEN": "web"}}"#,
+        )
+        .unwrap();
+        manager.save_token("sk-old").unwrap();
+
+        let restored = restore_bundle(&manager, &bundle).unwrap();
+        assert_eq!(restored.files, ["settings.json", "token"]);
+
+        let config = manager.load_current_config().unwrap();
+        assert_eq!(config.env["ANTHROPIC_AUTH_TOKEN"], "sk-bundle");
+        assert_eq!(config.extra["model"], "opus");
+        assert_eq!(
+            manager.load_saved_token().unwrap().as_deref(),
+            Some("sk-bundle")
+        );
+
+        // The config that was live is kept in the rotation
+        let backups = manager.list_backups().unwrap();
+        assert_eq!(backups.len(), 1);
+        let previous = manager.load_rotated_backup(&backups[0].path).unwrap();
+        assert_eq!(previous.env["ANTHROPIC_AUTH_TOKEN"], "web");
+    }
+
+    #[test]
+    fn test_restore_rejects_bundle_without_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = dir.path().join("partial.tar.gz");
+        archive::write_tar_gz(
+            &bundle,
+            &[ArchiveEntry {
+                name: TOKEN_ENTRY.to_string(),
+                data: b"sk-bundle".to_vec(),
+                mode: 0o600,
+            }],
+        )
+        .unwrap();
+
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        assert!(restore_bundle(&manager, &bundle).is_err());
+        assert!(!manager.settings_file().exists());
+    }
+}
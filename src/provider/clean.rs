@@ -0,0 +1,146 @@
+use crate::config::manager::ConfigManager;
+use crate::config::SwitchSettings;
+use anyhow::{Context, Result};
+use colored::*;
+use std::fs;
+use std::path::PathBuf;
+
+/// `clean`: remove leftovers from interrupted writes and, optionally, prune
+/// the backup rotation per the retention policy.
+pub struct CleanCommand {
+    config_manager: ConfigManager,
+}
+
+impl CleanCommand {
+    pub fn new(config_manager: ConfigManager) -> Self {
+        Self { config_manager }
+    }
+
+    /// Returns the files removed (or, with `dry_run`, that would be)
+    pub fn run(&self, backups: bool, dry_run: bool) -> Result<Vec<PathBuf>> {
+        let mut targets = self.config_manager.stray_files()?;
+        if backups {
+            let policy = SwitchSettings::load(self.config_manager.preferences_dir())?.backups;
+            targets.extend(self.config_manager.backups_to_prune(&policy)?);
+        }
+
+        if targets.is_empty() {
+            println!("{}", "✨ Nothing to clean".green());
+            return Ok(targets);
+        }
+
+        if !dry_run {
+            self.config_manager.ensure_writable()?;
+        }
+
+        for path in &targets {
+            if dry_run {
+                println!("  {}{}", "Would remove ".cyan(), path.display());
+                continue;
+            }
+            match fs::remove_file(path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to remove {}", path.display()))
+                }
+            }
+            println!("  {}{}", "🗑️  Removed ".green(), path.display());
+        }
+
+        if dry_run {
+            println!(
+                "{}",
+                format!("💡 {} file(s) would be removed (dry run)", targets.len()).cyan()
+            );
+        } else {
+            println!(
+                "{}",
+                format!("✅ Removed {} file(s)", targets.len()).green()
+            );
+        }
+        Ok(targets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, Provider};
+
+    fn seeded() -> (tempfile::TempDir, ConfigManager) {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        manager.save_current_config(&Config::default()).unwrap();
+        manager
+            .create_backup_with_metadata(&Config::default(), &Provider::Anthropic)
+            .unwrap();
+
+        fs::write(dir.path().join("settings.tmp"), "{").unwrap();
+        fs::write(dir.path().join("settings.json.tmp"), "{").unwrap();
+        fs::write(
+            manager
+                .backups_dir()
+                .join("settings-20240101T000000000Z.tmp"),
+            "{",
+        )
+        .unwrap();
+        // Not ours: left alone
+        fs::write(dir.path().join("other-tool.tmp"), "{").unwrap();
+        (dir, manager)
+    }
+
+    #[test]
+    fn test_clean_removes_stray_files_only() {
+        let (dir, manager) = seeded();
+        let rotated = manager.list_backups().unwrap();
+        let command = CleanCommand::new(manager);
+
+        let removed = command.run(false, false).unwrap();
+        assert_eq!(removed.len(), 3);
+        assert!(!dir.path().join("settings.tmp").exists());
+        assert!(!dir.path().join("settings.json.tmp").exists());
+        assert!(dir.path().join("other-tool.tmp").exists());
+        assert!(dir.path().join("settings.json").exists());
+        assert!(dir.path().join("settings.json.backup").exists());
+        assert!(dir.path().join("settings.json.meta").exists());
+        assert!(rotated[0].path.exists());
+    }
+
+    #[test]
+    fn test_clean_removes_orphaned_meta() {
+        let (dir, manager) = seeded();
+        fs::remove_file(dir.path().join("settings.json.backup")).unwrap();
+
+        let removed = CleanCommand::new(manager).run(false, false).unwrap();
+        assert!(removed.contains(&dir.path().join("settings.json.meta")));
+        assert!(!dir.path().join("settings.json.meta").exists());
+    }
+
+    #[test]
+    fn test_clean_dry_run_keeps_files() {
+        let (dir, manager) = seeded();
+
+        let removed = CleanCommand::new(manager).run(false, true).unwrap();
+        assert_eq!(removed.len(), 3);
+        assert!(dir.path().join("settings.tmp").exists());
+    }
+
+    #[test]
+    fn test_clean_backups_prunes_per_policy() {
+        let (dir, manager) = seeded();
+        fs::write(
+            dir.path().join(crate::config::SETTINGS_FILE_NAME),
+            "[backups]\nmax_count = 1\n",
+        )
+        .unwrap();
+        let old = manager
+            .backups_dir()
+            .join("settings-20200101T000000000Z.json");
+        fs::write(&old, "{}").unwrap();
+
+        let removed = CleanCommand::new(manager).run(true, false).unwrap();
+        assert!(removed.contains(&old));
+        assert!(!old.exists());
+    }
+}
@@ -0,0 +1,218 @@
+use crate::config::diff::diff_env;
+use crate::config::manager::ConfigManager;
+use crate::provider::detector::ProviderDetector;
+use anyhow::Result;
+use colored::*;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+
+/// Live config next to what `anthropic` would restore, for `status --compare-providers`
+#[derive(Debug, Clone, Serialize)]
+pub struct ComparisonReport {
+    pub live_provider: String,
+    /// `None` when there is no Anthropic backup
+    pub backup_provider: Option<String>,
+    pub rows: Vec<ComparisonRow>,
+}
+
+/// One `env` key, values masked when secret
+#[derive(Debug, Clone, Serialize)]
+pub struct ComparisonRow {
+    pub key: String,
+    pub live: Option<String>,
+    pub backup: Option<String>,
+    pub differs: bool,
+}
+
+impl ComparisonReport {
+    pub fn collect(config_manager: &ConfigManager) -> Result<Self> {
+        let live = config_manager.load_current_config()?;
+        let (has_backup, backup) = config_manager.has_valid_anthropic_backup()?;
+        let backup = backup.filter(|_| has_backup);
+
+        let empty = HashMap::new();
+        let backup_env = backup.as_ref().map_or(&empty, |backup| &backup.env);
+        Ok(Self {
+            live_provider: ProviderDetector::detect_provider(&live)
+                .as_str()
+                .to_string(),
+            backup_provider: backup
+                .as_ref()
+                .map(|backup| backup.metadata.provider.clone()),
+            rows: Self::rows(&live.env, backup_env),
+        })
+    }
+
+    fn rows(
+        live: &HashMap<String, String>,
+        backup: &HashMap<String, String>,
+    ) -> Vec<ComparisonRow> {
+        let changed: BTreeSet<String> = diff_env(backup, live)
+            .iter()
+            .map(|change| change.key().to_string())
+            .collect();
+        let keys: BTreeSet<&String> = live.keys().chain(backup.keys()).collect();
+
+        keys.into_iter()
+            .map(|key| ComparisonRow {
+                key: key.clone(),
+                live: live
+                    .get(key)
+                    .map(|value| ProviderDetector::display_value(key, value)),
+                backup: backup
+                    .get(key)
+                    .map(|value| ProviderDetector::display_value(key, value)),
+                differs: changed.contains(key),
+            })
+            .collect()
+    }
+
+    /// `env` keys whose live value differs from the backup; empty without a backup
+    pub fn differing_rows(&self) -> Vec<&ComparisonRow> {
+        if self.backup_provider.is_none() {
+            return Vec::new();
+        }
+        self.rows.iter().filter(|row| row.differs).collect()
+    }
+
+    /// Two-column table; rows that differ are marked with `*`
+    pub fn render(&self) -> Vec<String> {
+        let live_header = format!("Live ({})", self.live_provider);
+        let backup_header = match &self.backup_provider {
+            Some(provider) => format!("Backup ({})", provider),
+            None => "Backup (none)".to_string(),
+        };
+
+        let key_width = self
+            .rows
+            .iter()
+            .map(|row| row.key.len())
+            .chain(["Key".len()])
+            .max()
+            .unwrap_or_default();
+        let live_width = self
+            .rows
+            .iter()
+            .map(|row| row.live.as_deref().unwrap_or("-").chars().count())
+            .chain([live_header.chars().count()])
+            .max()
+            .unwrap_or_default();
+
+        let mut lines = vec![format!(
+            "  {:key_width$}  {:live_width$}  {}",
+            "Key", live_header, backup_header
+        )];
+        for row in &self.rows {
+            let marker = if row.differs { "*" } else { " " };
+            lines.push(format!(
+                "{} {:key_width$}  {:live_width$}  {}",
+                marker,
+                row.key,
+                row.live.as_deref().unwrap_or("-"),
+                row.backup.as_deref().unwrap_or("-"),
+            ));
+        }
+        lines
+    }
+
+    pub fn print(&self) {
+        println!("{}", "🔀 Live configuration vs Anthropic backup".cyan());
+        println!();
+        for (index, line) in self.render().into_iter().enumerate() {
+            if index == 0 {
+                println!("{}", line.bold());
+            } else if line.starts_with('*') {
+                println!("{}", line.yellow());
+            } else {
+                println!("{}", line);
+            }
+        }
+        println!();
+        if self.backup_provider.is_none() {
+            println!(
+                "{}",
+                "⚠️  No Anthropic backup: switching back would start from an empty config".yellow()
+            );
+        } else {
+            println!("{}", "  * differs between live and backup".cyan());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, Provider};
+
+    #[test]
+    fn test_comparison_shows_both_base_urls() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+
+        let mut backup = Config::default();
+        for (key, value) in [
+            ("ANTHROPIC_AUTH_TOKEN", "anthropic-web-token-123"),
+            ("ANTHROPIC_BASE_URL", "https://api.anthropic.com"),
+            ("DEBUG", "1"),
+        ] {
+            backup.env.insert(key.to_string(), value.to_string());
+        }
+        manager
+            .create_backup_with_metadata(&backup, &Provider::Anthropic)
+            .unwrap();
+
+        let mut live = Config::default();
+        for (key, value) in [
+            ("ANTHROPIC_AUTH_TOKEN", "sk-glm-token-4567"),
+            ("ANTHROPIC_BASE_URL", "https://api.z.ai/api/anthropic"),
+            ("DEBUG", "1"),
+        ] {
+            live.env.insert(key.to_string(), value.to_string());
+        }
+        manager.save_current_config(&live).unwrap();
+
+        let report = ComparisonReport::collect(&manager).unwrap();
+        assert_eq!(report.live_provider, "glm");
+        assert_eq!(report.backup_provider.as_deref(), Some("anthropic"));
+
+        let lines = report.render();
+        assert!(lines[0].contains("Live (glm)"));
+        assert!(lines[0].contains("Backup (anthropic)"));
+
+        let base_url = lines
+            .iter()
+            .find(|line| line.contains("ANTHROPIC_BASE_URL"))
+            .unwrap();
+        assert!(base_url.starts_with('*'));
+        assert!(base_url.contains("https://api.z.ai/api/anthropic"));
+        assert!(base_url.contains("https://api.anthropic.com"));
+
+        let token = lines
+            .iter()
+            .find(|line| line.contains("ANTHROPIC_AUTH_TOKEN"))
+            .unwrap();
+        assert!(token.contains("sk-g...4567"));
+        assert!(!token.contains("anthropic-web-token"));
+
+        let debug = lines.iter().find(|line| line.contains("DEBUG")).unwrap();
+        assert!(debug.starts_with(' '));
+    }
+
+    #[test]
+    fn test_comparison_without_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        let mut live = Config::default();
+        live.env.insert(
+            "ANTHROPIC_BASE_URL".to_string(),
+            "https://api.z.ai/api/anthropic".to_string(),
+        );
+        manager.save_current_config(&live).unwrap();
+
+        let report = ComparisonReport::collect(&manager).unwrap();
+        assert!(report.backup_provider.is_none());
+        assert!(report.render()[0].contains("Backup (none)"));
+        assert!(report.rows[0].differs);
+        assert_eq!(report.rows[0].backup, None);
+    }
+}
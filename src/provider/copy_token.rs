@@ -0,0 +1,70 @@
+use crate::utils::error::AppError;
+use crate::utils::mask::mask_token;
+use crate::utils::{Clipboard, TokenStore};
+use anyhow::Result;
+use colored::*;
+
+/// `copy-token`: put the saved token on the clipboard without ever printing it
+pub struct CopyTokenCommand<'a> {
+    store: &'a dyn TokenStore,
+}
+
+impl<'a> CopyTokenCommand<'a> {
+    pub fn new(store: &'a dyn TokenStore) -> Self {
+        Self { store }
+    }
+
+    pub fn run(&self, clipboard: &mut dyn Clipboard) -> Result<()> {
+        let token = self
+            .store
+            .load_saved_token()?
+            .ok_or_else(|| AppError::Token("No saved token to copy".to_string()))?;
+
+        clipboard.set_text(&token)?;
+        println!(
+            "{} {}",
+            "📋 Copied saved token to the clipboard:".green(),
+            mask_token(&token)
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::clipboard::tests::MockClipboard;
+    use crate::utils::token::tests::MockStore;
+
+    #[test]
+    fn test_copies_the_saved_token() {
+        let store = MockStore {
+            existing: Some("abcdef1234567890.secret".to_string()),
+            ..Default::default()
+        };
+        let mut clipboard = MockClipboard::default();
+
+        CopyTokenCommand::new(&store).run(&mut clipboard).unwrap();
+
+        assert_eq!(
+            clipboard.contents.as_deref(),
+            Some("abcdef1234567890.secret")
+        );
+    }
+
+    #[test]
+    fn test_errors_without_a_saved_token() {
+        let store = MockStore::default();
+        let mut clipboard = MockClipboard::default();
+
+        let err = CopyTokenCommand::new(&store)
+            .run(&mut clipboard)
+            .unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<AppError>(),
+            Some(AppError::Token(_))
+        ));
+        assert!(clipboard.contents.is_none());
+    }
+}
@@ -0,0 +1,304 @@
+use crate::config::history::{self, HistoryEntry};
+use crate::config::manager::ConfigManager;
+use crate::config::{Config, Precedence, Provider};
+use crate::provider::backup::{backup_anthropic_config, backup_to_rotation};
+use crate::provider::detector::ProviderDetector;
+use crate::utils::error::AppError;
+use crate::utils::{Explainer, TokenManager};
+use anyhow::{Context, Result};
+use colored::*;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, IsTerminal};
+
+/// Ollama's default port
+pub const LOCAL_DEFAULT_PORT: u16 = 11434;
+
+/// Command line options for a one-off switch to an arbitrary base URL
+#[derive(Debug, Clone, Default)]
+pub struct CustomOptions {
+    pub base_url: String,
+    pub token: Option<String>,
+    /// Read the token from a single line on stdin
+    pub token_stdin: bool,
+    /// Used for the opus, sonnet and haiku model slots
+    pub model: Option<String>,
+    pub merge_env: BTreeMap<String, String>,
+    /// Describe each step before it happens
+    pub explain: bool,
+}
+
+impl CustomOptions {
+    /// An endpoint on this machine; local proxies don't need a token
+    pub fn local(port: u16, model: Option<String>) -> Self {
+        Self {
+            base_url: format!("http://localhost:{}", port),
+            model,
+            ..Default::default()
+        }
+    }
+}
+
+pub struct CustomSwitcher {
+    config_manager: ConfigManager,
+    options: CustomOptions,
+    explainer: Explainer,
+}
+
+impl CustomSwitcher {
+    pub fn new(config_manager: ConfigManager, options: CustomOptions) -> Self {
+        Self {
+            config_manager,
+            explainer: Explainer::new(options.explain),
+            options,
+        }
+    }
+
+    pub fn switch_to_custom(&self) -> Result<()> {
+        println!(
+            "{}{}",
+            "🔄 Switching to custom endpoint: ".green(),
+            self.options.base_url
+        );
+
+        validate_base_url(&self.options.base_url)?;
+        let token = self.token()?;
+
+        self.config_manager.ensure_writable()?;
+
+        self.explainer
+            .reading_settings(self.config_manager.settings_file());
+
+        let config = self
+            .config_manager
+            .load_current_config()
+            .context("Failed to load current config")?;
+
+        match ProviderDetector::detect_provider(&config) {
+            Provider::Anthropic => {
+                backup_anthropic_config(&self.config_manager, &config, false, &self.explainer)?;
+            }
+            // GLM configs are rebuilt from the saved token, nothing to keep
+            Provider::GLM => {}
+            provider => {
+                backup_to_rotation(&self.config_manager, &config, &provider, &self.explainer)?
+            }
+        }
+
+        ProviderDetector::warn_unmanaged_settings(&config);
+
+        self.explainer.step(
+            "Writing the endpoint's base URL, and the token and model if given, into the \
+             Claude Code settings. Settings outside `env` are kept as they are.",
+            self.config_manager.settings_file(),
+        );
+        let new_config = self.custom_config_from(&config, token.as_deref());
+        ProviderDetector::check_model_names(&new_config.env)?;
+        self.config_manager
+            .save_current_config(&new_config)
+            .context("Failed to save custom configuration")?;
+
+        let detected = ProviderDetector::detect_provider(&new_config);
+        history::record(
+            self.config_manager.history_file(),
+            &HistoryEntry::now(&detected, None),
+        );
+        if detected == Provider::GLM {
+            println!(
+                "{}",
+                format!(
+                    "⚠️  This base URL is detected as {}, not custom",
+                    detected.as_str()
+                )
+                .yellow()
+            );
+        }
+
+        println!("{}", "✅ Custom configuration applied successfully".green());
+        println!();
+        println!(
+            "{}",
+            "💡 To switch back to Anthropic: claude-switch --anthropic".cyan()
+        );
+        Ok(())
+    }
+
+    /// The config `switch_to_custom` would write, without writing anything
+    /// (`--print-only`)
+    pub fn target_config(&self) -> Result<Config> {
+        validate_base_url(&self.options.base_url)?;
+        let token = self.token()?;
+        let config = self
+            .config_manager
+            .load_current_config()
+            .context("Failed to load current config")?;
+
+        let new_config = self.custom_config_from(&config, token.as_deref());
+        ProviderDetector::check_model_names(&new_config.env)?;
+        Ok(new_config)
+    }
+
+    /// `config` pointed at the endpoint: settings outside `env` are kept
+    fn custom_config_from(&self, config: &Config, token: Option<&str>) -> Config {
+        config.with_env(self.create_custom_config(token).env).merge(
+            &Config::from_env(self.options.merge_env.clone()),
+            Precedence::OtherWins,
+        )
+    }
+
+    fn token(&self) -> Result<Option<String>> {
+        if let Some(token) = &self.options.token {
+            return Ok(Some(token.trim().to_string()));
+        }
+        if !self.options.token_stdin {
+            return Ok(None);
+        }
+
+        let stdin = io::stdin();
+        if stdin.is_terminal() {
+            return Err(AppError::Token(
+                "--token-stdin requires the token to be piped (stdin is a terminal)".to_string(),
+            )
+            .into());
+        }
+        TokenManager::read_token_line(stdin.lock()).map(Some)
+    }
+
+    fn create_custom_config(&self, token: Option<&str>) -> Config {
+        let mut env = HashMap::new();
+
+        env.insert(
+            "ANTHROPIC_BASE_URL".to_string(),
+            ProviderDetector::normalize_base_url(&self.options.base_url).to_string(),
+        );
+        if let Some(token) = token {
+            env.insert("ANTHROPIC_AUTH_TOKEN".to_string(), token.to_string());
+        }
+        if let Some(model) = &self.options.model {
+            for key in [
+                "ANTHROPIC_DEFAULT_OPUS_MODEL",
+                "ANTHROPIC_DEFAULT_SONNET_MODEL",
+                "ANTHROPIC_DEFAULT_HAIKU_MODEL",
+            ] {
+                env.insert(key.to_string(), model.clone());
+            }
+        }
+
+        Config {
+            env,
+            ..Default::default()
+        }
+    }
+}
+
+fn validate_base_url(base_url: &str) -> Result<()> {
+    let valid = url::Url::parse(base_url).is_ok_and(|url| {
+        matches!(url.scheme(), "http" | "https")
+            && url.host_str().is_some_and(|host| !host.is_empty())
+    });
+    if !valid {
+        return Err(AppError::Provider(format!(
+            "Invalid base URL '{}' (expected http:// or https:// followed by a host)",
+            base_url
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn switch(dir: &std::path::Path, options: CustomOptions) -> Config {
+        let manager = ConfigManager::with_config_dir(dir.to_path_buf());
+        CustomSwitcher::new(manager, options)
+            .switch_to_custom()
+            .unwrap();
+        ConfigManager::with_config_dir(dir.to_path_buf())
+            .load_current_config()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_custom_switch_writes_base_url() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = switch(
+            dir.path(),
+            CustomOptions {
+                base_url: "https://gateway.example.com/anthropic".to_string(),
+                token: Some("gw-token-123456".to_string()),
+                model: Some("claude-proxy-1".to_string()),
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            config.env["ANTHROPIC_BASE_URL"],
+            "https://gateway.example.com/anthropic"
+        );
+        assert_eq!(config.env["ANTHROPIC_AUTH_TOKEN"], "gw-token-123456");
+        assert_eq!(
+            config.env["ANTHROPIC_DEFAULT_SONNET_MODEL"],
+            "claude-proxy-1"
+        );
+        assert_eq!(ProviderDetector::detect_provider(&config), Provider::Custom);
+    }
+
+    #[test]
+    fn test_empty_model_is_rejected_before_writing() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        let result = CustomSwitcher::new(manager, CustomOptions::local(8080, Some("  ".into())))
+            .switch_to_custom();
+
+        assert!(result.is_err());
+        assert!(!dir.path().join("settings.json").exists());
+    }
+
+    #[test]
+    fn test_custom_switch_backs_up_anthropic_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        let mut anthropic = Config::default();
+        anthropic
+            .env
+            .insert("ANTHROPIC_AUTH_TOKEN".to_string(), "web-token".to_string());
+        manager.save_current_config(&anthropic).unwrap();
+
+        let config = switch(
+            dir.path(),
+            CustomOptions {
+                base_url: "http://localhost:8080".to_string(),
+                ..Default::default()
+            },
+        );
+        assert!(!config.env.contains_key("ANTHROPIC_AUTH_TOKEN"));
+
+        let (has_backup, backup) = manager.has_valid_anthropic_backup().unwrap();
+        assert!(has_backup);
+        assert_eq!(backup.unwrap().env["ANTHROPIC_AUTH_TOKEN"], "web-token");
+    }
+
+    #[test]
+    fn test_local_switch_writes_localhost_url_without_token() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = switch(dir.path(), CustomOptions::local(8082, None));
+
+        assert_eq!(config.env["ANTHROPIC_BASE_URL"], "http://localhost:8082");
+        assert!(!config.env.contains_key("ANTHROPIC_AUTH_TOKEN"));
+        assert_eq!(ProviderDetector::detect_provider(&config), Provider::Local);
+
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        let history = history::load(manager.history_file()).unwrap();
+        assert_eq!(history[0].provider, "local");
+    }
+
+    #[test]
+    fn test_validate_base_url() {
+        assert!(validate_base_url("https://gateway.example.com").is_ok());
+        assert!(validate_base_url("http://localhost:11434/v1").is_ok());
+        assert!(validate_base_url("gateway.example.com").is_err());
+        assert!(validate_base_url("https://").is_err());
+        assert!(validate_base_url("ftp://example.com").is_err());
+    }
+}
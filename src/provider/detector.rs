@@ -1,32 +1,307 @@
+use crate::config::registry::ProfileRegistry;
 use crate::config::{Config, Provider, TokenType};
+use crate::utils::error::AppError;
+use crate::utils::mask::mask_token;
+use anyhow::Result;
 use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Hosts (and their subdomains) serving GLM: Z.AI's global endpoint and
+/// Zhipu's mainland China one (see `GlmRegion`)
+const GLM_HOSTS: &[&str] = &["z.ai", "bigmodel.cn"];
+
+/// Loopback hosts, detected as `Provider::Local`
+const LOCAL_HOSTS: &[&str] = &["localhost", "127.0.0.1", "[::1]"];
+
+const MODEL_KEYS: [&str; 3] = [
+    "ANTHROPIC_DEFAULT_OPUS_MODEL",
+    "ANTHROPIC_DEFAULT_SONNET_MODEL",
+    "ANTHROPIC_DEFAULT_HAIKU_MODEL",
+];
+
+/// The rule that decided the provider
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DetectionSignal {
+    /// No `env` entries at all
+    EmptyConfig,
+    /// No `env` entries, but an `apiKeyHelper` supplies the key
+    ApiKeyHelper,
+    /// `ANTHROPIC_BASE_URL` has the host of a `providers.toml` profile
+    ProfileBaseUrl,
+    /// `ANTHROPIC_BASE_URL` points at a Z.AI endpoint
+    BaseUrlMatch,
+    /// No `ANTHROPIC_BASE_URL`: Claude's default endpoint
+    DefaultBaseUrl,
+    /// `ANTHROPIC_BASE_URL` points at this machine
+    LocalBaseUrl,
+    /// Any other `ANTHROPIC_BASE_URL`
+    CustomBaseUrl,
+    /// GLM model names on a custom or local base URL, preferred by `provider_order`
+    ModelName,
+}
+
+impl DetectionSignal {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            DetectionSignal::EmptyConfig => "no env entries",
+            DetectionSignal::ApiKeyHelper => "apiKeyHelper set, no env entries",
+            DetectionSignal::ProfileBaseUrl => "base URL host is a providers.toml profile's",
+            DetectionSignal::BaseUrlMatch => "base URL host is a Z.AI endpoint",
+            DetectionSignal::DefaultBaseUrl => "no base URL set (Anthropic default)",
+            DetectionSignal::LocalBaseUrl => "base URL host is localhost",
+            DetectionSignal::CustomBaseUrl => "base URL is not a known provider",
+            DetectionSignal::ModelName => "model names look like GLM (provider_order)",
+        }
+    }
+}
+
+/// `detect_provider` with its reasoning
+#[derive(Debug, Clone)]
+pub struct ProviderDetection {
+    pub provider: Provider,
+    pub signal: DetectionSignal,
+    /// Signals that disagree with `signal`, e.g. GLM model names on a custom URL
+    pub warnings: Vec<String>,
+    /// How `provider_order` settled disagreeing signals, if they did disagree
+    pub tiebreak: Option<String>,
+}
+
+/// Which provider wins when a config's signals disagree, from `provider_order`
+/// in `claude-switch.toml`. Earlier entries win; unlisted providers follow in
+/// the default order, which lets the base URL decide.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "Vec<String>")]
+pub struct ProviderOrder(Vec<Provider>);
+
+impl ProviderOrder {
+    const DEFAULT: [Provider; 4] = [
+        Provider::Anthropic,
+        Provider::Local,
+        Provider::Custom,
+        Provider::GLM,
+    ];
+
+    fn rank(&self, provider: &Provider) -> usize {
+        self.0
+            .iter()
+            .position(|p| p == provider)
+            .or_else(|| {
+                Self::DEFAULT
+                    .iter()
+                    .position(|p| p == provider)
+                    .map(|position| self.0.len() + position)
+            })
+            .unwrap_or(usize::MAX)
+    }
+
+    /// The candidate ranked first, with a note on the preference applied
+    fn pick(&self, a: Provider, b: Provider) -> (Provider, String) {
+        let (winner, loser) = if self.rank(&b) < self.rank(&a) {
+            (b, a)
+        } else {
+            (a, b)
+        };
+        let note = format!(
+            "provider_order ranks {} before {}",
+            winner.as_str(),
+            loser.as_str()
+        );
+        (winner, note)
+    }
+}
+
+impl Default for ProviderOrder {
+    fn default() -> Self {
+        Self(Self::DEFAULT.to_vec())
+    }
+}
+
+impl TryFrom<Vec<String>> for ProviderOrder {
+    type Error = String;
+
+    fn try_from(names: Vec<String>) -> Result<Self, Self::Error> {
+        names
+            .iter()
+            .map(|name| name.parse::<Provider>().map_err(|e| e.to_string()))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Self)
+    }
+}
 
 pub struct ProviderDetector;
 
 impl ProviderDetector {
     pub fn detect_provider(config: &Config) -> Provider {
+        Self::detect_provider_detailed(config, &ProviderOrder::default()).provider
+    }
+
+    /// `--confirm-provider`: an error unless `config` is detected as `expected`
+    pub fn confirm_provider(
+        config: &Config,
+        expected: &Provider,
+        order: &ProviderOrder,
+    ) -> Result<()> {
+        let detection = Self::detect_provider_detailed(config, order);
+        if detection.provider == *expected {
+            return Ok(());
+        }
+        Err(AppError::Provider(format!(
+            "Expected provider {}, but the config is detected as {} ({})",
+            expected.as_str(),
+            detection.provider.as_str(),
+            detection.signal.describe()
+        ))
+        .into())
+    }
+
+    /// The provider and why, with `order` settling GLM model names on a
+    /// custom or local base URL
+    pub fn detect_provider_detailed(config: &Config, order: &ProviderOrder) -> ProviderDetection {
+        Self::detect_with_registry(config, order, &ProfileRegistry::default())
+    }
+
+    /// `detect_provider_detailed`, with a base URL on exactly the host of a
+    /// `registry` profile detected as that profile's provider
+    pub fn detect_with_registry(
+        config: &Config,
+        order: &ProviderOrder,
+        registry: &ProfileRegistry,
+    ) -> ProviderDetection {
         if config.env.is_empty() {
-            return Provider::Unknown;
+            let (provider, signal) = match config.api_key_helper() {
+                Some(_) => (Provider::Anthropic, DetectionSignal::ApiKeyHelper),
+                None => (Provider::Unknown, DetectionSignal::EmptyConfig),
+            };
+            return ProviderDetection {
+                provider,
+                signal,
+                warnings: Vec::new(),
+                tiebreak: None,
+            };
         }
 
-        let empty_string = String::new();
         let base_url = config
             .env
             .get("ANTHROPIC_BASE_URL")
-            .unwrap_or(&empty_string);
+            .map(String::as_str)
+            .unwrap_or_default();
+
+        let (mut provider, mut signal) =
+            if let Some(profile) = registry.profile_for_base_url(base_url) {
+                (profile.provider, DetectionSignal::ProfileBaseUrl)
+            } else if Self::is_glm_base_url(base_url) {
+                (Provider::GLM, DetectionSignal::BaseUrlMatch)
+            } else if base_url.is_empty() {
+                (Provider::Anthropic, DetectionSignal::DefaultBaseUrl)
+            } else if Self::is_local_base_url(base_url) {
+                (Provider::Local, DetectionSignal::LocalBaseUrl)
+            } else {
+                (Provider::Custom, DetectionSignal::CustomBaseUrl)
+            };
+
+        let mut warnings = Vec::new();
+        let mut tiebreak = None;
+        if provider != Provider::GLM {
+            if let Some(model) = Self::glm_model(config) {
+                warnings.push(format!(
+                    "model {} looks like GLM, but the base URL is not z.ai",
+                    model
+                ));
+                if matches!(provider, Provider::Custom | Provider::Local) {
+                    let (winner, note) = order.pick(provider.clone(), Provider::GLM);
+                    if winner == Provider::GLM {
+                        (provider, signal) = (winner, DetectionSignal::ModelName);
+                    }
+                    tiebreak = Some(note);
+                }
+            }
+        }
 
-        // GLM detection
-        if base_url.contains("z.ai") {
-            return Provider::GLM;
+        warnings.extend(Self::auth_conflict(config));
+
+        if provider != Provider::Anthropic && config.api_key_helper().is_some() {
+            warnings.push(
+                "apiKeyHelper is set: Claude Code may send its key instead of ANTHROPIC_AUTH_TOKEN"
+                    .to_string(),
+            );
         }
 
-        // If no custom base URL, it's Anthropic (default)
-        if base_url.is_empty() {
-            return Provider::Anthropic;
+        ProviderDetection {
+            provider,
+            signal,
+            warnings,
+            tiebreak,
         }
+    }
+
+    /// Warning when both auth vars are set; Claude Code sends
+    /// ANTHROPIC_AUTH_TOKEN as a bearer token ahead of ANTHROPIC_API_KEY
+    pub fn auth_conflict(config: &Config) -> Option<String> {
+        let set = |key: &str| {
+            config
+                .env
+                .get(key)
+                .is_some_and(|value| !value.trim().is_empty())
+        };
+        (set("ANTHROPIC_AUTH_TOKEN") && set("ANTHROPIC_API_KEY")).then(|| {
+            "ANTHROPIC_AUTH_TOKEN and ANTHROPIC_API_KEY are both set: Claude Code will likely \
+             use ANTHROPIC_AUTH_TOKEN"
+                .to_string()
+        })
+    }
+
+    /// Host of a base URL, lowercased; a missing scheme is read as https
+    pub fn base_url_host(base_url: &str) -> Option<String> {
+        let base_url = base_url.trim();
+        let url = if base_url.contains("://") {
+            Url::parse(base_url)
+        } else {
+            Url::parse(&format!("https://{}", base_url))
+        };
+        url.ok()?
+            .host_str()
+            .filter(|host| !host.is_empty())
+            .map(str::to_ascii_lowercase)
+    }
+
+    /// Matches the host exactly or as a subdomain, never as a substring
+    fn is_glm_base_url(base_url: &str) -> bool {
+        Self::base_url_host(base_url).is_some_and(|host| {
+            GLM_HOSTS.iter().any(|glm| {
+                host == *glm
+                    || host
+                        .strip_suffix(glm)
+                        .is_some_and(|prefix| prefix.ends_with('.'))
+            })
+        })
+    }
+
+    /// Loopback addresses and `localhost`, including `*.localhost`
+    fn is_local_base_url(base_url: &str) -> bool {
+        Self::base_url_host(base_url).is_some_and(|host| {
+            LOCAL_HOSTS.contains(&host.as_str()) || host.ends_with(".localhost")
+        })
+    }
+
+    /// `base_url` without trailing slashes, for comparing and writing
+    pub fn normalize_base_url(base_url: &str) -> &str {
+        base_url.trim().trim_end_matches('/')
+    }
 
-        // Custom provider
-        Provider::Custom
+    /// First configured model whose name looks like a GLM model
+    fn glm_model(config: &Config) -> Option<&str> {
+        MODEL_KEYS
+            .iter()
+            .filter_map(|key| config.env.get(*key))
+            .map(String::as_str)
+            .find(|model| Self::is_glm_model(model))
+    }
+
+    /// `GLM-4.7`, `glm-4.5-air`, ...
+    pub fn is_glm_model(model: &str) -> bool {
+        model.trim().to_ascii_lowercase().starts_with("glm")
     }
 
     pub fn is_anthropic_config(config: &Config) -> bool {
@@ -48,6 +323,20 @@ impl ProviderDetector {
         )
     }
 
+    /// Keys whose values are credentials and must never be shown in full
+    pub fn is_secret_key(key: &str) -> bool {
+        key.ends_with("_AUTH_TOKEN") || key.ends_with("_API_KEY")
+    }
+
+    /// `value` as safe to print: masked when `key` holds a secret
+    pub fn display_value(key: &str, value: &str) -> String {
+        if Self::is_secret_key(key) {
+            mask_token(value)
+        } else {
+            value.to_string()
+        }
+    }
+
     pub fn detect_token_type(token: &str) -> TokenType {
         if token.is_empty() {
             return TokenType::Unknown;
@@ -80,25 +369,21 @@ impl ProviderDetector {
         let token_type = Self::detect_token_type(token);
 
         match provider {
-            Provider::GLM => {
-                if token_type == TokenType::Anthropic {
-                    eprintln!(
-                        "{}",
-                        "⚠️  Warning: Token looks like an Anthropic token".yellow()
-                    );
-                    eprintln!(
-                        "{}",
-                        "   GLM typically uses API keys (sk-xxx or glm-xxx format)".yellow()
-                    );
-                    return true; // Still allow, just warn
-                }
+            Provider::GLM if token_type == TokenType::Anthropic => {
+                eprintln!(
+                    "{}",
+                    "⚠️  Warning: Token looks like an Anthropic token".yellow()
+                );
+                eprintln!(
+                    "{}",
+                    "   GLM typically uses API keys (sk-xxx or glm-xxx format)".yellow()
+                );
+                return true; // Still allow, just warn
             }
-            Provider::Anthropic => {
-                if token_type == TokenType::GLM {
-                    eprintln!("{}", "⚠️  Warning: Token looks like an API key".yellow());
-                    eprintln!("{}", "   Anthropic uses longer JWT-style tokens".yellow());
-                    return true; // Still allow, just warn
-                }
+            Provider::Anthropic if token_type == TokenType::GLM => {
+                eprintln!("{}", "⚠️  Warning: Token looks like an API key".yellow());
+                eprintln!("{}", "   Anthropic uses longer JWT-style tokens".yellow());
+                return true; // Still allow, just warn
             }
             _ => {}
         }
@@ -106,10 +391,315 @@ impl ProviderDetector {
         true
     }
 
-    pub fn mask_token(token: &str) -> String {
-        if token.len() <= 8 {
-            return "********".to_string();
+    /// Reject empty model names before they are written, and warn about ones
+    /// that don't look like a model id (letters, digits and `.-_:/`, e.g.
+    /// `GLM-4.7` or `qwen2.5-coder:7b`). Returns the warnings, already printed.
+    pub fn check_model_names<'a>(
+        env: impl IntoIterator<Item = (&'a String, &'a String)>,
+    ) -> Result<Vec<String>> {
+        let mut models: Vec<(&String, &String)> = env
+            .into_iter()
+            .filter(|(key, _)| MODEL_KEYS.contains(&key.as_str()))
+            .collect();
+        models.sort();
+
+        let mut warnings = Vec::new();
+        for (key, model) in models {
+            if model.trim().is_empty() {
+                return Err(AppError::Config(format!("{} cannot be empty", key)).into());
+            }
+            let looks_valid = model
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || ".-_:/".contains(c));
+            if !looks_valid {
+                let warning = format!("{} '{}' doesn't look like a model name", key, model);
+                // stderr, so `--print-only` JSON on stdout stays parseable
+                eprintln!("{}", format!("⚠️  {}", warning).yellow());
+                warnings.push(warning);
+            }
+        }
+        Ok(warnings)
+    }
+
+    /// Let the user know top-level settings we don't understand are left alone
+    pub fn warn_unmanaged_settings(config: &Config) {
+        let unmanaged = config.unmanaged_keys();
+        if !unmanaged.is_empty() {
+            println!(
+                "{}{}",
+                "ℹ️  Preserving settings not managed by claude-switch: ".cyan(),
+                unmanaged.join(", ")
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(entries: &[(&str, &str)]) -> Config {
+        let mut config = Config::default();
+        for (key, value) in entries {
+            config.env.insert(key.to_string(), value.to_string());
+        }
+        config
+    }
+
+    #[test]
+    fn test_detection_signal_for_each_path() {
+        let cases = [
+            (config(&[]), Provider::Unknown, DetectionSignal::EmptyConfig),
+            (
+                config(&[("ANTHROPIC_BASE_URL", "https://api.z.ai/api/anthropic")]),
+                Provider::GLM,
+                DetectionSignal::BaseUrlMatch,
+            ),
+            (
+                config(&[("ANTHROPIC_AUTH_TOKEN", "token")]),
+                Provider::Anthropic,
+                DetectionSignal::DefaultBaseUrl,
+            ),
+            (
+                config(&[("ANTHROPIC_BASE_URL", "https://gateway.example.com")]),
+                Provider::Custom,
+                DetectionSignal::CustomBaseUrl,
+            ),
+        ];
+        for (config, provider, signal) in cases {
+            let detection =
+                ProviderDetector::detect_provider_detailed(&config, &ProviderOrder::default());
+            assert_eq!(detection.provider, provider);
+            assert_eq!(detection.signal, signal);
+            assert!(detection.warnings.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_local_detection_matches_loopback_hosts() {
+        for (base_url, provider) in [
+            ("http://localhost:11434", Provider::Local),
+            ("http://127.0.0.1:8080/v1", Provider::Local),
+            ("http://[::1]:4000", Provider::Local),
+            ("http://ollama.localhost", Provider::Local),
+            ("https://localhost.example.com", Provider::Custom),
+        ] {
+            let detection = ProviderDetector::detect_provider_detailed(
+                &config(&[("ANTHROPIC_BASE_URL", base_url)]),
+                &ProviderOrder::default(),
+            );
+            assert_eq!(detection.provider, provider, "{}", base_url);
+        }
+    }
+
+    #[test]
+    fn test_glm_detection_matches_host_not_substring() {
+        for (base_url, provider) in [
+            ("https://api.z.ai/api/anthropic", Provider::GLM),
+            ("https://api.z.ai/api/anthropic/", Provider::GLM),
+            ("https://Z.AI", Provider::GLM),
+            ("api.z.ai/api/anthropic", Provider::GLM),
+            ("https://open.bigmodel.cn/api/anthropic", Provider::GLM),
+            ("https://notbigmodel.cn", Provider::Custom),
+            ("https://notz.aiproxy.com/v1", Provider::Custom),
+            ("https://z.ai.example.com", Provider::Custom),
+            ("https://proxy.example.com/z.ai", Provider::Custom),
+            ("https://fooz.ai", Provider::Custom),
+        ] {
+            assert_eq!(
+                ProviderDetector::detect_provider(&config(&[("ANTHROPIC_BASE_URL", base_url)])),
+                provider,
+                "{}",
+                base_url
+            );
+        }
+    }
+
+    #[test]
+    fn test_registry_base_url_is_detected_by_exact_host() {
+        let dir = tempfile::tempdir().unwrap();
+        let providers = dir.path().join("providers.toml");
+        std::fs::write(
+            &providers,
+            "[profiles.gateway]\nprovider = \"glm\"\nbase_url = \"https://gateway.example.com\"\n",
+        )
+        .unwrap();
+        let registry = ProfileRegistry::load(&providers).unwrap();
+        let order = ProviderOrder::default();
+
+        let exact = config(&[("ANTHROPIC_BASE_URL", "https://gateway.example.com/")]);
+        let detection = ProviderDetector::detect_with_registry(&exact, &order, &registry);
+        assert_eq!(detection.provider, Provider::GLM);
+        assert_eq!(detection.signal, DetectionSignal::ProfileBaseUrl);
+
+        for look_alike in [
+            "https://gateway.example.com.attacker.io",
+            "https://my-gateway.example.com",
+            "https://eu.gateway.example.com",
+        ] {
+            let config = config(&[("ANTHROPIC_BASE_URL", look_alike)]);
+            let detection = ProviderDetector::detect_with_registry(&config, &order, &registry);
+            assert_eq!(detection.provider, Provider::Custom, "{}", look_alike);
+        }
+    }
+
+    #[test]
+    fn test_base_url_host_and_normalization() {
+        assert_eq!(
+            ProviderDetector::base_url_host("https://Gateway.Example.com:8443/v1/").as_deref(),
+            Some("gateway.example.com")
+        );
+        assert_eq!(ProviderDetector::base_url_host("https://"), None);
+        assert_eq!(
+            ProviderDetector::normalize_base_url("https://api.z.ai/api/anthropic//"),
+            "https://api.z.ai/api/anthropic"
+        );
+        assert_eq!(
+            ProviderDetector::normalize_base_url("http://localhost:8080"),
+            "http://localhost:8080"
+        );
+    }
+
+    #[test]
+    fn test_detection_recognizes_api_key_helper() {
+        let mut helper_only = config(&[]);
+        helper_only.extra.insert(
+            "apiKeyHelper".to_string(),
+            serde_json::json!("~/bin/get-key.sh"),
+        );
+        let detection =
+            ProviderDetector::detect_provider_detailed(&helper_only, &ProviderOrder::default());
+        assert_eq!(detection.provider, Provider::Anthropic);
+        assert_eq!(detection.signal, DetectionSignal::ApiKeyHelper);
+
+        let mut glm = config(&[("ANTHROPIC_BASE_URL", "https://api.z.ai/api/anthropic")]);
+        glm.extra = helper_only.extra.clone();
+        let detection = ProviderDetector::detect_provider_detailed(&glm, &ProviderOrder::default());
+        assert_eq!(detection.provider, Provider::GLM);
+        assert!(detection.warnings[0].contains("apiKeyHelper"));
+    }
+
+    #[test]
+    fn test_detection_warns_about_both_auth_vars() {
+        let both = config(&[
+            ("ANTHROPIC_AUTH_TOKEN", "sk-ant-oat01-token"),
+            ("ANTHROPIC_API_KEY", "sk-ant-api03-key"),
+        ]);
+        let detection =
+            ProviderDetector::detect_provider_detailed(&both, &ProviderOrder::default());
+        assert_eq!(detection.warnings.len(), 1);
+        assert!(detection.warnings[0].contains("likely use ANTHROPIC_AUTH_TOKEN"));
+
+        let empty_key = config(&[
+            ("ANTHROPIC_AUTH_TOKEN", "sk-ant-oat01-token"),
+            ("ANTHROPIC_API_KEY", ""),
+        ]);
+        assert!(ProviderDetector::auth_conflict(&empty_key).is_none());
+    }
+
+    #[test]
+    fn test_detection_warns_about_glm_models_on_custom_url() {
+        let detection = ProviderDetector::detect_provider_detailed(
+            &config(&[
+                ("ANTHROPIC_BASE_URL", "https://proxy.example.com/glm"),
+                ("ANTHROPIC_DEFAULT_SONNET_MODEL", "GLM-4.7"),
+            ]),
+            &ProviderOrder::default(),
+        );
+        assert_eq!(detection.provider, Provider::Custom);
+        assert_eq!(detection.warnings.len(), 1);
+        assert!(detection.warnings[0].contains("GLM-4.7"));
+
+        let glm = ProviderDetector::detect_provider_detailed(
+            &config(&[
+                ("ANTHROPIC_BASE_URL", "https://api.z.ai/api/anthropic"),
+                ("ANTHROPIC_DEFAULT_SONNET_MODEL", "GLM-4.7"),
+            ]),
+            &ProviderOrder::default(),
+        );
+        assert!(glm.warnings.is_empty());
+        assert!(glm.tiebreak.is_none());
+    }
+
+    #[test]
+    fn test_model_names_are_checked() {
+        use std::collections::HashMap;
+
+        let models = |value: &str| {
+            HashMap::from([
+                (
+                    "ANTHROPIC_DEFAULT_OPUS_MODEL".to_string(),
+                    value.to_string(),
+                ),
+                ("ANTHROPIC_AUTH_TOKEN".to_string(), " ".to_string()),
+            ])
+        };
+
+        for empty in ["", "   "] {
+            let err = ProviderDetector::check_model_names(&models(empty)).unwrap_err();
+            assert!(err.to_string().contains("ANTHROPIC_DEFAULT_OPUS_MODEL"));
         }
-        format!("{}...{}", &token[..4], &token[token.len() - 4..])
+
+        let warnings =
+            ProviderDetector::check_model_names(&models("model name with spaces")).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("model name with spaces"));
+
+        for valid in ["GLM-4.7", "qwen2.5-coder:7b", "anthropic/claude_sonnet"] {
+            assert!(ProviderDetector::check_model_names(&models(valid))
+                .unwrap()
+                .is_empty());
+        }
+    }
+
+    #[test]
+    fn test_provider_order_settles_glm_models_on_custom_url() {
+        let ambiguous = config(&[
+            ("ANTHROPIC_BASE_URL", "https://proxy.example.com/glm"),
+            ("ANTHROPIC_DEFAULT_OPUS_MODEL", "glm-4.6"),
+        ]);
+
+        let default =
+            ProviderDetector::detect_provider_detailed(&ambiguous, &ProviderOrder::default());
+        assert_eq!(default.provider, Provider::Custom);
+        assert_eq!(default.signal, DetectionSignal::CustomBaseUrl);
+        assert_eq!(
+            default.tiebreak.as_deref(),
+            Some("provider_order ranks custom before glm")
+        );
+
+        let glm_first = ProviderOrder::try_from(vec!["glm".to_string()]).unwrap();
+        let flipped = ProviderDetector::detect_provider_detailed(&ambiguous, &glm_first);
+        assert_eq!(flipped.provider, Provider::GLM);
+        assert_eq!(flipped.signal, DetectionSignal::ModelName);
+        assert_eq!(
+            flipped.tiebreak.as_deref(),
+            Some("provider_order ranks glm before custom")
+        );
+        assert_eq!(flipped.warnings.len(), 1);
+
+        // Only custom and local endpoints are ambiguous; the default endpoint is Anthropic
+        let default_url = config(&[("ANTHROPIC_DEFAULT_OPUS_MODEL", "glm-4.6")]);
+        let detection = ProviderDetector::detect_provider_detailed(&default_url, &glm_first);
+        assert_eq!(detection.provider, Provider::Anthropic);
+        assert!(detection.tiebreak.is_none());
+
+        assert!(ProviderOrder::try_from(vec!["openai".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_confirm_provider() {
+        let order = ProviderOrder::default();
+        let glm = config(&[("ANTHROPIC_BASE_URL", "https://api.z.ai/api/anthropic")]);
+        assert!(ProviderDetector::confirm_provider(&glm, &Provider::GLM, &order).is_ok());
+
+        // A typo in the base URL makes it a custom endpoint
+        let typo = config(&[("ANTHROPIC_BASE_URL", "https://api.z.ia/api/anthropic")]);
+        let err = ProviderDetector::confirm_provider(&typo, &Provider::GLM, &order).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AppError>(),
+            Some(AppError::Provider(_))
+        ));
+        assert!(err.to_string().contains("detected as custom"));
     }
 }
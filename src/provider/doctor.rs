@@ -0,0 +1,457 @@
+use crate::config::manager::{BackupFileKind, ConfigManager};
+use crate::config::registry::ProfileRegistry;
+use crate::config::{Provider, SwitchSettings};
+use crate::provider::detector::ProviderDetector;
+use crate::utils::error::AppError;
+use crate::utils::ClaudeInstallation;
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use colored::*;
+use std::fs;
+
+/// Which auth var `doctor --prefer` keeps when both are set
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum AuthPreference {
+    /// Keep ANTHROPIC_AUTH_TOKEN, remove ANTHROPIC_API_KEY
+    Auth,
+    /// Keep ANTHROPIC_API_KEY, remove ANTHROPIC_AUTH_TOKEN
+    Key,
+}
+
+impl AuthPreference {
+    /// The env key this preference removes
+    pub fn removes(self) -> &'static str {
+        match self {
+            AuthPreference::Auth => "ANTHROPIC_API_KEY",
+            AuthPreference::Key => "ANTHROPIC_AUTH_TOKEN",
+        }
+    }
+}
+
+/// `doctor`: look for configurations Claude Code may not read the way the
+/// user expects, and fix the ones with an unambiguous fix
+pub struct DoctorCommand {
+    config_manager: ConfigManager,
+    prefer: Option<AuthPreference>,
+    installation: Option<ClaudeInstallation>,
+    fix_perms: bool,
+    fix: bool,
+}
+
+impl DoctorCommand {
+    pub fn new(config_manager: ConfigManager) -> Self {
+        Self {
+            config_manager,
+            prefer: None,
+            installation: None,
+            fix_perms: false,
+            fix: false,
+        }
+    }
+
+    /// Also check that Claude Code looks installed; `None` skips the check
+    pub fn with_installation(mut self, installation: Option<ClaudeInstallation>) -> Self {
+        self.installation = installation;
+        self
+    }
+
+    /// Tighten a saved token file others can read to 0600
+    pub fn with_fix_perms(mut self, fix_perms: bool) -> Self {
+        self.fix_perms = fix_perms;
+        self
+    }
+
+    /// Apply every safe repair: token file mode and format, leftover files and
+    /// a missing config directory. Problems that need a decision, such as a
+    /// missing backup, are still only reported.
+    pub fn with_fix(mut self, fix: bool) -> Self {
+        self.fix = fix;
+        self
+    }
+
+    /// Resolve both auth vars being set by removing the other one
+    pub fn with_prefer(mut self, prefer: Option<AuthPreference>) -> Self {
+        self.prefer = prefer;
+        self
+    }
+
+    /// Returns the problems left unfixed
+    pub fn run(&self) -> Result<Vec<String>> {
+        println!("{}", "🩺 Checking configuration".cyan());
+        if self.fix {
+            self.config_manager.ensure_writable()?;
+        }
+        let mut problems = Vec::new();
+
+        let config_dir = self.config_manager.config_dir();
+        if !config_dir.is_dir() {
+            if self.fix {
+                fs::create_dir_all(config_dir).map_err(|e| {
+                    AppError::FileSystem(format!(
+                        "Failed to create {}: {}",
+                        config_dir.display(),
+                        e
+                    ))
+                })?;
+                fixed(format!("Created {}", config_dir.display()));
+            } else {
+                flag(
+                    &mut problems,
+                    format!("Config directory {} does not exist", config_dir.display()),
+                    "Fix: claude-switch doctor --fix",
+                );
+            }
+        }
+
+        let mut config = self
+            .config_manager
+            .load_current_config()
+            .context("settings.json cannot be read")?;
+        println!("  {}", "✅ settings.json parses".green());
+
+        if let Some(installation) = &self.installation {
+            let missing = installation.problems();
+            if missing.is_empty() {
+                println!("  {}", "✅ Claude Code is installed".green());
+            }
+            for problem in missing {
+                println!("  {}", format!("⚠️  {}", problem).yellow());
+                problems.push(problem);
+            }
+        }
+
+        match (ProviderDetector::auth_conflict(&config), self.prefer) {
+            (None, _) => println!("  {}", "✅ One auth variable set".green()),
+            (Some(_), Some(prefer)) => {
+                self.config_manager.ensure_writable()?;
+                let provider = ProviderDetector::detect_provider(&config);
+                let backup = self
+                    .config_manager
+                    .backup_to_rotation(&config, &provider)
+                    .context("Failed to back up current configuration")?;
+                config.env.remove(prefer.removes());
+                self.config_manager
+                    .save_current_config(&config)
+                    .context("Failed to save settings")?;
+                println!(
+                    "  {}",
+                    format!(
+                        "🔧 Removed {} (backup: {})",
+                        prefer.removes(),
+                        backup.display()
+                    )
+                    .green()
+                );
+            }
+            (Some(warning), None) => {
+                println!("  {}", format!("⚠️  {}", warning).yellow());
+                println!(
+                    "     {}",
+                    "Fix: claude-switch doctor --prefer auth (or --prefer key)".cyan()
+                );
+                problems.push(warning);
+            }
+        }
+
+        match self.config_manager.token_file_permission_problem() {
+            None => {}
+            Some(_) if self.fix_perms || self.fix => {
+                if let Some(path) = self.config_manager.fix_token_file_permissions()? {
+                    if cfg!(windows) {
+                        fixed(format!("Restricted {} to the current user", path.display()));
+                    } else {
+                        fixed(format!("Set {} to mode 0600", path.display()));
+                    }
+                }
+            }
+            Some(problem) => {
+                println!("  {}", format!("⚠️  {}", problem).yellow());
+                println!("     {}", "Fix: claude-switch doctor --fix-perms".cyan());
+                problems.push(problem);
+            }
+        }
+
+        match self.config_manager.token_file_format_problem() {
+            None => {}
+            Some(_) if self.fix => {
+                if let Some(path) = self.config_manager.normalize_token_file()? {
+                    fixed(format!("Trimmed the token in {}", path.display()));
+                }
+            }
+            Some(problem) => flag(&mut problems, problem, "Fix: claude-switch doctor --fix"),
+        }
+
+        let stray = self.config_manager.stray_files()?;
+        if self.fix {
+            for path in stray {
+                match fs::remove_file(&path) {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                    Err(e) => {
+                        return Err(e)
+                            .with_context(|| format!("Failed to remove {}", path.display()))
+                    }
+                }
+                fixed(format!("Removed {}", path.display()));
+            }
+        } else if !stray.is_empty() {
+            flag(
+                &mut problems,
+                format!("{} leftover temp or orphaned metadata file(s)", stray.len()),
+                "Fix: claude-switch doctor --fix (or claude-switch clean)",
+            );
+        }
+
+        let backup = self.config_manager.backup_file();
+        let provider = ProviderDetector::detect_provider(&config);
+        match self.config_manager.classify_backup_file()? {
+            BackupFileKind::Missing
+                if !matches!(provider, Provider::Anthropic | Provider::Unknown) =>
+            {
+                // Not a safe repair: the Anthropic settings to back up aren't live
+                flag(
+                    &mut problems,
+                    format!(
+                        "No Anthropic backup ({}); claude-switch anthropic has nothing to restore",
+                        backup.display()
+                    ),
+                    "Not fixed automatically: claude-switch anthropic --from-credentials \
+                     rebuilds the config from the Claude CLI login",
+                );
+            }
+            BackupFileKind::Missing | BackupFileKind::Current => {}
+            BackupFileKind::Legacy => println!(
+                "  {}",
+                format!(
+                    "ℹ️  {} is in the legacy format (no metadata); it still restores",
+                    backup.display()
+                )
+                .cyan()
+            ),
+            kind => {
+                let problem = if kind == BackupFileKind::Corrupt {
+                    format!("{} is corrupt (not valid JSON)", backup.display())
+                } else {
+                    format!(
+                        "{} has an unrecognized schema (valid JSON, but not a config)",
+                        backup.display()
+                    )
+                };
+                println!("  {}", format!("⚠️  {}", problem).yellow());
+                println!(
+                    "     {}",
+                    "Fix: run claude-switch backup while on Anthropic to replace it".cyan()
+                );
+                problems.push(problem);
+            }
+        }
+
+        let order = SwitchSettings::load(self.config_manager.preferences_dir())?.provider_order;
+        let registry = ProfileRegistry::load_layered(
+            self.config_manager.system_providers_file(),
+            self.config_manager.providers_file(),
+        )
+        .unwrap_or_default();
+        let detection = ProviderDetector::detect_with_registry(&config, &order, &registry);
+        for warning in detection.warnings {
+            // The auth conflict was reported above
+            if problems.contains(&warning) {
+                continue;
+            }
+            println!("  {}", format!("⚠️  {}", warning).yellow());
+            problems.push(warning);
+        }
+
+        if problems.is_empty() {
+            println!("{}", "✨ No problems found".green());
+        }
+        Ok(problems)
+    }
+}
+
+/// Report a problem with how to fix it
+fn flag(problems: &mut Vec<String>, problem: String, hint: &str) {
+    println!("  {}", format!("⚠️  {}", problem).yellow());
+    println!("     {}", hint.cyan());
+    problems.push(problem);
+}
+
+fn fixed(what: String) {
+    println!("  {}", format!("🔧 {}", what).green());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::manager::tests::manager_with;
+
+    const BOTH: &str = r#"{"env": {
+        "ANTHROPIC_AUTH_TOKEN": "sk-ant-oat01-token",
+        "ANTHROPIC_API_KEY": "sk-ant-api03-key"
+    }}"#;
+
+    #[test]
+    fn test_reports_both_auth_vars() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager_with(dir.path(), BOTH);
+
+        let problems = DoctorCommand::new(manager).run().unwrap();
+
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("both set"));
+        assert_eq!(
+            fs::read_to_string(dir.path().join("settings.json")).unwrap(),
+            BOTH
+        );
+    }
+
+    #[test]
+    fn test_reports_missing_claude_code() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager_with(dir.path(), r#"{"env": {}}"#);
+        let missing = ClaudeInstallation {
+            binary: None,
+            config_dir: Some(dir.path().to_path_buf()),
+        };
+
+        let problems = DoctorCommand::new(manager)
+            .with_installation(Some(missing))
+            .run()
+            .unwrap();
+
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("not found on PATH"));
+    }
+
+    #[test]
+    fn test_prefer_removes_the_other_auth_var() {
+        for (prefer, kept, removed) in [
+            (
+                AuthPreference::Auth,
+                "ANTHROPIC_AUTH_TOKEN",
+                "ANTHROPIC_API_KEY",
+            ),
+            (
+                AuthPreference::Key,
+                "ANTHROPIC_API_KEY",
+                "ANTHROPIC_AUTH_TOKEN",
+            ),
+        ] {
+            let dir = tempfile::tempdir().unwrap();
+            let manager = manager_with(dir.path(), BOTH);
+
+            let problems = DoctorCommand::new(manager.clone())
+                .with_prefer(Some(prefer))
+                .run()
+                .unwrap();
+
+            assert!(problems.is_empty());
+            let config = manager.load_current_config().unwrap();
+            assert!(config.env.contains_key(kept));
+            assert!(!config.env.contains_key(removed));
+            assert_eq!(manager.list_backups().unwrap().len(), 1);
+        }
+    }
+
+    #[test]
+    fn test_classifies_the_anthropic_backup() {
+        for (content, reported) in [
+            ("{\"env\": {\"ANTHROPIC_AU", Some("corrupt")),
+            ("[]", Some("unrecognized schema")),
+            (r#"{"env": {"ANTHROPIC_AUTH_TOKEN": "old"}}"#, None),
+        ] {
+            let dir = tempfile::tempdir().unwrap();
+            let manager = manager_with(dir.path(), r#"{"env": {}}"#);
+            fs::write(manager.backup_file(), content).unwrap();
+
+            let problems = DoctorCommand::new(manager).run().unwrap();
+
+            match reported {
+                Some(classification) => {
+                    assert_eq!(problems.len(), 1, "{}", content);
+                    assert!(problems[0].contains(classification), "{}", content);
+                }
+                None => assert!(problems.is_empty(), "{}", content),
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_fix_perms_tightens_the_token_file() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager_with(dir.path(), r#"{"env": {}}"#);
+        fs::write(manager.token_file(), "sk-shared").unwrap();
+        fs::set_permissions(manager.token_file(), fs::Permissions::from_mode(0o644)).unwrap();
+
+        let problems = DoctorCommand::new(manager.clone()).run().unwrap();
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("readable by other users"));
+
+        let problems = DoctorCommand::new(manager.clone())
+            .with_fix_perms(true)
+            .run()
+            .unwrap();
+        assert!(problems.is_empty());
+        let mode = fs::metadata(manager.token_file())
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_fix_repairs_only_the_safe_problems() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager_with(
+            dir.path(),
+            r#"{"env": {"ANTHROPIC_BASE_URL": "https://api.z.ai/api/anthropic"}}"#,
+        );
+        fs::write(manager.token_file(), "  \"sk-padded\"\n").unwrap();
+        fs::set_permissions(manager.token_file(), fs::Permissions::from_mode(0o644)).unwrap();
+        fs::write(dir.path().join("settings.tmp"), "{").unwrap();
+        fs::write(dir.path().join("settings.json.meta"), "{}").unwrap();
+
+        let problems = DoctorCommand::new(manager.clone()).run().unwrap();
+        assert_eq!(problems.len(), 4, "{:?}", problems);
+
+        let problems = DoctorCommand::new(manager.clone())
+            .with_fix(true)
+            .run()
+            .unwrap();
+        // A GLM config without an Anthropic backup can't be repaired safely
+        assert_eq!(problems.len(), 1, "{:?}", problems);
+        assert!(problems[0].contains("No Anthropic backup"));
+
+        assert_eq!(
+            fs::read_to_string(manager.token_file()).unwrap(),
+            "sk-padded"
+        );
+        let mode = fs::metadata(manager.token_file())
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(!dir.path().join("settings.tmp").exists());
+        assert!(!dir.path().join("settings.json.meta").exists());
+        assert!(dir.path().join("settings.json").exists());
+    }
+
+    #[test]
+    fn test_fix_recreates_the_config_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_dir = dir.path().join(".claude");
+        let manager = ConfigManager::with_config_dir(config_dir.clone());
+
+        let problems = DoctorCommand::new(manager.clone()).run().unwrap();
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("does not exist"));
+
+        let problems = DoctorCommand::new(manager).with_fix(true).run().unwrap();
+        assert!(problems.is_empty());
+        assert!(config_dir.is_dir());
+    }
+}
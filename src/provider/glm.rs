@@ -1,36 +1,224 @@
-use crate::config::manager::ConfigManager;
-use crate::config::{Config, Provider};
+use crate::config::manager::{ConfigManager, STALE_TOKEN_HINT};
+use crate::config::registry::{EnvKeys, GLM_DEFAULT_TIMEOUT_MS};
+use crate::config::{Config, Precedence, Provider};
+use crate::provider::backup::{backup_anthropic_config, backup_to_rotation};
 use crate::provider::detector::ProviderDetector;
-use crate::utils::token::TokenManager;
+use crate::provider::health::{HealthProbe, ProbeOutcome};
+use crate::utils::error::AppError;
+use crate::utils::token::{ObtainedToken, SavePolicy, TokenManager, TokenSource};
+use crate::utils::Explainer;
 use anyhow::{Context, Result};
+use clap::ValueEnum;
 use colored::*;
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, IsTerminal};
+use std::time::SystemTime;
+
+/// Which Z.AI endpoint the GLM switch points Claude Code at
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum GlmRegion {
+    /// api.z.ai
+    #[default]
+    Global,
+    /// open.bigmodel.cn, Zhipu's mainland China endpoint
+    Cn,
+}
+
+impl GlmRegion {
+    pub fn base_url(&self) -> &'static str {
+        match self {
+            GlmRegion::Global => "https://api.z.ai/api/anthropic",
+            GlmRegion::Cn => "https://open.bigmodel.cn/api/anthropic",
+        }
+    }
+}
+
+/// Command line options for the GLM switch
+#[derive(Debug, Clone, Default)]
+pub struct GLMOptions {
+    /// Read the token from stdin instead of env/saved file/prompt
+    pub token_stdin: bool,
+    /// Run this shell command and use its stdout as the token
+    pub token_command: Option<String>,
+    /// Paste the token from the system clipboard
+    #[cfg(feature = "clipboard")]
+    pub from_clipboard: bool,
+    /// Pre-answered "save token?" question
+    pub save_policy: SavePolicy,
+    /// Never prompt; fail if no token is available from env or the saved file
+    pub non_interactive: bool,
+    /// Replace an existing Anthropic backup with the current config
+    pub force_backup: bool,
+    /// Skip backing up the current config entirely
+    pub no_backup: bool,
+    /// Endpoint to write; `None` keeps the one already active, or uses
+    /// `GlmRegion::Global` on a full switch
+    pub region: Option<GlmRegion>,
+    /// `API_TIMEOUT_MS` to write; `None` uses `GLM_DEFAULT_TIMEOUT_MS` on a
+    /// full switch and keeps the current value when GLM is already active
+    pub timeout_ms: Option<u64>,
+    /// Extra env entries from `--merge-env`, applied over the provider keys
+    pub merge_env: BTreeMap<String, String>,
+    /// Describe each step before it happens
+    pub explain: bool,
+    /// Re-run the whole switch (token, models) even when GLM is already active
+    pub force: bool,
+    /// Env var names for the token and models, from the profile
+    pub env_keys: EnvKeys,
+    /// Probe the endpoint after switching; on a rejected token, ask for
+    /// another once, and put the previous settings back if that fails too
+    pub verify: bool,
+}
 
 pub struct GLMSwitcher {
     config_manager: ConfigManager,
     token_manager: TokenManager,
+    options: GLMOptions,
+    explainer: Explainer,
 }
 
 impl GLMSwitcher {
-    pub fn new(config_manager: ConfigManager) -> Self {
+    pub fn new(config_manager: ConfigManager, options: GLMOptions) -> Self {
         Self {
             config_manager,
-            token_manager: TokenManager::new(),
+            token_manager: TokenManager::with_options(
+                options.save_policy,
+                !options.non_interactive,
+            ),
+            explainer: Explainer::new(options.explain),
+            options,
         }
     }
 
     pub fn switch_to_glm(&self) -> Result<()> {
+        if !self.options.verify {
+            return self.apply();
+        }
+
+        let previous = self
+            .config_manager
+            .load_current_config()
+            .context("Failed to load current config")?;
+        self.apply()?;
+
+        let interactive =
+            !self.options.non_interactive && !self.options.token_stdin && io::stdin().is_terminal();
+        let mut stdin = io::stdin().lock();
+        let input: Option<&mut dyn BufRead> = if interactive { Some(&mut stdin) } else { None };
+        if let Err(e) = self.verify_with(&HealthProbe::new(), input) {
+            self.config_manager
+                .save_current_config(&previous)
+                .context("Failed to put back the previous settings")?;
+            println!("{}", "↩️  Put back the previous settings".yellow());
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Probe the live config; a rejected token is re-entered from `input` and
+    /// probed once more. Without `input` (non-interactive) a rejection is an error.
+    /// An unreachable endpoint or other error status is only a warning.
+    fn verify_with(&self, probe: &HealthProbe, input: Option<&mut dyn BufRead>) -> Result<()> {
+        let auth_key = &self.options.env_keys.auth;
+        let mut config = self
+            .config_manager
+            .load_current_config()
+            .context("Failed to load current config")?;
+        println!("{}", "🩺 Verifying the token with the endpoint...".cyan());
+
+        let status = match probe.check(&config, auth_key) {
+            ProbeOutcome::AuthFailed(status) => status,
+            outcome => return Self::report_probe(outcome),
+        };
+        let Some(input) = input else {
+            return Err(AppError::Token(format!(
+                "The endpoint rejected the token (HTTP {})",
+                status
+            ))
+            .into());
+        };
+
+        println!(
+            "{}",
+            format!("❌ The endpoint rejected the token (HTTP {})", status).red()
+        );
+        let obtained = self
+            .token_manager
+            .reenter_token(&self.config_manager, input)?;
+        config.env.insert(auth_key.clone(), obtained.token);
+        self.config_manager
+            .save_current_config(&config)
+            .context("Failed to save GLM configuration")?;
+
+        match probe.check(&config, auth_key) {
+            ProbeOutcome::AuthFailed(status) => Err(AppError::Token(format!(
+                "The endpoint rejected the new token too (HTTP {})",
+                status
+            ))
+            .into()),
+            outcome => Self::report_probe(outcome),
+        }
+    }
+
+    fn report_probe(outcome: ProbeOutcome) -> Result<()> {
+        match outcome {
+            ProbeOutcome::Healthy => {
+                println!("{}", "✅ The endpoint accepted the token".green())
+            }
+            ProbeOutcome::Unexpected(status) => println!(
+                "{}",
+                format!("⚠️  Could not verify the token (HTTP {})", status).yellow()
+            ),
+            ProbeOutcome::Unreachable(e) => println!(
+                "{}",
+                format!(
+                    "⚠️  Could not reach the endpoint to verify the token: {}",
+                    e
+                )
+                .yellow()
+            ),
+            ProbeOutcome::AuthFailed(_) => unreachable!("handled by verify_with"),
+        }
+        Ok(())
+    }
+
+    fn apply(&self) -> Result<()> {
         println!("{}", "🔄 Switching to GLM API...".green());
 
+        self.config_manager.ensure_writable()?;
+
+        self.explainer
+            .reading_settings(self.config_manager.settings_file());
         // Load current config
         let config = self
             .config_manager
             .load_current_config()
             .context("Failed to load current config")?;
 
-        // Check if already using GLM
-        if ProviderDetector::is_glm_config(&config) {
-            println!("{}", "⚠️  Already using GLM configuration".yellow());
-            println!("{}", "   Use --status to check current settings".cyan());
+        // Check if already using GLM; only the timeout and extra env may need updating
+        if self.only_updates(&config) {
+            let updates = self.pending_updates(&config);
+
+            if updates.is_empty() {
+                println!("{}", "⚠️  Already using GLM configuration".yellow());
+                println!("{}", "   Use --status to check current settings".cyan());
+                println!(
+                    "{}",
+                    "   Use --force to re-apply the token and models".cyan()
+                );
+                return Ok(());
+            }
+
+            let mut env = config.env.clone();
+            for (key, value) in updates {
+                println!("{}{}={}", "✏️  Updated ".green(), key, value);
+                env.insert(key, value);
+            }
+            self.explainer
+                .updating_active("GLM", self.config_manager.settings_file());
+            self.config_manager
+                .save_current_config(&config.with_env(env))
+                .context("Failed to save GLM configuration")?;
             return Ok(());
         }
 
@@ -38,35 +226,66 @@ impl GLMSwitcher {
         let current_provider = ProviderDetector::detect_provider(&config);
 
         match current_provider {
+            _ if self.options.no_backup => {
+                println!(
+                    "{}",
+                    "⚠️  --no-backup: current configuration is not backed up".yellow()
+                );
+                println!(
+                    "{}",
+                    "   Switching back will not be able to restore it".yellow()
+                );
+            }
             Provider::Anthropic => {
                 self.backup_anthropic_config_if_needed(&config)?;
             }
-            Provider::Unknown => {
-                self.handle_unknown_provider()?;
-            }
-            Provider::Custom => {
-                self.handle_custom_provider();
+            Provider::GLM => {}
+            provider => {
+                self.handle_other_provider(&config, &provider)?;
             }
-            _ => {}
         }
 
+        self.explainer.step(
+            "Getting your GLM API token: from --token-command, the clipboard \
+             (--from-clipboard) or stdin (--token-stdin) when given, otherwise from \
+             Z_AI_AUTH_TOKEN or the saved token file, asking you only if neither has one.",
+            self.config_manager.token_file(),
+        );
         // Get GLM API token
-        let token = self
-            .token_manager
-            .prompt_for_token(&self.config_manager)
+        let obtained = self
+            .obtain_token(&self.token_manager)
             .context("Failed to get GLM API token")?;
+        let token = &obtained.token;
 
-        // Validate token format
-        ProviderDetector::validate_token_for_provider(&token, &Provider::GLM);
+        // Never write a config Claude Code can't authenticate with
+        if token.trim().is_empty() {
+            return Err(AppError::Token("GLM API token is empty".to_string()).into());
+        }
+
+        ProviderDetector::warn_unmanaged_settings(&config);
 
-        // Create new config for GLM
-        let new_config = self.create_glm_config(&token);
+        // Validate token format
+        ProviderDetector::validate_token_for_provider(token, &Provider::GLM);
 
+        self.explainer.step(
+            "Writing the Z.AI base URL, your token, the timeout and the GLM models into the \
+             Claude Code settings. Settings outside `env` are kept as they are.",
+            self.config_manager.settings_file(),
+        );
+        let new_config = self.glm_config_from(&config, token);
+        ProviderDetector::check_model_names(&new_config.env)?;
         self.config_manager
             .save_current_config(&new_config)
             .context("Failed to save GLM configuration")?;
 
         println!("{}", "✅ GLM configuration applied successfully".green());
+        println!("{}{}", "🔑 Token: ".cyan(), obtained.summary());
+        if obtained.source == TokenSource::SavedFile {
+            if let Some(warning) = self.config_manager.stale_token_warning(SystemTime::now())? {
+                println!("{}", format!("⚠️  {}", warning).yellow());
+                println!("{}", format!("   {}", STALE_TOKEN_HINT).yellow());
+            }
+        }
         println!();
         println!(
             "{}",
@@ -75,39 +294,113 @@ impl GLMSwitcher {
         Ok(())
     }
 
-    fn backup_anthropic_config_if_needed(&self, config: &Config) -> Result<()> {
-        // Check if valid Anthropic backup already exists
-        let (has_backup, existing_backup) = self
+    /// The config `switch_to_glm` would write, without writing, backing up
+    /// or saving a token (`--print-only`). Never prompts for the token.
+    pub fn target_config(&self) -> Result<Config> {
+        let config = self
             .config_manager
-            .has_valid_anthropic_backup()
-            .context("Failed to check existing backup")?;
+            .load_current_config()
+            .context("Failed to load current config")?;
+        if self.only_updates(&config) {
+            let mut env = config.env.clone();
+            env.extend(self.pending_updates(&config));
+            return Ok(config.with_env(env));
+        }
 
-        if has_backup && existing_backup.is_some() {
-            // Backup already exists - don't overwrite
-            println!(
-                "{}",
-                "💾 Existing Anthropic backup found (preserving configuration)".cyan()
+        let token_manager =
+            TokenManager::with_options(SavePolicy::Never, false).with_notices_on_stderr();
+        let obtained = self
+            .obtain_token(&token_manager)
+            .context("Failed to get GLM API token")?;
+        if obtained.token.trim().is_empty() {
+            return Err(AppError::Token("GLM API token is empty".to_string()).into());
+        }
+
+        let new_config = self.glm_config_from(&config, &obtained.token);
+        ProviderDetector::check_model_names(&new_config.env)?;
+        Ok(new_config)
+    }
+
+    /// The token from `--token-command`, the clipboard, stdin, or else env,
+    /// saved file or prompt
+    fn obtain_token(&self, token_manager: &TokenManager) -> Result<ObtainedToken> {
+        #[cfg(feature = "clipboard")]
+        if self.options.from_clipboard {
+            return token_manager.read_token_from_clipboard(
+                &mut crate::utils::SystemClipboard,
+                &self.config_manager,
+                &mut io::stdin().lock(),
             );
-            if let Some(backup) = existing_backup {
-                if let Some(created_at) = backup.metadata.created_at {
-                    println!(
-                        "{}{}",
-                        "   Backed up at: ".cyan(),
-                        created_at.format("%Y-%m-%d %H:%M:%S UTC")
-                    );
-                }
-            }
+        }
+        if let Some(command) = &self.options.token_command {
+            token_manager.read_token_from_command(command, &self.config_manager)
+        } else if self.options.token_stdin {
+            token_manager.read_token_from_stdin(&self.config_manager)
         } else {
-            // Create new backup with metadata
-            self.config_manager
-                .create_backup_with_metadata(config, &Provider::Anthropic)
-                .context("Failed to backup Anthropic configuration")?;
-            println!("{}", "✅ Anthropic configuration backed up".green());
+            token_manager.prompt_for_token(&self.config_manager)
+        }
+    }
+
+    /// GLM is already active, so only the timeout and extra env are written;
+    /// a profile writing different env var names, or a token source given on
+    /// the command line, needs the full switch
+    fn only_updates(&self, config: &Config) -> bool {
+        ProviderDetector::is_glm_config(config)
+            && config.env.contains_key(&self.options.env_keys.auth)
+            && !self.options.force
+            && !self.has_token_source()
+    }
+
+    /// `--token-stdin`, `--token-command` or `--from-clipboard` was given
+    fn has_token_source(&self) -> bool {
+        #[cfg(feature = "clipboard")]
+        if self.options.from_clipboard {
+            return true;
         }
+        self.options.token_stdin || self.options.token_command.is_some()
+    }
+
+    /// Explicit timeout, `--region` and `--merge-env` values that differ
+    /// from `config`
+    fn pending_updates(&self, config: &Config) -> BTreeMap<String, String> {
+        let mut updates = BTreeMap::new();
+        if let Some(timeout_ms) = self.options.timeout_ms {
+            updates.insert("API_TIMEOUT_MS".to_string(), timeout_ms.to_string());
+        }
+        if let Some(region) = self.options.region {
+            updates.insert(
+                "ANTHROPIC_BASE_URL".to_string(),
+                region.base_url().to_string(),
+            );
+        }
+        updates.extend(self.options.merge_env.clone());
+        updates.retain(|key, value| config.env.get(key) != Some(value));
+        updates
+    }
+
+    /// `config` switched to GLM: settings outside `env` are kept
+    fn glm_config_from(&self, config: &Config, token: &str) -> Config {
+        config.with_env(self.create_glm_config(token).env).merge(
+            &Config::from_env(self.options.merge_env.clone()),
+            Precedence::OtherWins,
+        )
+    }
+
+    fn backup_anthropic_config_if_needed(&self, config: &Config) -> Result<()> {
+        backup_anthropic_config(
+            &self.config_manager,
+            config,
+            self.options.force_backup,
+            &self.explainer,
+        )?;
         Ok(())
     }
 
-    fn handle_unknown_provider(&self) -> Result<()> {
+    /// Custom, local or unrecognized settings: kept in the rotation under the
+    /// provider they were detected as, leaving the Anthropic backup alone
+    fn handle_other_provider(&self, config: &Config, provider: &Provider) -> Result<()> {
+        backup_to_rotation(&self.config_manager, config, provider, &self.explainer)?;
+
         let (has_backup, _) = self.config_manager.has_valid_anthropic_backup()?;
         if has_backup {
             println!("{}", "💾 Using existing Anthropic backup".cyan());
@@ -121,39 +414,333 @@ impl GLMSwitcher {
         Ok(())
     }
 
-    fn handle_custom_provider(&self) {
-        println!(
-            "{}",
-            "⚠️  Current config is custom provider - not backing up".yellow()
-        );
-        println!(
-            "{}",
-            "   Anthropic backup will be preserved if it exists".yellow()
-        );
+    fn timeout_ms(&self) -> u64 {
+        self.options.timeout_ms.unwrap_or(GLM_DEFAULT_TIMEOUT_MS)
     }
 
     fn create_glm_config(&self, token: &str) -> Config {
+        let keys = &self.options.env_keys;
         let mut env = std::collections::HashMap::new();
 
-        env.insert("ANTHROPIC_AUTH_TOKEN".to_string(), token.to_string());
+        env.insert(keys.auth.clone(), token.to_string());
         env.insert(
             "ANTHROPIC_BASE_URL".to_string(),
-            "https://api.z.ai/api/anthropic".to_string(),
+            self.options
+                .region
+                .unwrap_or_default()
+                .base_url()
+                .to_string(),
         );
-        env.insert("API_TIMEOUT_MS".to_string(), "3000000".to_string());
-        env.insert(
-            "ANTHROPIC_DEFAULT_OPUS_MODEL".to_string(),
-            "GLM-4.7".to_string(),
-        );
-        env.insert(
-            "ANTHROPIC_DEFAULT_SONNET_MODEL".to_string(),
-            "GLM-4.7".to_string(),
+        env.insert("API_TIMEOUT_MS".to_string(), self.timeout_ms().to_string());
+        env.insert(keys.opus.clone(), "GLM-4.7".to_string());
+        env.insert(keys.sonnet.clone(), "GLM-4.7".to_string());
+        env.insert(keys.haiku.clone(), "GLM-4.5-Air".to_string());
+
+        Config {
+            env,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::BackupConfig;
+    use crate::provider::health::tests::mock_server;
+    use std::fs;
+
+    #[test]
+    fn test_switch_from_unknown_config_keeps_recoverable_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("settings.json"),
+            r#"{"model": "opus", "permissions": {"allow": ["Bash(ls)"]}}"#,
+        )
+        .unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        manager.save_token("sk-unknown-test").unwrap();
+        assert_eq!(
+            ProviderDetector::detect_provider(&manager.load_current_config().unwrap()),
+            Provider::Unknown
         );
-        env.insert(
-            "ANTHROPIC_DEFAULT_HAIKU_MODEL".to_string(),
-            "GLM-4.5-Air".to_string(),
+
+        let options = GLMOptions {
+            save_policy: SavePolicy::Never,
+            non_interactive: true,
+            ..Default::default()
+        };
+        GLMSwitcher::new(manager, options).switch_to_glm().unwrap();
+
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        assert!(ProviderDetector::is_glm_config(
+            &manager.load_current_config().unwrap()
+        ));
+        // The primary Anthropic backup is not touched
+        assert!(!manager.backup_file().exists());
+
+        let backups = manager.list_backups().unwrap();
+        assert_eq!(backups.len(), 1);
+        let backup: BackupConfig =
+            serde_json::from_str(&fs::read_to_string(&backups[0].path).unwrap()).unwrap();
+        assert_eq!(backup.metadata.provider, "unknown");
+        assert_eq!(backup.settings["model"], "opus");
+        assert_eq!(backup.settings["permissions"]["allow"][0], "Bash(ls)");
+    }
+
+    #[test]
+    fn test_backup_of_custom_config_records_custom() {
+        for (base_url, expected) in [
+            ("https://gateway.example.com/anthropic", "custom"),
+            ("http://localhost:8080", "local"),
+        ] {
+            let dir = tempfile::tempdir().unwrap();
+            let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+            manager.save_token("sk-custom-test").unwrap();
+            manager
+                .save_current_config(&Config::from_env([
+                    ("ANTHROPIC_BASE_URL".to_string(), base_url.to_string()),
+                    ("ANTHROPIC_AUTH_TOKEN".to_string(), "sk-gateway".to_string()),
+                ]))
+                .unwrap();
+
+            let options = GLMOptions {
+                save_policy: SavePolicy::Never,
+                non_interactive: true,
+                ..Default::default()
+            };
+            GLMSwitcher::new(manager.clone(), options)
+                .switch_to_glm()
+                .unwrap();
+
+            assert!(!manager.backup_file().exists());
+            let backups = manager.list_backups().unwrap();
+            assert_eq!(backups.len(), 1, "{}", expected);
+            let backup = manager.load_rotated_backup(&backups[0].path).unwrap();
+            assert_eq!(backup.metadata.provider, expected);
+            assert_eq!(backup.env["ANTHROPIC_BASE_URL"], base_url);
+        }
+    }
+
+    #[test]
+    fn test_switch_keeps_crlf_line_endings() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        manager.save_token("sk-crlf-test").unwrap();
+        fs::write(
+            manager.settings_file(),
+            "{\r\n  \"env\": {\r\n    \"ANTHROPIC_AUTH_TOKEN\": \"sk-ant-oat01-web\"\r\n  },\r\n  \"model\": \"opus\"\r\n}\r\n",
+        )
+        .unwrap();
+
+        let options = GLMOptions {
+            save_policy: SavePolicy::Never,
+            non_interactive: true,
+            ..Default::default()
+        };
+        GLMSwitcher::new(manager.clone(), options)
+            .switch_to_glm()
+            .unwrap();
+
+        let written = fs::read_to_string(manager.settings_file()).unwrap();
+        assert!(written.contains("sk-crlf-test"));
+        assert_eq!(
+            written.matches("\r\n").count(),
+            written.matches('\n').count()
         );
+        assert!(written.lines().count() > 5);
+        let config = manager.load_current_config().unwrap();
+        assert_eq!(config.extra["model"], "opus");
+    }
+
+    #[test]
+    fn test_each_region_writes_its_endpoint() {
+        for region in [GlmRegion::Global, GlmRegion::Cn] {
+            let dir = tempfile::tempdir().unwrap();
+            let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+            manager.save_token("sk-region-test").unwrap();
+
+            let options = GLMOptions {
+                save_policy: SavePolicy::Never,
+                non_interactive: true,
+                region: Some(region),
+                ..Default::default()
+            };
+            GLMSwitcher::new(manager.clone(), options)
+                .switch_to_glm()
+                .unwrap();
+
+            let config = manager.load_current_config().unwrap();
+            assert_eq!(config.env["ANTHROPIC_BASE_URL"], region.base_url());
+            assert!(ProviderDetector::is_glm_config(&config), "{:?}", region);
+        }
+    }
+
+    #[test]
+    fn test_region_change_on_active_glm_only_moves_the_endpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = glm_with_token(dir.path(), "sk-kept");
+
+        let options = GLMOptions {
+            save_policy: SavePolicy::Never,
+            non_interactive: true,
+            region: Some(GlmRegion::Cn),
+            ..Default::default()
+        };
+        GLMSwitcher::new(manager.clone(), options)
+            .switch_to_glm()
+            .unwrap();
+
+        let config = manager.load_current_config().unwrap();
+        assert_eq!(config.env["ANTHROPIC_BASE_URL"], GlmRegion::Cn.base_url());
+        assert_eq!(config.env["ANTHROPIC_AUTH_TOKEN"], "sk-kept");
+    }
+
+    #[test]
+    fn test_switch_preserves_api_key_helper() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("settings.json"),
+            r#"{"apiKeyHelper": "~/bin/get-key.sh"}"#,
+        )
+        .unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        manager.save_token("sk-helper-test").unwrap();
+
+        let options = GLMOptions {
+            save_policy: SavePolicy::Never,
+            non_interactive: true,
+            ..Default::default()
+        };
+        GLMSwitcher::new(manager, options).switch_to_glm().unwrap();
+
+        let config = ConfigManager::with_config_dir(dir.path().to_path_buf())
+            .load_current_config()
+            .unwrap();
+        assert!(ProviderDetector::is_glm_config(&config));
+        assert_eq!(config.api_key_helper(), Some("~/bin/get-key.sh"));
+    }
+
+    fn glm_with_token(dir: &std::path::Path, token: &str) -> ConfigManager {
+        let manager = ConfigManager::with_config_dir(dir.to_path_buf());
+        let mut env = std::collections::HashMap::new();
+        for (key, value) in [
+            ("ANTHROPIC_AUTH_TOKEN", token),
+            ("ANTHROPIC_BASE_URL", "https://api.z.ai/api/anthropic"),
+            ("ANTHROPIC_DEFAULT_SONNET_MODEL", "glm-old"),
+            ("API_TIMEOUT_MS", &GLM_DEFAULT_TIMEOUT_MS.to_string()),
+        ] {
+            env.insert(key.to_string(), value.to_string());
+        }
+        manager.save_current_config(&Config::from_env(env)).unwrap();
+        manager.save_token("sk-refreshed").unwrap();
+        manager
+    }
+
+    #[test]
+    fn test_already_on_glm_is_left_alone_without_force() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = glm_with_token(dir.path(), "sk-stale");
+        let options = GLMOptions {
+            save_policy: SavePolicy::Never,
+            non_interactive: true,
+            ..Default::default()
+        };
+        GLMSwitcher::new(manager, options).switch_to_glm().unwrap();
+
+        let config = ConfigManager::with_config_dir(dir.path().to_path_buf())
+            .load_current_config()
+            .unwrap();
+        assert_eq!(config.env["ANTHROPIC_AUTH_TOKEN"], "sk-stale");
+        assert_eq!(config.env["ANTHROPIC_DEFAULT_SONNET_MODEL"], "glm-old");
+    }
+
+    #[test]
+    fn test_force_reapplies_token_and_models_on_glm() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = glm_with_token(dir.path(), "sk-stale");
+        let options = GLMOptions {
+            save_policy: SavePolicy::Never,
+            non_interactive: true,
+            force: true,
+            ..Default::default()
+        };
+        GLMSwitcher::new(manager, options).switch_to_glm().unwrap();
+
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        let config = manager.load_current_config().unwrap();
+        assert_eq!(config.env["ANTHROPIC_AUTH_TOKEN"], "sk-refreshed");
+        assert_ne!(config.env["ANTHROPIC_DEFAULT_SONNET_MODEL"], "glm-old");
+        // Re-applying GLM over GLM takes no backup
+        assert!(!manager.backup_file().exists());
+        assert!(manager.list_backups().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_token_command_reapplies_on_glm() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = glm_with_token(dir.path(), "sk-stale");
+        let options = GLMOptions {
+            save_policy: SavePolicy::Never,
+            non_interactive: true,
+            token_command: Some("echo sk-from-command".to_string()),
+            ..Default::default()
+        };
+        GLMSwitcher::new(manager, options).switch_to_glm().unwrap();
+
+        let config = ConfigManager::with_config_dir(dir.path().to_path_buf())
+            .load_current_config()
+            .unwrap();
+        assert_eq!(config.env["ANTHROPIC_AUTH_TOKEN"], "sk-from-command");
+    }
+
+    fn verifying_switcher(dir: &std::path::Path, base_url: &str) -> GLMSwitcher {
+        let manager = ConfigManager::with_config_dir(dir.to_path_buf());
+        manager.save_token("sk-bad").unwrap();
+        let options = GLMOptions {
+            save_policy: SavePolicy::Never,
+            merge_env: BTreeMap::from([("ANTHROPIC_BASE_URL".to_string(), base_url.to_string())]),
+            ..Default::default()
+        };
+        GLMSwitcher::new(manager, options)
+    }
+
+    #[test]
+    fn test_verify_reenters_a_rejected_token() {
+        let dir = tempfile::tempdir().unwrap();
+        let (base_url, requests) = mock_server(vec![401, 200]);
+        let switcher = verifying_switcher(dir.path(), &base_url);
+        switcher.switch_to_glm().unwrap();
+
+        let mut input = std::io::Cursor::new("sk-good\n");
+        switcher
+            .verify_with(&HealthProbe::new(), Some(&mut input))
+            .unwrap();
+
+        assert_eq!(requests.recv().unwrap(), "Bearer sk-bad");
+        assert_eq!(requests.recv().unwrap(), "Bearer sk-good");
+        let config = switcher.config_manager.load_current_config().unwrap();
+        assert_eq!(config.env["ANTHROPIC_AUTH_TOKEN"], "sk-good");
+    }
+
+    #[test]
+    fn test_verify_puts_back_previous_settings_when_not_interactive() {
+        let dir = tempfile::tempdir().unwrap();
+        let previous = r#"{"env": {"ANTHROPIC_AUTH_TOKEN": "sk-ant-oat01-web"}}"#;
+        fs::write(dir.path().join("settings.json"), previous).unwrap();
+        let (base_url, _requests) = mock_server(vec![401]);
+        let mut switcher = verifying_switcher(dir.path(), &base_url);
+        switcher.options.verify = true;
+        switcher.options.non_interactive = true;
+
+        let err = switcher.switch_to_glm().unwrap_err();
 
-        Config { env }
+        assert!(matches!(
+            err.downcast_ref::<AppError>(),
+            Some(AppError::Token(_))
+        ));
+        let config = switcher.config_manager.load_current_config().unwrap();
+        assert_eq!(config.env["ANTHROPIC_AUTH_TOKEN"], "sk-ant-oat01-web");
+        assert!(!config.env.contains_key("ANTHROPIC_BASE_URL"));
     }
 }
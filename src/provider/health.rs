@@ -0,0 +1,155 @@
+use crate::config::Config;
+use std::time::Duration;
+
+/// Claude Code's endpoint when `ANTHROPIC_BASE_URL` is unset
+const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
+const PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// What the endpoint said to an authenticated request
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProbeOutcome {
+    Healthy,
+    /// 401 or 403: the token was rejected
+    AuthFailed(u16),
+    /// Any other error status; says nothing about the token
+    Unexpected(u16),
+    Unreachable(String),
+}
+
+/// `--verify`: a cheap authenticated request (`GET /v1/models`) against the
+/// endpoint a config points Claude Code at
+pub struct HealthProbe {
+    agent: ureq::Agent,
+}
+
+impl HealthProbe {
+    pub fn new() -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new()
+                .timeout(PROBE_TIMEOUT)
+                .user_agent(concat!("claude-switch/", env!("CARGO_PKG_VERSION")))
+                .build(),
+        }
+    }
+
+    /// Probe with the token stored under `auth_key`
+    pub fn check(&self, config: &Config, auth_key: &str) -> ProbeOutcome {
+        match models_request(&self.agent, config, auth_key).call() {
+            Ok(_) => ProbeOutcome::Healthy,
+            Err(ureq::Error::Status(status @ (401 | 403), _)) => ProbeOutcome::AuthFailed(status),
+            Err(ureq::Error::Status(status, _)) => ProbeOutcome::Unexpected(status),
+            Err(e) => ProbeOutcome::Unreachable(e.to_string()),
+        }
+    }
+}
+
+impl Default for HealthProbe {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Where `config` points Claude Code
+pub fn base_url(config: &Config) -> &str {
+    config
+        .env
+        .get("ANTHROPIC_BASE_URL")
+        .map(String::as_str)
+        .unwrap_or(DEFAULT_BASE_URL)
+        .trim_end_matches('/')
+}
+
+/// `GET <base URL>/v1/models` with the token stored under `auth_key`, sent
+/// both ways Anthropic-compatible endpoints accept it
+pub fn models_request(agent: &ureq::Agent, config: &Config, auth_key: &str) -> ureq::Request {
+    let token = config.env.get(auth_key).map(String::as_str).unwrap_or("");
+    agent
+        .get(&format!("{}/v1/models", base_url(config)))
+        .set("Authorization", &format!("Bearer {}", token))
+        .set("x-api-key", token)
+        .set("anthropic-version", "2023-06-01")
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+
+    /// Answers one request per status in `statuses` with an empty JSON
+    /// object, then stops; returns the base URL and a receiver of the
+    /// `Authorization` header of each request
+    pub(crate) fn mock_server(statuses: Vec<u16>) -> (String, mpsc::Receiver<String>) {
+        mock_responses(statuses.into_iter().map(|status| (status, "{}")).collect())
+    }
+
+    /// Like `mock_server`, with a body per response
+    pub(crate) fn mock_responses(
+        responses: Vec<(u16, &'static str)>,
+    ) -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            for (status, body) in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut authorization = String::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some(value) = line.strip_prefix("Authorization: ") {
+                        authorization = value.trim().to_string();
+                    }
+                }
+                sender.send(authorization).unwrap();
+                write!(
+                    &stream,
+                    "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+        });
+        (base_url, receiver)
+    }
+
+    fn config(base_url: &str) -> Config {
+        let mut config = Config::default();
+        config
+            .env
+            .insert("ANTHROPIC_BASE_URL".to_string(), format!("{}/", base_url));
+        config
+            .env
+            .insert("ANTHROPIC_AUTH_TOKEN".to_string(), "sk-probe".to_string());
+        config
+    }
+
+    #[test]
+    fn test_probe_classifies_responses() {
+        let (base_url, requests) = mock_server(vec![200, 401, 500]);
+        let probe = HealthProbe::new();
+        let config = config(&base_url);
+
+        assert_eq!(
+            probe.check(&config, "ANTHROPIC_AUTH_TOKEN"),
+            ProbeOutcome::Healthy
+        );
+        assert_eq!(requests.recv().unwrap(), "Bearer sk-probe");
+        assert_eq!(
+            probe.check(&config, "ANTHROPIC_AUTH_TOKEN"),
+            ProbeOutcome::AuthFailed(401)
+        );
+        assert_eq!(
+            probe.check(&config, "ANTHROPIC_AUTH_TOKEN"),
+            ProbeOutcome::Unexpected(500)
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use crate::config::history::{self, HistoryEntry};
+use crate::config::manager::ConfigManager;
+use crate::utils::Renderer;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use colored::*;
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct HistoryReport {
+    pub since: Option<DateTime<Utc>>,
+    pub entries: Vec<HistoryEntry>,
+}
+
+/// `history`: past switches, oldest first
+pub struct HistoryCommand {
+    config_manager: ConfigManager,
+    renderer: Renderer,
+}
+
+impl HistoryCommand {
+    pub fn new(config_manager: ConfigManager, renderer: Renderer) -> Self {
+        Self {
+            config_manager,
+            renderer,
+        }
+    }
+
+    pub fn collect(&self, since: Option<DateTime<Utc>>) -> Result<HistoryReport> {
+        let mut entries = history::load(self.config_manager.history_file())?;
+        if let Some(since) = since {
+            entries.retain(|entry| entry.timestamp >= since);
+        }
+        Ok(HistoryReport { since, entries })
+    }
+
+    pub fn run(&self, since: Option<DateTime<Utc>>) -> Result<()> {
+        let report = self.collect(since)?;
+        if self.renderer.is_json() {
+            return self.renderer.json(&report);
+        }
+
+        if report.entries.is_empty() {
+            println!("{}", "📜 No switches recorded".cyan());
+            return Ok(());
+        }
+
+        println!("{}", "📜 Switch history".cyan());
+        for entry in &report.entries {
+            let profile = entry
+                .profile
+                .as_deref()
+                .map(|profile| format!(" (profile {})", profile))
+                .unwrap_or_default();
+            println!(
+                "  {}  {}{}",
+                entry.timestamp.format("%Y-%m-%d %H:%M:%S UTC"),
+                entry.provider,
+                profile
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::OutputFormat;
+    use std::fs;
+
+    #[test]
+    fn test_since_keeps_only_recent_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        let lines: Vec<String> = [
+            ("2024-03-01T09:00:00Z", "anthropic"),
+            ("2024-03-05T09:00:00Z", "glm"),
+            ("2024-03-08T09:00:00Z", "anthropic"),
+            ("2024-03-09T18:00:00Z", "glm"),
+        ]
+        .iter()
+        .map(|(timestamp, provider)| {
+            format!(
+                r#"{{"timestamp":"{}","provider":"{}"}}"#,
+                timestamp, provider
+            )
+        })
+        .collect();
+        fs::write(manager.history_file(), lines.join("\n")).unwrap();
+
+        let command = HistoryCommand::new(manager, Renderer::new(OutputFormat::Json));
+        assert_eq!(command.collect(None).unwrap().entries.len(), 4);
+
+        let since = history::parse_since("2024-03-05").unwrap();
+        let recent = command.collect(Some(since)).unwrap().entries;
+        let providers: Vec<&str> = recent.iter().map(|e| e.provider.as_str()).collect();
+        assert_eq!(providers, ["glm", "anthropic", "glm"]);
+    }
+}
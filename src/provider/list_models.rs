@@ -0,0 +1,215 @@
+use crate::config::manager::ConfigManager;
+use crate::config::Provider;
+use crate::provider::detector::ProviderDetector;
+use crate::provider::health;
+use crate::provider::report::active_env_keys;
+use crate::utils::error::AppError;
+use crate::utils::Renderer;
+use anyhow::{Context, Result};
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Short, since the answer is only a convenience
+const LIST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// `/v1/models` in the Anthropic (and OpenAI) shape
+#[derive(Deserialize)]
+struct ModelsPage {
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+/// What `list-models` prints
+#[derive(Debug, Serialize)]
+pub struct ModelList {
+    pub provider: String,
+    pub base_url: String,
+    pub models: Vec<String>,
+}
+
+/// `list-models`: ask the active GLM, custom or local endpoint which model IDs it
+/// serves, for the `set-models` and `--model` flags
+pub struct ListModelsCommand {
+    config_manager: ConfigManager,
+    renderer: Renderer,
+}
+
+impl ListModelsCommand {
+    pub fn new(config_manager: ConfigManager, renderer: Renderer) -> Self {
+        Self {
+            config_manager,
+            renderer,
+        }
+    }
+
+    pub fn run(&self) -> Result<ModelList> {
+        let list = self.fetch()?;
+        if self.renderer.is_json() {
+            self.renderer.json(&list)?;
+            return Ok(list);
+        }
+
+        println!("{}{}", "📋 Models at ".cyan(), list.base_url);
+        if list.models.is_empty() {
+            println!("  {}", "(the endpoint listed no models)".yellow());
+        }
+        for model in &list.models {
+            println!("  {}", model);
+        }
+        Ok(list)
+    }
+
+    fn fetch(&self) -> Result<ModelList> {
+        let mut config = self
+            .config_manager
+            .load_current_config()
+            .context("Failed to load current config")?;
+        let provider = ProviderDetector::detect_provider(&config);
+        if matches!(provider, Provider::Anthropic | Provider::Unknown) {
+            return Err(AppError::Provider(format!(
+                "list-models needs a GLM, custom or local provider (current: {})",
+                provider.as_str()
+            ))
+            .into());
+        }
+
+        // The token in the config, else the saved one
+        let auth_key = active_env_keys(&self.config_manager, &config).auth;
+        if config
+            .env
+            .get(&auth_key)
+            .is_none_or(|token| token.is_empty())
+        {
+            if let Some(token) = self.config_manager.load_saved_token()? {
+                config.env.insert(auth_key.clone(), token);
+            }
+        }
+
+        let base_url = health::base_url(&config).to_string();
+        let agent = ureq::AgentBuilder::new()
+            .timeout(LIST_TIMEOUT)
+            .user_agent(concat!("claude-switch/", env!("CARGO_PKG_VERSION")))
+            .build();
+        let page: ModelsPage = match health::models_request(&agent, &config, &auth_key).call() {
+            Ok(response) => response.into_json().map_err(|e| {
+                AppError::Provider(format!(
+                    "{} returned an unexpected model list: {}",
+                    base_url, e
+                ))
+            })?,
+            Err(ureq::Error::Status(status @ (401 | 403), _)) => {
+                return Err(AppError::Token(format!(
+                    "{} rejected the token (HTTP {})",
+                    base_url, status
+                ))
+                .into())
+            }
+            Err(ureq::Error::Status(status, _)) => {
+                return Err(AppError::Provider(format!(
+                    "{} cannot list models (HTTP {})",
+                    base_url, status
+                ))
+                .into())
+            }
+            Err(e) => {
+                return Err(AppError::Provider(format!(
+                    "Could not reach {} (offline?): {}",
+                    base_url, e
+                ))
+                .into())
+            }
+        };
+
+        Ok(ModelList {
+            provider: provider.as_str().to_string(),
+            base_url,
+            models: page.data.into_iter().map(|model| model.id).collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::manager::tests::manager_with;
+    use crate::provider::health::tests::mock_responses;
+    use crate::utils::OutputFormat;
+    use std::fs;
+
+    fn settings(base_url: &str, token: &str) -> String {
+        format!(
+            r#"{{"env": {{"ANTHROPIC_BASE_URL": "{}", "ANTHROPIC_AUTH_TOKEN": "{}"}}}}"#,
+            base_url, token
+        )
+    }
+
+    #[test]
+    fn test_lists_model_ids_with_the_saved_token() {
+        let (base_url, requests) = mock_responses(vec![(
+            200,
+            r#"{"data": [{"id": "glm-4.7", "type": "model"}, {"id": "glm-4.5-air"}], "has_more": false}"#,
+        )]);
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager_with(dir.path(), &settings(&base_url, ""));
+        manager.save_token("sk-saved").unwrap();
+
+        let list = ListModelsCommand::new(manager, Renderer::new(OutputFormat::Human))
+            .run()
+            .unwrap();
+
+        assert_eq!(list.models, ["glm-4.7", "glm-4.5-air"]);
+        assert_eq!(list.provider, "local");
+        assert_eq!(requests.recv().unwrap(), "Bearer sk-saved");
+    }
+
+    #[test]
+    fn test_reports_rejected_token_and_offline_endpoint() {
+        let (base_url, _requests) = mock_responses(vec![(401, "{}")]);
+        let dir = tempfile::tempdir().unwrap();
+        let command = ListModelsCommand::new(
+            manager_with(dir.path(), &settings(&base_url, "sk-bad")),
+            Renderer::new(OutputFormat::Human),
+        );
+        let err = command.run().unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AppError>(),
+            Some(AppError::Token(_))
+        ));
+
+        // Nothing listens on a port that was just released
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let command = ListModelsCommand::new(
+            manager_with(
+                dir.path(),
+                &settings(&format!("http://127.0.0.1:{}", port), "sk-any"),
+            ),
+            Renderer::new(OutputFormat::Human),
+        );
+        let err = command.run().unwrap_err();
+        assert!(err.to_string().contains("offline?"), "{}", err);
+    }
+
+    #[test]
+    fn test_refuses_anthropic() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("settings.json"), r#"{"env": {}}"#).unwrap();
+        let command = ListModelsCommand::new(
+            ConfigManager::with_config_dir(dir.path().to_path_buf()),
+            Renderer::new(OutputFormat::Human),
+        );
+        let err = command.run().unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AppError>(),
+            Some(AppError::Provider(_))
+        ));
+    }
+}
@@ -1,8 +1,45 @@
 pub mod anthropic;
+pub mod backup;
+pub mod bundle;
+pub mod clean;
+pub mod compare;
+#[cfg(feature = "clipboard")]
+pub mod copy_token;
+pub mod custom;
 pub mod detector;
+pub mod doctor;
 pub mod glm;
+pub mod health;
+pub mod history;
+#[cfg(feature = "list-models")]
+pub mod list_models;
+pub mod models;
+pub mod picker;
+pub mod print_env;
+pub mod profiles;
+pub mod report;
+pub mod reset;
+pub mod restore;
 pub mod switcher;
+pub mod verify;
+pub mod watch;
 
-pub use anthropic::*;
+pub use backup::BackupCommand;
+pub use clean::CleanCommand;
+#[cfg(feature = "clipboard")]
+pub use copy_token::CopyTokenCommand;
+pub use custom::{CustomOptions, CustomSwitcher, LOCAL_DEFAULT_PORT};
+pub use doctor::{AuthPreference, DoctorCommand};
 pub use glm::*;
+pub use history::HistoryCommand;
+#[cfg(feature = "list-models")]
+pub use list_models::ListModelsCommand;
+pub use models::{ModelSelection, SetModelsCommand};
+pub use picker::{ProviderMenu, TerminalPicker};
+pub use print_env::PrintEnvCommand;
+pub use profiles::ProfileSwitcher;
+pub use reset::ResetCommand;
+pub use restore::{DeleteBackupCommand, PrintBackupCommand, RenameBackupCommand, RestoreCommand};
 pub use switcher::*;
+pub use verify::VerifyTokenCommand;
+pub use watch::{NotifyWatcher, WatchCommand};
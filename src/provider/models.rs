@@ -0,0 +1,184 @@
+use crate::config::manager::ConfigManager;
+use crate::config::Provider;
+use crate::provider::detector::ProviderDetector;
+use crate::utils::error::AppError;
+use anyhow::{Context, Result};
+use colored::*;
+
+/// New values for the model slots; `None` leaves a slot as it is
+#[derive(Debug, Clone, Default)]
+pub struct ModelSelection {
+    pub opus: Option<String>,
+    pub sonnet: Option<String>,
+    pub haiku: Option<String>,
+}
+
+impl ModelSelection {
+    fn entries(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("ANTHROPIC_DEFAULT_OPUS_MODEL", &self.opus),
+            ("ANTHROPIC_DEFAULT_SONNET_MODEL", &self.sonnet),
+            ("ANTHROPIC_DEFAULT_HAIKU_MODEL", &self.haiku),
+        ]
+        .into_iter()
+        .filter_map(|(key, model)| model.as_deref().map(|model| (key, model.trim())))
+        .collect()
+    }
+}
+
+/// `set-models`: change the model slots of the active provider, keeping its
+/// token, base URL and everything else
+pub struct SetModelsCommand {
+    config_manager: ConfigManager,
+}
+
+impl SetModelsCommand {
+    pub fn new(config_manager: ConfigManager) -> Self {
+        Self { config_manager }
+    }
+
+    pub fn run(&self, models: &ModelSelection) -> Result<()> {
+        let entries = models.entries();
+        if entries.is_empty() {
+            return Err(
+                AppError::Config("Give at least one of --opus, --sonnet, --haiku".into()).into(),
+            );
+        }
+
+        self.config_manager.ensure_writable()?;
+        let mut config = self
+            .config_manager
+            .load_current_config()
+            .context("Failed to load current config")?;
+
+        let provider = ProviderDetector::detect_provider(&config);
+        match provider {
+            Provider::Anthropic => {
+                return Err(AppError::Provider(
+                    "Anthropic uses Claude Code's default models; set-models only applies to \
+                     GLM, custom and local endpoints"
+                        .to_string(),
+                )
+                .into())
+            }
+            Provider::Unknown => {
+                return Err(AppError::Provider(
+                    "No provider is configured; switch to one first".to_string(),
+                )
+                .into())
+            }
+            Provider::GLM | Provider::Custom | Provider::Local => {}
+        }
+
+        let updates: Vec<(String, String)> = entries
+            .iter()
+            .map(|(key, model)| (key.to_string(), model.to_string()))
+            .collect();
+        ProviderDetector::check_model_names(updates.iter().map(|(key, model)| (key, model)))?;
+        if provider == Provider::GLM {
+            for (_, model) in entries
+                .iter()
+                .filter(|(_, model)| !ProviderDetector::is_glm_model(model))
+            {
+                println!(
+                    "{}",
+                    format!("⚠️  '{}' is not a GLM model; Z.AI may reject it", model).yellow()
+                );
+            }
+        }
+
+        for (key, model) in updates {
+            let previous = config.env.insert(key.clone(), model.clone());
+            println!(
+                "  {}{} → {}",
+                format!("{}: ", key).cyan(),
+                previous.as_deref().unwrap_or("(unset)"),
+                model
+            );
+        }
+        self.config_manager
+            .save_current_config(&config)
+            .context("Failed to save models")?;
+
+        println!(
+            "{}",
+            format!("✅ Models updated for {}", provider.as_str()).green()
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::manager::tests::manager_with;
+    use std::fs;
+
+    #[test]
+    fn test_updates_only_the_given_models() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager_with(
+            dir.path(),
+            r#"{"env": {
+                "ANTHROPIC_AUTH_TOKEN": "sk-glm-token",
+                "ANTHROPIC_BASE_URL": "https://api.z.ai/api/anthropic",
+                "ANTHROPIC_DEFAULT_OPUS_MODEL": "GLM-4.7",
+                "ANTHROPIC_DEFAULT_HAIKU_MODEL": "GLM-4.5-Air"
+            }, "model": "opus"}"#,
+        );
+
+        SetModelsCommand::new(manager)
+            .run(&ModelSelection {
+                opus: Some("GLM-4.6".to_string()),
+                sonnet: Some(" GLM-4.6 ".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+
+        let config = ConfigManager::with_config_dir(dir.path().to_path_buf())
+            .load_current_config()
+            .unwrap();
+        assert_eq!(config.env["ANTHROPIC_DEFAULT_OPUS_MODEL"], "GLM-4.6");
+        assert_eq!(config.env["ANTHROPIC_DEFAULT_SONNET_MODEL"], "GLM-4.6");
+        assert_eq!(config.env["ANTHROPIC_DEFAULT_HAIKU_MODEL"], "GLM-4.5-Air");
+        assert_eq!(config.env["ANTHROPIC_AUTH_TOKEN"], "sk-glm-token");
+        assert_eq!(
+            config.env["ANTHROPIC_BASE_URL"],
+            "https://api.z.ai/api/anthropic"
+        );
+        assert_eq!(config.extra["model"], "opus");
+    }
+
+    #[test]
+    fn test_refuses_anthropic_and_empty_models() {
+        let dir = tempfile::tempdir().unwrap();
+        let anthropic = r#"{"env": {"ANTHROPIC_AUTH_TOKEN": "web-token"}}"#;
+        let manager = manager_with(dir.path(), anthropic);
+
+        let error = SetModelsCommand::new(manager)
+            .run(&ModelSelection {
+                opus: Some("GLM-4.6".to_string()),
+                ..Default::default()
+            })
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<AppError>(),
+            Some(AppError::Provider(_))
+        ));
+        assert_eq!(
+            fs::read_to_string(dir.path().join("settings.json")).unwrap(),
+            anthropic
+        );
+
+        let manager = manager_with(
+            dir.path(),
+            r#"{"env": {"ANTHROPIC_BASE_URL": "https://api.z.ai/api/anthropic"}}"#,
+        );
+        assert!(SetModelsCommand::new(manager)
+            .run(&ModelSelection {
+                haiku: Some("  ".to_string()),
+                ..Default::default()
+            })
+            .is_err());
+    }
+}
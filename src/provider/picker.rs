@@ -0,0 +1,133 @@
+use crate::config::manager::ConfigManager;
+use crate::config::Provider;
+use crate::provider::detector::ProviderDetector;
+use crate::provider::glm::GLMOptions;
+use crate::provider::profiles::ProfileSwitcher;
+use anyhow::{Context, Result};
+use colored::*;
+
+/// Lets the user choose one item from a list; `None` means cancelled
+pub trait Picker {
+    fn pick(&self, prompt: &str, items: &[String], default: usize) -> Result<Option<usize>>;
+}
+
+/// Arrow-key menu on the terminal
+pub struct TerminalPicker;
+
+impl Picker for TerminalPicker {
+    fn pick(&self, prompt: &str, items: &[String], default: usize) -> Result<Option<usize>> {
+        dialoguer::Select::new()
+            .with_prompt(prompt)
+            .items(items)
+            .default(default)
+            .interact_opt()
+            .context("Failed to read selection")
+    }
+}
+
+/// Providers offered by the menu, in display order
+const MENU_PROVIDERS: [Provider; 2] = [Provider::Anthropic, Provider::GLM];
+
+pub struct ProviderMenu<P: Picker> {
+    config_manager: ConfigManager,
+    picker: P,
+}
+
+impl<P: Picker> ProviderMenu<P> {
+    pub fn new(config_manager: ConfigManager, picker: P) -> Self {
+        Self {
+            config_manager,
+            picker,
+        }
+    }
+
+    pub fn run(self) -> Result<()> {
+        let config = self.config_manager.load_current_config()?;
+        let current = ProviderDetector::detect_provider(&config);
+
+        let items: Vec<String> = MENU_PROVIDERS
+            .iter()
+            .map(|provider| {
+                let label = match provider {
+                    Provider::GLM => "GLM (Z.AI Models)",
+                    _ => "Anthropic (Default)",
+                };
+                if *provider == current {
+                    format!("{} (current)", label)
+                } else {
+                    label.to_string()
+                }
+            })
+            .collect();
+        let default = MENU_PROVIDERS
+            .iter()
+            .position(|provider| *provider == current)
+            .unwrap_or(0);
+
+        let Some(index) = self.picker.pick("Switch provider", &items, default)? else {
+            println!("{}", "Cancelled".yellow());
+            return Ok(());
+        };
+
+        ProfileSwitcher::new(self.config_manager, GLMOptions::default())
+            .switch_to(MENU_PROVIDERS[index].as_str())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::config::Config;
+    use std::cell::RefCell;
+
+    /// Picks `choice` and remembers the items it was offered
+    pub(crate) struct ScriptedPicker {
+        choice: Option<usize>,
+        pub(crate) seen: RefCell<Vec<String>>,
+    }
+
+    impl ScriptedPicker {
+        pub(crate) fn new(choice: Option<usize>) -> Self {
+            Self {
+                choice,
+                seen: RefCell::new(Vec::new()),
+            }
+        }
+    }
+
+    impl Picker for ScriptedPicker {
+        fn pick(&self, _prompt: &str, items: &[String], _default: usize) -> Result<Option<usize>> {
+            *self.seen.borrow_mut() = items.to_vec();
+            Ok(self.choice)
+        }
+    }
+
+    impl Picker for &ScriptedPicker {
+        fn pick(&self, prompt: &str, items: &[String], default: usize) -> Result<Option<usize>> {
+            (**self).pick(prompt, items, default)
+        }
+    }
+
+    #[test]
+    fn test_menu_marks_current_and_switches_to_choice() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        let mut glm = Config::default();
+        glm.env.insert(
+            "ANTHROPIC_BASE_URL".to_string(),
+            "https://api.z.ai/api/anthropic".to_string(),
+        );
+        manager.save_current_config(&glm).unwrap();
+
+        let picker = ScriptedPicker::new(Some(0));
+        ProviderMenu::new(manager, &picker).run().unwrap();
+
+        assert_eq!(
+            *picker.seen.borrow(),
+            vec!["Anthropic (Default)", "GLM (Z.AI Models) (current)"]
+        );
+        // No backup exists, so switching to Anthropic writes an empty config
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        assert!(manager.load_current_config().unwrap().env.is_empty());
+    }
+}
@@ -0,0 +1,308 @@
+use crate::config::manager::ConfigManager;
+use crate::config::{BackupConfig, Config};
+use crate::provider::detector::ProviderDetector;
+use crate::utils::mask::mask_token;
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// How `print-env` writes each variable
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum EnvFormat {
+    /// `export KEY='value'`, for `eval`
+    #[default]
+    Export,
+    /// `KEY='value'`, for an env file to `source` (or `set -a; source`)
+    Env,
+}
+
+/// `print-env`: the active `env` as `export` lines for `eval "$(claude-switch print-env)"`
+pub struct PrintEnvCommand {
+    config_manager: ConfigManager,
+    format: EnvFormat,
+}
+
+impl PrintEnvCommand {
+    pub fn new(config_manager: ConfigManager) -> Self {
+        Self {
+            config_manager,
+            format: EnvFormat::default(),
+        }
+    }
+
+    pub fn with_format(mut self, format: EnvFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Prints only shell code: anything else on stdout would break `eval`
+    pub fn run(&self, include_secrets: bool) -> Result<()> {
+        let config = self.config_manager.load_current_config()?;
+        let env: BTreeMap<&String, &String> = config.env.iter().collect();
+        for (key, value) in env {
+            println!("{}", env_line(self.format, key, value, include_secrets));
+        }
+        Ok(())
+    }
+}
+
+/// `config` as pretty JSON for `--print-only`, secrets masked unless
+/// `include_secrets`
+pub fn config_json(config: &Config, include_secrets: bool) -> Result<String> {
+    masked_json(config, &config.env, include_secrets)
+}
+
+/// A backup as pretty JSON for `print-backup`, secrets masked unless
+/// `include_secrets`
+pub fn backup_json(backup: &BackupConfig, include_secrets: bool) -> Result<String> {
+    masked_json(backup, &backup.env, include_secrets)
+}
+
+/// `value` serialized with its `env` object replaced by `env`, sorted and
+/// with secrets masked unless `include_secrets`
+fn masked_json(
+    value: &impl Serialize,
+    env: &HashMap<String, String>,
+    include_secrets: bool,
+) -> Result<String> {
+    let mut json = serde_json::to_value(value)?;
+    if let Some(json_env) = json.get_mut("env").and_then(|env| env.as_object_mut()) {
+        // Sorted, so the same config always prints the same thing
+        let sorted: BTreeMap<&String, &String> = env.iter().collect();
+        *json_env = sorted
+            .into_iter()
+            .map(|(key, value)| {
+                let value = if include_secrets {
+                    value.clone()
+                } else {
+                    ProviderDetector::display_value(key, value)
+                };
+                (key.clone(), value.into())
+            })
+            .collect();
+    }
+    Ok(serde_json::to_string_pretty(&json)?)
+}
+
+/// Secrets are commented out, masked, unless `include_secrets`. Entries a
+/// shell can't hold (a key that is no variable name, a value with a NUL) are
+/// commented out too, so the output always sources cleanly. Nothing from
+/// settings.json reaches a comment unescaped: a newline there would end the
+/// comment and run the rest as shell code.
+fn env_line(format: EnvFormat, key: &str, value: &str, include_secrets: bool) -> String {
+    if !is_shell_name(key) {
+        return "# skipped an entry whose name is not a shell variable".to_string();
+    }
+    if value.contains('\0') {
+        return format!("# skipped {}: the value contains a NUL byte", key);
+    }
+    if ProviderDetector::is_secret_key(key) && !include_secrets {
+        return format!(
+            "# {}={} (hidden, use --include-secrets)",
+            key,
+            escape_control(&mask_token(value))
+        );
+    }
+    match format {
+        EnvFormat::Export => format!("export {}={}", key, shell_quote(value)),
+        EnvFormat::Env => format!("{}={}", key, shell_quote(value)),
+    }
+}
+
+/// `[A-Za-z_][A-Za-z0-9_]*`, what POSIX shells accept as a variable name
+fn is_shell_name(key: &str) -> bool {
+    key.chars()
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// `text` with control characters (newlines above all) written as escapes,
+/// so it stays on one comment line
+fn escape_control(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_control() {
+                c.escape_default().to_string()
+            } else {
+                c.to_string()
+            }
+        })
+        .collect()
+}
+
+/// Single-quote `value` for POSIX shells; embedded `'` becomes `'\''`.
+/// Nothing else is special inside single quotes, newlines included.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_json_masks_secrets() {
+        let config = Config::from_env([
+            (
+                "ANTHROPIC_AUTH_TOKEN".to_string(),
+                "sk-1234567890abcdef".to_string(),
+            ),
+            ("API_TIMEOUT_MS".to_string(), "1000".to_string()),
+        ]);
+
+        let masked: serde_json::Value =
+            serde_json::from_str(&config_json(&config, false).unwrap()).unwrap();
+        assert_eq!(masked["env"]["ANTHROPIC_AUTH_TOKEN"], "sk-1...cdef");
+        assert_eq!(masked["env"]["API_TIMEOUT_MS"], "1000");
+
+        let full = config_json(&config, true).unwrap();
+        assert!(full.contains("sk-1234567890abcdef"));
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("plain"), "'plain'");
+        assert_eq!(shell_quote("with spaces"), "'with spaces'");
+        assert_eq!(shell_quote("it's $HOME"), r"'it'\''s $HOME'");
+        assert_eq!(shell_quote(""), "''");
+    }
+
+    #[test]
+    fn test_env_line_hides_secrets_by_default() {
+        let hidden = env_line(
+            EnvFormat::Export,
+            "ANTHROPIC_AUTH_TOKEN",
+            "sk-1234567890abcdef",
+            false,
+        );
+        assert!(hidden.starts_with("# "));
+        assert!(!hidden.contains("1234567890"));
+
+        assert_eq!(
+            env_line(
+                EnvFormat::Export,
+                "ANTHROPIC_AUTH_TOKEN",
+                "sk-1234567890abcdef",
+                true
+            ),
+            "export ANTHROPIC_AUTH_TOKEN='sk-1234567890abcdef'"
+        );
+        assert_eq!(
+            env_line(EnvFormat::Export, "API_TIMEOUT_MS", "3000000", false),
+            "export API_TIMEOUT_MS='3000000'"
+        );
+    }
+
+    #[test]
+    fn test_env_line_skips_what_a_shell_cannot_hold() {
+        assert_eq!(
+            env_line(EnvFormat::Env, "API_TIMEOUT_MS", "1", false),
+            "API_TIMEOUT_MS='1'"
+        );
+        for key in ["MY-VAR", "1ST", "", "A B", "X;rm -rf ~"] {
+            let line = env_line(EnvFormat::Env, key, "v", true);
+            assert!(line.starts_with("# skipped "), "{}", line);
+        }
+        assert!(env_line(EnvFormat::Env, "NUL", "a\0b", true).starts_with("# skipped NUL"));
+    }
+
+    #[test]
+    fn test_comments_never_span_lines() {
+        let skipped = env_line(EnvFormat::Export, "A\ntouch /tmp/pwned\n'", "v", true);
+        assert_eq!(
+            skipped,
+            "# skipped an entry whose name is not a shell variable"
+        );
+
+        let hidden = env_line(
+            EnvFormat::Export,
+            "ANTHROPIC_AUTH_TOKEN",
+            "\nid\nsecret-middle\nid\n",
+            false,
+        );
+        assert!(!hidden.contains('\n'), "{}", hidden);
+        assert!(
+            hidden.starts_with("# ANTHROPIC_AUTH_TOKEN=\\nid\\n"),
+            "{}",
+            hidden
+        );
+    }
+
+    /// Each tricky value, written as an env file, comes back unchanged
+    /// from `bash -c 'source file; printf %s "$KEY"'`
+    #[cfg(unix)]
+    #[test]
+    fn test_env_format_round_trips_through_bash() {
+        use std::process::Command;
+
+        let values = [
+            "plain",
+            "",
+            "with spaces",
+            "  leading and trailing  ",
+            "it's",
+            "'",
+            "''",
+            r#"double "quoted""#,
+            "$HOME",
+            "${HOME:-x} $(id) `id`",
+            r"back\slash\",
+            "line one\nline two\n",
+            "tab\there",
+            "!history ~tilde *glob? [set] #comment",
+            "semi; colon && pipe | amp &",
+            "ünïcödé ✓",
+        ];
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("claude.env");
+        for value in values {
+            for format in [EnvFormat::Env, EnvFormat::Export] {
+                std::fs::write(&file, env_line(format, "KEY", value, false) + "\n").unwrap();
+                let output = match Command::new("bash")
+                    .arg("-c")
+                    .arg(r#"source "$1"; printf %s "$KEY""#)
+                    .arg("bash")
+                    .arg(&file)
+                    .env_remove("KEY")
+                    .output()
+                {
+                    Ok(output) => output,
+                    // No bash to check against
+                    Err(_) => return,
+                };
+                assert!(output.status.success(), "{:?}", value);
+                assert_eq!(String::from_utf8(output.stdout).unwrap(), value);
+            }
+        }
+
+        // A key or a masked secret with newlines must not run anything
+        let marker = dir.path().join("pwned");
+        let injection = format!("\ntouch '{}'\n", marker.display());
+        let lines = [
+            env_line(EnvFormat::Export, &format!("A{}'", injection), "v", true),
+            env_line(EnvFormat::Export, "ANTHROPIC_AUTH_TOKEN", &injection, false),
+            env_line(
+                EnvFormat::Export,
+                "ANTHROPIC_AUTH_TOKEN",
+                "\nid\nsecret-middle\nid\n",
+                false,
+            ),
+            env_line(EnvFormat::Env, "KEY", &injection, false),
+        ];
+        std::fs::write(&file, lines.join("\n") + "\n").unwrap();
+        let output = Command::new("bash")
+            .arg("-c")
+            .arg(r#"source "$1"; printf %s "$KEY""#)
+            .arg("bash")
+            .arg(&file)
+            .env_remove("KEY")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), injection);
+        assert!(output.stderr.is_empty(), "{:?}", output.stderr);
+        assert!(!marker.exists());
+    }
+}
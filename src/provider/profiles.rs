@@ -0,0 +1,245 @@
+use crate::config::history::{self, HistoryEntry};
+use crate::config::manager::ConfigManager;
+use crate::config::registry::{ProfileRegistry, ResolvedProfile};
+use crate::config::{Config, Provider};
+use crate::provider::anthropic::AnthropicSwitcher;
+use crate::provider::glm::{GLMOptions, GLMSwitcher};
+use anyhow::Result;
+use colored::*;
+use std::collections::BTreeMap;
+
+/// Switches to a named profile from `providers.toml` (or a built-in one).
+///
+/// A `timeout_ms` in `options` (from `--timeout-ms`) wins over the profile's;
+/// `merge_env` applies to both GLM and Anthropic profiles.
+pub struct ProfileSwitcher {
+    config_manager: ConfigManager,
+    options: GLMOptions,
+    from_credentials: bool,
+}
+
+impl ProfileSwitcher {
+    pub fn new(config_manager: ConfigManager, options: GLMOptions) -> Self {
+        Self {
+            config_manager,
+            options,
+            from_credentials: false,
+        }
+    }
+
+    /// Anthropic profiles only: without a backup, use the Claude CLI's OAuth login
+    pub fn with_credentials_fallback(mut self, from_credentials: bool) -> Self {
+        self.from_credentials = from_credentials;
+        self
+    }
+
+    /// The config `switch_to` would write, without writing anything
+    /// (`--print-only`)
+    pub fn target_config(self, name: &str) -> Result<Config> {
+        let profile = ProfileRegistry::load_layered(
+            self.config_manager.system_providers_file(),
+            self.config_manager.providers_file(),
+        )?
+        .resolve(name)?;
+        let timeout_ms = self.options.timeout_ms.or(profile.timeout_ms);
+        let merge_env = Self::merge_env(&self.options, &profile);
+        match profile.provider {
+            Provider::GLM => {
+                let options = GLMOptions {
+                    timeout_ms,
+                    env_keys: profile.env_keys,
+                    merge_env,
+                    ..self.options
+                };
+                GLMSwitcher::new(self.config_manager, options).target_config()
+            }
+            _ => AnthropicSwitcher::new(self.config_manager)
+                .with_timeout_ms(timeout_ms)
+                .with_merge_env(merge_env)
+                .with_credentials_fallback(self.from_credentials)
+                .target_config(),
+        }
+    }
+
+    pub fn switch_to(self, name: &str) -> Result<()> {
+        let profile = ProfileRegistry::load_layered(
+            self.config_manager.system_providers_file(),
+            self.config_manager.providers_file(),
+        )?
+        .resolve(name)?;
+        let timeout_ms = self.options.timeout_ms.or(profile.timeout_ms);
+
+        if profile.name != profile.provider.as_str() {
+            println!("{}{}", "📋 Using profile: ".cyan(), profile.name);
+        }
+        if let Some(system) = &profile.overrides {
+            println!(
+                "{}",
+                format!(
+                    "📋 Profile '{}' from {} overrides the one in {}",
+                    profile.name,
+                    self.config_manager.providers_file().display(),
+                    system.display()
+                )
+                .cyan()
+            );
+        }
+
+        let history_file = self.config_manager.history_file().to_path_buf();
+        let merge_env = Self::merge_env(&self.options, &profile);
+        match profile.provider {
+            Provider::GLM => {
+                let options = GLMOptions {
+                    timeout_ms,
+                    env_keys: profile.env_keys,
+                    merge_env,
+                    ..self.options
+                };
+                GLMSwitcher::new(self.config_manager, options).switch_to_glm()?
+            }
+            _ => AnthropicSwitcher::new(self.config_manager)
+                .with_timeout_ms(timeout_ms)
+                .with_merge_env(merge_env)
+                .with_explain(self.options.explain)
+                .with_credentials_fallback(self.from_credentials)
+                .switch_to_anthropic()?,
+        }
+
+        history::record(
+            &history_file,
+            &HistoryEntry::now(&profile.provider, Some(&profile.name)),
+        );
+        Ok(())
+    }
+
+    /// `--merge-env` entries plus the profile's `base_url`, which a
+    /// `--merge-env ANTHROPIC_BASE_URL=...` still overrides
+    fn merge_env(options: &GLMOptions, profile: &ResolvedProfile) -> BTreeMap<String, String> {
+        let mut merge_env = options.merge_env.clone();
+        if let Some(base_url) = &profile.base_url {
+            merge_env
+                .entry("ANTHROPIC_BASE_URL".to_string())
+                .or_insert_with(|| base_url.clone());
+        }
+        merge_env
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::registry::PROVIDERS_FILE_NAME;
+    use crate::utils::token::SavePolicy;
+    use std::fs;
+
+    fn switch(dir: &std::path::Path, name: &str, timeout_ms: Option<u64>) -> Config {
+        let manager = ConfigManager::with_config_dir(dir.to_path_buf());
+        manager.save_token("sk-profile-test").unwrap();
+        let options = GLMOptions {
+            save_policy: SavePolicy::Never,
+            non_interactive: true,
+            timeout_ms,
+            ..Default::default()
+        };
+        ProfileSwitcher::new(manager, options)
+            .switch_to(name)
+            .unwrap();
+        ConfigManager::with_config_dir(dir.to_path_buf())
+            .load_current_config()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_profiles_write_their_timeouts() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join(PROVIDERS_FILE_NAME),
+            "[profiles.fast]\nprovider = \"glm\"\ntimeout_ms = 60000\n\n\
+             [profiles.slow]\nprovider = \"glm\"\ntimeout_ms = 9000000\n",
+        )
+        .unwrap();
+
+        let config = switch(dir.path(), "fast", None);
+        assert_eq!(config.env["API_TIMEOUT_MS"], "60000");
+
+        let config = switch(dir.path(), "slow", None);
+        assert_eq!(config.env["API_TIMEOUT_MS"], "9000000");
+        assert_eq!(config.env["ANTHROPIC_AUTH_TOKEN"], "sk-profile-test");
+    }
+
+    #[test]
+    fn test_flag_overrides_profile_and_builtin_default() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = switch(dir.path(), "glm", None);
+        assert_eq!(config.env["API_TIMEOUT_MS"], "3000000");
+
+        let config = switch(dir.path(), "glm", Some(42));
+        assert_eq!(config.env["API_TIMEOUT_MS"], "42");
+    }
+
+    #[test]
+    fn test_rerunning_glm_keeps_a_custom_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = switch(dir.path(), "glm", None);
+        config
+            .env
+            .insert("API_TIMEOUT_MS".to_string(), "120000".to_string());
+        ConfigManager::with_config_dir(dir.path().to_path_buf())
+            .save_current_config(&config)
+            .unwrap();
+
+        let config = switch(dir.path(), "glm", None);
+        assert_eq!(config.env["API_TIMEOUT_MS"], "120000");
+
+        let config = switch(dir.path(), "glm", Some(42));
+        assert_eq!(config.env["API_TIMEOUT_MS"], "42");
+    }
+
+    #[test]
+    fn test_profile_base_url_is_interpolated() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join(PROVIDERS_FILE_NAME),
+            "[profiles.gateway]\nprovider = \"glm\"\n\
+             base_url = \"${CLAUDE_SWITCH_TEST_UNSET_GATEWAY:-https://gateway.example.com}/api\"\n",
+        )
+        .unwrap();
+
+        let config = switch(dir.path(), "gateway", None);
+        assert_eq!(
+            config.env["ANTHROPIC_BASE_URL"],
+            "https://gateway.example.com/api"
+        );
+    }
+
+    #[test]
+    fn test_profile_with_custom_auth_var() {
+        use crate::provider::report::StatusReport;
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join(PROVIDERS_FILE_NAME),
+            "[profiles.gateway]\nprovider = \"glm\"\n\
+             env_keys = { auth = \"OPENAI_API_KEY\", sonnet = \"GATEWAY_MODEL\" }\n",
+        )
+        .unwrap();
+
+        let config = switch(dir.path(), "gateway", None);
+        assert_eq!(config.env["OPENAI_API_KEY"], "sk-profile-test");
+        assert_eq!(config.env["GATEWAY_MODEL"], "GLM-4.7");
+        assert!(!config.env.contains_key("ANTHROPIC_AUTH_TOKEN"));
+        assert!(!config.env.contains_key("ANTHROPIC_DEFAULT_SONNET_MODEL"));
+        assert_eq!(config.env["ANTHROPIC_DEFAULT_OPUS_MODEL"], "GLM-4.7");
+
+        let report =
+            StatusReport::collect(&ConfigManager::with_config_dir(dir.path().to_path_buf()))
+                .unwrap();
+        assert_eq!(report.auth_env, "OPENAI_API_KEY");
+        assert_eq!(report.auth_token.as_deref(), Some("sk-p...test"));
+        assert_eq!(report.models.sonnet.as_deref(), Some("GLM-4.7"));
+
+        // Back on the plain glm profile, the default names are written again
+        let config = switch(dir.path(), "glm", None);
+        assert_eq!(config.env["ANTHROPIC_AUTH_TOKEN"], "sk-profile-test");
+    }
+}
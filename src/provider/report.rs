@@ -0,0 +1,736 @@
+use crate::config::manager::ConfigManager;
+use crate::config::registry::{EnvKeys, ProfileRegistry};
+use crate::config::{Config, Provider, SwitchSettings, TokenType};
+use crate::provider::detector::{DetectionSignal, ProviderDetector};
+use crate::utils::jwt;
+use crate::utils::mask::mask_token;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::time::SystemTime;
+
+/// Everything `status` knows about the live configuration, independent of how
+/// it is rendered.
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusReport {
+    /// Active profile, `None` for the global config
+    pub profile: Option<String>,
+    pub provider: String,
+    /// Why `provider` was chosen
+    pub detected_by: DetectionSignal,
+    pub detection_warnings: Vec<String>,
+    /// The `provider_order` preference that settled disagreeing signals
+    pub detection_tiebreak: Option<String>,
+    pub configured: bool,
+    pub base_url: Option<String>,
+    pub models: ModelReport,
+    pub timeout_ms: Option<String>,
+    /// Env var holding the auth token: `ANTHROPIC_AUTH_TOKEN` unless the
+    /// last applied profile declares another
+    pub auth_env: String,
+    /// Masked auth token
+    pub auth_token: Option<String>,
+    pub token_type: Option<String>,
+    /// `exp` claim of a web-login (JWT) auth token
+    pub token_expires_at: Option<DateTime<Utc>>,
+    pub other_env_vars: usize,
+    pub backup: BackupReport,
+    pub saved_token: bool,
+    /// Set when the saved token is older than `max_token_age_days`
+    pub saved_token_warning: Option<String>,
+    /// `$schema` declared by settings.json
+    pub schema: Option<String>,
+    /// Top-level settings preserved untouched by switches
+    pub unmanaged_keys: Vec<String>,
+    /// Credential settings outside `env`, such as `apiKeyHelper`
+    pub auth_settings: Vec<String>,
+    pub api_key_helper: Option<String>,
+    /// `settings.local.json`, which Claude Code applies over settings.json
+    pub local_override: Option<LocalOverrideReport>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LocalOverrideReport {
+    pub path: String,
+    /// Keys set by the override, `env.<NAME>` for env entries; empty if it
+    /// could not be parsed
+    pub keys: Vec<String>,
+    pub parse_error: Option<String>,
+}
+
+/// A single `status` value for scripts (`status --field <name>`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "snake_case")]
+pub enum StatusField {
+    Provider,
+    BaseUrl,
+    TokenType,
+    Timeout,
+    OpusModel,
+    SonnetModel,
+    HaikuModel,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ModelReport {
+    pub opus: Option<String>,
+    pub sonnet: Option<String>,
+    pub haiku: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupReport {
+    /// `available`, `unknown_format` or `missing`
+    pub status: String,
+    /// `current`, `legacy`, `unrecognized_schema`, `corrupt` or `missing`
+    pub format: String,
+    pub provider: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub token_type: Option<String>,
+    /// `exp` claim of the backed-up web-login token
+    pub token_expires_at: Option<DateTime<Utc>>,
+}
+
+/// `status --provider-env`: every env key a provider switch writes, whether
+/// or not it is set
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderEnvReport {
+    pub keys: Vec<ProviderEnvKey>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderEnvKey {
+    pub key: String,
+    pub present: bool,
+    /// Masked when the key holds a credential
+    pub value: Option<String>,
+}
+
+impl ProviderEnvReport {
+    pub fn collect(config_manager: &ConfigManager) -> Result<Self> {
+        let config = config_manager.load_current_config()?;
+        Ok(Self::of(&config, &active_env_keys(config_manager, &config)))
+    }
+
+    /// The token and model keys under the names in `keys`, plus the other
+    /// keys GLM switches write
+    pub fn of(config: &Config, keys: &EnvKeys) -> Self {
+        let names = [
+            keys.auth.as_str(),
+            "ANTHROPIC_BASE_URL",
+            "API_TIMEOUT_MS",
+            keys.opus.as_str(),
+            keys.sonnet.as_str(),
+            keys.haiku.as_str(),
+        ];
+        Self {
+            keys: names
+                .into_iter()
+                .map(|key| {
+                    let value = config
+                        .env
+                        .get(key)
+                        .map(|value| ProviderDetector::display_value(key, value));
+                    ProviderEnvKey {
+                        key: key.to_string(),
+                        present: value.is_some(),
+                        value,
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// `status --all-backups`, newest backup first
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupAudit {
+    pub backups: Vec<BackupHealth>,
+}
+
+/// One rotated backup as listed by `status --all-backups`
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupHealth {
+    pub id: String,
+    pub label: Option<String>,
+    pub provider: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    /// Note recorded when the backup was taken
+    pub note: Option<String>,
+    /// Masked auth token
+    pub auth_token: Option<String>,
+    pub token_type: Option<String>,
+    /// `exp` claim of a web-login (JWT) auth token
+    pub token_expires_at: Option<DateTime<Utc>>,
+    pub token_expired: bool,
+    /// Why the backup could not be read
+    pub error: Option<String>,
+}
+
+impl BackupHealth {
+    /// Every backup in the rotation, newest first; unreadable ones are listed
+    /// with their error rather than failing the whole audit
+    pub fn collect_all(config_manager: &ConfigManager, now: DateTime<Utc>) -> Result<Vec<Self>> {
+        let mut rows = Vec::new();
+        for entry in config_manager.list_backups()? {
+            let mut row = Self {
+                id: entry.id(),
+                label: None,
+                provider: None,
+                created_at: entry.timestamp,
+                note: None,
+                auth_token: None,
+                token_type: None,
+                token_expires_at: None,
+                token_expired: false,
+                error: None,
+            };
+            match config_manager.load_rotated_backup(&entry.path) {
+                Ok(backup) => {
+                    let token = backup.env.get("ANTHROPIC_AUTH_TOKEN");
+                    row.label = backup.metadata.label;
+                    row.note = backup.metadata.note;
+                    row.provider = Some(backup.metadata.provider);
+                    row.created_at = backup.metadata.created_at.or(entry.timestamp);
+                    row.auth_token = token.map(|token| mask_token(token));
+                    row.token_type = token.map(|token| {
+                        ProviderDetector::detect_token_type(token)
+                            .as_str()
+                            .to_string()
+                    });
+                    row.token_expires_at = token_expiry(token);
+                    row.token_expired = row.token_expires_at.is_some_and(|exp| exp <= now);
+                }
+                Err(e) => row.error = Some(e.to_string()),
+            }
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+}
+
+impl StatusReport {
+    pub fn collect(config_manager: &ConfigManager) -> Result<Self> {
+        let config = config_manager.load_current_config()?;
+        let order = SwitchSettings::load(config_manager.preferences_dir())?.provider_order;
+        let registry = ProfileRegistry::load_layered(
+            config_manager.system_providers_file(),
+            config_manager.providers_file(),
+        )
+        .unwrap_or_default();
+        let detection = ProviderDetector::detect_with_registry(&config, &order, &registry);
+        let keys = active_env_keys(config_manager, &config);
+        let token = config.env.get(&keys.auth);
+
+        Ok(Self {
+            profile: config_manager.profile().map(str::to_string),
+            provider: detection.provider.as_str().to_string(),
+            detected_by: detection.signal,
+            detection_warnings: detection.warnings,
+            detection_tiebreak: detection.tiebreak,
+            configured: !config.env.is_empty() || !config.auth_settings().is_empty(),
+            base_url: config.env.get("ANTHROPIC_BASE_URL").cloned(),
+            models: ModelReport {
+                opus: config.env.get(&keys.opus).cloned(),
+                sonnet: config.env.get(&keys.sonnet).cloned(),
+                haiku: config.env.get(&keys.haiku).cloned(),
+            },
+            timeout_ms: config.env.get("API_TIMEOUT_MS").cloned(),
+            auth_token: token.map(|token| mask_token(token)),
+            token_type: token.map(|token| {
+                ProviderDetector::detect_token_type(token)
+                    .as_str()
+                    .to_string()
+            }),
+            token_expires_at: token_expiry(token),
+            other_env_vars: Self::count_other_env_vars(&config, &keys),
+            auth_env: keys.auth,
+            backup: BackupReport::collect(config_manager)?,
+            saved_token: matches!(config_manager.load_saved_token(), Ok(Some(_))),
+            saved_token_warning: config_manager.stale_token_warning(SystemTime::now())?,
+            schema: config.schema().map(str::to_string),
+            unmanaged_keys: config.unmanaged_keys(),
+            auth_settings: config.auth_settings(),
+            api_key_helper: config.api_key_helper().map(str::to_string),
+            local_override: LocalOverrideReport::collect(config_manager),
+        })
+    }
+
+    /// `None` when the config doesn't set it
+    pub fn field(&self, field: StatusField) -> Option<&str> {
+        match field {
+            StatusField::Provider => Some(&self.provider),
+            StatusField::BaseUrl => self.base_url.as_deref(),
+            StatusField::TokenType => self.token_type.as_deref(),
+            StatusField::Timeout => self.timeout_ms.as_deref(),
+            StatusField::OpusModel => self.models.opus.as_deref(),
+            StatusField::SonnetModel => self.models.sonnet.as_deref(),
+            StatusField::HaikuModel => self.models.haiku.as_deref(),
+        }
+    }
+
+    /// `status --oneline`: space-separated `key=value` pairs for a shell
+    /// prompt, `-` for what is unset, e.g.
+    /// `provider=glm url=api.z.ai token=sk-1...abcd backup=yes`
+    pub fn oneline(&self) -> String {
+        let url = self.base_url.as_deref().map(|base_url| {
+            ProviderDetector::base_url_host(base_url).unwrap_or_else(|| base_url.to_string())
+        });
+        format!(
+            "provider={} url={} token={} backup={}",
+            self.provider,
+            url.as_deref().unwrap_or("-"),
+            self.auth_token.as_deref().unwrap_or("-"),
+            if self.backup.status == "available" {
+                "yes"
+            } else {
+                "no"
+            }
+        )
+    }
+
+    pub fn provider(&self) -> Provider {
+        match self.provider.as_str() {
+            "anthropic" => Provider::Anthropic,
+            "glm" => Provider::GLM,
+            "custom" => Provider::Custom,
+            "local" => Provider::Local,
+            _ => Provider::Unknown,
+        }
+    }
+
+    fn count_other_env_vars(config: &Config, keys: &EnvKeys) -> usize {
+        config
+            .env
+            .keys()
+            .filter(|key| !ProviderDetector::is_glm_key(key) && !keys.is_model_key(key))
+            .count()
+    }
+}
+
+impl LocalOverrideReport {
+    fn collect(config_manager: &ConfigManager) -> Option<Self> {
+        let path = config_manager.local_settings_file();
+        if !path.exists() {
+            return None;
+        }
+
+        let (keys, parse_error) = match config_manager.load_config(&path) {
+            Ok(local) => {
+                let mut keys: Vec<String> = local
+                    .env
+                    .keys()
+                    .map(|key| format!("env.{}", key))
+                    .chain(local.extra.keys().cloned())
+                    .collect();
+                keys.sort();
+                (keys, None)
+            }
+            Err(e) => (Vec::new(), Some(e.to_string())),
+        };
+        Some(Self {
+            path: path.display().to_string(),
+            keys,
+            parse_error,
+        })
+    }
+}
+
+impl BackupReport {
+    fn collect(config_manager: &ConfigManager) -> Result<Self> {
+        let (has_backup, backup) = config_manager.has_valid_anthropic_backup()?;
+        let format = config_manager.classify_backup_file()?.as_str().to_string();
+
+        let report = match (has_backup, backup) {
+            (true, Some(backup)) => Self {
+                status: "available".to_string(),
+                format,
+                provider: Some(backup.metadata.provider.clone()),
+                created_at: backup.metadata.created_at,
+                token_type: backup.env.get("ANTHROPIC_AUTH_TOKEN").map(|token| {
+                    ProviderDetector::detect_token_type(token)
+                        .as_str()
+                        .to_string()
+                }),
+                token_expires_at: token_expiry(backup.env.get("ANTHROPIC_AUTH_TOKEN")),
+            },
+            (_, backup) => Self {
+                status: if config_manager.backup_file().exists() {
+                    "unknown_format".to_string()
+                } else {
+                    "missing".to_string()
+                },
+                format,
+                // A current-format backup of another provider
+                provider: backup.map(|backup| backup.metadata.provider),
+                created_at: None,
+                token_type: None,
+                token_expires_at: None,
+            },
+        };
+        Ok(report)
+    }
+}
+
+/// Env var names the live `config` holds its token and models under, from the
+/// providers.toml profile that wrote it (see `ProfileRegistry::env_keys_for`)
+pub fn active_env_keys(config_manager: &ConfigManager, config: &Config) -> EnvKeys {
+    ProfileRegistry::load_layered(
+        config_manager.system_providers_file(),
+        config_manager.providers_file(),
+    )
+    .ok()
+    .and_then(|registry| registry.env_keys_for(config))
+    .unwrap_or_default()
+}
+
+/// Expiry of a web-login auth token; API keys and malformed tokens have none
+fn token_expiry(token: Option<&String>) -> Option<DateTime<Utc>> {
+    let token = token?;
+    if ProviderDetector::detect_token_type(token) != TokenType::Anthropic {
+        return None;
+    }
+    jwt::expiry(token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glm_config() -> Config {
+        let mut config = Config::default();
+        for (key, value) in [
+            ("ANTHROPIC_AUTH_TOKEN", "sk-1234567890abcdef"),
+            ("ANTHROPIC_BASE_URL", "https://api.z.ai/api/anthropic"),
+            ("ANTHROPIC_DEFAULT_SONNET_MODEL", "GLM-4.7"),
+            ("API_TIMEOUT_MS", "3000000"),
+        ] {
+            config.env.insert(key.to_string(), value.to_string());
+        }
+        config
+    }
+
+    #[test]
+    fn test_collect_glm_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        manager.save_current_config(&glm_config()).unwrap();
+
+        let report = StatusReport::collect(&manager).unwrap();
+        assert_eq!(report.provider(), Provider::GLM);
+        assert_eq!(
+            report.base_url.as_deref(),
+            Some("https://api.z.ai/api/anthropic")
+        );
+        assert_eq!(report.models.sonnet.as_deref(), Some("GLM-4.7"));
+        assert_eq!(report.auth_token.as_deref(), Some("sk-1...cdef"));
+        assert_eq!(report.token_type.as_deref(), Some("glm"));
+        assert_eq!(report.other_env_vars, 1);
+        assert_eq!(report.backup.status, "missing");
+        assert!(!report.saved_token);
+        assert!(report.schema.is_none());
+        assert!(report.unmanaged_keys.is_empty());
+        assert!(report.token_expires_at.is_none());
+    }
+
+    #[test]
+    fn test_oneline_for_glm_and_anthropic() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        manager.save_current_config(&glm_config()).unwrap();
+        assert_eq!(
+            StatusReport::collect(&manager).unwrap().oneline(),
+            "provider=glm url=api.z.ai token=sk-1...cdef backup=no"
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        let anthropic = Config::from_env([(
+            "ANTHROPIC_AUTH_TOKEN".to_string(),
+            "sk-ant-oat01-abcdefghijkl".to_string(),
+        )]);
+        manager.save_current_config(&anthropic).unwrap();
+        manager
+            .create_backup_with_metadata(&anthropic, &Provider::Anthropic)
+            .unwrap();
+        assert_eq!(
+            StatusReport::collect(&manager).unwrap().oneline(),
+            "provider=anthropic url=- token=sk-a...ijkl backup=yes"
+        );
+    }
+
+    #[test]
+    fn test_provider_env_lists_each_key_with_masked_values() {
+        let mut config = glm_config();
+        for key in [
+            "ANTHROPIC_DEFAULT_OPUS_MODEL",
+            "ANTHROPIC_DEFAULT_HAIKU_MODEL",
+        ] {
+            config.env.insert(key.to_string(), "GLM-4.7".to_string());
+        }
+        config.env.remove("API_TIMEOUT_MS");
+
+        let report = ProviderEnvReport::of(&config, &EnvKeys::default());
+        let presence: Vec<(&str, bool, Option<&str>)> = report
+            .keys
+            .iter()
+            .map(|key| (key.key.as_str(), key.present, key.value.as_deref()))
+            .collect();
+        assert_eq!(
+            presence,
+            vec![
+                ("ANTHROPIC_AUTH_TOKEN", true, Some("sk-1...cdef")),
+                (
+                    "ANTHROPIC_BASE_URL",
+                    true,
+                    Some("https://api.z.ai/api/anthropic")
+                ),
+                ("API_TIMEOUT_MS", false, None),
+                ("ANTHROPIC_DEFAULT_OPUS_MODEL", true, Some("GLM-4.7")),
+                ("ANTHROPIC_DEFAULT_SONNET_MODEL", true, Some("GLM-4.7")),
+                ("ANTHROPIC_DEFAULT_HAIKU_MODEL", true, Some("GLM-4.7")),
+            ]
+        );
+
+        // A profile's own names replace the defaults
+        let keys = EnvKeys {
+            auth: "OPENAI_API_KEY".to_string(),
+            ..EnvKeys::default()
+        };
+        let report = ProviderEnvReport::of(&config, &keys);
+        assert_eq!(report.keys[0].key, "OPENAI_API_KEY");
+        assert!(!report.keys[0].present);
+    }
+
+    #[test]
+    fn test_backup_health_for_mixed_providers() {
+        use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        use base64::Engine;
+
+        let payload = URL_SAFE_NO_PAD.encode(r#"{"exp":1700000000}"#);
+        let web_token = format!("eyJhbGciOiJIUzI1NiJ9.{}.{}", payload, "s".repeat(80));
+
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        let backups = manager.backups_dir();
+        std::fs::create_dir_all(&backups).unwrap();
+        std::fs::write(
+            backups.join("settings-20240102T000000000Z.json"),
+            r#"{"_metadata": {"provider": "glm", "created_at": 1704153600, "version": "2.2.0"},
+                "env": {"ANTHROPIC_AUTH_TOKEN": "sk-1234567890abcdef"}}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            backups.join("settings-20240101T000000000Z.json"),
+            format!(
+                r#"{{"_metadata": {{"provider": "anthropic", "created_at": null, "version": "2.2.0", "label": "work"}},
+                    "env": {{"ANTHROPIC_AUTH_TOKEN": "{}"}}}}"#,
+                web_token
+            ),
+        )
+        .unwrap();
+        std::fs::write(backups.join("settings-20231231T000000000Z.json"), "{ torn").unwrap();
+
+        let now = DateTime::from_timestamp(1704240000, 0).unwrap();
+        let rows = BackupHealth::collect_all(&manager, now).unwrap();
+        assert_eq!(rows.len(), 3);
+
+        let glm = &rows[0];
+        assert_eq!(glm.id, "20240102T000000000Z");
+        assert_eq!(glm.provider.as_deref(), Some("glm"));
+        assert_eq!(glm.auth_token.as_deref(), Some("sk-1...cdef"));
+        assert_eq!(glm.token_type.as_deref(), Some("glm"));
+        assert!(glm.token_expires_at.is_none());
+        assert!(!glm.token_expired);
+
+        let anthropic = &rows[1];
+        assert_eq!(anthropic.label.as_deref(), Some("work"));
+        assert_eq!(anthropic.provider.as_deref(), Some("anthropic"));
+        assert_eq!(anthropic.token_type.as_deref(), Some("anthropic"));
+        // No created_at in the metadata: the file name's timestamp is used
+        assert_eq!(
+            anthropic.created_at.map(|created| created.timestamp()),
+            Some(1704067200)
+        );
+        assert!(anthropic.token_expired);
+
+        let torn = &rows[2];
+        assert!(torn.provider.is_none());
+        assert!(torn.error.is_some());
+    }
+
+    #[test]
+    fn test_field_values() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        manager.save_current_config(&glm_config()).unwrap();
+
+        let report = StatusReport::collect(&manager).unwrap();
+        assert_eq!(report.field(StatusField::Provider), Some("glm"));
+        assert_eq!(
+            report.field(StatusField::BaseUrl),
+            Some("https://api.z.ai/api/anthropic")
+        );
+        assert_eq!(report.field(StatusField::TokenType), Some("glm"));
+        assert_eq!(report.field(StatusField::Timeout), Some("3000000"));
+        assert_eq!(report.field(StatusField::SonnetModel), Some("GLM-4.7"));
+        assert_eq!(report.field(StatusField::OpusModel), None);
+    }
+
+    #[test]
+    fn test_collect_reports_api_key_helper() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("settings.json"),
+            r#"{"apiKeyHelper": "~/bin/get-key.sh", "awsAuthRefresh": "aws sso login"}"#,
+        )
+        .unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+
+        let report = StatusReport::collect(&manager).unwrap();
+        assert!(report.configured);
+        assert_eq!(report.provider(), Provider::Anthropic);
+        assert_eq!(report.detected_by, DetectionSignal::ApiKeyHelper);
+        assert_eq!(report.api_key_helper.as_deref(), Some("~/bin/get-key.sh"));
+        assert_eq!(report.auth_settings, ["apiKeyHelper", "awsAuthRefresh"]);
+        assert!(report.unmanaged_keys.is_empty());
+    }
+
+    #[test]
+    fn test_collect_web_token_expiry() {
+        use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        use base64::Engine;
+
+        let payload = URL_SAFE_NO_PAD.encode(r#"{"exp":1700000000}"#);
+        let token = format!("eyJhbGciOiJIUzI1NiJ9.{}.{}", payload, "s".repeat(80));
+
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        let mut config = Config::default();
+        config.env.insert("ANTHROPIC_AUTH_TOKEN".to_string(), token);
+        manager.save_current_config(&config).unwrap();
+
+        let report = StatusReport::collect(&manager).unwrap();
+        assert_eq!(report.token_type.as_deref(), Some("anthropic"));
+        assert_eq!(
+            report.token_expires_at.map(|exp| exp.timestamp()),
+            Some(1700000000)
+        );
+    }
+
+    #[test]
+    fn test_collect_classifies_the_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        manager.save_current_config(&glm_config()).unwrap();
+
+        for (content, status, format) in [
+            (
+                r#"{"env": {"ANTHROPIC_AUTH_TOKEN": "old"}}"#,
+                "available",
+                "legacy",
+            ),
+            (
+                r#"{"model": ["opus"], "env": []}"#,
+                "unknown_format",
+                "unrecognized_schema",
+            ),
+            ("{ torn", "unknown_format", "corrupt"),
+        ] {
+            std::fs::write(manager.backup_file(), content).unwrap();
+            let backup = StatusReport::collect(&manager).unwrap().backup;
+            assert_eq!(backup.status, status, "{}", content);
+            assert_eq!(backup.format, format, "{}", content);
+        }
+
+        manager
+            .create_backup_with_metadata(&glm_config(), &Provider::GLM)
+            .unwrap();
+        let backup = StatusReport::collect(&manager).unwrap().backup;
+        assert_eq!(backup.status, "unknown_format");
+        assert_eq!(backup.format, "current");
+        assert_eq!(backup.provider.as_deref(), Some("glm"));
+    }
+
+    #[test]
+    fn test_legacy_backup_shows_its_mtime() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        std::fs::write(
+            manager.backup_file(),
+            r#"{"env": {"ANTHROPIC_AUTH_TOKEN": "old"}}"#,
+        )
+        .unwrap();
+        let written = Utc::now() - chrono::Duration::days(3 * 365);
+        std::fs::File::options()
+            .write(true)
+            .open(manager.backup_file())
+            .unwrap()
+            .set_modified(written.into())
+            .unwrap();
+
+        let backup = StatusReport::collect(&manager).unwrap().backup;
+        assert_eq!(backup.format, "legacy");
+        // Shown as when the file was written, not as just created
+        assert_eq!(
+            backup.created_at.map(|created| created.timestamp()),
+            Some(written.timestamp())
+        );
+    }
+
+    #[test]
+    fn test_collect_reports_schema_and_unmanaged_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        std::fs::write(
+            dir.path().join("settings.json"),
+            r#"{
+                "$schema": "https://json.schemastore.org/claude-code-settings.json",
+                "permissions": {"allow": ["Bash(ls)"]},
+                "model": "opus",
+                "env": {}
+            }"#,
+        )
+        .unwrap();
+
+        let report = StatusReport::collect(&manager).unwrap();
+        assert_eq!(
+            report.schema.as_deref(),
+            Some("https://json.schemastore.org/claude-code-settings.json")
+        );
+        assert_eq!(report.unmanaged_keys, vec!["model", "permissions"]);
+    }
+
+    #[test]
+    fn test_local_override_lists_its_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        manager.save_current_config(&glm_config()).unwrap();
+        assert!(StatusReport::collect(&manager)
+            .unwrap()
+            .local_override
+            .is_none());
+
+        std::fs::write(
+            manager.local_settings_file(),
+            r#"{"env": {"ANTHROPIC_BASE_URL": "http://localhost:4000"}, "model": "opus"}"#,
+        )
+        .unwrap();
+        let local = StatusReport::collect(&manager)
+            .unwrap()
+            .local_override
+            .unwrap();
+        assert!(local.path.ends_with("settings.local.json"));
+        assert_eq!(local.keys, ["env.ANTHROPIC_BASE_URL", "model"]);
+        assert_eq!(local.parse_error, None);
+
+        std::fs::write(manager.local_settings_file(), "{ not json").unwrap();
+        let local = StatusReport::collect(&manager)
+            .unwrap()
+            .local_override
+            .unwrap();
+        assert!(local.keys.is_empty());
+        assert!(local.parse_error.is_some());
+    }
+}
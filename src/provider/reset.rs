@@ -0,0 +1,137 @@
+use crate::config::manager::ConfigManager;
+use crate::utils::error::AppError;
+use anyhow::{Context, Result};
+use colored::*;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+
+/// `reset`: delete every file holding claude-switch state, leaving the rest
+/// of the (shared) config directory alone
+pub struct ResetCommand {
+    config_manager: ConfigManager,
+}
+
+impl ResetCommand {
+    pub fn new(config_manager: ConfigManager) -> Self {
+        Self { config_manager }
+    }
+
+    /// Without `confirm`, asks on the terminal; returns the files removed
+    pub fn run(&self, confirm: bool) -> Result<Vec<PathBuf>> {
+        let targets = self.config_manager.state_files()?;
+        if targets.is_empty() {
+            println!("{}", "✨ Nothing to reset".green());
+            return Ok(targets);
+        }
+
+        println!("{}", "🧨 Reset will remove:".yellow());
+        for path in &targets {
+            println!("  {}", path.display());
+        }
+
+        if !confirm {
+            let stdin = io::stdin();
+            if !stdin.is_terminal() {
+                return Err(AppError::Config(
+                    "reset needs --confirm when stdin is not a terminal".to_string(),
+                )
+                .into());
+            }
+            if !Self::ask(&mut stdin.lock())? {
+                println!("{}", "❌ Reset cancelled".yellow());
+                return Ok(Vec::new());
+            }
+        }
+
+        self.config_manager.ensure_writable()?;
+        for path in &targets {
+            match fs::remove_file(path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to remove {}", path.display()))
+                }
+            }
+            println!("  {}{}", "🗑️  Removed ".green(), path.display());
+        }
+
+        println!(
+            "{}",
+            format!("✅ Reset complete, removed {} file(s)", targets.len()).green()
+        );
+        Ok(targets)
+    }
+
+    fn ask(input: &mut dyn BufRead) -> Result<bool> {
+        println!("{}", "\nRemove these files? (y/n)".cyan());
+        print!("> ");
+        io::stdout().flush()?;
+
+        let mut answer = String::new();
+        input.read_line(&mut answer)?;
+        let answer = answer.trim().to_lowercase();
+        Ok(answer == "y" || answer == "yes")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::registry::PROVIDERS_FILE_NAME;
+    use crate::config::{Config, Provider, SETTINGS_FILE_NAME};
+
+    #[test]
+    fn test_reset_removes_only_managed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        manager.save_current_config(&Config::default()).unwrap();
+        manager
+            .create_backup_with_metadata(&Config::default(), &Provider::Anthropic)
+            .unwrap();
+        manager.save_token("sk-reset").unwrap();
+        fs::write(dir.path().join(".z_ai_token"), "sk-legacy").unwrap();
+        fs::write(manager.history_file(), "{}\n").unwrap();
+        fs::write(dir.path().join("settings.tmp"), "{").unwrap();
+        let rotated = manager.list_backups().unwrap();
+        assert_eq!(rotated.len(), 1);
+
+        // Claude Code's own files and our preferences stay
+        let kept = [
+            dir.path().join("CLAUDE.md"),
+            dir.path().join("projects").join("session.jsonl"),
+            dir.path().join(SETTINGS_FILE_NAME),
+            dir.path().join(PROVIDERS_FILE_NAME),
+            manager.backups_dir().join("hand-made.json"),
+        ];
+        for path in &kept {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "keep").unwrap();
+        }
+
+        let removed = ResetCommand::new(manager).run(true).unwrap();
+        assert_eq!(removed.len(), 8);
+        for path in &removed {
+            assert!(!path.exists(), "{} survived", path.display());
+        }
+        assert!(removed.contains(&rotated[0].path));
+        for path in &kept {
+            assert!(path.exists(), "{} was removed", path.display());
+        }
+    }
+
+    #[test]
+    fn test_reset_with_nothing_to_remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        assert!(ResetCommand::new(manager).run(true).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_ask_accepts_yes_only() {
+        assert!(ResetCommand::ask(&mut "yes\n".as_bytes()).unwrap());
+        assert!(ResetCommand::ask(&mut "Y\n".as_bytes()).unwrap());
+        assert!(!ResetCommand::ask(&mut "\n".as_bytes()).unwrap());
+        assert!(!ResetCommand::ask(&mut "no\n".as_bytes()).unwrap());
+    }
+}
@@ -0,0 +1,555 @@
+use crate::config::history::{self, HistoryEntry};
+use crate::config::manager::ConfigManager;
+use crate::config::{Config, Precedence, Provider};
+use crate::provider::backup::backup_to_rotation;
+use crate::provider::detector::ProviderDetector;
+use crate::provider::picker::Picker;
+use crate::provider::report::BackupHealth;
+use crate::provider::{bundle, print_env};
+use crate::utils::error::AppError;
+use crate::utils::jwt::format_duration;
+use crate::utils::Explainer;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use colored::*;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+/// `restore <backup>`: make a rotated backup the live config again
+pub struct RestoreCommand {
+    config_manager: ConfigManager,
+}
+
+impl RestoreCommand {
+    pub fn new(config_manager: ConfigManager) -> Self {
+        Self { config_manager }
+    }
+
+    /// `reference` is a backup id (its timestamp) or a label
+    pub fn run(&self, reference: &str) -> Result<()> {
+        let (entry, backup) = self.config_manager.find_backup(reference)?;
+        self.config_manager.ensure_writable()?;
+
+        let current = self
+            .config_manager
+            .load_current_config()
+            .context("Failed to load current config")?;
+        backup_to_rotation(
+            &self.config_manager,
+            &current,
+            &ProviderDetector::detect_provider(&current),
+            &Explainer::default(),
+        )?;
+
+        // Settings recorded in the backup win; other live settings are kept
+        let restored = current.with_env(backup.env.clone()).merge(
+            &Config {
+                extra: backup.settings.clone(),
+                ..Default::default()
+            },
+            Precedence::OtherWins,
+        );
+        self.config_manager
+            .save_current_config(&restored)
+            .context("Failed to restore config")?;
+
+        let provider = ProviderDetector::detect_provider(&restored);
+        history::record(
+            self.config_manager.history_file(),
+            &HistoryEntry::now(&provider, None),
+        );
+
+        let name = match &backup.metadata.label {
+            Some(label) => format!("{} ({})", entry.id(), label),
+            None => entry.id(),
+        };
+        println!(
+            "{}{} ({})",
+            "✅ Restored backup ".green(),
+            name,
+            provider.as_str()
+        );
+        Ok(())
+    }
+
+    /// `restore --select`: choose the backup from a menu, then restore it as
+    /// `run` would. Without a `picker` (no terminal) this is an error.
+    pub fn run_select(&self, picker: Option<&dyn Picker>) -> Result<()> {
+        let Some(picker) = picker else {
+            return Err(AppError::Config(
+                "restore --select needs a terminal; pass a backup id (timestamp) or label instead"
+                    .to_string(),
+            )
+            .into());
+        };
+        let now = Utc::now();
+        let rows = BackupHealth::collect_all(&self.config_manager, now)?;
+        match Self::select(&rows, picker, now)? {
+            Some(id) => self.run(&id),
+            None => {
+                println!("{}", "Cancelled".yellow());
+                Ok(())
+            }
+        }
+    }
+
+    /// The id of the backup picked from `rows`, newest first; `None` if cancelled
+    fn select(
+        rows: &[BackupHealth],
+        picker: &dyn Picker,
+        now: DateTime<Utc>,
+    ) -> Result<Option<String>> {
+        if rows.is_empty() {
+            return Err(AppError::Config("No rotated backups to restore".to_string()).into());
+        }
+        let items: Vec<String> = rows
+            .iter()
+            .enumerate()
+            .map(|(index, row)| {
+                let age = match row.created_at {
+                    Some(created_at) => format!("{} ago", format_duration(now - created_at)),
+                    None => "age unknown".to_string(),
+                };
+                let mut item = format!(
+                    "{:>2}. {}  {}  {}",
+                    index + 1,
+                    row.id,
+                    row.provider.as_deref().unwrap_or("unreadable"),
+                    age
+                );
+                if let Some(label) = &row.label {
+                    item.push_str(&format!("  ({})", label));
+                }
+                item
+            })
+            .collect();
+        Ok(picker
+            .pick("Restore backup", &items, 0)?
+            .map(|index| rows[index].id.clone()))
+    }
+
+    /// `restore <backup> --to <path>`: write the backup to `path` alone, leaving
+    /// the live config, the rotation and the history untouched. GLM keys are
+    /// stripped from Anthropic backups, as when switching back to Anthropic.
+    pub fn run_to(&self, reference: &str, path: &Path) -> Result<()> {
+        if Self::same_file(path, self.config_manager.settings_file()) {
+            return Err(AppError::Config(format!(
+                "{} is the live config; run restore without --to instead",
+                path.display()
+            ))
+            .into());
+        }
+        let (entry, backup) = self.config_manager.find_backup(reference)?;
+
+        let mut env = backup.env.clone();
+        if backup.metadata.provider == Provider::Anthropic.as_str() {
+            env.retain(|key, _| !ProviderDetector::is_glm_key(key));
+        }
+        let restored = Config {
+            env,
+            extra: backup.settings.clone(),
+        };
+        self.config_manager
+            .save_config_atomic(path, &restored)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+
+        println!(
+            "{}{} → {}",
+            "✅ Restored backup ".green(),
+            entry.id(),
+            path.display()
+        );
+        Ok(())
+    }
+
+    /// `restore --from <archive.tar.gz>`: make a `backup --format tar` bundle live
+    pub fn run_from(&self, archive: &Path) -> Result<()> {
+        let metadata = bundle::restore_bundle(&self.config_manager, archive)?;
+        println!(
+            "{}{} ({}, from {})",
+            "✅ Restored bundle ".green(),
+            archive.display(),
+            metadata.provider,
+            metadata.created_at.format("%Y-%m-%d %H:%M:%S UTC")
+        );
+        if metadata.has_token() {
+            println!("{}", "🔑 Saved token restored".cyan());
+        }
+        Ok(())
+    }
+
+    /// Whether `a` and `b` name the same file, following symlinks where they exist
+    fn same_file(a: &Path, b: &Path) -> bool {
+        match (a.canonicalize(), b.canonicalize()) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => a == b,
+        }
+    }
+}
+
+/// `rename-backup <id> <label>`
+pub struct RenameBackupCommand {
+    config_manager: ConfigManager,
+}
+
+impl RenameBackupCommand {
+    pub fn new(config_manager: ConfigManager) -> Self {
+        Self { config_manager }
+    }
+
+    pub fn run(&self, id: &str, label: &str) -> Result<()> {
+        self.config_manager.ensure_writable()?;
+        let path = self.config_manager.label_backup(id, label)?;
+        println!(
+            "{}{} → {}",
+            "🏷️  Labelled backup ".green(),
+            path.display(),
+            label.trim()
+        );
+        println!(
+            "{}",
+            format!("💡 Restore it with: claude-switch restore {}", label.trim()).cyan()
+        );
+        Ok(())
+    }
+}
+
+/// `delete-backup <backup>`: remove one rotated backup
+pub struct DeleteBackupCommand {
+    config_manager: ConfigManager,
+}
+
+impl DeleteBackupCommand {
+    pub fn new(config_manager: ConfigManager) -> Self {
+        Self { config_manager }
+    }
+
+    /// `reference` is an id, label or index. Without `confirm`, asks on the
+    /// terminal; the only backup left is kept unless `force`. Returns the
+    /// files removed.
+    pub fn run(&self, reference: &str, confirm: bool, force: bool) -> Result<Vec<PathBuf>> {
+        let entry = self.config_manager.find_backup_entry(reference)?;
+        if !force && self.config_manager.list_backups()?.len() == 1 {
+            return Err(AppError::Config(format!(
+                "{} is the only backup left; pass --force to delete it anyway",
+                entry.id()
+            ))
+            .into());
+        }
+
+        if !confirm {
+            let stdin = io::stdin();
+            if !stdin.is_terminal() {
+                return Err(AppError::Config(
+                    "delete-backup needs --confirm when stdin is not a terminal".to_string(),
+                )
+                .into());
+            }
+            if !Self::ask(&entry.id(), &mut stdin.lock())? {
+                println!("{}", "❌ Delete cancelled".yellow());
+                return Ok(Vec::new());
+            }
+        }
+
+        self.config_manager.ensure_writable()?;
+        let removed = self.config_manager.delete_backup(&entry)?;
+        for path in &removed {
+            println!("{}{}", "🗑️  Removed ".green(), path.display());
+        }
+        Ok(removed)
+    }
+
+    fn ask(id: &str, input: &mut dyn BufRead) -> Result<bool> {
+        println!("{}", format!("\nDelete backup {}? (y/n)", id).cyan());
+        print!("> ");
+        io::stdout().flush()?;
+
+        let mut answer = String::new();
+        input.read_line(&mut answer)?;
+        let answer = answer.trim().to_lowercase();
+        Ok(answer == "y" || answer == "yes")
+    }
+}
+
+/// `print-backup [backup]`: a rotated backup's JSON, as `restore` would read it
+pub struct PrintBackupCommand {
+    config_manager: ConfigManager,
+}
+
+impl PrintBackupCommand {
+    pub fn new(config_manager: ConfigManager) -> Self {
+        Self { config_manager }
+    }
+
+    pub fn run(&self, reference: Option<&str>, include_secrets: bool) -> Result<()> {
+        println!("{}", self.json(reference, include_secrets)?);
+        Ok(())
+    }
+
+    /// `reference` as for `restore`; the newest backup when `None`
+    fn json(&self, reference: Option<&str>, include_secrets: bool) -> Result<String> {
+        let backup = match reference {
+            Some(reference) => self.config_manager.find_backup(reference)?.1,
+            None => {
+                let newest = self.config_manager.list_backups()?.into_iter().next();
+                let entry = newest
+                    .ok_or_else(|| AppError::Config("No rotated backups to print".to_string()))?;
+                self.config_manager.load_rotated_backup(&entry.path)?
+            }
+        };
+        print_env::backup_json(&backup, include_secrets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::picker::tests::ScriptedPicker;
+
+    fn config(entries: &[(&str, &str)]) -> Config {
+        Config::from_env(
+            entries
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string())),
+        )
+    }
+
+    #[test]
+    fn test_restore_by_label() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        let mut anthropic = config(&[("ANTHROPIC_AUTH_TOKEN", "anthropic-web-token")]);
+        anthropic
+            .extra
+            .insert("model".to_string(), serde_json::json!("opus"));
+        manager
+            .backup_to_rotation(&anthropic, &Provider::Anthropic)
+            .unwrap();
+        let id = manager.list_backups().unwrap()[0].id();
+        RenameBackupCommand::new(ConfigManager::with_config_dir(dir.path().to_path_buf()))
+            .run(&id, "good-anthropic")
+            .unwrap();
+
+        let mut live = config(&[
+            ("ANTHROPIC_AUTH_TOKEN", "sk-glm"),
+            ("ANTHROPIC_BASE_URL", "https://api.z.ai/api/anthropic"),
+        ]);
+        live.extra
+            .insert("theme".to_string(), serde_json::json!("dark"));
+        manager.save_current_config(&live).unwrap();
+
+        RestoreCommand::new(manager).run("good-anthropic").unwrap();
+
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        let restored = manager.load_current_config().unwrap();
+        assert_eq!(restored.env["ANTHROPIC_AUTH_TOKEN"], "anthropic-web-token");
+        assert!(!restored.env.contains_key("ANTHROPIC_BASE_URL"));
+        assert_eq!(restored.extra["model"], "opus");
+        assert_eq!(restored.extra["theme"], "dark");
+
+        // The GLM config that was live is kept in the rotation
+        let backups = manager.list_backups().unwrap();
+        assert_eq!(backups.len(), 2);
+        let previous = manager.load_rotated_backup(&backups[0].path).unwrap();
+        assert_eq!(previous.metadata.provider, "glm");
+    }
+
+    #[test]
+    fn test_restore_to_scratch_file_leaves_live_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        let mut anthropic = config(&[
+            ("ANTHROPIC_AUTH_TOKEN", "anthropic-web-token"),
+            ("API_TIMEOUT_MS", "3000000"),
+        ]);
+        anthropic
+            .extra
+            .insert("model".to_string(), serde_json::json!("opus"));
+        manager
+            .backup_to_rotation(&anthropic, &Provider::Anthropic)
+            .unwrap();
+        let id = manager.list_backups().unwrap()[0].id();
+
+        let live = config(&[
+            ("ANTHROPIC_AUTH_TOKEN", "sk-glm"),
+            ("ANTHROPIC_BASE_URL", "https://api.z.ai/api/anthropic"),
+        ]);
+        manager.save_current_config(&live).unwrap();
+        let live_before = std::fs::read_to_string(manager.settings_file()).unwrap();
+
+        let scratch = dir.path().join("scratch/settings.json");
+        RestoreCommand::new(ConfigManager::with_config_dir(dir.path().to_path_buf()))
+            .run_to(&id, &scratch)
+            .unwrap();
+
+        let restored = manager.load_config(&scratch).unwrap();
+        assert_eq!(restored.env["ANTHROPIC_AUTH_TOKEN"], "anthropic-web-token");
+        assert!(!restored.env.contains_key("API_TIMEOUT_MS"));
+        assert_eq!(restored.extra["model"], "opus");
+        assert_eq!(
+            std::fs::read_to_string(manager.settings_file()).unwrap(),
+            live_before
+        );
+        assert_eq!(manager.list_backups().unwrap().len(), 1);
+
+        let live_path = manager.settings_file().to_path_buf();
+        assert!(RestoreCommand::new(manager)
+            .run_to(&id, &live_path)
+            .is_err());
+    }
+
+    #[test]
+    fn test_select_maps_the_picked_row_to_its_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        let anthropic = config(&[("ANTHROPIC_AUTH_TOKEN", "anthropic-web-token")]);
+        manager
+            .backup_to_rotation(&anthropic, &Provider::Anthropic)
+            .unwrap();
+        let glm = config(&[
+            ("ANTHROPIC_AUTH_TOKEN", "sk-glm"),
+            ("ANTHROPIC_BASE_URL", "https://api.z.ai/api/anthropic"),
+        ]);
+        manager.backup_to_rotation(&glm, &Provider::GLM).unwrap();
+        let ids: Vec<String> = manager
+            .list_backups()
+            .unwrap()
+            .iter()
+            .map(|entry| entry.id())
+            .collect();
+        RenameBackupCommand::new(manager.clone())
+            .run(&ids[1], "web-login")
+            .unwrap();
+
+        let rows = BackupHealth::collect_all(&manager, Utc::now()).unwrap();
+        let picker = ScriptedPicker::new(Some(1));
+        let picked = RestoreCommand::select(&rows, &picker, Utc::now()).unwrap();
+        assert_eq!(picked.as_deref(), Some(ids[1].as_str()));
+
+        let items = picker.seen.borrow();
+        assert!(items[0].starts_with(&format!(" 1. {}  glm  ", ids[0])));
+        assert!(items[1].starts_with(&format!(" 2. {}  anthropic  ", ids[1])));
+        assert!(items[1].ends_with("ago  (web-login)"));
+
+        let cancel = ScriptedPicker::new(None);
+        assert_eq!(
+            RestoreCommand::select(&rows, &cancel, Utc::now()).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_select_without_a_terminal_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        let anthropic = config(&[("ANTHROPIC_AUTH_TOKEN", "anthropic-web-token")]);
+        manager
+            .backup_to_rotation(&anthropic, &Provider::Anthropic)
+            .unwrap();
+
+        let error = RestoreCommand::new(manager).run_select(None).unwrap_err();
+        assert!(error.to_string().contains("pass a backup id"));
+        assert!(!dir.path().join("settings.json").exists());
+    }
+
+    #[test]
+    fn test_delete_backup_by_id_label_and_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        for token in ["first", "second", "third"] {
+            manager
+                .backup_to_rotation(
+                    &config(&[("ANTHROPIC_AUTH_TOKEN", token)]),
+                    &Provider::Anthropic,
+                )
+                .unwrap();
+        }
+        let entries = manager.list_backups().unwrap();
+        RenameBackupCommand::new(manager.clone())
+            .run(&entries[2].id(), "oldest")
+            .unwrap();
+        std::fs::write(entries[0].path.with_extension("meta"), "{}").unwrap();
+        let command = DeleteBackupCommand::new(manager.clone());
+
+        let removed = command.run(&entries[0].id(), true, false).unwrap();
+        assert_eq!(
+            removed,
+            vec![
+                entries[0].path.clone(),
+                entries[0].path.with_extension("meta")
+            ]
+        );
+        assert_eq!(
+            command.run("oldest", true, false).unwrap(),
+            vec![entries[2].path.clone()]
+        );
+
+        let remaining = manager.list_backups().unwrap();
+        assert_eq!(remaining.len(), 1);
+        let kept = manager.load_rotated_backup(&remaining[0].path).unwrap();
+        assert_eq!(kept.env["ANTHROPIC_AUTH_TOKEN"], "second");
+
+        // Index 1 is the newest backup, now the only one left
+        let error = command.run("1", true, false).unwrap_err();
+        assert!(error.to_string().contains("only backup left"), "{}", error);
+        assert!(remaining[0].path.exists());
+        assert_eq!(command.run("1", true, true).unwrap().len(), 1);
+        assert!(manager.list_backups().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_delete_unknown_backup_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        let anthropic = config(&[("ANTHROPIC_AUTH_TOKEN", "anthropic-web-token")]);
+        manager
+            .backup_to_rotation(&anthropic, &Provider::Anthropic)
+            .unwrap();
+        manager
+            .backup_to_rotation(&anthropic, &Provider::Anthropic)
+            .unwrap();
+
+        let command = DeleteBackupCommand::new(manager.clone());
+        for reference in ["nope", "0", "3"] {
+            let error = command.run(reference, true, true).unwrap_err();
+            assert!(error.to_string().contains("No backup with id"), "{}", error);
+        }
+        assert_eq!(manager.list_backups().unwrap().len(), 2);
+        assert!(DeleteBackupCommand::ask("x", &mut "y\n".as_bytes()).unwrap());
+        assert!(!DeleteBackupCommand::ask("x", &mut "\n".as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn test_restore_unknown_reference_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        assert!(RestoreCommand::new(manager).run("nope").is_err());
+        assert!(!dir.path().join("settings.json").exists());
+    }
+
+    #[test]
+    fn test_print_backup_masks_the_token() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        let glm = config(&[
+            ("ANTHROPIC_AUTH_TOKEN", "sk-1234567890abcdef"),
+            ("ANTHROPIC_BASE_URL", "https://api.z.ai/api/anthropic"),
+        ]);
+        manager.backup_to_rotation(&glm, &Provider::GLM).unwrap();
+        let id = manager.list_backups().unwrap()[0].id();
+        let command = PrintBackupCommand::new(manager);
+
+        let newest: serde_json::Value =
+            serde_json::from_str(&command.json(None, false).unwrap()).unwrap();
+        assert_eq!(
+            newest["env"]["ANTHROPIC_BASE_URL"],
+            "https://api.z.ai/api/anthropic"
+        );
+        assert_eq!(newest["env"]["ANTHROPIC_AUTH_TOKEN"], "sk-1...cdef");
+        assert_eq!(newest["_metadata"]["provider"], "glm");
+
+        let revealed = command.json(Some(&id), true).unwrap();
+        assert!(revealed.contains("sk-1234567890abcdef"));
+        assert!(command.json(Some("missing"), false).is_err());
+    }
+}
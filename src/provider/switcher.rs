@@ -1,25 +1,84 @@
-use crate::config::manager::ConfigManager;
-use crate::config::{Config, Provider};
-use crate::provider::detector::ProviderDetector;
+use crate::config::manager::{ConfigManager, STALE_TOKEN_HINT};
+use crate::config::Provider;
+use crate::provider::compare::ComparisonReport;
+use crate::provider::report::{
+    BackupAudit, BackupHealth, BackupReport, ProviderEnvReport, StatusField, StatusReport,
+};
+use crate::utils::jwt;
+use crate::utils::output::Renderer;
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use colored::*;
+use std::io::{self, BufRead, IsTerminal, Write};
 
 pub struct StatusDisplay {
     config_manager: ConfigManager,
+    renderer: Renderer,
+    reveal_token: bool,
 }
 
 impl StatusDisplay {
-    pub fn new(config_manager: ConfigManager) -> Self {
-        Self { config_manager }
+    pub fn new(config_manager: ConfigManager, renderer: Renderer) -> Self {
+        Self {
+            config_manager,
+            renderer,
+            reveal_token: false,
+        }
+    }
+
+    /// `--show-token`: print the auth token unmasked. Needs a terminal and a
+    /// yes at the prompt; `force` lifts only the terminal check, so a script
+    /// still has to answer `y` on stdin.
+    pub fn with_token_reveal(mut self, show_token: bool, force: bool) -> Result<Self> {
+        if show_token {
+            eprintln!(
+                "{}",
+                "⚠️  --show-token prints the full auth token; it may stay in terminal scrollback or logs"
+                    .yellow()
+            );
+            let is_tty = io::stdin().is_terminal() && io::stdout().is_terminal();
+            self.reveal_token = token_reveal_allowed(is_tty, force, || {
+                Self::confirm_reveal(&mut io::stdin().lock())
+            })?;
+            if !self.reveal_token {
+                eprintln!("{}", "   Token stays masked".yellow());
+            }
+        }
+        Ok(self)
+    }
+
+    fn confirm_reveal(input: &mut dyn BufRead) -> Result<bool> {
+        eprint!("{}", "Show the unmasked token? (y/n) > ".cyan());
+        io::stderr().flush()?;
+
+        let mut answer = String::new();
+        input.read_line(&mut answer)?;
+        let answer = answer.trim().to_lowercase();
+        Ok(answer == "y" || answer == "yes")
     }
 
     pub fn show_status(&self) -> Result<()> {
+        let mut report = StatusReport::collect(&self.config_manager)?;
+        if self.reveal_token {
+            report.auth_token = self
+                .config_manager
+                .load_current_config()?
+                .env
+                .remove(&report.auth_env);
+        }
+
+        if self.renderer.is_json() {
+            return self.renderer.json(&report);
+        }
+
         println!("{}", "📊 Current Configuration Status".cyan());
+        if let Some(profile) = &report.profile {
+            println!("{}{}", "📁 Profile: ".cyan(), profile);
+        }
         println!();
+        self.show_local_override(&report);
 
-        let config = self.config_manager.load_current_config()?;
-
-        if config.env.is_empty() {
+        if !report.configured {
             println!(
                 "{}",
                 "⚠️  No configuration found (empty or missing)".yellow()
@@ -27,143 +86,464 @@ impl StatusDisplay {
             return Ok(());
         }
 
-        let base_url = config
-            .env
-            .get("ANTHROPIC_BASE_URL")
-            .cloned()
-            .unwrap_or_default();
-        let provider = ProviderDetector::detect_provider(&config);
-
-        match provider {
-            Provider::GLM => self.show_glm_status(&config, &base_url),
-            Provider::Anthropic => self.show_anthropic_status(&config),
-            Provider::Custom => self.show_custom_status(&config, &base_url),
+        match report.provider() {
+            Provider::GLM => self.show_glm_status(&report),
+            Provider::Anthropic => self.show_anthropic_status(&report),
+            Provider::Custom => self.show_custom_status(&report),
+            Provider::Local => self.show_local_status(&report),
             Provider::Unknown => self.show_unknown_status(),
         }
+        self.show_detection(&report);
 
         println!();
 
         // Show other environment variables
-        self.show_other_env_vars(&config);
+        self.show_other_env_vars(&report);
 
         // Show backup status
-        self.show_backup_status()?;
+        self.show_backup_status(&report.backup);
 
         // Show saved token status
-        self.show_saved_token_status()?;
+        self.show_saved_token_status(&report);
+
+        // Show settings this tool doesn't manage
+        self.show_settings_schema(&report);
+
+        Ok(())
+    }
+
+    /// `--all-backups`: one line per rotated backup with its token's health
+    pub fn show_all_backups(&self) -> Result<()> {
+        let now = Utc::now();
+        let audit = BackupAudit {
+            backups: BackupHealth::collect_all(&self.config_manager, now)?,
+        };
+        if self.renderer.is_json() {
+            return self.renderer.json(&audit);
+        }
+        let rows = audit.backups;
+
+        if rows.is_empty() {
+            println!("{}", "💾 No rotated backups".yellow());
+            return Ok(());
+        }
+        println!("{}", format!("💾 Backups ({})", rows.len()).cyan());
+        for row in &rows {
+            let name = match &row.label {
+                Some(label) => format!("{} [{}]", row.id, label),
+                None => row.id.clone(),
+            };
+            if let Some(error) = &row.error {
+                println!(
+                    "  {}  {}",
+                    name,
+                    format!("⚠️  unreadable: {}", error).yellow()
+                );
+                continue;
+            }
+
+            let age = row
+                .created_at
+                .map(|created| format!("{} old", jwt::format_duration(now - created)))
+                .unwrap_or_else(|| "age unknown".to_string());
+            let token = match (&row.auth_token, &row.token_type) {
+                (Some(token), Some(token_type)) => format!("{} ({})", token, token_type),
+                _ => "no token".to_string(),
+            };
+            let line = format!(
+                "  {}  {}  {}  {}",
+                name,
+                row.provider.as_deref().unwrap_or("unknown"),
+                age,
+                token
+            );
+            match row.token_expires_at {
+                Some(expires_at) if row.token_expired => println!(
+                    "{}",
+                    format!("{}  ⚠️  {}", line, jwt::describe_expiry(expires_at, now)).yellow()
+                ),
+                Some(expires_at) => {
+                    println!("{}  {}", line, jwt::describe_expiry(expires_at, now))
+                }
+                None => println!("{}", line),
+            }
+            if let Some(note) = &row.note {
+                println!("     📝 {}", note);
+            }
+        }
+        Ok(())
+    }
+
+    /// `status --provider-env`: each key a provider switch writes, set or not
+    pub fn show_provider_env(&self) -> Result<()> {
+        let report = ProviderEnvReport::collect(&self.config_manager)?;
+        if self.renderer.is_json() {
+            return self.renderer.json(&report);
+        }
+
+        println!("{}", "🔑 Provider env vars".cyan());
+        for key in &report.keys {
+            match &key.value {
+                Some(value) => println!("  ✅ {}  {}", key.key, value),
+                None => println!("  ➖ {}  {}", key.key, "(unset)".yellow()),
+            }
+        }
+        Ok(())
+    }
+
+    /// `status --oneline`: one uncolored line for shell prompts
+    pub fn show_oneline(&self) -> Result<()> {
+        let report = StatusReport::collect(&self.config_manager)?;
+        if self.renderer.is_json() {
+            return self.renderer.json(&report);
+        }
+        println!("{}", report.oneline());
+        Ok(())
+    }
 
+    /// Just one value, unformatted (an empty line when unset)
+    pub fn show_field(&self, field: StatusField) -> Result<()> {
+        let report = StatusReport::collect(&self.config_manager)?;
+        println!("{}", report.field(field).unwrap_or_default());
         Ok(())
     }
 
-    fn show_glm_status(&self, config: &Config, base_url: &str) {
-        println!("{}", "┌─────────────────────────────────────┐".green());
-        println!("{}", "│  🔗 Provider: GLM (Z.AI Models)      │".green());
-        println!("{}", "└─────────────────────────────────────┘".green());
+    /// Live config and Anthropic backup side by side
+    pub fn show_comparison(&self) -> Result<()> {
+        let report = ComparisonReport::collect(&self.config_manager)?;
+        if self.renderer.is_json() {
+            return self.renderer.json(&report);
+        }
+        report.print();
+        Ok(())
+    }
+
+    /// `status --diff-backup`: fails (exit 1) when the live `env` differs
+    /// from the Anthropic backup. Without a backup there is nothing to differ.
+    pub fn show_backup_diff(&self) -> Result<()> {
+        let report = ComparisonReport::collect(&self.config_manager)?;
+        let differing = report.differing_rows();
+        if self.renderer.is_json() {
+            self.renderer.json(&report)?;
+        } else if report.backup_provider.is_none() {
+            println!("{}", "💾 No Anthropic backup to compare against".cyan());
+        } else if differing.is_empty() {
+            println!("{}", "✅ Live config matches the backup".green());
+        } else {
+            println!("{}", "⚠️  Live config differs from the backup:".yellow());
+            for row in &differing {
+                println!(
+                    "  {}: {} → {}",
+                    row.key,
+                    row.backup.as_deref().unwrap_or("(unset)"),
+                    row.live.as_deref().unwrap_or("(unset)")
+                );
+            }
+            println!(
+                "{}",
+                "💡 Update the backup with: claude-switch backup --force-backup".cyan()
+            );
+        }
+
+        if !differing.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Live config differs from the backup in {} key(s)",
+                differing.len()
+            ));
+        }
+        Ok(())
+    }
+
+    fn print_panel(&self, icon: &str, title: &str) {
+        for line in provider_panel(icon, title, self.renderer.is_ascii()) {
+            println!("{}", line.green());
+        }
+    }
+
+    fn show_glm_status(&self, report: &StatusReport) {
+        self.print_panel("🔗", "Provider: GLM (Z.AI Models)");
         println!();
-        println!("  {}{}", "Base URL: ".cyan(), base_url);
+        println!(
+            "  {}{}",
+            "Base URL: ".cyan(),
+            report.base_url.as_deref().unwrap_or_default()
+        );
 
-        if let Some(model) = config.env.get("ANTHROPIC_DEFAULT_SONNET_MODEL") {
+        if let Some(model) = &report.models.sonnet {
             println!("  {}{}", "Sonnet Model: ".cyan(), model);
         }
-        if let Some(model) = config.env.get("ANTHROPIC_DEFAULT_OPUS_MODEL") {
+        if let Some(model) = &report.models.opus {
             println!("  {}{}", "Opus Model: ".cyan(), model);
         }
-        if let Some(model) = config.env.get("ANTHROPIC_DEFAULT_HAIKU_MODEL") {
+        if let Some(model) = &report.models.haiku {
             println!("  {}{}", "Haiku Model: ".cyan(), model);
         }
-        if let Some(timeout) = config.env.get("API_TIMEOUT_MS") {
+        if let Some(timeout) = &report.timeout_ms {
             println!("  {}{} {}", "Timeout: ".cyan(), timeout, "ms".cyan());
         }
 
-        // Show masked token with type detection
-        if let Some(token) = config.env.get("ANTHROPIC_AUTH_TOKEN") {
-            let masked_token = ProviderDetector::mask_token(token);
-            let token_type = ProviderDetector::detect_token_type(token);
-            let token_type_str = match token_type {
-                crate::config::TokenType::GLM => " (API key)",
-                crate::config::TokenType::Anthropic => " (web token - unexpected for GLM)",
-                crate::config::TokenType::Unknown => "",
+        // Show token (masked unless --show-token) with type detection
+        if let Some(masked_token) = &report.auth_token {
+            let token_type_str = match report.token_type.as_deref() {
+                Some("glm") => " (API key)",
+                Some("anthropic") => " (web token - unexpected for GLM)",
+                _ => "",
             };
-            println!(
-                "  {}{}{}",
-                "Auth Token: ".cyan(),
-                masked_token,
-                token_type_str
-            );
+            let label = match report.auth_env.as_str() {
+                "ANTHROPIC_AUTH_TOKEN" => "Auth Token: ".to_string(),
+                auth_env => format!("Auth Token ({}): ", auth_env),
+            };
+            println!("  {}{}{}", label.cyan(), masked_token, token_type_str);
         }
     }
 
-    fn show_anthropic_status(&self, _config: &Config) {
-        println!("{}", "┌─────────────────────────────────────┐".green());
-        println!("{}", "│  🔗 Provider: Anthropic (Default)   │".green());
-        println!("{}", "└─────────────────────────────────────┘".green());
+    fn show_anthropic_status(&self, report: &StatusReport) {
+        self.print_panel("🔗", "Provider: Anthropic (Default)");
         println!();
         println!("{}", "  Base URL: api.anthropic.com (default)".cyan());
+        if let Some(expires_at) = report.token_expires_at {
+            println!("  {}{}", "Token: ".cyan(), Self::expiry_label(expires_at));
+        }
     }
 
-    fn show_custom_status(&self, _config: &Config, base_url: &str) {
-        println!("{}", "┌─────────────────────────────────────┐".green());
-        println!("{}", "│  🔗 Provider: Custom                │".green());
-        println!("{}", "└─────────────────────────────────────┘".green());
+    fn show_custom_status(&self, report: &StatusReport) {
+        self.print_panel("🔗", "Provider: Custom");
         println!();
-        println!("  {}{}", "Base URL: ".cyan(), base_url);
+        println!(
+            "  {}{}",
+            "Base URL: ".cyan(),
+            report.base_url.as_deref().unwrap_or_default()
+        );
     }
 
-    fn show_unknown_status(&self) {
-        println!("{}", "⚠️  Unknown provider configuration".yellow());
+    fn show_local_status(&self, report: &StatusReport) {
+        self.print_panel("🏠", "Provider: Local");
+        println!();
+        println!(
+            "  {}{}",
+            "Base URL: ".cyan(),
+            report.base_url.as_deref().unwrap_or_default()
+        );
     }
 
-    fn show_other_env_vars(&self, config: &Config) {
-        let other_env_count = config
-            .env
-            .keys()
-            .filter(|key| !ProviderDetector::is_glm_key(key) && *key != "ANTHROPIC_BASE_URL")
-            .count();
+    fn show_local_override(&self, report: &StatusReport) {
+        let Some(local) = &report.local_override else {
+            return;
+        };
+        println!(
+            "{}",
+            format!(
+                "⚠️  {} exists and may supersede the managed settings",
+                local.path
+            )
+            .yellow()
+        );
+        if let Some(error) = &local.parse_error {
+            println!(
+                "{}",
+                format!("   It could not be parsed: {}", error).yellow()
+            );
+        } else if !local.keys.is_empty() {
+            println!(
+                "{}",
+                format!("   Overridden keys: {}", local.keys.join(", ")).yellow()
+            );
+        }
+        println!();
+    }
 
-        if other_env_count > 0 {
-            println!("  {}{}", "Other env vars: ".cyan(), other_env_count);
+    fn show_detection(&self, report: &StatusReport) {
+        println!(
+            "  {}{}",
+            "Detected by: ".cyan(),
+            report.detected_by.describe()
+        );
+        if let Some(tiebreak) = &report.detection_tiebreak {
+            println!("  {}{}", "Tiebreak: ".cyan(), tiebreak);
+        }
+        if let Some(helper) = &report.api_key_helper {
+            println!("  {}{}", "API key helper: ".cyan(), helper);
         }
+        let other_auth: Vec<&str> = report
+            .auth_settings
+            .iter()
+            .map(String::as_str)
+            .filter(|key| *key != "apiKeyHelper")
+            .collect();
+        if !other_auth.is_empty() {
+            println!("  {}{}", "Auth settings: ".cyan(), other_auth.join(", "));
+        }
+        for warning in &report.detection_warnings {
+            println!("{}", format!("  ⚠️  {}", warning).yellow());
+        }
+    }
+
+    fn show_unknown_status(&self) {
+        println!("{}", "⚠️  Unknown provider configuration".yellow());
     }
 
-    fn show_backup_status(&self) -> Result<()> {
-        let (has_backup, backup) = self.config_manager.has_valid_anthropic_backup()?;
+    fn show_other_env_vars(&self, report: &StatusReport) {
+        if report.other_env_vars > 0 {
+            println!("  {}{}", "Other env vars: ".cyan(), report.other_env_vars);
+        }
+    }
 
-        if has_backup && backup.is_some() {
-            let backup = backup.unwrap();
-            println!("  {}", "💾 Backup: Available (Anthropic)".cyan());
-            if let Some(created_at) = backup.metadata.created_at {
-                println!(
-                    "     {}{}",
-                    "Created: ".cyan(),
-                    created_at.format("%Y-%m-%d %H:%M:%S UTC")
-                );
-            }
-            // Show token type in backup
-            if let Some(token) = backup.env.get("ANTHROPIC_AUTH_TOKEN") {
-                let token_type = ProviderDetector::detect_token_type(token);
-                match token_type {
-                    crate::config::TokenType::Anthropic => {
+    fn show_backup_status(&self, backup: &BackupReport) {
+        match backup.status.as_str() {
+            "available" => {
+                if backup.format == "legacy" {
+                    println!(
+                        "  {}",
+                        "💾 Backup: Available (Anthropic, legacy format without metadata)".cyan()
+                    );
+                } else {
+                    println!("  {}", "💾 Backup: Available (Anthropic)".cyan());
+                }
+                if let Some(created_at) = backup.created_at {
+                    println!(
+                        "     {}{}",
+                        "Created: ".cyan(),
+                        created_at.format("%Y-%m-%d %H:%M:%S UTC")
+                    );
+                }
+                // Show token type in backup
+                match backup.token_type.as_deref() {
+                    Some("anthropic") => {
                         println!("     {}", "Token: Web login token".cyan());
+                        if let Some(expires_at) = backup.token_expires_at {
+                            println!("     {}", Self::expiry_label(expires_at));
+                        }
                     }
-                    crate::config::TokenType::GLM => {
+                    Some("glm") => {
                         println!("     {}", "Token: API key (unexpected)".yellow());
                     }
                     _ => {}
                 }
             }
-        } else if self.config_manager.backup_file().exists() {
-            println!("  {}", "💾 Backup: Available (unknown format)".yellow());
+            "unknown_format" => {
+                let label = match (backup.format.as_str(), backup.provider.as_deref()) {
+                    ("corrupt", _) => "Corrupt (not valid JSON)".to_string(),
+                    ("unrecognized_schema", _) => {
+                        "Unrecognized schema (valid JSON, but not a config)".to_string()
+                    }
+                    (_, Some(provider)) => format!("Not restorable ({} backup)", provider),
+                    _ => "Available (unknown format)".to_string(),
+                };
+                println!("  {}", format!("💾 Backup: {}", label).yellow());
+            }
+            _ => {
+                println!("  {}", "💾 Backup: Not found".yellow());
+            }
+        }
+    }
+
+    fn expiry_label(expires_at: DateTime<Utc>) -> ColoredString {
+        let description = jwt::describe_expiry(expires_at, Utc::now());
+        if expires_at <= Utc::now() {
+            format!("⚠️  Web token {} (re-login required)", description).yellow()
         } else {
-            println!("  {}", "💾 Backup: Not found".yellow());
+            format!("Web token {}", description).cyan()
         }
+    }
 
-        Ok(())
+    fn show_settings_schema(&self, report: &StatusReport) {
+        if let Some(schema) = &report.schema {
+            println!("  {}{}", "📄 Schema: ".cyan(), schema);
+        }
+        if !report.unmanaged_keys.is_empty() {
+            println!(
+                "  {}{}",
+                "⚠️  Unmanaged settings (preserved untouched): ".yellow(),
+                report.unmanaged_keys.join(", ")
+            );
+        }
     }
 
-    fn show_saved_token_status(&self) -> Result<()> {
-        if let Ok(Some(_)) = self.config_manager.load_saved_token() {
+    fn show_saved_token_status(&self, report: &StatusReport) {
+        if report.saved_token {
             println!("  {}", "🔑 Saved Token: Available".cyan());
         }
-        Ok(())
+        if let Some(warning) = &report.saved_token_warning {
+            println!("     {}", format!("⚠️  {}", warning).yellow());
+            println!("     {}", STALE_TOKEN_HINT.yellow());
+        }
+    }
+}
+
+/// Columns between the borders of the provider panel
+const PANEL_WIDTH: usize = 37;
+
+/// The three-line provider title box. `ascii` swaps the box-drawing
+/// characters for `+`, `-` and `|` and drops the icon.
+fn provider_panel(icon: &str, title: &str, ascii: bool) -> [String; 3] {
+    if ascii {
+        let border = format!("+{}+", "-".repeat(PANEL_WIDTH));
+        let title = format!("|  {:<width$}|", title, width = PANEL_WIDTH - 2);
+        return [border.clone(), title, border];
+    }
+    [
+        format!("┌{}┐", "─".repeat(PANEL_WIDTH)),
+        // The icon is two columns wide
+        format!("│  {} {:<width$}│", icon, title, width = PANEL_WIDTH - 5),
+        format!("└{}┘", "─".repeat(PANEL_WIDTH)),
+    ]
+}
+
+/// Whether `--show-token` may reveal the token: never without a terminal
+/// unless forced, and always only after `confirm`
+fn token_reveal_allowed(
+    is_tty: bool,
+    force: bool,
+    confirm: impl FnOnce() -> Result<bool>,
+) -> Result<bool> {
+    if !is_tty && !force {
+        return Ok(false);
+    }
+    confirm()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_reveal_gating() {
+        let never = || -> Result<bool> { panic!("must not prompt") };
+        assert!(!token_reveal_allowed(false, false, never).unwrap());
+        // --force allows a pipe but still needs the answer
+        assert!(token_reveal_allowed(false, true, || Ok(true)).unwrap());
+        assert!(!token_reveal_allowed(false, true, || Ok(false)).unwrap());
+        assert!(!token_reveal_allowed(true, true, || Ok(false)).unwrap());
+        assert!(token_reveal_allowed(true, false, || Ok(true)).unwrap());
+        assert!(!token_reveal_allowed(true, false, || Ok(false)).unwrap());
+    }
+
+    #[test]
+    fn test_ascii_panels_are_pure_ascii() {
+        for (icon, title) in [
+            ("🔗", "Provider: GLM (Z.AI Models)"),
+            ("🔗", "Provider: Anthropic (Default)"),
+            ("🔗", "Provider: Custom"),
+            ("🏠", "Provider: Local"),
+        ] {
+            let ascii = provider_panel(icon, title, true);
+            assert!(ascii.iter().all(|line| line.is_ascii()), "{:?}", ascii);
+            assert!(ascii.iter().all(|line| line.len() == PANEL_WIDTH + 2));
+            assert!(ascii[1].contains(title));
+
+            let unicode = provider_panel(icon, title, false);
+            assert_eq!(unicode[0], "┌─────────────────────────────────────┐");
+            assert!(unicode[1].starts_with("│  ") && unicode[1].ends_with('│'));
+        }
+        assert_eq!(
+            provider_panel("🔗", "Provider: Custom", false)[1],
+            "│  🔗 Provider: Custom                │"
+        );
+    }
+
+    #[test]
+    fn test_confirm_reveal_needs_yes() {
+        assert!(StatusDisplay::confirm_reveal(&mut "y\n".as_bytes()).unwrap());
+        assert!(!StatusDisplay::confirm_reveal(&mut "\n".as_bytes()).unwrap());
     }
 }
@@ -0,0 +1,157 @@
+use crate::config::{Provider, TokenType};
+use crate::provider::detector::ProviderDetector;
+use crate::utils::error::AppError;
+use crate::utils::mask::mask_token;
+use crate::utils::{Renderer, TokenManager};
+use anyhow::Result;
+use colored::*;
+use serde::Serialize;
+use std::io::{self, IsTerminal};
+
+/// Result of checking a token's shape against an expected provider
+#[derive(Debug, Serialize)]
+pub struct TokenCheck {
+    pub token: String,
+    pub token_type: String,
+    pub expected_provider: Option<String>,
+    /// `None` when no provider was given to compare against
+    pub matches: Option<bool>,
+}
+
+impl TokenCheck {
+    pub fn new(token: &str, expected: Option<&Provider>) -> Self {
+        let token_type = ProviderDetector::detect_token_type(token);
+        let matches = expected.map(|provider| Self::type_fits(&token_type, provider));
+        Self {
+            token: mask_token(token),
+            token_type: token_type.as_str().to_string(),
+            expected_provider: expected.map(|provider| provider.as_str().to_string()),
+            matches,
+        }
+    }
+
+    fn type_fits(token_type: &TokenType, provider: &Provider) -> bool {
+        match provider {
+            Provider::GLM => *token_type == TokenType::GLM,
+            Provider::Anthropic => *token_type == TokenType::Anthropic,
+            // Custom endpoints accept whatever credential they were set up with
+            Provider::Custom | Provider::Local | Provider::Unknown => true,
+        }
+    }
+}
+
+/// `verify-token`: report what kind of token this looks like, without touching any config
+pub struct VerifyTokenCommand {
+    renderer: Renderer,
+}
+
+impl VerifyTokenCommand {
+    pub fn new(renderer: Renderer) -> Self {
+        Self { renderer }
+    }
+
+    pub fn run(
+        &self,
+        token: Option<String>,
+        expected: Option<Provider>,
+        strict: bool,
+    ) -> Result<()> {
+        let token = match token {
+            Some(token) => token.trim().to_string(),
+            None => Self::token_from_env_or_stdin()?,
+        };
+        if token.is_empty() {
+            return Err(AppError::Token("Token is empty".to_string()).into());
+        }
+
+        let check = TokenCheck::new(&token, expected.as_ref());
+        if self.renderer.is_json() {
+            self.renderer.json(&check)?;
+        } else {
+            Self::print_check(&check);
+        }
+
+        if strict && check.matches == Some(false) {
+            return Err(AppError::Token(format!(
+                "Token looks like a {} token, not {}",
+                check.token_type,
+                check.expected_provider.as_deref().unwrap_or("unknown")
+            ))
+            .into());
+        }
+        Ok(())
+    }
+
+    fn token_from_env_or_stdin() -> Result<String> {
+        if let Ok(token) = std::env::var("Z_AI_AUTH_TOKEN") {
+            return Ok(token.trim().to_string());
+        }
+        let stdin = io::stdin();
+        if stdin.is_terminal() {
+            return Err(AppError::Token(
+                "No token given (use --token, Z_AI_AUTH_TOKEN or pipe it on stdin)".to_string(),
+            )
+            .into());
+        }
+        TokenManager::read_token_line(stdin.lock())
+    }
+
+    fn print_check(check: &TokenCheck) {
+        println!("{}{}", "🔑 Token: ".cyan(), check.token);
+        println!("{}{}", "🏷️  Detected type: ".cyan(), check.token_type);
+
+        match (&check.expected_provider, check.matches) {
+            (Some(provider), Some(true)) => {
+                println!("{}{}", "✅ Looks valid for ".green(), provider);
+            }
+            (Some(provider), Some(false)) => {
+                println!(
+                    "{}",
+                    format!("⚠️  Does not look like a {} token", provider).yellow()
+                );
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glm_key_matches_glm() {
+        let check = TokenCheck::new("sk-abcdef1234567890", Some(&Provider::GLM));
+        assert_eq!(check.token_type, "glm");
+        assert_eq!(check.matches, Some(true));
+        assert_eq!(check.token, "sk-a...7890");
+    }
+
+    #[test]
+    fn test_glm_key_does_not_match_anthropic() {
+        let check = TokenCheck::new("sk-abcdef1234567890", Some(&Provider::Anthropic));
+        assert_eq!(check.matches, Some(false));
+
+        let command = VerifyTokenCommand::new(Renderer::default());
+        let lenient = command.run(
+            Some("sk-abcdef1234567890".to_string()),
+            Some(Provider::Anthropic),
+            false,
+        );
+        assert!(lenient.is_ok());
+
+        let strict = command.run(
+            Some("sk-abcdef1234567890".to_string()),
+            Some(Provider::Anthropic),
+            true,
+        );
+        assert!(strict.is_err());
+    }
+
+    #[test]
+    fn test_no_provider_only_reports_type() {
+        let check = TokenCheck::new("sk-abcdef1234567890", None);
+        assert_eq!(check.matches, None);
+        assert_eq!(check.expected_provider, None);
+    }
+}
@@ -0,0 +1,316 @@
+use crate::config::manager::ConfigManager;
+use crate::provider::glm::GLMOptions;
+use crate::provider::profiles::ProfileSwitcher;
+use anyhow::{Context, Result};
+use chrono::Local;
+use colored::*;
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How often a waiting watcher checks whether Ctrl-C was pressed
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Quiet period a change must be followed by before it is acted on
+pub const DEFAULT_DEBOUNCE_MS: u64 = 500;
+
+/// Blocks until the watched settings file may have changed
+pub trait SettingsWatcher {
+    /// `Ok(false)` once watching should stop
+    fn wait_for_change(&mut self) -> Result<bool>;
+}
+
+/// Watches the settings file through the platform's change notifications.
+///
+/// The directory is watched rather than the file, since settings.json is
+/// replaced by a rename on every atomic save.
+pub struct NotifyWatcher {
+    path: PathBuf,
+    debounce: Duration,
+    events: Receiver<notify::Result<Event>>,
+    stop: Arc<AtomicBool>,
+    // Dropping it ends the notifications
+    _watcher: RecommendedWatcher,
+}
+
+impl NotifyWatcher {
+    pub fn new(path: PathBuf, debounce: Duration) -> Result<Self> {
+        let dir = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let (sender, events) = mpsc::channel();
+        let mut watcher =
+            notify::recommended_watcher(sender).context("Failed to set up the settings watcher")?;
+        watcher
+            .watch(&dir, RecursiveMode::NonRecursive)
+            .with_context(|| format!("Failed to watch {}", dir.display()))?;
+        Ok(Self {
+            path,
+            debounce,
+            events,
+            stop: Arc::new(AtomicBool::new(false)),
+            _watcher: watcher,
+        })
+    }
+
+    /// Stop cleanly on Ctrl-C
+    pub fn stop_on_ctrl_c(self) -> Result<Self> {
+        let stop = Arc::clone(&self.stop);
+        ctrlc::set_handler(move || stop.store(true, Ordering::SeqCst))
+            .context("Failed to set up the Ctrl-C handler")?;
+        Ok(self)
+    }
+
+    /// Whether `event` touches the settings file (not a neighbour in its directory)
+    fn concerns_settings(&self, event: &notify::Result<Event>) -> bool {
+        let name = self.path.file_name();
+        match event {
+            Ok(event) => event.paths.iter().any(|path| path.file_name() == name),
+            // Missed events: better to check the file once too often
+            Err(_) => true,
+        }
+    }
+
+    /// The next event, waiting at most `timeout` (forever when `None`)
+    fn next_event(&self, timeout: Option<Duration>) -> Wait {
+        let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+        loop {
+            if self.stop.load(Ordering::SeqCst) {
+                return Wait::Stopped;
+            }
+            let mut wait = STOP_CHECK_INTERVAL;
+            if let Some(deadline) = deadline {
+                let left = deadline.saturating_duration_since(Instant::now());
+                if left.is_zero() {
+                    return Wait::TimedOut;
+                }
+                wait = wait.min(left);
+            }
+            match self.events.recv_timeout(wait) {
+                Ok(event) => return Wait::Event(event),
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => return Wait::Stopped,
+            }
+        }
+    }
+}
+
+/// What `NotifyWatcher::next_event` saw
+enum Wait {
+    Event(notify::Result<Event>),
+    TimedOut,
+    /// Ctrl-C, or the notifications ended
+    Stopped,
+}
+
+impl SettingsWatcher for NotifyWatcher {
+    fn wait_for_change(&mut self) -> Result<bool> {
+        loop {
+            match self.next_event(None) {
+                Wait::Event(event) if self.concerns_settings(&event) => break,
+                Wait::Event(_) | Wait::TimedOut => continue,
+                Wait::Stopped => return Ok(false),
+            }
+        }
+        // Let a burst of writes settle before reporting one change
+        loop {
+            match self.next_event(Some(self.debounce)) {
+                Wait::Event(_) => continue,
+                Wait::TimedOut => return Ok(true),
+                Wait::Stopped => return Ok(false),
+            }
+        }
+    }
+}
+
+/// The `env` keys whose value in `current` no longer matches what the profile
+/// wrote, sorted. Keys added by other tools are not drift.
+pub fn drifted_keys(
+    expected: &HashMap<String, String>,
+    current: &HashMap<String, String>,
+) -> Vec<String> {
+    let mut keys: Vec<String> = expected
+        .iter()
+        .filter(|(key, value)| current.get(*key) != Some(*value))
+        .map(|(key, _)| key.clone())
+        .collect();
+    keys.sort();
+    keys
+}
+
+/// `watch`: apply a profile, then re-apply it whenever the settings file is
+/// changed behind our back
+pub struct WatchCommand {
+    config_manager: ConfigManager,
+    profile: String,
+    options: GLMOptions,
+}
+
+impl WatchCommand {
+    pub fn new(config_manager: ConfigManager, profile: String, options: GLMOptions) -> Self {
+        // Nobody is around to answer prompts, and re-applying GLM over GLM is the point
+        let options = GLMOptions {
+            non_interactive: true,
+            save_policy: crate::utils::SavePolicy::Never,
+            force: true,
+            ..options
+        };
+        Self {
+            config_manager,
+            profile,
+            options,
+        }
+    }
+
+    /// Returns how many times the profile was re-applied
+    pub fn run(&self, watcher: &mut dyn SettingsWatcher) -> Result<usize> {
+        let mut expected = self.apply()?;
+        println!(
+            "{}{} {}",
+            "👀 Watching ".cyan(),
+            self.config_manager.settings_file().display(),
+            format!("for drift from profile '{}' (Ctrl-C to stop)", self.profile).cyan()
+        );
+
+        let mut reapplied = 0;
+        while watcher.wait_for_change()? {
+            let drift = match self.config_manager.load_current_config() {
+                Ok(config) => drifted_keys(&expected, &config.env),
+                Err(e) => vec![format!("unreadable settings ({})", e)],
+            };
+            if drift.is_empty() {
+                continue;
+            }
+
+            println!(
+                "{} {}{}",
+                Local::now().format("%Y-%m-%d %H:%M:%S"),
+                "🔁 Settings drifted, re-applying profile: ".yellow(),
+                drift.join(", ")
+            );
+            match self.apply() {
+                Ok(env) => {
+                    expected = env;
+                    reapplied += 1;
+                }
+                Err(e) => eprintln!("{} {}", "❌ Re-applying failed:".red(), e),
+            }
+        }
+
+        println!("{}", "👋 Stopped watching".cyan());
+        Ok(reapplied)
+    }
+
+    /// Switch to the profile and return the `env` it wrote
+    fn apply(&self) -> Result<HashMap<String, String>> {
+        ProfileSwitcher::new(self.config_manager.clone(), self.options.clone())
+            .switch_to(&self.profile)?;
+        Ok(self.config_manager.load_current_config()?.env)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::fs;
+
+    /// Runs one scripted edit per "change", then stops
+    struct ScriptedWatcher {
+        edits: VecDeque<Box<dyn FnOnce()>>,
+    }
+
+    impl SettingsWatcher for ScriptedWatcher {
+        fn wait_for_change(&mut self) -> Result<bool> {
+            match self.edits.pop_front() {
+                Some(edit) => {
+                    edit();
+                    Ok(true)
+                }
+                None => Ok(false),
+            }
+        }
+    }
+
+    fn env(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_notify_watcher_reports_a_write_and_ignores_neighbours() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = dir.path().join("settings.json");
+        fs::write(&settings, "{}").unwrap();
+        let mut watcher = NotifyWatcher::new(settings.clone(), Duration::from_millis(50)).unwrap();
+
+        fs::write(dir.path().join("other.json"), "{}").unwrap();
+        fs::write(&settings, r#"{"env": {}}"#).unwrap();
+        assert!(watcher.wait_for_change().unwrap());
+
+        watcher.stop.store(true, Ordering::SeqCst);
+        assert!(!watcher.wait_for_change().unwrap());
+    }
+
+    #[test]
+    fn test_drifted_keys_ignores_additions() {
+        let expected = env(&[
+            ("ANTHROPIC_BASE_URL", "https://api.z.ai"),
+            ("API_TIMEOUT_MS", "1"),
+        ]);
+
+        let added = env(&[
+            ("ANTHROPIC_BASE_URL", "https://api.z.ai"),
+            ("API_TIMEOUT_MS", "1"),
+            ("DISABLE_TELEMETRY", "1"),
+        ]);
+        assert!(drifted_keys(&expected, &added).is_empty());
+
+        let reset = env(&[("API_TIMEOUT_MS", "2")]);
+        assert_eq!(
+            drifted_keys(&expected, &reset),
+            vec!["ANTHROPIC_BASE_URL", "API_TIMEOUT_MS"]
+        );
+    }
+
+    #[test]
+    fn test_reapplies_profile_only_when_settings_drift() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        manager.save_token("sk-watch-test").unwrap();
+        let settings = manager.settings_file().to_path_buf();
+
+        let reset = settings.clone();
+        let unrelated = settings.clone();
+        let mut watcher = ScriptedWatcher {
+            edits: VecDeque::from([
+                // Another tool puts back a plain Anthropic config
+                Box::new(move || fs::write(&reset, r#"{"env": {}}"#).unwrap()) as Box<dyn FnOnce()>,
+                // ...and later only adds a setting of its own
+                Box::new(move || {
+                    let mut config: serde_json::Value =
+                        serde_json::from_str(&fs::read_to_string(&unrelated).unwrap()).unwrap();
+                    config["theme"] = "dark".into();
+                    fs::write(&unrelated, config.to_string()).unwrap();
+                }),
+            ]),
+        };
+
+        let reapplied =
+            WatchCommand::new(manager.clone(), "glm".to_string(), GLMOptions::default())
+                .run(&mut watcher)
+                .unwrap();
+
+        assert_eq!(reapplied, 1);
+        let config = manager.load_current_config().unwrap();
+        assert_eq!(config.env["ANTHROPIC_AUTH_TOKEN"], "sk-watch-test");
+        assert_eq!(config.extra["theme"], "dark");
+    }
+}
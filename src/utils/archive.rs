@@ -0,0 +1,161 @@
+use crate::utils::error::AppError;
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+/// A regular file in a `.tar.gz`; archives are flat, so names have no `/`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveEntry {
+    pub name: String,
+    pub data: Vec<u8>,
+    /// Unix permission bits, e.g. `0o600` for secrets
+    pub mode: u32,
+}
+
+/// Write `entries` as a gzipped tar archive, readable only by the owner on
+/// Unix since bundles may carry tokens
+pub fn write_tar_gz(path: &Path, entries: &[ArchiveEntry]) -> Result<()> {
+    if let Some(entry) = entries.iter().find(|entry| !is_flat_name(&entry.name)) {
+        return Err(AppError::FileSystem(format!(
+            "Cannot archive '{}': names must be flat",
+            entry.name
+        ))
+        .into());
+    }
+
+    let temp_path = path.with_extension("tmp");
+    let written = create_private(&temp_path).and_then(|file| {
+        let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+        let mtime = chrono::Utc::now().timestamp().max(0) as u64;
+        for entry in entries {
+            let mut header = tar::Header::new_ustar();
+            header.set_entry_type(tar::EntryType::Regular);
+            header.set_size(entry.data.len() as u64);
+            header.set_mode(entry.mode & 0o7777);
+            header.set_mtime(mtime);
+            builder.append_data(&mut header, &entry.name, entry.data.as_slice())?;
+        }
+        builder.into_inner()?.finish()?;
+        Ok(())
+    });
+    if let Err(e) = written {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e)
+            .with_context(|| format!("Failed to write temp file: {}", temp_path.display()));
+    }
+    fs::rename(&temp_path, path)
+        .with_context(|| format!("Failed to rename temp file to: {}", path.display()))?;
+    Ok(())
+}
+
+/// A new file that is private from the moment it exists (0600 on Unix)
+fn create_private(path: &Path) -> std::io::Result<File> {
+    // A leftover temp file would keep its old mode
+    let _ = fs::remove_file(path);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
+
+/// The regular files in a gzipped tar archive written by `write_tar_gz` (or
+/// any tool, as long as it only holds flat regular files)
+pub fn read_tar_gz(path: &Path) -> Result<Vec<ArchiveEntry>> {
+    let file =
+        File::open(path).with_context(|| format!("Failed to read archive: {}", path.display()))?;
+    let mut archive = tar::Archive::new(GzDecoder::new(file));
+
+    let mut entries = Vec::new();
+    for entry in archive.entries().map_err(|e| invalid(path, &e))? {
+        let mut entry = entry.map_err(|e| invalid(path, &e))?;
+        // Regular files only; directories, links and extended headers are skipped
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = entry
+            .path()
+            .map_err(|e| invalid(path, &e))?
+            .to_string_lossy()
+            .into_owned();
+        if !is_flat_name(&name) {
+            return Err(invalid(path, &format!("unexpected entry '{}'", name)));
+        }
+        let mode = entry.header().mode().map_err(|e| invalid(path, &e))?;
+        let mut data = Vec::new();
+        entry
+            .read_to_end(&mut data)
+            .map_err(|e| invalid(path, &e))?;
+        entries.push(ArchiveEntry { name, data, mode });
+    }
+    Ok(entries)
+}
+
+fn is_flat_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['/', '\\']) && name != ".." && name != "."
+}
+
+fn invalid(path: &Path, reason: &dyn std::fmt::Display) -> anyhow::Error {
+    AppError::Config(format!("Invalid archive {}: {}", path.display(), reason)).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tar_gz_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bundle.tar.gz");
+        let entries = vec![
+            ArchiveEntry {
+                name: "settings.json".to_string(),
+                data: br#"{"env": {}}"#.to_vec(),
+                mode: 0o644,
+            },
+            ArchiveEntry {
+                name: "empty".to_string(),
+                data: Vec::new(),
+                mode: 0o600,
+            },
+            ArchiveEntry {
+                name: "block-sized".to_string(),
+                data: vec![b'x'; 512],
+                mode: 0o600,
+            },
+        ];
+
+        write_tar_gz(&path, &entries).unwrap();
+        assert_eq!(read_tar_gz(&path).unwrap(), entries);
+        assert!(!path.with_extension("tmp").exists());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn test_rejects_corrupt_archives() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bundle.tar.gz");
+        fs::write(&path, "plain text").unwrap();
+        assert!(read_tar_gz(&path).is_err());
+
+        let nested = ArchiveEntry {
+            name: "../escape".to_string(),
+            data: Vec::new(),
+            mode: 0o644,
+        };
+        assert!(write_tar_gz(&path, &[nested]).is_err());
+    }
+}
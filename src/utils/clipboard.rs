@@ -0,0 +1,58 @@
+use anyhow::{anyhow, Result};
+
+/// Somewhere text can be copied to and pasted from; the system clipboard
+/// outside of tests
+pub trait Clipboard {
+    fn set_text(&mut self, text: &str) -> Result<()>;
+    fn get_text(&mut self) -> Result<String>;
+}
+
+/// The desktop clipboard, through `arboard`
+#[derive(Debug, Default)]
+pub struct SystemClipboard;
+
+impl SystemClipboard {
+    fn open() -> Result<arboard::Clipboard> {
+        arboard::Clipboard::new().map_err(|e| anyhow!("No clipboard available ({})", e))
+    }
+}
+
+impl Clipboard for SystemClipboard {
+    fn set_text(&mut self, text: &str) -> Result<()> {
+        // On X11 and Wayland the text lives in this process; dropping the
+        // clipboard hands it to the clipboard manager before we exit
+        Self::open()?
+            .set_text(text)
+            .map_err(|e| anyhow!("Failed to copy to the clipboard ({})", e))
+    }
+
+    fn get_text(&mut self) -> Result<String> {
+        Self::open()?
+            .get_text()
+            .map_err(|e| anyhow!("Failed to read the clipboard ({})", e))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// An in-memory clipboard
+    #[derive(Default)]
+    pub(crate) struct MockClipboard {
+        pub(crate) contents: Option<String>,
+    }
+
+    impl Clipboard for MockClipboard {
+        fn set_text(&mut self, text: &str) -> Result<()> {
+            self.contents = Some(text.to_string());
+            Ok(())
+        }
+
+        fn get_text(&mut self) -> Result<String> {
+            self.contents
+                .clone()
+                .ok_or_else(|| anyhow!("Clipboard is empty"))
+        }
+    }
+}
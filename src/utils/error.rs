@@ -1,5 +1,15 @@
+use crate::utils::mask::mask_token;
 use thiserror::Error;
 
+/// Process exit codes. These are part of the CLI contract; do not renumber.
+pub const EXIT_GENERIC: i32 = 1;
+pub const EXIT_CONFIG: i32 = 2;
+pub const EXIT_TOKEN: i32 = 3;
+pub const EXIT_FILESYSTEM: i32 = 4;
+pub const EXIT_PROVIDER: i32 = 5;
+pub const EXIT_INSTALLATION: i32 = 6;
+pub const EXIT_USAGE: i32 = 64;
+
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Configuration error: {0}")]
@@ -27,8 +37,160 @@ pub enum AppError {
     HomeDirNotFound,
 }
 
+impl AppError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            AppError::Config(_) | AppError::Json(_) => EXIT_CONFIG,
+            AppError::Token(_) => EXIT_TOKEN,
+            AppError::FileSystem(_) | AppError::Io(_) | AppError::HomeDirNotFound => {
+                EXIT_FILESYSTEM
+            }
+            AppError::Provider(_) => EXIT_PROVIDER,
+            AppError::Installation(_) => EXIT_INSTALLATION,
+        }
+    }
+
+    /// The variant name, for `kind` in JSON error output
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppError::Config(_) => "Config",
+            AppError::FileSystem(_) => "FileSystem",
+            AppError::Token(_) => "Token",
+            AppError::Provider(_) => "Provider",
+            AppError::Installation(_) => "Installation",
+            AppError::Io(_) => "Io",
+            AppError::Json(_) => "Json",
+            AppError::HomeDirNotFound => "HomeDirNotFound",
+        }
+    }
+}
+
+/// Pick the exit code for an error, looking through its whole context chain.
+///
+/// An `AppError` anywhere in the chain wins; otherwise raw IO and JSON errors
+/// are classified as filesystem and config failures respectively.
+pub fn exit_code_for(error: &anyhow::Error) -> i32 {
+    if let Some(app_error) = error.chain().find_map(|e| e.downcast_ref::<AppError>()) {
+        return app_error.exit_code();
+    }
+    for cause in error.chain() {
+        if cause.is::<std::io::Error>() {
+            return EXIT_FILESYSTEM;
+        }
+        if cause.is::<serde_json::Error>() {
+            return EXIT_CONFIG;
+        }
+    }
+    EXIT_GENERIC
+}
+
+/// The `AppError` kind of an error, classified like `exit_code_for`; `Other`
+/// when nothing in its chain says more
+pub fn error_kind(error: &anyhow::Error) -> &'static str {
+    if let Some(app_error) = error.chain().find_map(|e| e.downcast_ref::<AppError>()) {
+        return app_error.kind();
+    }
+    for cause in error.chain() {
+        if cause.is::<std::io::Error>() {
+            return "Io";
+        }
+        if cause.is::<serde_json::Error>() {
+            return "Json";
+        }
+    }
+    "Other"
+}
+
+/// A `serde_json` error as text, with every quoted string value masked.
+///
+/// Messages such as `invalid type: string "sk-...", expected a map` echo the
+/// input verbatim, so errors about config content go through here instead of
+/// `Display` (or `?` into `AppError::Json`).
+pub fn redacted_json_error(error: &serde_json::Error) -> String {
+    const MARKER: &str = "string \"";
+    let message = error.to_string();
+    let mut redacted = String::with_capacity(message.len());
+    let mut rest = message.as_str();
+    while let Some(start) = rest.find(MARKER) {
+        let (head, tail) = rest.split_at(start + MARKER.len());
+        redacted.push_str(head);
+        // The value ends at the last quote before ", expected", or the line number
+        let end = tail
+            .find("\", expected")
+            .or_else(|| tail.find("\" at line"))
+            .or_else(|| tail.rfind('"'))
+            .unwrap_or(tail.len());
+        redacted.push_str(&mask_token(&tail[..end]));
+        rest = &tail[end..];
+    }
+    redacted.push_str(rest);
+    redacted
+}
+
 impl From<AppError> for String {
     fn from(error: AppError) -> Self {
         error.to_string()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_exit_code_found_through_context() {
+        let error = Err::<(), _>(AppError::Token("empty".to_string()))
+            .context("Failed to get GLM API token")
+            .unwrap_err();
+        assert_eq!(exit_code_for(&error), EXIT_TOKEN);
+    }
+
+    #[test]
+    fn test_error_kind_is_the_variant_name() {
+        let error = Err::<(), _>(AppError::Provider("refused".to_string()))
+            .context("Failed to switch")
+            .unwrap_err();
+        assert_eq!(error_kind(&error), "Provider");
+
+        let io = anyhow::Error::new(std::io::Error::from(std::io::ErrorKind::NotFound));
+        assert_eq!(error_kind(&io), "Io");
+        assert_eq!(error_kind(&anyhow::anyhow!("plain")), "Other");
+    }
+
+    #[test]
+    fn test_exit_code_for_raw_errors() {
+        let io = anyhow::Error::new(std::io::Error::from(std::io::ErrorKind::PermissionDenied));
+        assert_eq!(exit_code_for(&io), EXIT_FILESYSTEM);
+
+        let json = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+        let json = anyhow::Error::new(json).context("Failed to parse config file");
+        assert_eq!(exit_code_for(&json), EXIT_CONFIG);
+
+        assert_eq!(exit_code_for(&anyhow::anyhow!("boom")), EXIT_GENERIC);
+    }
+
+    #[test]
+    fn test_redacted_json_error_masks_string_values() {
+        let token = "sk-ant-api03-secretvalue\\\"quoted";
+        for json in [
+            format!(r#"{{"env": "{}"}}"#, token),
+            format!(r#""{}""#, token),
+        ] {
+            let error = serde_json::from_str::<crate::config::Config>(&json).unwrap_err();
+            assert!(error.to_string().contains("secretvalue"));
+
+            let redacted = redacted_json_error(&error);
+            assert!(!redacted.contains("secretvalue"), "{}", redacted);
+            assert!(redacted.contains("at line 1"), "{}", redacted);
+        }
+    }
+
+    #[test]
+    fn test_redacted_json_error_masks_non_ascii_values() {
+        let error =
+            serde_json::from_str::<crate::config::Config>(r#"{"env": "aéééééééé"}"#).unwrap_err();
+        let redacted = redacted_json_error(&error);
+        assert!(redacted.contains("aééé...éééé"), "{}", redacted);
+    }
+}
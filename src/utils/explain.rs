@@ -0,0 +1,80 @@
+use colored::*;
+use std::path::Path;
+
+/// `--explain`: a plain-language sentence before each step of a switch.
+///
+/// Disabled by default, in which case every call is a no-op.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Explainer {
+    enabled: bool,
+}
+
+impl Explainer {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled }
+    }
+
+    /// Print `sentence`, followed by the file the step touches
+    pub fn step(&self, sentence: &str, path: &Path) {
+        if let Some(text) = self.text(sentence, path) {
+            println!("{}", text.blue());
+        }
+    }
+
+    /// Shared by every switch: the live settings are read first
+    pub fn reading_settings(&self, path: &Path) {
+        self.step(
+            "Reading the current Claude Code settings to find out which provider is active.",
+            path,
+        );
+    }
+
+    /// `provider` (e.g. "GLM") is already active and only changes are written
+    pub fn updating_active(&self, provider: &str, path: &Path) {
+        self.step(
+            &format!(
+                "{} is already active, so only the changed values are written to the settings.",
+                provider
+            ),
+            path,
+        );
+    }
+
+    /// A new primary Anthropic backup is about to be written
+    pub fn anthropic_backup(&self, path: &Path) {
+        self.step(
+            "The current settings use Anthropic. Saving a copy so `claude-switch anthropic` \
+             can bring them back later.",
+            path,
+        );
+    }
+
+    /// Settings of another provider are about to be kept in the rotation
+    pub fn rotated_backup(&self, path: &Path) {
+        self.step(
+            "The current settings are not Anthropic's. Saving a copy among the rotated \
+             backups so they can be restored later.",
+            path,
+        );
+    }
+
+    fn text(&self, sentence: &str, path: &Path) -> Option<String> {
+        self.enabled
+            .then(|| format!("ℹ️  {}\n   File: {}", sentence, path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disabled_explainer_is_silent() {
+        let path = Path::new("/tmp/settings.json");
+        assert_eq!(Explainer::default().text("Reading settings", path), None);
+        assert_eq!(
+            Explainer::new(true).text("Reading settings", path).unwrap(),
+            "ℹ️  Reading settings\n   File: /tmp/settings.json"
+        );
+    }
+}
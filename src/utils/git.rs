@@ -0,0 +1,71 @@
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// "git main@1a2b3c4" for the commit checked out in the repository holding
+/// `dir` ("git 1a2b3c4" on a detached HEAD); `None` when `dir` is not in a
+/// repository, it has no commits yet, or git is not installed
+pub fn describe_checkout(dir: &Path) -> Option<String> {
+    let commit = rev_parse(dir, &["--short", "HEAD"])?;
+    match rev_parse(dir, &["--abbrev-ref", "HEAD"]) {
+        Some(branch) if branch != "HEAD" => Some(format!("git {}@{}", branch, commit)),
+        _ => Some(format!("git {}", commit)),
+    }
+}
+
+fn rev_parse(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .arg("rev-parse")
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let value = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (output.status.success() && !value.is_empty()).then_some(value)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Run git in `dir` with a throwaway identity; `false` if git is missing
+    pub(crate) fn git(dir: &Path, args: &[&str]) -> bool {
+        Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    }
+
+    #[test]
+    fn test_describes_branch_and_short_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(describe_checkout(dir.path()), None);
+        if !git(dir.path(), &["init", "-q", "-b", "dotfiles"]) {
+            return;
+        }
+        // No commit yet
+        assert_eq!(describe_checkout(dir.path()), None);
+
+        std::fs::write(dir.path().join("settings.json"), "{}").unwrap();
+        assert!(git(dir.path(), &["add", "settings.json"]));
+        assert!(git(dir.path(), &["commit", "-q", "-m", "settings"]));
+        let commit = rev_parse(dir.path(), &["--short", "HEAD"]).unwrap();
+        assert_eq!(
+            describe_checkout(dir.path()),
+            Some(format!("git dotfiles@{}", commit))
+        );
+
+        assert!(git(dir.path(), &["checkout", "-q", "--detach"]));
+        assert_eq!(
+            describe_checkout(dir.path()),
+            Some(format!("git {}", commit))
+        );
+    }
+}
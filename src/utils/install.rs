@@ -1,43 +1,103 @@
+use crate::utils::error::AppError;
 use anyhow::{Context, Result};
 use colored::*;
+use serde::{Deserialize, Serialize};
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
-pub struct Installer;
+const INSTALL_PATH: &str = "/usr/local/bin/claude-switch";
+/// First line of the block we append to shell configs
+const ALIAS_MARKER: &str = "# Claude Code API Switcher";
+
+/// Shell configs we have written aliases to, so re-installs and `uninstall`
+/// touch exactly those files
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct AliasManifest {
+    shell_configs: Vec<PathBuf>,
+}
+
+impl AliasManifest {
+    fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content).map_err(|e| {
+                AppError::Installation(format!("Failed to parse {}: {}", path.display(), e)).into()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// What `install` will do, worked out without touching anything
+#[derive(Debug)]
+struct InstallPlan {
+    /// Binary to copy to the install path, unless it is skipped or already there
+    copy_binary_from: Option<PathBuf>,
+    /// Path the aliases run
+    alias_target: PathBuf,
+    /// Manifest entries that still exist, plus the detected config
+    shell_configs: Vec<PathBuf>,
+}
+
+pub struct Installer {
+    home: PathBuf,
+    shell: String,
+    install_path: PathBuf,
+    manifest_path: PathBuf,
+    skip_binary: bool,
+}
 
 impl Installer {
-    pub fn new() -> Result<Self> {
-        Ok(Self)
+    /// `manifest_path` records which shell configs received aliases
+    pub fn new(manifest_path: &Path) -> Result<Self> {
+        let home = dirs::home_dir().ok_or(AppError::HomeDirNotFound)?;
+        Ok(Self {
+            home,
+            shell: env::var("SHELL").unwrap_or_default(),
+            install_path: PathBuf::from(INSTALL_PATH),
+            manifest_path: manifest_path.to_path_buf(),
+            skip_binary: false,
+        })
+    }
+
+    /// Only install aliases, pointing them at the running binary
+    /// (for binaries already on PATH, e.g. from `cargo install`)
+    pub fn with_skip_binary(mut self, skip_binary: bool) -> Self {
+        self.skip_binary = skip_binary;
+        self
     }
 
     pub fn install(&self) -> Result<()> {
         println!("{}", "🚀 Installing Claude Code API Switcher...".green());
         println!();
 
-        // Get current executable path
-        let exec_path = env::current_exe().context("Failed to get executable path")?;
-
-        // Resolve symlinks
-        let exec_path = exec_path
-            .canonicalize()
-            .context("Failed to resolve executable path")?;
-
-        // Install binary to /usr/local/bin
-        let install_path = PathBuf::from("/usr/local/bin/claude-switch");
-
-        if exec_path != install_path {
-            self.install_binary(&exec_path, &install_path)?;
-        } else {
-            println!(
+        let plan = self.plan()?;
+        match &plan.copy_binary_from {
+            // Install binary to /usr/local/bin
+            Some(source) => self.install_binary(source, &self.install_path)?,
+            None if self.skip_binary => println!(
+                "{}{}",
+                "📦 Skipping binary installation, using ".cyan(),
+                plan.alias_target.display()
+            ),
+            None => println!(
                 "{}",
                 "📦 Binary already installed at /usr/local/bin/claude-switch".cyan()
-            );
+            ),
         }
 
         // Install shell aliases
-        self.install_shell_aliases(&install_path)?;
+        self.install_shell_aliases(&plan.alias_target, &plan.shell_configs)?;
 
         println!();
         println!("{}", "🎉 Installation complete!".green());
@@ -47,6 +107,84 @@ impl Installer {
         Ok(())
     }
 
+    /// Print what `install` would change, without writing anything or running sudo
+    pub fn dry_run(&self) -> Result<()> {
+        let plan = self.plan()?;
+        println!("{}", "🔍 Dry run: nothing will be written".cyan());
+        println!();
+
+        match &plan.copy_binary_from {
+            Some(source) => println!(
+                "{}{} → {} (may ask for sudo)",
+                "📦 Would copy binary: ".cyan(),
+                source.display(),
+                self.install_path.display()
+            ),
+            None => println!(
+                "{}{}",
+                "📦 Binary stays at ".cyan(),
+                plan.alias_target.display()
+            ),
+        }
+
+        for shell_rc in &plan.shell_configs {
+            let is_fish = shell_rc.to_string_lossy().contains("fish");
+            println!();
+            println!(
+                "{}{}",
+                "📝 Would write this alias block to ".cyan(),
+                shell_rc.display()
+            );
+            print!("{}", alias_block(&plan.alias_target, is_fish));
+        }
+        println!();
+        println!(
+            "{}{}",
+            "📋 Would record these configs in ".cyan(),
+            self.manifest_path.display()
+        );
+        Ok(())
+    }
+
+    fn plan(&self) -> Result<InstallPlan> {
+        // Get current executable path, with symlinks resolved
+        let exec_path = env::current_exe()
+            .context("Failed to get executable path")?
+            .canonicalize()
+            .context("Failed to resolve executable path")?;
+
+        let (copy_binary_from, alias_target) = if self.skip_binary {
+            (None, exec_path)
+        } else if exec_path != self.install_path {
+            (Some(exec_path), self.install_path.clone())
+        } else {
+            (None, self.install_path.clone())
+        };
+
+        let mut manifest = AliasManifest::load(&self.manifest_path)?;
+        manifest.shell_configs.retain(|path| path.exists());
+
+        // Previously written configs stay in sync with the detected one
+        let mut shell_configs = manifest.shell_configs;
+        for detected in self.detect_shell_configs() {
+            if !shell_configs.contains(&detected) {
+                shell_configs.push(detected);
+            }
+        }
+        if shell_configs.is_empty() {
+            return Err(AppError::Installation(
+                "No supported shell configuration found".to_string(),
+            )
+            .into());
+        }
+
+        Ok(InstallPlan {
+            copy_binary_from,
+            alias_target,
+            shell_configs,
+        })
+    }
+
     fn install_binary(&self, source_path: &Path, install_path: &Path) -> Result<()> {
         println!("{}", "📦 Installing binary to /usr/local/bin...".cyan());
 
@@ -73,17 +211,18 @@ impl Installer {
             println!("Running: {}", cmd);
 
             let output = Command::new("bash")
-                .args(&["-c", &cmd])
+                .args(["-c", &cmd])
                 .output()
                 .context("Failed to execute sudo command")?;
 
             fs::remove_file(&temp_file)?;
 
             if !output.status.success() {
-                return Err(anyhow::anyhow!(
+                return Err(AppError::Installation(format!(
                     "Failed to install binary (try running with sudo): {}",
                     String::from_utf8_lossy(&output.stderr)
-                ));
+                ))
+                .into());
             }
         }
 
@@ -102,68 +241,44 @@ impl Installer {
         Ok(())
     }
 
-    fn install_shell_aliases(&self, exec_path: &Path) -> Result<()> {
-        let shell_configs = self.detect_shell_configs();
-        if shell_configs.is_empty() {
-            return Err(anyhow::anyhow!("No supported shell configuration found"));
-        }
-
-        let exec_path_str = exec_path.to_string_lossy();
-
-        // Create alias block for bash/zsh
-        let alias_block = format!(
-            r#"
-# Claude Code API Switcher
-alias claude-switch='{}'
-alias claude-anthropic='{} --anthropic'
-alias claude-glm='{} --glm'
-alias claude-status='{} --status'
-"#,
-            exec_path_str, exec_path_str, exec_path_str, exec_path_str
-        );
-
-        // Fish shell uses different syntax
-        let fish_alias_block = format!(
-            r#"
-# Claude Code API Switcher
-alias claude-switch '{}'
-alias claude-anthropic '{} --anthropic'
-alias claude-glm '{} --glm'
-alias claude-status '{} --status'
-"#,
-            exec_path_str, exec_path_str, exec_path_str, exec_path_str
-        );
-
+    fn install_shell_aliases(&self, exec_path: &Path, shell_configs: &[PathBuf]) -> Result<()> {
         let mut installed_count = 0;
 
-        for shell_rc in &shell_configs {
+        for shell_rc in shell_configs {
             let is_fish = shell_rc.to_string_lossy().contains("fish");
-            let block = if is_fish {
-                &fish_alias_block
-            } else {
-                &alias_block
-            };
+            let block = alias_block(exec_path, is_fish);
 
             // Read existing shell config
             let content = fs::read_to_string(shell_rc).unwrap_or_default();
+            let (base, had_block) = match strip_alias_block(&content) {
+                Some(stripped) => (stripped, true),
+                None => (content.clone(), false),
+            };
 
-            // Check if aliases already exist
-            if content.contains("Claude Code API Switcher") {
+            let updated = format!("{}\n{}", base, block);
+            if updated == content {
                 println!(
                     "{}{}",
                     "⚠️  Aliases already exist in ".yellow(),
                     shell_rc.display()
                 );
-                continue;
+            } else {
+                fs::write(shell_rc, updated)
+                    .with_context(|| format!("Failed to write to {}", shell_rc.display()))?;
+                let label = if had_block {
+                    "✅ Aliases updated in "
+                } else {
+                    "✅ Aliases added to "
+                };
+                println!("{}{}", label.green(), shell_rc.display());
+                installed_count += 1;
             }
+        }
 
-            // Append aliases
-            fs::write(shell_rc, format!("{}\n{}", content, block))
-                .with_context(|| format!("Failed to write to {}", shell_rc.display()))?;
-
-            println!("{}{}", "✅ Aliases added to ".green(), shell_rc.display());
-            installed_count += 1;
+        AliasManifest {
+            shell_configs: shell_configs.to_vec(),
         }
+        .save(&self.manifest_path)?;
 
         if installed_count == 0 {
             println!("{}", "⚠️  No new aliases were installed".yellow());
@@ -172,13 +287,54 @@ alias claude-status '{} --status'
         Ok(())
     }
 
-    fn detect_shell_configs(&self) -> Vec<PathBuf> {
-        let home = match dirs::home_dir() {
-            Some(h) => h,
-            None => return Vec::new(),
+    /// Remove our alias block from every shell config in the manifest
+    /// (or the detected one, for installs that predate it)
+    pub fn uninstall(&self) -> Result<()> {
+        println!(
+            "{}",
+            "🧹 Removing Claude Code API Switcher aliases...".green()
+        );
+
+        let manifest = AliasManifest::load(&self.manifest_path)?;
+        let shell_configs = if manifest.shell_configs.is_empty() {
+            self.detect_shell_configs()
+        } else {
+            manifest.shell_configs
         };
 
-        let shell = env::var("SHELL").unwrap_or_default();
+        let mut removed_count = 0;
+        for shell_rc in shell_configs.iter().filter(|path| path.exists()) {
+            let content = fs::read_to_string(shell_rc)
+                .with_context(|| format!("Failed to read {}", shell_rc.display()))?;
+            if let Some(stripped) = strip_alias_block(&content) {
+                fs::write(shell_rc, stripped)
+                    .with_context(|| format!("Failed to write to {}", shell_rc.display()))?;
+                println!(
+                    "{}{}",
+                    "✅ Aliases removed from ".green(),
+                    shell_rc.display()
+                );
+                removed_count += 1;
+            }
+        }
+
+        match fs::remove_file(&self.manifest_path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(e)
+                    .with_context(|| format!("Failed to remove {}", self.manifest_path.display()))
+            }
+            _ => {}
+        }
+
+        if removed_count == 0 {
+            println!("{}", "⚠️  No aliases were found".yellow());
+        }
+        Ok(())
+    }
+
+    fn detect_shell_configs(&self) -> Vec<PathBuf> {
+        let home = &self.home;
+        let shell = &self.shell;
 
         // Common shell config files
         let candidates = vec![
@@ -228,3 +384,163 @@ alias claude-status '{} --status'
         }
     }
 }
+
+fn alias_block(exec_path: &Path, is_fish: bool) -> String {
+    let exec_path = exec_path.to_string_lossy();
+    // Fish shell uses different syntax
+    let assign = if is_fish { " " } else { "=" };
+    format!(
+        "\n{marker}\n\
+         alias claude-switch{assign}'{exec}'\n\
+         alias claude-anthropic{assign}'{exec} --anthropic'\n\
+         alias claude-glm{assign}'{exec} --glm'\n\
+         alias claude-status{assign}'{exec} --status'\n",
+        marker = ALIAS_MARKER,
+        assign = assign,
+        exec = exec_path
+    )
+}
+
+/// `content` without our alias block and the blank lines before it,
+/// or `None` if there is no block
+fn strip_alias_block(content: &str) -> Option<String> {
+    let mut lines: Vec<&str> = content.lines().collect();
+    let start = lines.iter().position(|line| line.trim() == ALIAS_MARKER)?;
+    let mut end = start + 1;
+    while end < lines.len() && lines[end].starts_with("alias claude-") {
+        end += 1;
+    }
+    let mut from = start;
+    while from > 0 && lines[from - 1].trim().is_empty() {
+        from -= 1;
+    }
+    lines.drain(from..end);
+
+    let mut stripped = lines.join("\n");
+    if !stripped.is_empty() {
+        stripped.push('\n');
+    }
+    Some(stripped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn installer(home: &Path, shell: &str, bin_dir: &Path) -> Installer {
+        Installer {
+            home: home.to_path_buf(),
+            shell: shell.to_string(),
+            install_path: bin_dir.join("claude-switch"),
+            manifest_path: home.join(".claude").join("aliases.json"),
+            skip_binary: true,
+        }
+    }
+
+    fn marker_count(path: &Path) -> usize {
+        fs::read_to_string(path)
+            .unwrap()
+            .matches(ALIAS_MARKER)
+            .count()
+    }
+
+    #[test]
+    fn test_reinstall_across_shells_keeps_one_block_each() {
+        let home = tempfile::tempdir().unwrap();
+        let bin_dir = tempfile::tempdir().unwrap();
+        let zshrc = home.path().join(".zshrc");
+        let bashrc = home.path().join(".bashrc");
+        fs::write(&zshrc, "export ZSH=1\n").unwrap();
+        fs::write(&bashrc, "export BASH=1\n").unwrap();
+
+        installer(home.path(), "/bin/zsh", bin_dir.path())
+            .install()
+            .unwrap();
+        // Shell changed: both files are now tracked
+        installer(home.path(), "/bin/bash", bin_dir.path())
+            .install()
+            .unwrap();
+        let after_second = fs::read_to_string(&bashrc).unwrap();
+        installer(home.path(), "/bin/bash", bin_dir.path())
+            .install()
+            .unwrap();
+
+        assert_eq!(marker_count(&zshrc), 1);
+        assert_eq!(marker_count(&bashrc), 1);
+        assert_eq!(fs::read_to_string(&bashrc).unwrap(), after_second);
+
+        installer(home.path(), "/bin/bash", bin_dir.path())
+            .uninstall()
+            .unwrap();
+        assert_eq!(fs::read_to_string(&zshrc).unwrap(), "export ZSH=1\n");
+        assert_eq!(fs::read_to_string(&bashrc).unwrap(), "export BASH=1\n");
+        assert!(!home.path().join(".claude/aliases.json").exists());
+    }
+
+    #[test]
+    fn test_dry_run_lists_targets_without_writing() {
+        let home = tempfile::tempdir().unwrap();
+        let bin_dir = tempfile::tempdir().unwrap();
+        let zshrc = home.path().join(".zshrc");
+        fs::write(&zshrc, "export ZSH=1\n").unwrap();
+
+        let installer = Installer {
+            skip_binary: false,
+            ..installer(home.path(), "/bin/zsh", bin_dir.path())
+        };
+        let plan = installer.plan().unwrap();
+        assert_eq!(plan.shell_configs, vec![zshrc.clone()]);
+        assert_eq!(plan.alias_target, bin_dir.path().join("claude-switch"));
+        assert_eq!(
+            plan.copy_binary_from,
+            Some(env::current_exe().unwrap().canonicalize().unwrap())
+        );
+
+        installer.dry_run().unwrap();
+        assert_eq!(fs::read_to_string(&zshrc).unwrap(), "export ZSH=1\n");
+        assert!(!bin_dir.path().join("claude-switch").exists());
+        assert!(!home.path().join(".claude").exists());
+    }
+
+    #[test]
+    fn test_reinstall_updates_stale_exec_path() {
+        let home = tempfile::tempdir().unwrap();
+        let bin_dir = tempfile::tempdir().unwrap();
+        let bashrc = home.path().join(".bashrc");
+        fs::write(
+            &bashrc,
+            format!(
+                "# mine\n{}",
+                alias_block(Path::new("/old/claude-switch"), false)
+            ),
+        )
+        .unwrap();
+
+        installer(home.path(), "bash", bin_dir.path())
+            .install()
+            .unwrap();
+
+        let content = fs::read_to_string(&bashrc).unwrap();
+        assert_eq!(content.matches(ALIAS_MARKER).count(), 1);
+        assert!(!content.contains("/old/claude-switch"));
+        assert!(content.starts_with("# mine\n"));
+    }
+
+    #[test]
+    fn test_skip_binary_only_writes_aliases() {
+        let home = tempfile::tempdir().unwrap();
+        let bin_dir = tempfile::tempdir().unwrap();
+        fs::write(home.path().join(".bashrc"), "# existing\n").unwrap();
+
+        installer(home.path(), "bash", bin_dir.path())
+            .install()
+            .unwrap();
+
+        assert!(!bin_dir.path().join("claude-switch").exists());
+
+        let bashrc = fs::read_to_string(home.path().join(".bashrc")).unwrap();
+        let exec_path = env::current_exe().unwrap().canonicalize().unwrap();
+        assert!(bashrc.starts_with("# existing"));
+        assert!(bashrc.contains(&format!("alias claude-switch='{}'", exec_path.display())));
+    }
+}
@@ -0,0 +1,83 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+
+/// Read the `exp` claim of a JWT without verifying its signature.
+///
+/// Returns `None` for anything that is not a three-part token with a JSON
+/// payload carrying a numeric `exp`.
+pub fn expiry(token: &str) -> Option<DateTime<Utc>> {
+    let mut parts = token.split('.');
+    let (_header, payload, _signature) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() {
+        return None;
+    }
+
+    // Some issuers pad their base64url segments
+    let payload = URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok()?;
+    let claims: serde_json::Value = serde_json::from_slice(&payload).ok()?;
+    let exp = claims.get("exp")?.as_i64()?;
+    DateTime::from_timestamp(exp, 0)
+}
+
+/// "expired 3h ago" / "expires in 2d 4h"
+pub fn describe_expiry(expires_at: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let remaining = expires_at - now;
+    if remaining.num_seconds() <= 0 {
+        format!("expired {} ago", format_duration(-remaining))
+    } else {
+        format!("expires in {}", format_duration(remaining))
+    }
+}
+
+/// "2d 4h", "3h 0m" or "12m"
+pub fn format_duration(duration: chrono::Duration) -> String {
+    let days = duration.num_days();
+    let hours = duration.num_hours() % 24;
+    let minutes = duration.num_minutes() % 60;
+    match (days, hours) {
+        (0, 0) => format!("{}m", minutes),
+        (0, _) => format!("{}h {}m", hours, minutes),
+        _ => format!("{}d {}h", days, hours),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    /// An unsigned JWT long enough to be detected as an Anthropic web token
+    fn jwt_with_exp(exp: i64) -> String {
+        let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"HS256","typ":"JWT"}"#);
+        let payload = URL_SAFE_NO_PAD.encode(format!(r#"{{"sub":"user","exp":{}}}"#, exp));
+        format!("{}.{}.{}", header, payload, "s".repeat(64))
+    }
+
+    #[test]
+    fn test_expiry_past_and_future() {
+        let now = Utc::now();
+
+        let past = (now - Duration::hours(3)).timestamp();
+        let expires_at = expiry(&jwt_with_exp(past)).unwrap();
+        assert_eq!(expires_at.timestamp(), past);
+        assert_eq!(describe_expiry(expires_at, now), "expired 3h 0m ago");
+
+        let future = (now + Duration::days(2) + Duration::hours(5)).timestamp();
+        let expires_at = expiry(&jwt_with_exp(future)).unwrap();
+        assert_eq!(describe_expiry(expires_at, now), "expires in 2d 4h");
+    }
+
+    #[test]
+    fn test_expiry_ignores_non_jwt_tokens() {
+        assert_eq!(expiry("sk-1234567890abcdef"), None);
+        assert_eq!(expiry("a.b.c"), None);
+        assert_eq!(expiry("a.b.c.d"), None);
+
+        let no_exp = format!(
+            "x.{}.y",
+            URL_SAFE_NO_PAD.encode(r#"{"sub":"user","exp":"soon"}"#)
+        );
+        assert_eq!(expiry(&no_exp), None);
+    }
+}
@@ -0,0 +1,23 @@
+/// First and last 4 characters of `token`, or stars when that would show
+/// most of it. Counts characters, not bytes, so any UTF-8 is safe.
+pub fn mask_token(token: &str) -> String {
+    let chars: Vec<char> = token.chars().collect();
+    if chars.len() <= 8 {
+        return "********".to_string();
+    }
+    let head: String = chars[..4].iter().collect();
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}...{}", head, tail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask_token_counts_characters() {
+        assert_eq!(mask_token("sk-1234567890abcd"), "sk-1...abcd");
+        assert_eq!(mask_token("aéééééééééé"), "aééé...éééé");
+        assert_eq!(mask_token("éééééééé"), "********");
+    }
+}
@@ -1,6 +1,22 @@
+pub mod archive;
+#[cfg(feature = "clipboard")]
+pub mod clipboard;
 pub mod error;
-pub mod token;
+pub mod explain;
+pub mod git;
 pub mod install;
+pub mod jwt;
+pub mod mask;
+pub mod output;
+pub mod preflight;
+pub mod token;
+pub mod update;
 
+#[cfg(feature = "clipboard")]
+pub use clipboard::{Clipboard, SystemClipboard};
+pub use explain::Explainer;
+pub use install::*;
+pub use output::*;
+pub use preflight::ClaudeInstallation;
 pub use token::*;
-pub use install::*;
\ No newline at end of file
+pub use update::SelfUpdater;
@@ -0,0 +1,203 @@
+use crate::utils::error::error_kind;
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use colored::*;
+use serde::Serialize;
+
+/// Version of the JSON output format, included in every payload as
+/// `schema_version`. Bump it when a field is renamed, removed or changes type.
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+/// Shared output settings consulted by every command that reports data.
+///
+/// Commands build a serializable report and either hand it to `json` or
+/// print their own human-readable panel when `is_json` is false. Reports
+/// must serialize as JSON objects so `schema_version` can be prepended.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Renderer {
+    format: OutputFormat,
+    compact: bool,
+    ascii: bool,
+}
+
+impl Renderer {
+    pub fn new(format: OutputFormat) -> Self {
+        Self {
+            format,
+            compact: false,
+            ascii: false,
+        }
+    }
+
+    /// Draw panels with `+`, `-` and `|` instead of box-drawing characters
+    pub fn with_ascii(mut self, ascii: bool) -> Self {
+        self.ascii = ascii;
+        self
+    }
+
+    pub fn is_ascii(&self) -> bool {
+        self.ascii
+    }
+
+    /// Single-line JSON for piping into other tools
+    pub fn with_compact(mut self, compact: bool) -> Self {
+        self.compact = compact;
+        self
+    }
+
+    pub fn is_json(&self) -> bool {
+        self.format == OutputFormat::Json
+    }
+
+    pub fn json_string<T: Serialize>(&self, value: &T) -> Result<String> {
+        let versioned = Versioned {
+            schema_version: JSON_SCHEMA_VERSION,
+            payload: value,
+        };
+        if self.compact {
+            serde_json::to_string(&versioned)
+        } else {
+            serde_json::to_string_pretty(&versioned)
+        }
+        .context("Failed to serialize output")
+    }
+
+    pub fn json<T: Serialize>(&self, value: &T) -> Result<()> {
+        println!("{}", self.json_string(value)?);
+        Ok(())
+    }
+
+    /// Report a failed command on stderr: `Error: ...` in human mode, an
+    /// `{"error": {"kind", "message"}}` object in JSON mode
+    pub fn error(&self, error: &anyhow::Error) {
+        if !self.is_json() {
+            eprintln!("{}{}", "Error: ".red(), error);
+            return;
+        }
+        let report = ErrorReport {
+            error: ErrorDetail {
+                kind: error_kind(error),
+                message: format!("{:#}", error),
+            },
+        };
+        match self.json_string(&report) {
+            Ok(json) => eprintln!("{}", json),
+            Err(_) => eprintln!("Error: {:#}", error),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorReport {
+    error: ErrorDetail,
+}
+
+#[derive(Serialize)]
+struct ErrorDetail {
+    kind: &'static str,
+    /// The error with its whole context chain
+    message: String,
+}
+
+/// Whether the terminal can be expected to show box-drawing characters:
+/// not with `TERM=dumb` or a non-UTF-8 locale, nor in the legacy Windows
+/// console (anything but Windows Terminal)
+pub fn unicode_supported() -> bool {
+    let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+    let locale = var("LC_ALL")
+        .or_else(|| var("LC_CTYPE"))
+        .or_else(|| var("LANG"));
+    unicode_supported_with(
+        var("TERM").as_deref(),
+        locale.as_deref(),
+        cfg!(windows) && var("WT_SESSION").is_none(),
+    )
+}
+
+fn unicode_supported_with(term: Option<&str>, locale: Option<&str>, legacy_console: bool) -> bool {
+    if legacy_console || term == Some("dumb") {
+        return false;
+    }
+    // No locale at all (common on macOS and in containers) is left as UTF-8
+    locale.is_none_or(|locale| {
+        let locale = locale.to_ascii_lowercase();
+        locale.contains("utf-8") || locale.contains("utf8")
+    })
+}
+
+#[derive(Serialize)]
+struct Versioned<'a, T: Serialize> {
+    schema_version: u32,
+    #[serde(flatten)]
+    payload: &'a T,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Report {
+        provider: &'static str,
+    }
+
+    #[test]
+    fn test_json_output_leads_with_schema_version() {
+        let renderer = Renderer::new(OutputFormat::Json);
+        let output = renderer.json_string(&Report { provider: "glm" }).unwrap();
+
+        assert!(output.starts_with("{\n  \"schema_version\": 1,"));
+        let value: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(value["schema_version"], JSON_SCHEMA_VERSION);
+        assert_eq!(value["provider"], "glm");
+    }
+
+    #[test]
+    fn test_compact_output_is_single_line() {
+        let report = Report { provider: "glm" };
+        let pretty = Renderer::new(OutputFormat::Json)
+            .json_string(&report)
+            .unwrap();
+        let compact = Renderer::new(OutputFormat::Json)
+            .with_compact(true)
+            .json_string(&report)
+            .unwrap();
+
+        assert!(!compact.contains('\n'));
+        assert!(compact.starts_with("{\"schema_version\":1,"));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&compact).unwrap(),
+            serde_json::from_str::<serde_json::Value>(&pretty).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_unicode_support_detection() {
+        assert!(unicode_supported_with(
+            Some("xterm-256color"),
+            Some("en_US.UTF-8"),
+            false
+        ));
+        assert!(unicode_supported_with(None, Some("C.utf8"), false));
+        assert!(unicode_supported_with(None, None, false));
+        assert!(!unicode_supported_with(Some("xterm"), Some("C"), false));
+        assert!(!unicode_supported_with(
+            Some("xterm"),
+            Some("en_US.ISO-8859-1"),
+            false
+        ));
+        assert!(!unicode_supported_with(
+            Some("dumb"),
+            Some("en_US.UTF-8"),
+            false
+        ));
+        assert!(!unicode_supported_with(None, Some("en_US.UTF-8"), true));
+    }
+}
@@ -0,0 +1,147 @@
+use crate::config::manager::CONFIG_DIR_ENV;
+use colored::*;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+/// Name of the Claude Code executable
+const CLAUDE_BINARY: &str = "claude";
+
+/// What can be seen of a Claude Code installation from here
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClaudeInstallation {
+    /// `claude` on PATH
+    pub binary: Option<PathBuf>,
+    /// Claude Code's own directory (`~/.claude` or `CLAUDE_CONFIG_DIR`)
+    pub config_dir: Option<PathBuf>,
+}
+
+impl ClaudeInstallation {
+    /// Look at the real PATH and home directory
+    pub fn detect() -> Self {
+        let config_dir = match std::env::var_os(CONFIG_DIR_ENV) {
+            Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+            _ => dirs::home_dir().map(|home| home.join(".claude")),
+        };
+        Self::detect_in(std::env::var_os("PATH").as_deref(), config_dir)
+    }
+
+    pub fn detect_in(path_var: Option<&OsStr>, config_dir: Option<PathBuf>) -> Self {
+        Self {
+            binary: path_var.and_then(|path_var| find_on_path(CLAUDE_BINARY, path_var)),
+            config_dir: config_dir.filter(|dir| dir.is_dir()),
+        }
+    }
+
+    /// Why Claude Code doesn't look installed; empty when it does
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.binary.is_none() {
+            problems.push("Claude Code's `claude` binary was not found on PATH".to_string());
+        }
+        if self.config_dir.is_none() {
+            problems.push(
+                "Claude Code's config directory (~/.claude or CLAUDE_CONFIG_DIR) does not exist"
+                    .to_string(),
+            );
+        }
+        problems
+    }
+
+    /// Pre-flight for switches: warn, but never block, when Claude Code
+    /// doesn't look installed
+    pub fn warn_if_missing(&self) {
+        let problems = self.problems();
+        if problems.is_empty() {
+            return;
+        }
+        for problem in &problems {
+            eprintln!("{}", format!("⚠️  {}", problem).yellow());
+        }
+        eprintln!(
+            "{}",
+            "   Switching anyway; install Claude Code or pass --ignore-missing-claude".yellow()
+        );
+    }
+}
+
+/// First executable called `name` in a PATH-style list of directories
+pub fn find_on_path(name: &str, path_var: &OsStr) -> Option<PathBuf> {
+    let names: Vec<String> = if cfg!(windows) {
+        vec![format!("{}.exe", name), format!("{}.cmd", name)]
+    } else {
+        vec![name.to_string()]
+    };
+    std::env::split_paths(path_var)
+        .flat_map(|dir| names.iter().map(move |name| dir.join(name)))
+        .find(|candidate| is_executable(candidate))
+}
+
+fn is_executable(path: &Path) -> bool {
+    let Ok(metadata) = path.metadata() else {
+        return false;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        metadata.is_file()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn stub_binary(dir: &Path, mode: u32) {
+        let path = dir.join(CLAUDE_BINARY);
+        fs::write(&path, "#!/bin/sh\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+        }
+        #[cfg(not(unix))]
+        let _ = mode;
+    }
+
+    #[test]
+    fn test_finds_claude_on_stubbed_path() {
+        let empty = tempfile::tempdir().unwrap();
+        let bin = tempfile::tempdir().unwrap();
+        stub_binary(bin.path(), 0o755);
+        let path_var = std::env::join_paths([empty.path(), bin.path()]).unwrap();
+
+        let installation =
+            ClaudeInstallation::detect_in(Some(&path_var), Some(bin.path().to_path_buf()));
+        assert_eq!(installation.binary, Some(bin.path().join(CLAUDE_BINARY)));
+        assert!(installation.problems().is_empty());
+    }
+
+    #[test]
+    fn test_reports_missing_binary_and_config_dir() {
+        let empty = tempfile::tempdir().unwrap();
+        let path_var = std::env::join_paths([empty.path()]).unwrap();
+
+        let installation =
+            ClaudeInstallation::detect_in(Some(&path_var), Some(empty.path().join(".claude")));
+        assert!(installation.binary.is_none());
+        assert!(installation.config_dir.is_none());
+        assert_eq!(installation.problems().len(), 2);
+
+        assert!(ClaudeInstallation::detect_in(None, None).binary.is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_ignores_non_executable_files() {
+        let bin = tempfile::tempdir().unwrap();
+        stub_binary(bin.path(), 0o644);
+        let path_var = std::env::join_paths([bin.path()]).unwrap();
+
+        assert!(find_on_path(CLAUDE_BINARY, &path_var).is_none());
+    }
+}
@@ -1,66 +1,354 @@
 use crate::config::manager::ConfigManager;
+use crate::utils::error::AppError;
+use crate::utils::mask::mask_token;
+#[cfg(feature = "clipboard")]
+use crate::utils::Clipboard;
 use anyhow::Result;
 use colored::*;
-use std::io::{self, Write};
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::process::{Command, Stdio};
 
-pub struct TokenManager;
+/// Where saved tokens are read from and written to
+pub trait TokenStore {
+    fn load_saved_token(&self) -> Result<Option<String>>;
+    fn save_token(&self, token: &str) -> Result<()>;
+}
+
+impl TokenStore for ConfigManager {
+    fn load_saved_token(&self) -> Result<Option<String>> {
+        ConfigManager::load_saved_token(self)
+    }
+
+    fn save_token(&self, token: &str) -> Result<()> {
+        ConfigManager::save_token(self, token)
+    }
+}
+
+/// Where the token for a switch came from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenSource {
+    Env,
+    SavedFile,
+    Prompt,
+    Stdin,
+    Command,
+    #[cfg(feature = "clipboard")]
+    Clipboard,
+}
+
+impl TokenSource {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            TokenSource::Env => "Z_AI_AUTH_TOKEN environment variable",
+            TokenSource::SavedFile => "saved token file",
+            TokenSource::Prompt => "entered at the prompt",
+            TokenSource::Stdin => "stdin",
+            TokenSource::Command => "--token-command",
+            #[cfg(feature = "clipboard")]
+            TokenSource::Clipboard => "clipboard",
+        }
+    }
+}
+
+/// A token, where it came from, and whether it was saved on the way
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObtainedToken {
+    pub token: String,
+    pub source: TokenSource,
+    /// Newly written to the token file during this run
+    pub saved: bool,
+}
+
+impl ObtainedToken {
+    fn new(token: String, source: TokenSource) -> Self {
+        Self {
+            token,
+            source,
+            saved: false,
+        }
+    }
+
+    /// One line for the end of a switch, e.g. `stdin (newly saved)`
+    pub fn summary(&self) -> String {
+        if self.saved {
+            format!("{} (newly saved)", self.source.describe())
+        } else {
+            self.source.describe().to_string()
+        }
+    }
+}
+
+/// Answer to the "Save token for future use?" question
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SavePolicy {
+    #[default]
+    Ask,
+    Always,
+    Never,
+}
+
+pub struct TokenManager {
+    save_policy: SavePolicy,
+    interactive: bool,
+    notices_on_stderr: bool,
+}
 
 impl TokenManager {
-    pub fn new() -> Self {
-        Self
+    pub fn with_options(save_policy: SavePolicy, interactive: bool) -> Self {
+        Self {
+            save_policy,
+            interactive,
+            notices_on_stderr: false,
+        }
+    }
+
+    /// Report where the token came from on stderr, keeping stdout for output
+    /// meant for other programs (`--print-only`)
+    pub fn with_notices_on_stderr(mut self) -> Self {
+        self.notices_on_stderr = true;
+        self
     }
 
-    pub fn prompt_for_token(&self, config_manager: &ConfigManager) -> Result<String> {
-        // Check environment variable first
-        if let Ok(token) = std::env::var("Z_AI_AUTH_TOKEN") {
-            if !token.is_empty() {
-                println!("{}", "📌 Using token from Z_AI_AUTH_TOKEN environment variable".cyan());
-                return Ok(token);
+    fn notice(&self, message: ColoredString) {
+        if self.notices_on_stderr {
+            eprintln!("{}", message);
+        } else {
+            println!("{}", message);
+        }
+    }
+
+    pub fn prompt_for_token(&self, store: &dyn TokenStore) -> Result<ObtainedToken> {
+        let env_token = std::env::var("Z_AI_AUTH_TOKEN").ok();
+        self.prompt_for_token_from(store, env_token, &mut io::stdin().lock())
+    }
+
+    fn prompt_for_token_from(
+        &self,
+        store: &dyn TokenStore,
+        env_token: Option<String>,
+        input: &mut dyn BufRead,
+    ) -> Result<ObtainedToken> {
+        // Check environment variable first; set-but-blank is a mistake, not "unset"
+        if let Some(token) = env_token {
+            let token = token.trim();
+            if token.is_empty() {
+                return Err(AppError::Token(
+                    "Z_AI_AUTH_TOKEN is set but empty (unset it or provide a token)".to_string(),
+                )
+                .into());
             }
+            self.notice("📌 Using token from Z_AI_AUTH_TOKEN environment variable".cyan());
+            return Ok(ObtainedToken::new(token.to_string(), TokenSource::Env));
         }
 
         // Check if token file exists
-        if let Ok(Some(saved_token)) = config_manager.load_saved_token() {
-            println!("{}", "📌 Using token from saved token file".cyan());
-            return Ok(saved_token);
+        if let Ok(Some(saved_token)) = store.load_saved_token() {
+            self.notice("📌 Using token from saved token file".cyan());
+            return Ok(ObtainedToken::new(saved_token, TokenSource::SavedFile));
+        }
+
+        if !self.interactive {
+            return Err(AppError::Token(
+                "No API token found (set Z_AI_AUTH_TOKEN or save a token first)".to_string(),
+            )
+            .into());
         }
 
         // Prompt user for token
         println!("{}", "⚠️  No API token found".yellow());
+        self.read_prompted_token(store, input)
+    }
+
+    /// Ask for a replacement after the endpoint rejected the token in use
+    /// (`glm --verify`); asks whether to save it as usual
+    pub fn reenter_token(
+        &self,
+        store: &dyn TokenStore,
+        input: &mut dyn BufRead,
+    ) -> Result<ObtainedToken> {
+        self.read_prompted_token(store, input)
+    }
+
+    fn read_prompted_token(
+        &self,
+        store: &dyn TokenStore,
+        input: &mut dyn BufRead,
+    ) -> Result<ObtainedToken> {
         println!();
         println!("{}", "Please enter your Z.AI API token:".cyan());
         print!("> ");
         io::stdout().flush()?;
 
         let mut token = String::new();
-        io::stdin().read_line(&mut token)?;
+        input.read_line(&mut token)?;
         token = token.trim().to_string();
 
         if token.is_empty() {
-            return Err(anyhow::anyhow!("Token cannot be empty"));
+            return Err(AppError::Token("Token cannot be empty".to_string()).into());
         }
 
-        // Ask if user wants to save the token
-        println!("{}", "\nSave token for future use? (y/n)".cyan());
-        print!("> ");
-        io::stdout().flush()?;
+        let mut obtained = ObtainedToken::new(token, TokenSource::Prompt);
+        if self.should_save(input)? {
+            obtained.saved = Self::save(store, &obtained.token);
+        }
+
+        Ok(obtained)
+    }
+
+    fn should_save(&self, input: &mut dyn BufRead) -> Result<bool> {
+        match self.save_policy {
+            SavePolicy::Always => Ok(true),
+            SavePolicy::Never => Ok(false),
+            SavePolicy::Ask => {
+                // Ask if user wants to save the token
+                println!("{}", "\nSave token for future use? (y/n)".cyan());
+                print!("> ");
+                io::stdout().flush()?;
 
-        let mut answer = String::new();
-        io::stdin().read_line(&mut answer)?;
-        answer = answer.trim().to_lowercase();
+                let mut answer = String::new();
+                input.read_line(&mut answer)?;
+                answer = answer.trim().to_lowercase();
 
-        if answer == "y" || answer == "yes" {
-            match config_manager.save_token(&token) {
-                Ok(_) => println!("{}", "✅ Token saved successfully".green()),
-                Err(e) => println!("{}{}", "⚠️  Failed to save token: ".yellow(), e),
+                Ok(answer == "y" || answer == "yes")
             }
         }
+    }
+
+    /// Whether the token was saved; failing to save is only a warning
+    fn save(store: &dyn TokenStore, token: &str) -> bool {
+        match store.save_token(token) {
+            Ok(_) => {
+                println!("{}", "✅ Token saved successfully".green());
+                true
+            }
+            Err(e) => {
+                println!("{}{}", "⚠️  Failed to save token: ".yellow(), e);
+                false
+            }
+        }
+    }
+
+    /// Read a single-line token piped on stdin, skipping env, saved file and prompts.
+    ///
+    /// The token is only saved under an explicit `--save-token`.
+    pub fn read_token_from_stdin(&self, store: &dyn TokenStore) -> Result<ObtainedToken> {
+        let stdin = io::stdin();
+        if stdin.is_terminal() {
+            return Err(AppError::Token(
+                "--token-stdin requires the token to be piped (stdin is a terminal)".to_string(),
+            )
+            .into());
+        }
+        let token = Self::read_token_line(stdin.lock())?;
+        self.notice("📌 Using token from stdin".cyan());
+        let mut obtained = ObtainedToken::new(token, TokenSource::Stdin);
+        if self.save_policy == SavePolicy::Always {
+            obtained.saved = Self::save(store, &obtained.token);
+        }
+        Ok(obtained)
+    }
+
+    /// Run `command` through the shell and use its trimmed stdout as the
+    /// token, e.g. `op read op://vault/glm/token`. Its stderr and stdin stay
+    /// on the terminal so secret managers can ask to unlock. Like stdin, the
+    /// token is only saved under an explicit `--save-token`.
+    pub fn read_token_from_command(
+        &self,
+        command: &str,
+        store: &dyn TokenStore,
+    ) -> Result<ObtainedToken> {
+        let mut shell = if cfg!(windows) {
+            let mut shell = Command::new("cmd");
+            shell.arg("/C");
+            shell
+        } else {
+            let mut shell = Command::new("sh");
+            shell.arg("-c");
+            shell
+        };
+        let output = shell
+            .arg(command)
+            .stdin(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .output()
+            .map_err(|e| AppError::Token(format!("Failed to run token command: {}", e)))?;
+        if !output.status.success() {
+            let status = match output.status.code() {
+                Some(code) => format!("exit code {}", code),
+                None => "a signal".to_string(),
+            };
+            return Err(AppError::Token(format!("Token command failed with {}", status)).into());
+        }
+
+        let token = String::from_utf8(output.stdout)
+            .map_err(|_| AppError::Token("Token command printed invalid UTF-8".to_string()))?
+            .trim()
+            .to_string();
+        if token.is_empty() {
+            return Err(AppError::Token("Token command printed no token".to_string()).into());
+        }
+        self.notice("📌 Using token from --token-command".cyan());
+        let mut obtained = ObtainedToken::new(token, TokenSource::Command);
+        if self.save_policy == SavePolicy::Always {
+            obtained.saved = Self::save(store, &obtained.token);
+        }
+        Ok(obtained)
+    }
+
+    /// Use the trimmed clipboard contents as the token, e.g. one just copied
+    /// from the provider's dashboard. Asks whether to save it as a prompted
+    /// token would, except when non-interactive.
+    #[cfg(feature = "clipboard")]
+    pub fn read_token_from_clipboard(
+        &self,
+        clipboard: &mut dyn Clipboard,
+        store: &dyn TokenStore,
+        input: &mut dyn BufRead,
+    ) -> Result<ObtainedToken> {
+        let token = clipboard
+            .get_text()
+            .map_err(|e| AppError::Token(format!("Failed to read the clipboard: {}", e)))?
+            .trim()
+            .to_string();
+        if token.is_empty() {
+            return Err(AppError::Token("The clipboard holds no token".to_string()).into());
+        }
+        self.notice("📌 Using token from the clipboard".cyan());
+        let mut obtained = ObtainedToken::new(token, TokenSource::Clipboard);
+        let may_ask = self.interactive || self.save_policy != SavePolicy::Ask;
+        if may_ask && self.should_save(input)? {
+            obtained.saved = Self::save(store, &obtained.token);
+        }
+        Ok(obtained)
+    }
+
+    pub fn read_token_line(mut reader: impl BufRead) -> Result<String> {
+        let mut token = String::new();
+        reader.read_line(&mut token)?;
+        let token = token.trim().to_string();
 
+        if token.is_empty() {
+            return Err(AppError::Token("No token received on stdin".to_string()).into());
+        }
         Ok(token)
     }
 
-    pub fn clear_saved_token(config_manager: &ConfigManager) -> Result<()> {
+    /// Remove the saved token; with `dry_run`, only report whether there is
+    /// one and where
+    pub fn clear_saved_token(config_manager: &ConfigManager, dry_run: bool) -> Result<()> {
+        let path = config_manager.token_file().display();
         match config_manager.load_saved_token() {
+            Ok(Some(token)) if dry_run => {
+                println!(
+                    "{}{} ({})",
+                    "🔍 Would remove saved token ".cyan(),
+                    path,
+                    mask_token(&token)
+                );
+            }
+            Ok(None) if dry_run => {
+                println!("{}{}", "⚠️  No saved token found at ".yellow(), path);
+            }
             Ok(Some(_)) => {
                 config_manager.remove_saved_token()?;
                 println!("{}", "✅ Saved token removed successfully".green());
@@ -69,9 +357,274 @@ impl TokenManager {
                 println!("{}", "⚠️  No saved token found".yellow());
             }
             Err(e) => {
-                return Err(anyhow::anyhow!("Failed to check for saved token: {}", e));
+                return Err(
+                    AppError::Token(format!("Failed to check for saved token: {}", e)).into(),
+                );
             }
         }
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// A token store that returns `existing` and records what is saved
+    #[derive(Default)]
+    pub(crate) struct MockStore {
+        pub(crate) existing: Option<String>,
+        pub(crate) saved: RefCell<Vec<String>>,
+    }
+
+    impl TokenStore for MockStore {
+        fn load_saved_token(&self) -> Result<Option<String>> {
+            Ok(self.existing.clone())
+        }
+
+        fn save_token(&self, token: &str) -> Result<()> {
+            self.saved.borrow_mut().push(token.to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_empty_env_token_aborts() {
+        let store = MockStore::default();
+        let manager = TokenManager::with_options(SavePolicy::Never, true);
+
+        for env_token in ["", "   "] {
+            let error = manager
+                .prompt_for_token_from(
+                    &store,
+                    Some(env_token.to_string()),
+                    &mut io::Cursor::new("sk-typed\n".to_string()),
+                )
+                .unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<AppError>(),
+                Some(AppError::Token(_))
+            ));
+        }
+    }
+
+    fn prompt(manager: &TokenManager, store: &MockStore, input: &str) -> Result<String> {
+        manager
+            .prompt_for_token_from(store, None, &mut io::Cursor::new(input.to_string()))
+            .map(|obtained| obtained.token)
+    }
+
+    #[test]
+    fn test_token_source_is_reported() {
+        let manager = TokenManager::with_options(SavePolicy::Ask, true);
+        let obtain = |store: &MockStore, env: Option<&str>, input: &str| {
+            manager
+                .prompt_for_token_from(
+                    store,
+                    env.map(str::to_string),
+                    &mut io::Cursor::new(input.to_string()),
+                )
+                .unwrap()
+        };
+        let saved = MockStore {
+            existing: Some("sk-saved".to_string()),
+            ..Default::default()
+        };
+
+        let from_env = obtain(&saved, Some("sk-env"), "");
+        assert_eq!(
+            (from_env.token.as_str(), from_env.source, from_env.saved),
+            ("sk-env", TokenSource::Env, false)
+        );
+
+        let from_file = obtain(&saved, None, "");
+        assert_eq!(
+            (from_file.token.as_str(), from_file.source, from_file.saved),
+            ("sk-saved", TokenSource::SavedFile, false)
+        );
+        assert_eq!(from_file.summary(), "saved token file");
+
+        let typed_and_saved = obtain(&MockStore::default(), None, "sk-typed\ny\n");
+        assert_eq!(typed_and_saved.source, TokenSource::Prompt);
+        assert!(typed_and_saved.saved);
+        assert_eq!(
+            typed_and_saved.summary(),
+            "entered at the prompt (newly saved)"
+        );
+
+        let typed_only = obtain(&MockStore::default(), None, "sk-typed\nn\n");
+        assert_eq!(typed_only.source, TokenSource::Prompt);
+        assert!(!typed_only.saved);
+    }
+
+    #[test]
+    fn test_save_token_flag_saves_without_asking() {
+        let store = MockStore::default();
+        let manager = TokenManager::with_options(SavePolicy::Always, true);
+
+        // No answer line is provided, so asking would read an empty answer
+        assert_eq!(prompt(&manager, &store, "sk-typed\n").unwrap(), "sk-typed");
+        assert_eq!(*store.saved.borrow(), vec!["sk-typed".to_string()]);
+    }
+
+    #[test]
+    fn test_no_save_token_flag_skips_saving() {
+        let store = MockStore::default();
+        let manager = TokenManager::with_options(SavePolicy::Never, true);
+
+        assert_eq!(
+            prompt(&manager, &store, "sk-typed\ny\n").unwrap(),
+            "sk-typed"
+        );
+        assert!(store.saved.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_ask_policy_uses_answer() {
+        let store = MockStore::default();
+        let manager = TokenManager::with_options(SavePolicy::Ask, true);
+
+        prompt(&manager, &store, "sk-typed\nyes\n").unwrap();
+        assert_eq!(store.saved.borrow().len(), 1);
+    }
+
+    #[test]
+    fn test_non_interactive_never_prompts() {
+        let store = MockStore::default();
+        let manager = TokenManager::with_options(SavePolicy::Never, false);
+
+        assert!(prompt(&manager, &store, "sk-typed\n").is_err());
+        assert!(store.saved.borrow().is_empty());
+    }
+
+    #[cfg(unix)]
+    fn fake_command(dir: &std::path::Path, script: &str) -> String {
+        use std::os::unix::fs::PermissionsExt;
+        let path = dir.join("fake-op");
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        format!("{} read op://vault/glm/token", path.display())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_token_command_output_is_the_token() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = MockStore::default();
+        let command = fake_command(
+            dir.path(),
+            r#"[ "$2" = op://vault/glm/token ] && echo "  sk-from-op  ""#,
+        );
+
+        let obtained = TokenManager::with_options(SavePolicy::Ask, false)
+            .read_token_from_command(&command, &store)
+            .unwrap();
+        assert_eq!(
+            (obtained.token.as_str(), obtained.source, obtained.saved),
+            ("sk-from-op", TokenSource::Command, false)
+        );
+
+        TokenManager::with_options(SavePolicy::Always, false)
+            .read_token_from_command(&command, &store)
+            .unwrap();
+        assert_eq!(*store.saved.borrow(), vec!["sk-from-op".to_string()]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_token_command_failure_is_a_token_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = TokenManager::with_options(SavePolicy::Always, false);
+        let store = MockStore::default();
+
+        for (script, message) in [
+            ("echo sk-partial; exit 7", "exit code 7"),
+            ("true", "no token"),
+        ] {
+            let command = fake_command(dir.path(), script);
+            let err = manager
+                .read_token_from_command(&command, &store)
+                .unwrap_err();
+            match err.downcast_ref::<AppError>() {
+                Some(AppError::Token(text)) => {
+                    assert!(text.contains(message), "{}", text);
+                    assert!(!text.contains("sk-partial"));
+                }
+                other => panic!("unexpected error: {:?}", other),
+            }
+        }
+        assert!(store.saved.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_read_token_line_uses_first_line() {
+        let input = io::Cursor::new("sk-piped-token\nignored\n");
+        assert_eq!(
+            TokenManager::read_token_line(input).unwrap(),
+            "sk-piped-token"
+        );
+    }
+
+    #[test]
+    fn test_read_token_line_rejects_empty_input() {
+        assert!(TokenManager::read_token_line(io::Cursor::new("\n")).is_err());
+        assert!(TokenManager::read_token_line(io::Cursor::new("")).is_err());
+    }
+
+    #[test]
+    fn test_clear_token_dry_run_keeps_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = ConfigManager::with_config_dir(dir.path().to_path_buf());
+        manager.save_token("sk-keep-me").unwrap();
+
+        TokenManager::clear_saved_token(&manager, true).unwrap();
+        assert!(manager.token_file().exists());
+        assert_eq!(
+            manager.load_saved_token().unwrap().as_deref(),
+            Some("sk-keep-me")
+        );
+
+        TokenManager::clear_saved_token(&manager, false).unwrap();
+        assert!(!manager.token_file().exists());
+        // Nothing left to preview
+        TokenManager::clear_saved_token(&manager, true).unwrap();
+    }
+
+    #[cfg(feature = "clipboard")]
+    #[test]
+    fn test_clipboard_token_is_trimmed_and_asked_about() {
+        use crate::utils::clipboard::tests::MockClipboard;
+
+        let store = MockStore::default();
+        let mut clipboard = MockClipboard {
+            contents: Some("  sk-from-dashboard\n".to_string()),
+        };
+        let obtained = TokenManager::with_options(SavePolicy::Ask, true)
+            .read_token_from_clipboard(&mut clipboard, &store, &mut "y\n".as_bytes())
+            .unwrap();
+        assert_eq!(obtained.token, "sk-from-dashboard");
+        assert_eq!(obtained.source, TokenSource::Clipboard);
+        assert!(obtained.saved);
+        assert_eq!(*store.saved.borrow(), ["sk-from-dashboard"]);
+
+        // Non-interactive: never asks, so never saves
+        let store = MockStore::default();
+        let obtained = TokenManager::with_options(SavePolicy::Ask, false)
+            .read_token_from_clipboard(&mut clipboard, &store, &mut "y\n".as_bytes())
+            .unwrap();
+        assert!(!obtained.saved);
+        assert!(store.saved.borrow().is_empty());
+
+        let mut blank = MockClipboard {
+            contents: Some(" \n".to_string()),
+        };
+        let err = TokenManager::with_options(SavePolicy::Never, true)
+            .read_token_from_clipboard(&mut blank, &store, &mut "".as_bytes())
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AppError>(),
+            Some(AppError::Token(_))
+        ));
+    }
+}
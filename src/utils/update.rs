@@ -0,0 +1,290 @@
+use crate::utils::error::AppError;
+use anyhow::{Context, Result};
+use colored::*;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::time::Duration;
+
+const BINARY_NAME: &str = "claude-switch";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    #[serde(default)]
+    pub assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+/// Parse `v1.2.3` / `1.2.3` into a comparable tuple
+pub fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let version = version.trim().trim_start_matches('v');
+    // Ignore pre-release/build suffixes such as 1.2.3-rc1
+    let core = version.split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|part| part.parse::<u64>().ok());
+    let major = parts.next()??;
+    let minor = parts.next().unwrap_or(Some(0))?;
+    let patch = parts.next().unwrap_or(Some(0))?;
+    Some((major, minor, patch))
+}
+
+pub fn is_newer(latest: &str, current: &str) -> bool {
+    match (parse_version(latest), parse_version(current)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => false,
+    }
+}
+
+/// Release asset name for a platform, matching the names produced by `make build-all`
+pub fn asset_name_for(os: &str, arch: &str) -> Option<String> {
+    let os = match os {
+        "macos" => "darwin",
+        "linux" => "linux",
+        "windows" => "windows",
+        _ => return None,
+    };
+    let arch = match arch {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        _ => return None,
+    };
+    let suffix = if os == "windows" { ".exe" } else { "" };
+    Some(format!("{}-{}-{}{}", BINARY_NAME, os, arch, suffix))
+}
+
+pub fn select_asset<'a>(release: &'a Release, os: &str, arch: &str) -> Option<&'a ReleaseAsset> {
+    let name = asset_name_for(os, arch)?;
+    release.assets.iter().find(|asset| asset.name == name)
+}
+
+/// Checksum sidecar published next to a binary (`<asset>.sha256`)
+pub fn select_checksum_asset<'a>(
+    release: &'a Release,
+    asset: &ReleaseAsset,
+) -> Option<&'a ReleaseAsset> {
+    let name = format!("{}.sha256", asset.name);
+    release
+        .assets
+        .iter()
+        .find(|candidate| candidate.name == name)
+}
+
+/// Check `data` against the first hex token of a `sha256sum`-style file
+pub fn verify_checksum(data: &[u8], checksum_file: &str) -> Result<()> {
+    let expected = checksum_file
+        .split_whitespace()
+        .next()
+        .ok_or_else(|| AppError::Installation("Checksum file is empty".to_string()))?
+        .to_lowercase();
+
+    let actual: String = Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+
+    if actual != expected {
+        return Err(AppError::Installation(format!(
+            "Checksum mismatch (expected {}, got {})",
+            expected, actual
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+pub struct SelfUpdater {
+    current_version: String,
+    releases_url: String,
+}
+
+impl SelfUpdater {
+    pub fn new(current_version: &str) -> Self {
+        let repository = env!("CARGO_PKG_REPOSITORY").trim_end_matches('/');
+        let slug = repository.trim_start_matches("https://github.com/");
+        Self {
+            current_version: current_version.to_string(),
+            releases_url: format!("https://api.github.com/repos/{}/releases/latest", slug),
+        }
+    }
+
+    pub fn update(&self, check_only: bool) -> Result<()> {
+        println!("{}", "🔍 Checking for updates...".cyan());
+
+        let release = self.fetch_latest_release()?;
+        if !is_newer(&release.tag_name, &self.current_version) {
+            println!(
+                "{}{}",
+                "✅ Already up to date: v".green(),
+                self.current_version
+            );
+            return Ok(());
+        }
+
+        println!(
+            "{}v{} → {}",
+            "⬆️  Update available: ".cyan(),
+            self.current_version,
+            release.tag_name
+        );
+        if check_only {
+            return Ok(());
+        }
+
+        let asset =
+            select_asset(&release, env::consts::OS, env::consts::ARCH).ok_or_else(|| {
+                AppError::Installation(format!(
+                    "No release asset for {}-{}",
+                    env::consts::OS,
+                    env::consts::ARCH
+                ))
+            })?;
+        let checksum_asset = select_checksum_asset(&release, asset).ok_or_else(|| {
+            AppError::Installation(format!("No checksum published for {}", asset.name))
+        })?;
+
+        println!("{}{}", "📦 Downloading ".cyan(), asset.name);
+        let data = self.download(&asset.browser_download_url)?;
+        let checksum =
+            String::from_utf8_lossy(&self.download(&checksum_asset.browser_download_url)?)
+                .to_string();
+        verify_checksum(&data, &checksum)?;
+        println!("{}", "✅ Checksum verified".green());
+
+        let exec_path = env::current_exe()
+            .context("Failed to get executable path")?
+            .canonicalize()
+            .context("Failed to resolve executable path")?;
+        Self::replace_binary(&exec_path, &data)?;
+
+        println!(
+            "{}{}",
+            "🎉 Updated to ".green(),
+            release.tag_name.trim_start_matches('v')
+        );
+        Ok(())
+    }
+
+    fn agent() -> ureq::Agent {
+        ureq::AgentBuilder::new()
+            .timeout(Duration::from_secs(30))
+            .user_agent(concat!("claude-switch/", env!("CARGO_PKG_VERSION")))
+            .build()
+    }
+
+    fn fetch_latest_release(&self) -> Result<Release> {
+        Self::agent()
+            .get(&self.releases_url)
+            .set("Accept", "application/vnd.github+json")
+            .call()
+            .context("Failed to query latest release")?
+            .into_json::<Release>()
+            .context("Failed to parse release information")
+    }
+
+    fn download(&self, url: &str) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        Self::agent()
+            .get(url)
+            .call()
+            .with_context(|| format!("Failed to download {}", url))?
+            .into_reader()
+            .read_to_end(&mut data)
+            .with_context(|| format!("Failed to read {}", url))?;
+        Ok(data)
+    }
+
+    /// Write next to the installed binary, then rename over it
+    fn replace_binary(install_path: &Path, data: &[u8]) -> Result<()> {
+        let temp_path = install_path.with_extension("update-tmp");
+
+        fs::write(&temp_path, data).map_err(|e| {
+            AppError::Installation(format!(
+                "Failed to write {} (try running with sudo): {}",
+                temp_path.display(),
+                e
+            ))
+        })?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&temp_path, fs::Permissions::from_mode(0o755))?;
+        }
+
+        fs::rename(&temp_path, install_path).map_err(|e| {
+            let _ = fs::remove_file(&temp_path);
+            AppError::Installation(format!(
+                "Failed to replace {}: {}",
+                install_path.display(),
+                e
+            ))
+        })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RELEASE_JSON: &str = r#"{
+        "tag_name": "v2.3.0",
+        "assets": [
+            {"name": "claude-switch-linux-amd64", "browser_download_url": "https://example.com/linux-amd64"},
+            {"name": "claude-switch-linux-amd64.sha256", "browser_download_url": "https://example.com/linux-amd64.sha256"},
+            {"name": "claude-switch-darwin-arm64", "browser_download_url": "https://example.com/darwin-arm64"},
+            {"name": "claude-switch-windows-amd64.exe", "browser_download_url": "https://example.com/windows-amd64.exe"}
+        ]
+    }"#;
+
+    #[test]
+    fn test_version_compare() {
+        assert!(is_newer("v2.3.0", "2.2.0"));
+        assert!(is_newer("2.10.0", "2.9.9"));
+        assert!(!is_newer("v2.2.0", "2.2.0"));
+        assert!(!is_newer("v2.1.9", "2.2.0"));
+        assert!(!is_newer("nightly", "2.2.0"));
+        assert_eq!(parse_version("v3.0.1-rc1"), Some((3, 0, 1)));
+    }
+
+    #[test]
+    fn test_asset_selection() {
+        let release: Release = serde_json::from_str(RELEASE_JSON).unwrap();
+
+        let linux = select_asset(&release, "linux", "x86_64").unwrap();
+        assert_eq!(
+            linux.browser_download_url,
+            "https://example.com/linux-amd64"
+        );
+        assert_eq!(
+            select_checksum_asset(&release, linux).unwrap().name,
+            "claude-switch-linux-amd64.sha256"
+        );
+
+        let mac = select_asset(&release, "macos", "aarch64").unwrap();
+        assert_eq!(mac.name, "claude-switch-darwin-arm64");
+        assert!(select_checksum_asset(&release, mac).is_none());
+
+        let windows = select_asset(&release, "windows", "x86_64").unwrap();
+        assert_eq!(windows.name, "claude-switch-windows-amd64.exe");
+
+        assert!(select_asset(&release, "linux", "aarch64").is_none());
+        assert!(select_asset(&release, "freebsd", "x86_64").is_none());
+    }
+
+    #[test]
+    fn test_verify_checksum() {
+        let digest = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        assert!(verify_checksum(b"hello", &format!("{}  claude-switch\n", digest)).is_ok());
+        assert!(verify_checksum(b"tampered", digest).is_err());
+        assert!(verify_checksum(b"hello", "").is_err());
+    }
+}
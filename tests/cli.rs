@@ -0,0 +1,1228 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+fn run_with_stdin(home: &Path, args: &[&str], stdin: &str) -> Output {
+    run_in_dir(home, home, args, stdin)
+}
+
+fn run_in_dir(home: &Path, cwd: &Path, args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_claude-switch"))
+        .args(args)
+        .current_dir(cwd)
+        .env("HOME", home)
+        .env_remove("Z_AI_AUTH_TOKEN")
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("CLAUDE_BACKUP_DIR")
+        .env_remove("CLAUDE_CONFIG_DIR")
+        .env_remove("CLAUDE_TOKEN_FILE")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run claude-switch");
+    // The command may exit without reading stdin
+    if let Err(e) = child.stdin.take().unwrap().write_all(stdin.as_bytes()) {
+        assert_eq!(e.kind(), std::io::ErrorKind::BrokenPipe);
+    }
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_glm_token_stdin_uses_piped_token_without_prompts() {
+    let home = tempfile::tempdir().unwrap();
+    // A saved token must be ignored in favour of stdin
+    fs::create_dir_all(home.path().join(".claude")).unwrap();
+    fs::write(home.path().join(".claude/.claude-switch-token"), "sk-saved").unwrap();
+
+    let output = run_with_stdin(home.path(), &["glm", "--token-stdin"], "sk-piped\n");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Using token from stdin"));
+    assert!(stdout.contains("Token: stdin\n"));
+    assert!(!stdout.contains("Please enter"));
+    assert!(!stdout.contains("Save token"));
+
+    let settings = fs::read_to_string(home.path().join(".claude/settings.json")).unwrap();
+    assert!(settings.contains("sk-piped"));
+    assert!(!settings.contains("sk-saved"));
+}
+
+#[test]
+fn test_glm_token_stdin_rejects_empty_input() {
+    let home = tempfile::tempdir().unwrap();
+
+    let output = run_with_stdin(home.path(), &["glm", "--token-stdin"], "");
+    assert_eq!(output.status.code(), Some(3));
+    assert!(!home.path().join(".claude/settings.json").exists());
+}
+
+#[test]
+fn test_output_json_status_for_glm_config() {
+    let home = tempfile::tempdir().unwrap();
+    fs::create_dir_all(home.path().join(".claude")).unwrap();
+    fs::write(
+        home.path().join(".claude/settings.json"),
+        r#"{"env": {
+            "ANTHROPIC_AUTH_TOKEN": "sk-1234567890abcdef",
+            "ANTHROPIC_BASE_URL": "https://api.z.ai/api/anthropic",
+            "ANTHROPIC_DEFAULT_OPUS_MODEL": "GLM-4.7"
+        }}"#,
+    )
+    .unwrap();
+
+    let output = run_with_stdin(home.path(), &["--output", "json", "status"], "");
+    assert!(output.status.success());
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    // JSON_SCHEMA_VERSION
+    assert_eq!(json["schema_version"], 1);
+    assert_eq!(json["provider"], "glm");
+    assert_eq!(json["base_url"], "https://api.z.ai/api/anthropic");
+    assert_eq!(json["models"]["opus"], "GLM-4.7");
+    assert_eq!(json["auth_token"], "sk-1...cdef");
+    assert_eq!(json["backup"]["status"], "missing");
+}
+
+#[test]
+fn test_status_oneline_prints_a_single_plain_line() {
+    let home = tempfile::tempdir().unwrap();
+    fs::create_dir_all(home.path().join(".claude")).unwrap();
+    fs::write(
+        home.path().join(".claude/settings.json"),
+        r#"{"env": {
+            "ANTHROPIC_AUTH_TOKEN": "sk-1234567890abcdef",
+            "ANTHROPIC_BASE_URL": "https://api.z.ai/api/anthropic"
+        }}"#,
+    )
+    .unwrap();
+
+    let output = run_with_stdin(home.path(), &["status", "--oneline"], "");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "provider=glm url=api.z.ai token=sk-1...cdef backup=no\n"
+    );
+}
+
+#[test]
+fn test_provider_order_flips_ambiguous_status() {
+    let home = tempfile::tempdir().unwrap();
+    let claude_dir = home.path().join(".claude");
+    fs::create_dir_all(&claude_dir).unwrap();
+    fs::write(
+        claude_dir.join("settings.json"),
+        r#"{"env": {
+            "ANTHROPIC_BASE_URL": "https://proxy.example.com/glm",
+            "ANTHROPIC_DEFAULT_OPUS_MODEL": "glm-4.6"
+        }}"#,
+    )
+    .unwrap();
+    let status = |home: &Path| {
+        let output = run_with_stdin(home, &["--output", "json", "status"], "");
+        assert!(output.status.success());
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()
+    };
+
+    let json = status(home.path());
+    assert_eq!(json["provider"], "custom");
+    assert_eq!(json["detected_by"], "custom_base_url");
+
+    fs::write(
+        claude_dir.join("claude-switch.toml"),
+        "provider_order = [\"glm\", \"custom\"]\n",
+    )
+    .unwrap();
+    let json = status(home.path());
+    assert_eq!(json["provider"], "glm");
+    assert_eq!(json["detected_by"], "model_name");
+    assert_eq!(
+        json["detection_tiebreak"],
+        "provider_order ranks glm before custom"
+    );
+}
+
+#[test]
+fn test_doctor_resolves_both_auth_vars() {
+    let home = tempfile::tempdir().unwrap();
+    let claude_dir = home.path().join(".claude");
+    fs::create_dir_all(&claude_dir).unwrap();
+    fs::write(
+        claude_dir.join("settings.json"),
+        r#"{"env": {
+            "ANTHROPIC_AUTH_TOKEN": "sk-ant-oat01-token",
+            "ANTHROPIC_API_KEY": "sk-ant-api03-key"
+        }}"#,
+    )
+    .unwrap();
+
+    let output = run_with_stdin(home.path(), &["--output", "json", "status"], "");
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(json["detection_warnings"][0]
+        .as_str()
+        .unwrap()
+        .contains("both set"));
+
+    let output = run_with_stdin(home.path(), &["doctor", "--ignore-missing-claude"], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("--prefer auth"));
+
+    let output = run_with_stdin(
+        home.path(),
+        &["doctor", "--prefer", "auth", "--ignore-missing-claude"],
+        "",
+    );
+    assert!(output.status.success());
+    let settings = fs::read_to_string(claude_dir.join("settings.json")).unwrap();
+    assert!(settings.contains("sk-ant-oat01-token"));
+    assert!(!settings.contains("ANTHROPIC_API_KEY"));
+}
+
+#[test]
+fn test_status_all_backups_json() {
+    let home = tempfile::tempdir().unwrap();
+    let claude_dir = home.path().join(".claude");
+    fs::create_dir_all(&claude_dir).unwrap();
+    fs::write(
+        claude_dir.join("settings.json"),
+        r#"{"env": {"ANTHROPIC_AUTH_TOKEN": "sk-ant-api03-abcdef"}}"#,
+    )
+    .unwrap();
+    assert!(run_with_stdin(
+        home.path(),
+        &["glm", "--token-stdin"],
+        "sk-glm-1234567890\n"
+    )
+    .status
+    .success());
+
+    let output = run_with_stdin(
+        home.path(),
+        &["--output", "json", "status", "--all-backups"],
+        "",
+    );
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let backups = json["backups"].as_array().unwrap();
+    assert_eq!(backups.len(), 1);
+    assert_eq!(backups[0]["provider"], "anthropic");
+    assert_eq!(backups[0]["token_expired"], false);
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("sk-ant-api03-abcdef"));
+}
+
+#[test]
+fn test_tar_bundle_round_trip() {
+    let source = tempfile::tempdir().unwrap();
+    fs::create_dir_all(source.path().join(".claude")).unwrap();
+    fs::write(
+        source.path().join(".claude/settings.json"),
+        r#"{"env": {"ANTHROPIC_AUTH_TOKEN": "sk-1234567890abcdef", "ANTHROPIC_BASE_URL": "https://api.z.ai/api/anthropic"}}"#,
+    )
+    .unwrap();
+    let archive = source.path().join("setup.tar.gz");
+
+    let output = run_with_stdin(
+        source.path(),
+        &[
+            "backup",
+            "--format",
+            "tar",
+            "--archive",
+            archive.to_str().unwrap(),
+        ],
+        "",
+    );
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("credentials"));
+
+    let target = tempfile::tempdir().unwrap();
+    let output = run_with_stdin(
+        target.path(),
+        &["restore", "--from", archive.to_str().unwrap()],
+        "",
+    );
+    assert!(output.status.success());
+    let restored: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(target.path().join(".claude/settings.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        restored["env"]["ANTHROPIC_AUTH_TOKEN"],
+        "sk-1234567890abcdef"
+    );
+}
+
+#[test]
+fn test_switches_preserve_top_level_settings() {
+    let home = tempfile::tempdir().unwrap();
+    let settings_path = home.path().join(".claude/settings.json");
+    fs::create_dir_all(home.path().join(".claude")).unwrap();
+    fs::write(
+        &settings_path,
+        r#"{
+            "$schema": "https://json.schemastore.org/claude-code-settings.json",
+            "permissions": {"allow": ["Bash(npm run test)"], "deny": ["Read(./.env)"]},
+            "includeCoAuthoredBy": false,
+            "env": {"ANTHROPIC_AUTH_TOKEN": "web-login-token"}
+        }"#,
+    )
+    .unwrap();
+    let read_extras = || {
+        let mut settings: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&settings_path).unwrap()).unwrap();
+        settings.as_object_mut().unwrap().remove("env");
+        settings
+    };
+    let original = read_extras();
+
+    let output = run_with_stdin(home.path(), &["glm", "--token-stdin"], "sk-piped\n");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("Preserving settings not managed by claude-switch"));
+    assert_eq!(read_extras(), original);
+
+    let output = run_with_stdin(home.path(), &["anthropic"], "");
+    assert!(output.status.success());
+    assert_eq!(read_extras(), original);
+}
+
+#[test]
+fn test_backup_warns_only_when_live_config_diverged() {
+    let home = tempfile::tempdir().unwrap();
+    let settings_path = home.path().join(".claude/settings.json");
+    fs::create_dir_all(home.path().join(".claude")).unwrap();
+    fs::write(
+        &settings_path,
+        r#"{"env": {"ANTHROPIC_AUTH_TOKEN": "web-token-1111111111"}}"#,
+    )
+    .unwrap();
+
+    assert!(run_with_stdin(home.path(), &["backup"], "")
+        .status
+        .success());
+
+    // Matching backup: preserved silently
+    let output = run_with_stdin(home.path(), &["backup"], "");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("preserving configuration"));
+    assert!(!stdout.contains("Backup is older"));
+
+    fs::write(
+        &settings_path,
+        r#"{"env": {"ANTHROPIC_AUTH_TOKEN": "web-token-2222222222"}}"#,
+    )
+    .unwrap();
+    let output = run_with_stdin(home.path(), &["backup"], "");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Backup is older"));
+    assert!(stdout.contains("~ ANTHROPIC_AUTH_TOKEN: web-...1111 → web-...2222"));
+    assert!(stdout.contains("--force-backup"));
+    assert!(!stdout.contains("web-token-2222222222"));
+}
+
+#[test]
+fn test_profile_resolution_tiers() {
+    let home = tempfile::tempdir().unwrap();
+    let project = tempfile::tempdir().unwrap();
+    fs::write(project.path().join(".claude-switch"), "project-profile\n").unwrap();
+    let glm = ["glm", "--token-stdin"];
+
+    // Global: no flag, no project file
+    assert!(run_with_stdin(home.path(), &glm, "sk-1\n").status.success());
+    assert!(home.path().join(".claude/settings.json").is_file());
+
+    // Project file
+    assert!(run_in_dir(home.path(), project.path(), &glm, "sk-2\n")
+        .status
+        .success());
+    assert!(home
+        .path()
+        .join(".claude/profiles/project-profile/settings.json")
+        .is_file());
+
+    // Explicit flag beats the project file
+    let args = ["-p", "flagged", "glm", "--token-stdin"];
+    assert!(run_in_dir(home.path(), project.path(), &args, "sk-3\n")
+        .status
+        .success());
+    let flagged = home.path().join(".claude/profiles/flagged/settings.json");
+    assert!(fs::read_to_string(flagged).unwrap().contains("sk-3"));
+
+    let output = run_in_dir(home.path(), project.path(), &["status"], "");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Profile: project-profile"));
+}
+
+#[test]
+fn test_verify_token_strict_mismatch() {
+    let home = tempfile::tempdir().unwrap();
+
+    let output = run_with_stdin(
+        home.path(),
+        &["verify-token", "--for", "glm"],
+        "sk-test-123\n",
+    );
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Looks valid for glm"));
+
+    let args = ["verify-token", "--for", "anthropic", "--strict"];
+    let output = run_with_stdin(home.path(), &args, "sk-test-123\n");
+    assert_eq!(output.status.code(), Some(3));
+    assert!(!home.path().join(".claude/settings.json").exists());
+}
+
+#[test]
+fn test_merge_env_on_switch() {
+    let home = tempfile::tempdir().unwrap();
+    let settings = home.path().join(".claude/settings.json");
+
+    let args = [
+        "glm",
+        "--token-stdin",
+        "--merge-env",
+        "HTTPS_PROXY=http://proxy:3128",
+    ];
+    assert!(run_with_stdin(home.path(), &args, "sk-merge\n")
+        .status
+        .success());
+    let config: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&settings).unwrap()).unwrap();
+    assert_eq!(config["env"]["HTTPS_PROXY"], "http://proxy:3128");
+    assert_eq!(config["env"]["ANTHROPIC_AUTH_TOKEN"], "sk-merge");
+
+    // Explicit values win over provider keys; several can be given
+    let args = [
+        "glm",
+        "--merge-env",
+        "API_TIMEOUT_MS=1000",
+        "--merge-env",
+        "DEBUG=1",
+    ];
+    assert!(run_with_stdin(home.path(), &args, "").status.success());
+    let config: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&settings).unwrap()).unwrap();
+    assert_eq!(config["env"]["API_TIMEOUT_MS"], "1000");
+    assert_eq!(config["env"]["DEBUG"], "1");
+    assert_eq!(config["env"]["HTTPS_PROXY"], "http://proxy:3128");
+}
+
+#[test]
+fn test_merge_env_rejects_bad_input() {
+    let home = tempfile::tempdir().unwrap();
+
+    let output = run_with_stdin(home.path(), &["glm", "--merge-env", "NOEQUALS"], "");
+    assert_eq!(output.status.code(), Some(64));
+
+    let args = ["anthropic", "--merge-env", "A=1", "--merge-env", "A=2"];
+    let output = run_with_stdin(home.path(), &args, "");
+    assert_eq!(output.status.code(), Some(2));
+
+    let args = [
+        "anthropic",
+        "--merge-env",
+        "A=1",
+        "--merge-env",
+        "A=2",
+        "--last-wins",
+    ];
+    assert!(run_with_stdin(home.path(), &args, "").status.success());
+}
+
+#[test]
+fn test_glm_no_backup_skips_backup() {
+    let home = tempfile::tempdir().unwrap();
+    let claude_dir = home.path().join(".claude");
+    fs::create_dir_all(&claude_dir).unwrap();
+    fs::write(
+        claude_dir.join("settings.json"),
+        r#"{"env": {"ANTHROPIC_AUTH_TOKEN": "anthropic-web-token"}}"#,
+    )
+    .unwrap();
+
+    let args = ["glm", "--token-stdin", "--no-backup"];
+    let output = run_with_stdin(home.path(), &args, "sk-nobackup\n");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("not backed up"));
+    assert!(!claude_dir.join("settings.json.backup").exists());
+    assert!(!claude_dir.join("backups").exists());
+
+    let args = ["glm", "--no-backup", "--force-backup"];
+    assert_eq!(
+        run_with_stdin(home.path(), &args, "").status.code(),
+        Some(64)
+    );
+}
+
+#[test]
+fn test_print_env_output_is_evaluable() {
+    let home = tempfile::tempdir().unwrap();
+    fs::create_dir_all(home.path().join(".claude")).unwrap();
+    fs::write(
+        home.path().join(".claude/settings.json"),
+        r#"{"env": {
+            "ANTHROPIC_AUTH_TOKEN": "sk-1234567890abcdef",
+            "ANTHROPIC_BASE_URL": "https://api.z.ai/api/anthropic",
+            "NOTE": "two words, it's $HOME"
+        }}"#,
+    )
+    .unwrap();
+
+    let output = run_with_stdin(home.path(), &["print-env"], "");
+    assert!(output.status.success());
+    let exports = String::from_utf8(output.stdout).unwrap();
+    assert!(exports.contains("export NOTE='two words, it'\\''s $HOME'"));
+    assert!(!exports.contains("sk-1234567890abcdef"));
+
+    let output = run_with_stdin(home.path(), &["print-env", "--include-secrets"], "");
+    let exports = String::from_utf8(output.stdout).unwrap();
+    let shell = Command::new("sh")
+        .args([
+            "-c",
+            r#"eval "$1" && printf '%s\n%s\n%s' "$NOTE" "$ANTHROPIC_BASE_URL" "$ANTHROPIC_AUTH_TOKEN""#,
+            "sh",
+            &exports,
+        ])
+        .output()
+        .unwrap();
+    assert!(shell.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&shell.stdout),
+        "two words, it's $HOME\nhttps://api.z.ai/api/anthropic\nsk-1234567890abcdef"
+    );
+}
+
+#[test]
+fn test_switch_is_recorded_in_history() {
+    let home = tempfile::tempdir().unwrap();
+
+    let output = run_with_stdin(home.path(), &["glm", "--token-stdin"], "sk-history\n");
+    assert!(output.status.success());
+
+    let output = run_with_stdin(
+        home.path(),
+        &["--output", "json", "history", "--since", "1h"],
+        "",
+    );
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["entries"].as_array().unwrap().len(), 1);
+    assert_eq!(json["entries"][0]["provider"], "glm");
+}
+
+#[test]
+fn test_history_rejects_invalid_since() {
+    let home = tempfile::tempdir().unwrap();
+
+    let output = run_with_stdin(home.path(), &["history", "--since", "7y"], "");
+    assert_eq!(output.status.code(), Some(64));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid duration or date"));
+}
+
+#[test]
+fn test_reset_without_confirm_refuses_when_piped() {
+    let home = tempfile::tempdir().unwrap();
+    fs::create_dir_all(home.path().join(".claude")).unwrap();
+    fs::write(home.path().join(".claude/settings.json"), "{}").unwrap();
+
+    let output = run_with_stdin(home.path(), &["reset"], "y\n");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(home.path().join(".claude/settings.json").exists());
+
+    let output = run_with_stdin(home.path(), &["reset", "--confirm"], "");
+    assert!(output.status.success());
+    assert!(!home.path().join(".claude/settings.json").exists());
+}
+
+#[test]
+fn test_status_show_token_needs_force_without_terminal() {
+    let home = tempfile::tempdir().unwrap();
+    fs::create_dir_all(home.path().join(".claude")).unwrap();
+    fs::write(
+        home.path().join(".claude/settings.json"),
+        r#"{"env": {
+            "ANTHROPIC_AUTH_TOKEN": "sk-1234567890abcdef",
+            "ANTHROPIC_BASE_URL": "https://api.z.ai/api/anthropic"
+        }}"#,
+    )
+    .unwrap();
+
+    for args in [&["status"][..], &["status", "--show-token"]] {
+        let output = run_with_stdin(home.path(), args, "y\n");
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("sk-1...cdef"));
+        assert!(!stdout.contains("sk-1234567890abcdef"));
+    }
+
+    // --force still wants the answer, even from a pipe
+    let output = run_with_stdin(home.path(), &["status", "--show-token", "--force"], "");
+    assert!(output.status.success());
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("sk-1234567890abcdef"));
+
+    let output = run_with_stdin(home.path(), &["status", "--show-token", "--force"], "y\n");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("sk-1234567890abcdef"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("scrollback"));
+}
+
+/// Runs with `CLAUDE_CONFIG_DIR` pointing at `config_dir` and `HOME` elsewhere
+fn run_in_config_dir(
+    home: &Path,
+    config_dir: &Path,
+    args: &[&str],
+    envs: &[(&str, &str)],
+) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_claude-switch"))
+        .args(args)
+        .current_dir(home)
+        .env("HOME", home)
+        .env("CLAUDE_CONFIG_DIR", config_dir)
+        .env_remove("Z_AI_AUTH_TOKEN")
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("CLAUDE_BACKUP_DIR")
+        .env_remove("CLAUDE_TOKEN_FILE")
+        .envs(envs.iter().copied())
+        .stdin(Stdio::null())
+        .output()
+        .expect("failed to run claude-switch")
+}
+
+#[test]
+fn test_end_to_end_glm_round_trip_in_config_dir() {
+    let home = tempfile::tempdir().unwrap();
+    let config_dir = tempfile::tempdir().unwrap();
+    let run = |args: &[&str], envs: &[(&str, &str)]| {
+        let output = run_in_config_dir(home.path(), config_dir.path(), args, envs);
+        assert!(
+            output.status.success(),
+            "{:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let stdout = run(&["status"], &[]);
+    assert!(stdout.contains("No configuration found"));
+
+    fs::write(
+        config_dir.path().join("settings.json"),
+        r#"{"env": {"ANTHROPIC_AUTH_TOKEN": "anthropic-web-token-0001"}, "model": "opus"}"#,
+    )
+    .unwrap();
+    let stdout = run(&["status"], &[]);
+    assert!(stdout.contains("Provider: Anthropic"));
+
+    let stdout = run(
+        &["glm", "--non-interactive"],
+        &[("Z_AI_AUTH_TOKEN", "sk-e2e-token-1234")],
+    );
+    assert!(stdout.contains("GLM configuration applied successfully"));
+    assert!(config_dir.path().join("settings.json.backup").exists());
+
+    let stdout = run(&["status"], &[]);
+    assert!(stdout.contains("Provider: GLM"));
+    assert!(stdout.contains("sk-e...1234"));
+    assert!(stdout.contains("Backup: Available (Anthropic)"));
+
+    let stdout = run(&["anthropic"], &[]);
+    assert!(stdout.contains("Anthropic configuration restored from backup"));
+
+    let stdout = run(&["status"], &[]);
+    assert!(stdout.contains("Provider: Anthropic"));
+    let settings = fs::read_to_string(config_dir.path().join("settings.json")).unwrap();
+    assert!(settings.contains("anthropic-web-token-0001"));
+    assert!(settings.contains("\"model\": \"opus\""));
+    assert!(!settings.contains("z.ai"));
+
+    // Nothing leaked into the default location
+    assert!(!home.path().join(".claude").exists());
+}
+
+#[test]
+fn test_status_field_prints_single_value() {
+    let home = tempfile::tempdir().unwrap();
+    fs::create_dir_all(home.path().join(".claude")).unwrap();
+    fs::write(
+        home.path().join(".claude/settings.json"),
+        r#"{"env": {
+            "ANTHROPIC_AUTH_TOKEN": "sk-1234567890abcdef",
+            "ANTHROPIC_BASE_URL": "https://api.z.ai/api/anthropic"
+        }}"#,
+    )
+    .unwrap();
+
+    let output = run_with_stdin(home.path(), &["status", "--field", "base_url"], "");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "https://api.z.ai/api/anthropic\n"
+    );
+
+    let output = run_with_stdin(home.path(), &["status", "--field", "password"], "");
+    assert_eq!(output.status.code(), Some(64));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_explain_describes_glm_switch_steps_in_order() {
+    let home = tempfile::tempdir().unwrap();
+    let claude_dir = home.path().join(".claude");
+    fs::create_dir_all(&claude_dir).unwrap();
+    fs::write(
+        claude_dir.join("settings.json"),
+        r#"{"env": {"ANTHROPIC_AUTH_TOKEN": "anthropic-web-token"}}"#,
+    )
+    .unwrap();
+
+    let output = run_with_stdin(
+        home.path(),
+        &["--explain", "glm", "--token-stdin"],
+        "sk-explained\n",
+    );
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    let steps = [
+        "Reading the current Claude Code settings",
+        "The current settings use Anthropic",
+        "Getting your GLM API token",
+        "Writing the Z.AI base URL",
+    ];
+    let positions: Vec<usize> = steps
+        .iter()
+        .map(|step| {
+            stdout
+                .find(step)
+                .unwrap_or_else(|| panic!("missing: {}", step))
+        })
+        .collect();
+    assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(stdout.contains(&format!(
+        "File: {}",
+        claude_dir.join("settings.json.backup").display()
+    )));
+
+    // Without --explain the switch stays terse
+    let output = run_with_stdin(home.path(), &["glm", "--token-stdin"], "sk-explained\n");
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("Reading the current"));
+
+    // The backup step is only explained when a backup is written
+    assert!(run_with_stdin(home.path(), &["anthropic"], "")
+        .status
+        .success());
+    let output = run_with_stdin(
+        home.path(),
+        &["--explain", "glm", "--token-stdin"],
+        "sk-explained\n",
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Existing Anthropic backup found"),
+        "{}",
+        stdout
+    );
+    assert!(
+        !stdout.contains("The current settings use Anthropic"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn test_status_warns_about_settings_local_override() {
+    let home = tempfile::tempdir().unwrap();
+    let claude_dir = home.path().join(".claude");
+    fs::create_dir_all(&claude_dir).unwrap();
+    fs::write(
+        claude_dir.join("settings.json"),
+        r#"{"env": {"ANTHROPIC_AUTH_TOKEN": "anthropic-web-token"}}"#,
+    )
+    .unwrap();
+    fs::write(
+        claude_dir.join("settings.local.json"),
+        r#"{"env": {"ANTHROPIC_BASE_URL": "https://api.z.ai/api/anthropic"}, "model": "opus"}"#,
+    )
+    .unwrap();
+
+    let output = run_with_stdin(home.path(), &["status"], "");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("settings.local.json exists and may supersede"));
+    assert!(stdout.contains("Overridden keys: env.ANTHROPIC_BASE_URL, model"));
+
+    let output = run_with_stdin(home.path(), &["--output", "json", "status"], "");
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        report["local_override"]["keys"],
+        serde_json::json!(["env.ANTHROPIC_BASE_URL", "model"])
+    );
+}
+
+#[test]
+fn test_token_file_flag_and_env_override_saved_token_location() {
+    let home = tempfile::tempdir().unwrap();
+    let token_file = home.path().join("project/.glm-token");
+    let token_arg = token_file.to_str().unwrap();
+
+    let args = [
+        "--token-file",
+        token_arg,
+        "glm",
+        "--token-stdin",
+        "--save-token",
+    ];
+    assert!(run_with_stdin(home.path(), &args, "sk-project\n")
+        .status
+        .success());
+    assert_eq!(fs::read_to_string(&token_file).unwrap(), "sk-project");
+    assert!(!home.path().join(".claude/.claude-switch-token").exists());
+
+    // The env var is read the same way, e.g. by a non-interactive switch back to GLM
+    assert!(run_with_stdin(home.path(), &["anthropic"], "")
+        .status
+        .success());
+    let output = run_in_config_dir(
+        home.path(),
+        &home.path().join(".claude"),
+        &["glm", "--non-interactive"],
+        &[("CLAUDE_TOKEN_FILE", token_arg)],
+    );
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("saved token file"));
+
+    let args = ["--token-file", token_arg, "clear-token", "--dry-run"];
+    let output = run_with_stdin(home.path(), &args, "");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains(token_arg));
+    assert!(token_file.exists());
+
+    let args = ["--token-file", token_arg, "clear-token"];
+    assert!(run_with_stdin(home.path(), &args, "").status.success());
+    assert!(!token_file.exists());
+}
+
+#[test]
+fn test_permissions_block_is_byte_preserved_across_switches() {
+    let home = tempfile::tempdir().unwrap();
+    let claude_dir = home.path().join(".claude");
+    fs::create_dir_all(&claude_dir).unwrap();
+    // Keys deliberately out of alphabetical order
+    let permissions = r#"  "permissions": {
+    "defaultMode": "acceptEdits",
+    "allow": [
+      "Bash(npm run lint)",
+      "Bash(npm run test:*)",
+      "Read(~/.zshrc)"
+    ],
+    "deny": [
+      "Bash(curl:*)",
+      "Read(./.env)",
+      "Read(./secrets/**)"
+    ],
+    "additionalDirectories": [
+      "../docs/"
+    ]
+  }"#;
+    fs::write(
+        claude_dir.join("settings.json"),
+        format!(
+            "{{\n  \"env\": {{\n    \"ANTHROPIC_AUTH_TOKEN\": \"anthropic-web-token\"\n  }},\n{}\n}}",
+            permissions
+        ),
+    )
+    .unwrap();
+
+    let output = run_with_stdin(home.path(), &["glm", "--token-stdin"], "sk-perms\n");
+    assert!(output.status.success());
+    let settings = fs::read_to_string(claude_dir.join("settings.json")).unwrap();
+    assert!(settings.contains("sk-perms"));
+    assert!(settings.contains(permissions), "{}", settings);
+
+    assert!(run_with_stdin(home.path(), &["anthropic"], "")
+        .status
+        .success());
+    let settings = fs::read_to_string(claude_dir.join("settings.json")).unwrap();
+    assert!(settings.contains("anthropic-web-token"));
+    assert!(settings.contains(permissions), "{}", settings);
+}
+
+#[test]
+fn test_timeout_accepts_human_durations() {
+    let home = tempfile::tempdir().unwrap();
+    let args = ["glm", "--token-stdin", "--timeout", "5m"];
+    assert!(run_with_stdin(home.path(), &args, "sk-timeout\n")
+        .status
+        .success());
+    let settings = fs::read_to_string(home.path().join(".claude/settings.json")).unwrap();
+    assert!(settings.contains(r#""API_TIMEOUT_MS": "300000""#));
+
+    let args = ["glm", "--token-stdin", "--timeout-ms", "5x"];
+    let output = run_with_stdin(home.path(), &args, "sk-timeout\n");
+    assert_eq!(output.status.code(), Some(64));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid timeout"));
+}
+
+#[test]
+fn test_status_diff_backup_exit_codes() {
+    let home = tempfile::tempdir().unwrap();
+    let claude_dir = home.path().join(".claude");
+    fs::create_dir_all(&claude_dir).unwrap();
+    let settings = claude_dir.join("settings.json");
+    fs::write(
+        &settings,
+        r#"{"env": {"ANTHROPIC_AUTH_TOKEN": "anthropic-web-token"}}"#,
+    )
+    .unwrap();
+
+    // No backup yet: nothing to differ from
+    let output = run_with_stdin(home.path(), &["status", "--diff-backup"], "");
+    assert_eq!(output.status.code(), Some(0));
+
+    assert!(run_with_stdin(home.path(), &["backup"], "")
+        .status
+        .success());
+    let output = run_with_stdin(home.path(), &["status", "--diff-backup"], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("matches the backup"));
+
+    fs::write(
+        &settings,
+        r#"{"env": {"ANTHROPIC_AUTH_TOKEN": "anthropic-web-token", "API_TIMEOUT_MS": "60000"}}"#,
+    )
+    .unwrap();
+    let output = run_with_stdin(home.path(), &["status", "--diff-backup"], "");
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("API_TIMEOUT_MS: (unset) → 60000"));
+}
+
+#[test]
+fn test_user_alias_dispatches_to_glm_switch() {
+    let home = tempfile::tempdir().unwrap();
+    let claude_dir = home.path().join(".claude");
+    fs::create_dir_all(&claude_dir).unwrap();
+    fs::write(
+        claude_dir.join("claude-switch.toml"),
+        "[aliases]\nz = \"glm\"\n",
+    )
+    .unwrap();
+
+    let output = run_with_stdin(
+        home.path(),
+        &["--explain", "z", "--token-stdin"],
+        "sk-alias\n",
+    );
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Switching to GLM API"));
+    let settings = fs::read_to_string(claude_dir.join("settings.json")).unwrap();
+    assert!(settings.contains("sk-alias"));
+    assert!(settings.contains("api.z.ai"));
+
+    // Unknown names are still usage errors
+    assert_eq!(
+        run_with_stdin(home.path(), &["zz"], "").status.code(),
+        Some(64)
+    );
+
+    // An alias shadowing a built-in command fails once aliases are consulted,
+    // but built-in commands never read them
+    fs::write(
+        claude_dir.join("claude-switch.toml"),
+        "[aliases]\ns = \"glm\"\nz = \"glm\"\n",
+    )
+    .unwrap();
+    let output = run_with_stdin(home.path(), &["z"], "");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("conflicts with the built-in"));
+    assert!(run_with_stdin(home.path(), &["status"], "")
+        .status
+        .success());
+}
+
+#[test]
+fn test_switch_warns_when_claude_code_is_missing() {
+    let home = tempfile::tempdir().unwrap();
+    let config_dir = home.path().join(".claude");
+    fs::create_dir_all(&config_dir).unwrap();
+    let empty_path = tempfile::tempdir().unwrap();
+    let path = empty_path.path().to_str().unwrap();
+
+    let output = run_in_config_dir(home.path(), &config_dir, &["anthropic"], &[("PATH", path)]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not found on PATH"));
+
+    let args = ["anthropic", "--ignore-missing-claude"];
+    let output = run_in_config_dir(home.path(), &config_dir, &args, &[("PATH", path)]);
+    assert!(output.status.success());
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("not found on PATH"));
+
+    let output = run_in_config_dir(home.path(), &config_dir, &["doctor"], &[("PATH", path)]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("not found on PATH"));
+}
+
+/// Every file under `dir` with its contents, to check that nothing was written
+fn snapshot(dir: &Path) -> Vec<(std::path::PathBuf, Vec<u8>)> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                pending.push(path);
+            } else {
+                let content = fs::read(&path).unwrap();
+                files.push((path, content));
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+#[test]
+fn test_print_only_switches_write_nothing() {
+    let home = tempfile::tempdir().unwrap();
+    let claude_dir = home.path().join(".claude");
+    fs::create_dir_all(&claude_dir).unwrap();
+    fs::write(
+        claude_dir.join("settings.json"),
+        r#"{"env": {"ANTHROPIC_AUTH_TOKEN": "web-login-token"}, "model": "opus"}"#,
+    )
+    .unwrap();
+    // Not even the legacy token file is migrated
+    fs::write(claude_dir.join(".z_ai_token"), "sk-legacy").unwrap();
+    let before = snapshot(home.path());
+
+    let output = run_with_stdin(
+        home.path(),
+        &["glm", "--token-stdin", "--save-token", "--print-only"],
+        "sk-print-only-1234\n",
+    );
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        json["env"]["ANTHROPIC_BASE_URL"],
+        "https://api.z.ai/api/anthropic"
+    );
+    assert_eq!(json["env"]["ANTHROPIC_AUTH_TOKEN"], "sk-p...1234");
+    assert_eq!(json["env"]["ANTHROPIC_DEFAULT_HAIKU_MODEL"], "GLM-4.5-Air");
+    assert_eq!(json["model"], "opus");
+
+    let args = [
+        "custom",
+        "--base-url",
+        "https://gateway.example.com",
+        "--token",
+        "sk-gateway-secret",
+        "--model",
+        "gw-large",
+        "--print-only",
+        "--include-secrets",
+    ];
+    let output = run_with_stdin(home.path(), &args, "");
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        json["env"]["ANTHROPIC_BASE_URL"],
+        "https://gateway.example.com"
+    );
+    assert_eq!(json["env"]["ANTHROPIC_AUTH_TOKEN"], "sk-gateway-secret");
+    assert_eq!(json["env"]["ANTHROPIC_DEFAULT_OPUS_MODEL"], "gw-large");
+
+    assert_eq!(snapshot(home.path()), before);
+}
+
+#[test]
+fn test_print_only_anthropic_keeps_notices_off_stdout() {
+    let home = tempfile::tempdir().unwrap();
+    let claude_dir = home.path().join(".claude");
+    fs::create_dir_all(&claude_dir).unwrap();
+    fs::write(
+        claude_dir.join("settings.json"),
+        r#"{"env": {"ANTHROPIC_AUTH_TOKEN": "sk-glm-token-1234", "ANTHROPIC_BASE_URL": "https://api.z.ai/api/anthropic"}}"#,
+    )
+    .unwrap();
+
+    let args = [
+        "anthropic",
+        "--from-credentials",
+        "--merge-env",
+        "ANTHROPIC_DEFAULT_OPUS_MODEL=odd model",
+        "--print-only",
+    ];
+    let output = run_with_stdin(home.path(), &args, "");
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["env"]["ANTHROPIC_DEFAULT_OPUS_MODEL"], "odd model");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("No usable Claude CLI credentials"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("doesn't look like a model name"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_from_credentials_warns_that_the_token_expires() {
+    let home = tempfile::tempdir().unwrap();
+    let claude_dir = home.path().join(".claude");
+    fs::create_dir_all(&claude_dir).unwrap();
+    fs::write(
+        claude_dir.join(".credentials.json"),
+        r#"{"claudeAiOauth": {"accessToken": "sk-ant-oat01-fixture", "expiresAt": 4102444800000}}"#,
+    )
+    .unwrap();
+
+    let output = run_with_stdin(home.path(), &["anthropic", "--from-credentials"], "");
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("expires 2100-01-01 00:00:00 UTC and is not refreshed"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_config_flag_operates_on_an_arbitrary_file() {
+    let home = tempfile::tempdir().unwrap();
+    let elsewhere = tempfile::tempdir().unwrap();
+    let colleague = elsewhere.path().join("colleague.json");
+    fs::write(
+        &colleague,
+        r#"{"env": {"ANTHROPIC_AUTH_TOKEN": "sk-ant-oat01-colleague"}, "model": "opus"}"#,
+    )
+    .unwrap();
+    let config = colleague.to_str().unwrap();
+
+    let output = run_with_stdin(
+        home.path(),
+        &["--config", config, "--output", "json", "status"],
+        "",
+    );
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["provider"], "anthropic");
+
+    let output = run_with_stdin(
+        home.path(),
+        &[
+            "--config",
+            config,
+            "--ignore-missing-claude",
+            "glm",
+            "--token-stdin",
+        ],
+        "sk-piped\n",
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let switched: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&colleague).unwrap()).unwrap();
+    assert_eq!(switched["env"]["ANTHROPIC_AUTH_TOKEN"], "sk-piped");
+    assert_eq!(switched["model"], "opus");
+    // The backup sits next to the file; the real config directory is untouched
+    let backup = fs::read_to_string(elsewhere.path().join("settings.json.backup")).unwrap();
+    assert!(backup.contains("sk-ant-oat01-colleague"));
+    assert!(!home.path().join(".claude/settings.json").exists());
+
+    let output = run_with_stdin(
+        home.path(),
+        &["--config", config, "--profile", "work", "status"],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(64));
+}
+
+/// Serve `body` with status 200 to one request; returns the base URL
+#[cfg(feature = "list-models")]
+fn serve_once(body: &'static str) -> String {
+    use std::io::{BufRead, BufReader};
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut line = String::new();
+        while reader.read_line(&mut line).unwrap() > 0 && line.trim() != "" {
+            line.clear();
+        }
+        write!(
+            &stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        )
+        .unwrap();
+    });
+    base_url
+}
+
+#[cfg(feature = "list-models")]
+#[test]
+fn test_list_models_prints_model_ids() {
+    let home = tempfile::tempdir().unwrap();
+    let base_url = serve_once(r#"{"data": [{"id": "glm-4.7"}, {"id": "glm-4.5-air"}]}"#);
+    fs::create_dir_all(home.path().join(".claude")).unwrap();
+    fs::write(
+        home.path().join(".claude/settings.json"),
+        format!(
+            r#"{{"env": {{"ANTHROPIC_BASE_URL": "{}", "ANTHROPIC_AUTH_TOKEN": "sk-list"}}}}"#,
+            base_url
+        ),
+    )
+    .unwrap();
+
+    let output = run_with_stdin(home.path(), &["list-models"], "");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("  glm-4.7\n"), "{}", stdout);
+    assert!(stdout.contains("  glm-4.5-air\n"), "{}", stdout);
+}
+
+#[test]
+fn test_confirm_provider_fails_on_a_mismatch() {
+    let home = tempfile::tempdir().unwrap();
+    let custom = |expected: &str| {
+        run_with_stdin(
+            home.path(),
+            &[
+                "custom",
+                "--base-url",
+                "https://api.z.ia/api/anthropic",
+                "--token",
+                "sk-typo",
+                "--confirm-provider",
+                expected,
+                "--ignore-missing-claude",
+            ],
+            "",
+        )
+    };
+
+    let output = custom("custom");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Confirmed provider: custom"));
+
+    // The typo in the host means this is not the GLM endpoint that was meant
+    let output = custom("glm");
+    assert_eq!(output.status.code(), Some(5));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("detected as custom"));
+}
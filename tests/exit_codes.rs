@@ -0,0 +1,131 @@
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+fn claude_switch(home: &Path, args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_claude-switch"))
+        .args(args)
+        .env("HOME", home)
+        .env_remove("Z_AI_AUTH_TOKEN")
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("CLAUDE_BACKUP_DIR")
+        .env_remove("CLAUDE_CONFIG_DIR")
+        .env_remove("CLAUDE_TOKEN_FILE")
+        .stdin(Stdio::null())
+        .output()
+        .expect("failed to run claude-switch")
+}
+
+#[test]
+fn test_invalid_settings_exits_with_config_code() {
+    let home = tempfile::tempdir().unwrap();
+    fs::create_dir_all(home.path().join(".claude")).unwrap();
+    fs::write(home.path().join(".claude/settings.json"), "{ not json").unwrap();
+
+    let output = claude_switch(home.path(), &["status"]);
+    assert_eq!(output.status.code(), Some(2));
+
+    // A non-ASCII value echoed in the parse error is masked, not a panic
+    fs::write(
+        home.path().join(".claude/settings.json"),
+        r#"{"env": "aéééééééé"}"#,
+    )
+    .unwrap();
+    let output = claude_switch(home.path(), &["status"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_empty_token_exits_with_token_code() {
+    let home = tempfile::tempdir().unwrap();
+
+    let output = claude_switch(home.path(), &["glm"]);
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[cfg(feature = "clipboard")]
+#[test]
+fn test_copy_token_without_saved_token_exits_with_token_code() {
+    let home = tempfile::tempdir().unwrap();
+
+    let output = claude_switch(home.path(), &["copy-token"]);
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn test_blank_env_token_aborts_without_writing() {
+    let home = tempfile::tempdir().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_claude-switch"))
+        .args(["glm"])
+        .env("HOME", home.path())
+        .env("Z_AI_AUTH_TOKEN", "")
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("CLAUDE_BACKUP_DIR")
+        .env_remove("CLAUDE_CONFIG_DIR")
+        .env_remove("CLAUDE_TOKEN_FILE")
+        .stdin(Stdio::null())
+        .output()
+        .expect("failed to run claude-switch");
+
+    assert_eq!(output.status.code(), Some(3));
+    assert!(!home.path().join(".claude/settings.json").exists());
+}
+
+#[test]
+fn test_unreadable_settings_exits_with_filesystem_code() {
+    let home = tempfile::tempdir().unwrap();
+    // A directory where the settings file should be fails regardless of privileges
+    fs::create_dir_all(home.path().join(".claude/settings.json")).unwrap();
+
+    let output = claude_switch(home.path(), &["status"]);
+    assert_eq!(output.status.code(), Some(4));
+}
+
+#[test]
+fn test_unknown_command_exits_with_usage_code() {
+    let home = tempfile::tempdir().unwrap();
+
+    let output = claude_switch(home.path(), &["no-such-command"]);
+    assert_eq!(output.status.code(), Some(64));
+}
+
+#[test]
+fn test_success_exits_zero() {
+    let home = tempfile::tempdir().unwrap();
+
+    let output = claude_switch(home.path(), &["status"]);
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn test_failing_token_command_exits_with_token_code() {
+    let home = tempfile::tempdir().unwrap();
+
+    let output = claude_switch(
+        home.path(),
+        &["glm", "--token-command", "echo sk-leaked; exit 1"],
+    );
+    assert_eq!(output.status.code(), Some(3));
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("sk-leaked"));
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("sk-leaked"));
+    assert!(!home.path().join(".claude/settings.json").exists());
+}
+
+#[test]
+fn test_json_output_reports_errors_as_json() {
+    let home = tempfile::tempdir().unwrap();
+
+    let output = claude_switch(home.path(), &["--output", "json", "restore", "missing"]);
+    assert_eq!(output.status.code(), Some(2));
+    let error: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(error["error"]["kind"], "Config");
+    assert!(error["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("No backup with id or label 'missing'"));
+
+    // Human mode keeps the plain message
+    let output = claude_switch(home.path(), &["restore", "missing"]);
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("Error: "));
+}